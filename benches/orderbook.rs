@@ -0,0 +1,113 @@
+// Per-operation latency of every book under each price distribution
+//
+// Run with `cargo bench`. Criterion writes its estimates as JSON under
+// target/criterion/<operation>/<implementation>/<distribution>/, which is
+// what to keep and diff across commits.
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use orderbook::benchmark::{Distribution, Fixture, time_in_batches};
+use orderbook::orderbook::OrderbookTrait;
+use orderbook::orderbook::SoA::orderbook::Orderbook as SoAOrderbook;
+use orderbook::orderbook::arena::orderbook::Orderbook as ArenaOrderbook;
+use orderbook::orderbook::fixed_tick::orderbook::Orderbook as FixedTickOrderbook;
+use orderbook::orderbook::hybrid::orderbook::Orderbook as HybridOrderbook;
+use orderbook::orderbook::tree::orderbook::Orderbook as TreeOrderbook;
+use orderbook::types::order::{OrderId, Side};
+use orderbook::types::quantity::Quantity;
+use orderbook::workload::ORDER_QUANTITY;
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::Duration;
+
+const SEED: u64 = 42;
+
+fn bench_implementation<O: OrderbookTrait>(c: &mut Criterion, implementation: &str) {
+    for distribution in Distribution::ALL {
+        let id = || BenchmarkId::new(implementation, distribution.name());
+
+        // Add the spare orders, then cancel them back out untimed
+        c.benchmark_group("add_order").bench_function(id(), |b| {
+            let mut fixture = Fixture::<O>::new(distribution, SEED);
+            b.iter_custom(|iters| {
+                time_in_batches(
+                    iters,
+                    &mut fixture,
+                    |f, i| {
+                        black_box(f.book.add_order(f.spare[i]).unwrap());
+                    },
+                    |f, batch| {
+                        for order in &f.spare[..batch] {
+                            f.book.cancel_order(order.id()).unwrap();
+                        }
+                    },
+                )
+            });
+        });
+
+        // Cancel resting orders, then put them back (at the back of their levels)
+        c.benchmark_group("cancel_order").bench_function(id(), |b| {
+            let mut fixture = Fixture::<O>::new(distribution, SEED);
+            b.iter_custom(|iters| {
+                time_in_batches(
+                    iters,
+                    &mut fixture,
+                    |f, i| {
+                        f.book.cancel_order(f.resting[i].id()).unwrap();
+                    },
+                    |f, batch| {
+                        for &order in &f.resting[..batch] {
+                            f.book.add_order(order).unwrap();
+                        }
+                    },
+                )
+            });
+        });
+
+        // Alternate buys and sells that each take one best order; re-add the
+        // makers afterwards so the book never drains
+        c.benchmark_group("execute_market_order").bench_function(id(), |b| {
+            let fixture = Fixture::<O>::new(distribution, SEED);
+            let by_id: HashMap<OrderId, _> = fixture.resting.iter().map(|o| (o.id(), *o)).collect();
+            let mut state = (fixture, Vec::new());
+            b.iter_custom(|iters| {
+                time_in_batches(
+                    iters,
+                    &mut state,
+                    |(f, taken), i| {
+                        let side = if i % 2 == 0 { Side::Bid } else { Side::Ask };
+                        let taker = f.ids.next();
+                        f.book.execute_market_order_with(side, Quantity::define(ORDER_QUANTITY), taker, |fill| {
+                            taken.push(fill.maker_order_id)
+                        });
+                    },
+                    |(f, taken), _| {
+                        for id in taken.drain(..) {
+                            f.book.add_order(by_id[&id]).unwrap();
+                        }
+                    },
+                )
+            });
+        });
+
+        c.benchmark_group("best_bid").bench_function(id(), |b| {
+            let fixture = Fixture::<O>::new(distribution, SEED);
+            b.iter(|| black_box(&fixture.book).best_bid());
+        });
+    }
+}
+
+fn benches(c: &mut Criterion) {
+    bench_implementation::<TreeOrderbook>(c, "Tree");
+    bench_implementation::<FixedTickOrderbook>(c, "FixedTick");
+    bench_implementation::<SoAOrderbook>(c, "SoA");
+    bench_implementation::<HybridOrderbook>(c, "Hybrid");
+    bench_implementation::<ArenaOrderbook>(c, "Arena");
+}
+
+criterion_group! {
+    name = orderbook;
+    config = Criterion::default()
+        .warm_up_time(Duration::from_secs(1))
+        .measurement_time(Duration::from_secs(2));
+    targets = benches
+}
+criterion_main!(orderbook);
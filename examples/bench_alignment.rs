@@ -288,7 +288,7 @@ fn bench_sequential_default(seed: u64) -> BenchResult {
         });
     }
 
-    let p = tracker.precentiles().expect("No samples");
+    let p = tracker.percentiles().expect("No samples");
     BenchResult { p50: p.p50, p99: p.p99, max: p.max }
 }
 
@@ -316,7 +316,7 @@ fn bench_sequential_packed(seed: u64) -> BenchResult {
         });
     }
 
-    let p = tracker.precentiles().expect("No samples");
+    let p = tracker.percentiles().expect("No samples");
     BenchResult { p50: p.p50, p99: p.p99, max: p.max }
 }
 
@@ -342,7 +342,7 @@ fn bench_sequential_aligned(seed: u64) -> BenchResult {
         });
     }
 
-    let p = tracker.precentiles().expect("No samples");
+    let p = tracker.percentiles().expect("No samples");
     BenchResult { p50: p.p50, p99: p.p99, max: p.max }
 }
 
@@ -378,7 +378,7 @@ fn bench_random_access_default(seed: u64) -> BenchResult {
         });
     }
 
-    let p = tracker.precentiles().expect("No samples");
+    let p = tracker.percentiles().expect("No samples");
     BenchResult { p50: p.p50, p99: p.p99, max: p.max }
 }
 
@@ -408,7 +408,7 @@ fn bench_random_access_packed(seed: u64) -> BenchResult {
         });
     }
 
-    let p = tracker.precentiles().expect("No samples");
+    let p = tracker.percentiles().expect("No samples");
     BenchResult { p50: p.p50, p99: p.p99, max: p.max }
 }
 
@@ -437,7 +437,7 @@ fn bench_random_access_aligned(seed: u64) -> BenchResult {
         });
     }
 
-    let p = tracker.precentiles().expect("No samples");
+    let p = tracker.percentiles().expect("No samples");
     BenchResult { p50: p.p50, p99: p.p99, max: p.max }
 }
 
@@ -474,7 +474,7 @@ fn bench_insert_default(seed: u64) -> BenchResult {
         });
     }
 
-    let p = tracker.precentiles().expect("No samples");
+    let p = tracker.percentiles().expect("No samples");
     BenchResult { p50: p.p50, p99: p.p99, max: p.max }
 }
 
@@ -501,7 +501,7 @@ fn bench_insert_packed(seed: u64) -> BenchResult {
         });
     }
 
-    let p = tracker.precentiles().expect("No samples");
+    let p = tracker.percentiles().expect("No samples");
     BenchResult { p50: p.p50, p99: p.p99, max: p.max }
 }
 
@@ -528,7 +528,7 @@ fn bench_insert_aligned(seed: u64) -> BenchResult {
         });
     }
 
-    let p = tracker.precentiles().expect("No samples");
+    let p = tracker.percentiles().expect("No samples");
     BenchResult { p50: p.p50, p99: p.p99, max: p.max }
 }
 
@@ -0,0 +1,129 @@
+/// Occupancy bitmap vs linear scan for next-best level recovery
+///
+/// When the best level of the Fixed-Tick book empties, the next populated
+/// level has to be found. The old code scanned Level slots one by one; the
+/// book now keeps a two-level occupancy bitmap per side and jumps with
+/// trailing_zeros / leading_zeros.
+///
+/// Run with: cargo run --release --example bench_bitmap
+use orderbook::orderbook::fixed_tick::bitmap::OccupancyBitmap;
+use orderbook::orderbook::fixed_tick::orderbook::Level;
+use orderbook::orderbook::level::PriceLevel;
+use orderbook::perf::latency::LatencyTracker;
+use orderbook::perf::{cycles_to_ns, get_cpu_frequency};
+use orderbook::types::order::{IdCounter, Order, Side};
+use orderbook::types::price::Price;
+use orderbook::types::quantity::Quantity;
+use rand::SeedableRng;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+const NUM_SAMPLES: usize = 10_000;
+const ELEMENT_NUM: usize = 10_000;
+
+// ============================================================================
+// WHAT WE TEST:
+//   Sparse books (10 / 100 / 1,000 populated slots out of 10,000).
+//   From a random start slot, find the next populated level upward and
+//   downward — exactly what recovery-after-empty does for asks and bids.
+//
+// EXPECTED RESULTS:
+//   - Linear scan cost grows with the gap between populated levels
+//   - Bitmap cost is flat: one masked word + at most 3 summary words
+// ============================================================================
+
+fn main() {
+    println!("=== Occupancy Bitmap vs Linear Scan ===\n");
+
+    let cpu_ghz = get_cpu_frequency();
+    println!("CPU frequency: {:.3} GHz\n", cpu_ghz);
+
+    println!(
+        "{:<10} | {:<10} | {:>16} | {:>16} | {:>8}",
+        "Populated", "Direction", "Linear p50", "Bitmap p50", "Speedup"
+    );
+    println!("{:-<72}", "");
+
+    for populated in [10, 100, 1_000] {
+        let (levels, bitmap) = build_sparse_side(42, populated);
+        let starts = random_starts(7, NUM_SAMPLES);
+
+        bench_direction(&levels, &bitmap, &starts, true, populated, cpu_ghz);
+        bench_direction(&levels, &bitmap, &starts, false, populated, cpu_ghz);
+    }
+}
+
+fn bench_direction(
+    levels: &[Level],
+    bitmap: &OccupancyBitmap,
+    starts: &[usize],
+    upward: bool,
+    populated: usize,
+    cpu_ghz: f64,
+) {
+    let mut tracker_linear = LatencyTracker::new(starts.len());
+    for &start in starts {
+        tracker_linear.record(|| {
+            let found = if upward {
+                (start..ELEMENT_NUM).find(|&j| !levels[j].is_empty())
+            } else {
+                (0..=start).rev().find(|&j| !levels[j].is_empty())
+            };
+            std::hint::black_box(found);
+        });
+    }
+
+    let mut tracker_bitmap = LatencyTracker::new(starts.len());
+    for &start in starts {
+        tracker_bitmap.record(|| {
+            let found = if upward {
+                bitmap.next_at_or_above(start)
+            } else {
+                bitmap.prev_at_or_below(start)
+            };
+            std::hint::black_box(found);
+        });
+    }
+
+    let p_linear = tracker_linear.percentiles().unwrap();
+    let p_bitmap = tracker_bitmap.percentiles().unwrap();
+
+    println!(
+        "{:<10} | {:<10} | {:>7} cy {:>4.0}ns | {:>7} cy {:>4.0}ns | {:>7.2}x",
+        populated,
+        if upward { "up (ask)" } else { "down (bid)" },
+        p_linear.p50,
+        cycles_to_ns(p_linear.p50, cpu_ghz),
+        p_bitmap.p50,
+        cycles_to_ns(p_bitmap.p50, cpu_ghz),
+        p_linear.p50 as f64 / p_bitmap.p50.max(1) as f64,
+    );
+}
+
+/// One side of a Fixed-Tick book with `populated` random non-empty slots,
+/// plus the matching occupancy bitmap
+fn build_sparse_side(seed: u64, populated: usize) -> (Vec<Level>, OccupancyBitmap) {
+    let mut levels: Vec<Level> = (0..ELEMENT_NUM).map(|_| Level::default()).collect();
+    let mut bitmap = OccupancyBitmap::new(ELEMENT_NUM);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut counter = IdCounter::new();
+
+    for _ in 0..populated {
+        let idx = rng.random_range(1..ELEMENT_NUM);
+        let order = Order::new(
+            Price::define(idx as u32),
+            Quantity::define(100),
+            Side::Ask,
+            &mut counter,
+        );
+        levels[idx].add(order);
+        bitmap.set(idx);
+    }
+
+    (levels, bitmap)
+}
+
+fn random_starts(seed: u64, count: usize) -> Vec<usize> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count).map(|_| rng.random_range(0..ELEMENT_NUM)).collect()
+}
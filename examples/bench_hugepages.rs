@@ -265,7 +265,7 @@ fn bench_sequential(num_slots: usize, _seed: u64, _cpu_ghz: f64) -> (u64, u64) {
         });
     }
 
-    let p_normal = tracker_normal.precentiles().unwrap();
+    let p_normal = tracker_normal.percentiles().unwrap();
     free_mmap(ptr_normal, size);
 
     // Huge pages
@@ -287,7 +287,7 @@ fn bench_sequential(num_slots: usize, _seed: u64, _cpu_ghz: f64) -> (u64, u64) {
         });
     }
 
-    let p_huge = tracker_huge.precentiles().unwrap();
+    let p_huge = tracker_huge.percentiles().unwrap();
     free_mmap(ptr_huge, size);
 
     (p_normal.p50, p_huge.p50)
@@ -322,7 +322,7 @@ fn bench_random(num_slots: usize, seed: u64, _cpu_ghz: f64) -> (u64, u64) {
         });
     }
 
-    let p_normal = tracker_normal.precentiles().unwrap();
+    let p_normal = tracker_normal.percentiles().unwrap();
     free_mmap(ptr_normal, size);
 
     // Huge pages
@@ -344,7 +344,7 @@ fn bench_random(num_slots: usize, seed: u64, _cpu_ghz: f64) -> (u64, u64) {
         });
     }
 
-    let p_huge = tracker_huge.precentiles().unwrap();
+    let p_huge = tracker_huge.percentiles().unwrap();
     free_mmap(ptr_huge, size);
 
     (p_normal.p50, p_huge.p50)
@@ -379,7 +379,7 @@ fn bench_strided(num_slots: usize, _seed: u64, _cpu_ghz: f64) -> (u64, u64) {
         });
     }
 
-    let p_normal = tracker_normal.precentiles().unwrap();
+    let p_normal = tracker_normal.percentiles().unwrap();
     free_mmap(ptr_normal, size);
 
     // Huge pages
@@ -403,7 +403,7 @@ fn bench_strided(num_slots: usize, _seed: u64, _cpu_ghz: f64) -> (u64, u64) {
         });
     }
 
-    let p_huge = tracker_huge.precentiles().unwrap();
+    let p_huge = tracker_huge.percentiles().unwrap();
     free_mmap(ptr_huge, size);
 
     (p_normal.p50, p_huge.p50)
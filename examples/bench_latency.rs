@@ -1,4 +1,4 @@
-use orderbook::analysis::{CsvExporter, ResultRow};
+use orderbook::analysis::{compare, CsvExporter, ResultRow};
 use orderbook::orderbook::OrderbookTrait;
 use orderbook::orderbook::SoA::orderbook::Orderbook as SoAOrderbook;
 /// Latency benchmark for orderbook implementations
@@ -17,8 +17,11 @@ use orderbook::perf::{cycles_to_ns, get_cpu_frequency};
 use orderbook::types::order::{IdCounter, Order, Side};
 use orderbook::types::price::Price;
 use orderbook::types::quantity::Quantity;
+use orderbook::workload::resting_price;
 
 const NUM_SAMPLES: usize = 10_000;
+/// Significance level below which a p50 lead counts as real rather than noise
+const ALPHA: f64 = 0.05;
 
 fn main() {
     println!("=== Orderbook Latency Benchmark ===\n");
@@ -104,6 +107,10 @@ struct BenchmarkResults {
     add_order: Percentiles,
     cancel_order: Percentiles,
     market_order: Percentiles,
+    /// Raw cycle counts per operation, for significance tests
+    add_samples: Vec<u64>,
+    cancel_samples: Vec<u64>,
+    market_samples: Vec<u64>,
 }
 
 fn benchmark_orderbook<O: OrderbookTrait>() -> BenchmarkResults {
@@ -123,6 +130,7 @@ fn benchmark_orderbook<O: OrderbookTrait>() -> BenchmarkResults {
         // Spread orders across wide range [4000, 6000]
         let price_offset = (i % 2000) as u32;
         let price_value = 4000 + price_offset;
+        let price_value = resting_price(price_value, side);
 
         let order = Order::new(
             Price::define(price_value),
@@ -166,20 +174,24 @@ fn benchmark_orderbook<O: OrderbookTrait>() -> BenchmarkResults {
 
     // Execute 100 market buy orders
     for _ in 0..100 {
+        let taker_id = id_counter.next();
         market_tracker.record(|| {
-            book.execute_market_order(Side::Bid, Quantity::define(100))
-                .expect("Failed to execute market order");
+            let result = book.execute_market_order(Side::Bid, Quantity::define(100), taker_id);
+            assert!(result.is_fully_filled(), "Failed to execute market order");
         });
     }
 
     BenchmarkResults {
-        add_order: add_tracker.precentiles().expect("No add_order samples"),
+        add_order: add_tracker.percentiles().expect("No add_order samples"),
         cancel_order: cancel_tracker
-            .precentiles()
+            .percentiles()
             .expect("No cancel_order samples"),
         market_order: market_tracker
-            .precentiles()
+            .percentiles()
             .expect("No market_order samples"),
+        add_samples: add_tracker.samples().to_vec(),
+        cancel_samples: cancel_tracker.samples().to_vec(),
+        market_samples: market_tracker.samples().to_vec(),
     }
 }
 
@@ -309,28 +321,28 @@ fn compare_all_implementations(
     print_winner(
         "add_order (p50)",
         &[
-            ("Fixed-Tick", fixed.add_order.p50),
-            ("SoA", soa.add_order.p50),
-            ("Hybrid", hybrid.add_order.p50),
-            ("Tree", tree.add_order.p50),
+            ("Fixed-Tick", fixed.add_order.p50, &fixed.add_samples),
+            ("SoA", soa.add_order.p50, &soa.add_samples),
+            ("Hybrid", hybrid.add_order.p50, &hybrid.add_samples),
+            ("Tree", tree.add_order.p50, &tree.add_samples),
         ],
     );
     print_winner(
         "cancel_order (p50)",
         &[
-            ("Fixed-Tick", fixed.cancel_order.p50),
-            ("SoA", soa.cancel_order.p50),
-            ("Hybrid", hybrid.cancel_order.p50),
-            ("Tree", tree.cancel_order.p50),
+            ("Fixed-Tick", fixed.cancel_order.p50, &fixed.cancel_samples),
+            ("SoA", soa.cancel_order.p50, &soa.cancel_samples),
+            ("Hybrid", hybrid.cancel_order.p50, &hybrid.cancel_samples),
+            ("Tree", tree.cancel_order.p50, &tree.cancel_samples),
         ],
     );
     print_winner(
         "market_order (p50)",
         &[
-            ("Fixed-Tick", fixed.market_order.p50),
-            ("SoA", soa.market_order.p50),
-            ("Hybrid", hybrid.market_order.p50),
-            ("Tree", tree.market_order.p50),
+            ("Fixed-Tick", fixed.market_order.p50, &fixed.market_samples),
+            ("SoA", soa.market_order.p50, &soa.market_samples),
+            ("Hybrid", hybrid.market_order.p50, &hybrid.market_samples),
+            ("Tree", tree.market_order.p50, &tree.market_samples),
         ],
     );
 }
@@ -342,19 +354,30 @@ fn print_comparison_row(name: &str, fixed: u64, soa: u64, hybrid: u64, tree: u64
     );
 }
 
-fn print_winner(operation: &str, results: &[(&str, u64)]) {
-    let (winner_name, winner_cycles) = results.iter().min_by_key(|(_, cycles)| cycles).unwrap();
+/// Lowest p50 wins, unless a Mann-Whitney test cannot tell it from the
+/// runner-up; then the lead is reported as noise
+fn print_winner(operation: &str, results: &[(&str, u64, &Vec<u64>)]) {
+    let (winner_name, winner_cycles, winner_samples) =
+        results.iter().min_by_key(|(_, cycles, _)| cycles).unwrap();
 
-    let second_best_cycles = results
+    let (second_name, second_best_cycles, second_samples) = results
         .iter()
-        .filter(|(name, _)| name != winner_name)
-        .min_by_key(|(_, cycles)| cycles)
-        .map(|(_, cycles)| *cycles)
+        .filter(|(name, _, _)| name != winner_name)
+        .min_by_key(|(_, cycles, _)| cycles)
         .unwrap();
 
-    let speedup = second_best_cycles as f64 / *winner_cycles as f64;
+    let comparison = compare(winner_samples, second_samples);
+    if !comparison.is_significant(ALPHA) {
+        println!(
+            "  {:<20} : no clear winner ({} vs {}: not significant, p={:.2})",
+            operation, winner_name, second_name, comparison.p_value
+        );
+        return;
+    }
+
+    let speedup = *second_best_cycles as f64 / *winner_cycles as f64;
     println!(
-        "  {:<20} : {} ({} cycles, {:.2}x faster than 2nd best)",
-        operation, winner_name, winner_cycles, speedup
+        "  {:<20} : {} ({} cycles, {:.2}x faster than 2nd best, p={:.1e})",
+        operation, winner_name, winner_cycles, speedup, comparison.p_value
     );
 }
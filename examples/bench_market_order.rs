@@ -318,8 +318,8 @@ fn main() {
             });
         }
 
-        let current_p = current_tracker.precentiles().unwrap();
-        let optimized_p = optimized_tracker.precentiles().unwrap();
+        let current_p = current_tracker.percentiles().unwrap();
+        let optimized_p = optimized_tracker.percentiles().unwrap();
 
         let speedup = current_p.p50 as f64 / optimized_p.p50 as f64;
 
@@ -359,8 +359,8 @@ fn main() {
         });
     }
 
-    let sc = scan_current.precentiles().unwrap();
-    let so = scan_optimized.precentiles().unwrap();
+    let sc = scan_current.percentiles().unwrap();
+    let so = scan_optimized.percentiles().unwrap();
     println!(
         "  Current  (scan 0..5100):  {:>6} cy ({:.0} ns)",
         sc.p50,
@@ -404,8 +404,8 @@ fn main() {
         });
     }
 
-    let dc = deep_current.precentiles().unwrap();
-    let do_ = deep_optimized.precentiles().unwrap();
+    let dc = deep_current.percentiles().unwrap();
+    let do_ = deep_optimized.percentiles().unwrap();
 
     println!(
         "{:<10} | {:>14} | {:>14}",
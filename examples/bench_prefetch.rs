@@ -217,9 +217,9 @@ fn bench_sequential_scan(cpu_ghz: f64) {
         });
     }
 
-    let p_none = tracker_none.precentiles().unwrap();
-    let p_pf4 = tracker_pf4.precentiles().unwrap();
-    let p_pf16 = tracker_pf16.precentiles().unwrap();
+    let p_none = tracker_none.percentiles().unwrap();
+    let p_pf4 = tracker_pf4.percentiles().unwrap();
+    let p_pf16 = tracker_pf16.percentiles().unwrap();
 
     println!(
         "{:<20} | {:>14} | {:>8}",
@@ -318,9 +318,9 @@ fn bench_random_access(seed: u64, cpu_ghz: f64) {
         });
     }
 
-    let p_none = tracker_none.precentiles().unwrap();
-    let p_pf1 = tracker_pf1.precentiles().unwrap();
-    let p_pf4 = tracker_pf4.precentiles().unwrap();
+    let p_none = tracker_none.percentiles().unwrap();
+    let p_pf1 = tracker_pf1.percentiles().unwrap();
+    let p_pf4 = tracker_pf4.percentiles().unwrap();
 
     println!(
         "{:<20} | {:>14} | {:>8}",
@@ -441,9 +441,9 @@ fn bench_pointer_chase(seed: u64, cpu_ghz: f64) {
         });
     }
 
-    let p_none = tracker_none.precentiles().unwrap();
-    let p_pf = tracker_pf.precentiles().unwrap();
-    let p_pf8 = tracker_pf8.precentiles().unwrap();
+    let p_none = tracker_none.percentiles().unwrap();
+    let p_pf = tracker_pf.percentiles().unwrap();
+    let p_pf8 = tracker_pf8.percentiles().unwrap();
 
     println!(
         "{:<25} | {:>14} | {:>8}",
@@ -507,8 +507,8 @@ fn bench_market_order_sim(seed: u64, cpu_ghz: f64) {
         });
     }
 
-    let p_none = tracker_none.precentiles().unwrap();
-    let p_pf = tracker_pf.precentiles().unwrap();
+    let p_none = tracker_none.percentiles().unwrap();
+    let p_pf = tracker_pf.percentiles().unwrap();
 
     println!(
         "{:<25} | {:>14} | {:>8}",
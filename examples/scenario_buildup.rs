@@ -4,6 +4,7 @@
 /// Tests allocation patterns and cold-start behavior
 ///
 /// Run with: cargo run --release --example scenario_buildup
+/// Add --features alloc-stats to also count allocations per add_order
 use orderbook::orderbook::fixed_tick::orderbook::Orderbook as FixedTickOrderbook;
 use orderbook::orderbook::hybrid::orderbook::Orderbook as HybridOrderbook;
 use orderbook::orderbook::tree::orderbook::Orderbook as TreeOrderbook;
@@ -14,6 +15,7 @@ use orderbook::perf::{cycles_to_ns, get_cpu_frequency};
 use orderbook::types::order::{IdCounter, Order, Side};
 use orderbook::types::price::Price;
 use orderbook::types::quantity::Quantity;
+use orderbook::workload::resting_price;
 use rand::prelude::*;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
@@ -26,6 +28,10 @@ const TOTAL_ORDERS: usize = 10_000;
 const MEASUREMENT_POINTS: [usize; 5] = [0, 25, 50, 75, 100];
 const ORDERS_PER_MEASUREMENT: usize = 500; // Sample size at each point
 
+// Second pass calls reserve() with this first; around 100 orders land on each
+// of the ~100 levels, so most queues never grow past it
+const RESERVED_ORDERS_PER_LEVEL: usize = 128;
+
 // ============================================================================
 // Scenario 4.2c: Order Book Build-Up
 // ============================================================================
@@ -97,19 +103,19 @@ fn main() {
     let seed: u64 = 42;
 
     println!("--- Fixed-Tick Array ---");
-    let fixed = run_buildup_benchmark::<FixedTickOrderbook>(seed);
+    let fixed = run_buildup_benchmark::<FixedTickOrderbook>(seed, None);
     print_results(&fixed, cpu_ghz);
 
     println!("\n--- Structure-of-Arrays (SoA) ---");
-    let soa = run_buildup_benchmark::<SoAOrderbook>(seed);
+    let soa = run_buildup_benchmark::<SoAOrderbook>(seed, None);
     print_results(&soa, cpu_ghz);
 
     println!("\n--- Hybrid (Hot/Cold) ---");
-    let hybrid = run_buildup_benchmark::<HybridOrderbook>(seed);
+    let hybrid = run_buildup_benchmark::<HybridOrderbook>(seed, None);
     print_results(&hybrid, cpu_ghz);
 
     println!("\n--- Tree-Based ---");
-    let tree = run_buildup_benchmark::<TreeOrderbook>(seed);
+    let tree = run_buildup_benchmark::<TreeOrderbook>(seed, None);
     print_results(&tree, cpu_ghz);
 
     println!("\n--- Comparison: p50 latency by fill level (cycles) ---");
@@ -117,6 +123,70 @@ fn main() {
 
     println!("\n--- Warm-up Analysis (latency change from 0% to 100%) ---");
     print_warmup_analysis(&fixed, &soa, &hybrid, &tree, cpu_ghz);
+
+    println!(
+        "\n--- reserve({}) first: max latency by fill level (cycles) ---",
+        RESERVED_ORDERS_PER_LEVEL
+    );
+    let reserved = Some(RESERVED_ORDERS_PER_LEVEL);
+    print_reserve_comparison(
+        &[&fixed, &soa, &hybrid, &tree],
+        &[
+            &run_buildup_benchmark::<FixedTickOrderbook>(seed, reserved),
+            &run_buildup_benchmark::<SoAOrderbook>(seed, reserved),
+            &run_buildup_benchmark::<HybridOrderbook>(seed, reserved),
+            &run_buildup_benchmark::<TreeOrderbook>(seed, reserved),
+        ],
+    );
+
+    #[cfg(feature = "alloc-stats")]
+    {
+        println!("\n--- Allocations during build-up (no reserve) ---");
+        println!("{:<12} | {:>15} | {:>12} | {:>12}", "Book", "Allocating adds", "Allocations", "Bytes");
+        println!("{:-<60}", "");
+        print_allocations::<FixedTickOrderbook>("Fixed-Tick", seed);
+        print_allocations::<SoAOrderbook>("SoA", seed);
+        print_allocations::<HybridOrderbook>("Hybrid", seed);
+        print_allocations::<TreeOrderbook>("Tree", seed);
+    }
+}
+
+/// Add TOTAL_ORDERS resting orders to an empty book, bracketing each
+/// add_order with alloc_stats
+#[cfg(feature = "alloc-stats")]
+fn print_allocations<O: OrderbookTrait>(name: &str, seed: u64) {
+    use orderbook::perf::{count_allocations, AllocStats};
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut book = O::new();
+    let mut id_counter = IdCounter::new();
+    let mut allocating_adds = 0usize;
+    let mut total = AllocStats::default();
+
+    for _ in 0..TOTAL_ORDERS {
+        let side = if rng.random_bool(0.5) { Side::Bid } else { Side::Ask };
+        let offset = rng.random_range(0..PRICE_SPREAD);
+        let price_value = (MID_PRICE - PRICE_SPREAD / 2 + offset).clamp(1, 9999);
+        let order = Order::new(
+            Price::define(resting_price(price_value, side)),
+            Quantity::define(100),
+            side,
+            &mut id_counter,
+        );
+
+        let (result, stats) = count_allocations(|| book.add_order(order));
+        result.expect("Failed to add order");
+        if stats.allocations > 0 {
+            allocating_adds += 1;
+        }
+        total.allocations += stats.allocations;
+        total.bytes_allocated += stats.bytes_allocated;
+    }
+
+    println!(
+        "{:<12} | {:>15} | {:>12} | {:>12}",
+        name, allocating_adds, total.allocations, total.bytes_allocated
+    );
 }
 
 struct BuildupResults {
@@ -126,10 +196,14 @@ struct BuildupResults {
     max_at_level: [u64; 5],
 }
 
-fn run_buildup_benchmark<O: OrderbookTrait>(seed: u64) -> BuildupResults {
+/// `reserve` pre-sizes every level before the first order, when given
+fn run_buildup_benchmark<O: OrderbookTrait>(seed: u64, reserve: Option<usize>) -> BuildupResults {
     let mut rng = StdRng::seed_from_u64(seed);
 
     let mut book = O::new();
+    if let Some(orders_per_level) = reserve {
+        book.reserve(orders_per_level);
+    }
     let mut id_counter = IdCounter::new();
 
     let mut p50_at_level = [0u64; 5];
@@ -165,7 +239,7 @@ fn run_buildup_benchmark<O: OrderbookTrait>(seed: u64) -> BuildupResults {
             for j in i..measure_end {
                 let measure_side = if rng.random_bool(0.5) { Side::Bid } else { Side::Ask };
                 let order = Order::new(
-                    Price::define(prices[j]),
+                    Price::define(resting_price(prices[j], measure_side)),
                     Quantity::define(100),
                     measure_side,
                     &mut id_counter,
@@ -178,7 +252,7 @@ fn run_buildup_benchmark<O: OrderbookTrait>(seed: u64) -> BuildupResults {
                 orders_added += 1;
             }
 
-            if let Some(p) = tracker.precentiles() {
+            if let Some(p) = tracker.percentiles() {
                 p50_at_level[measurement_idx] = p.p50;
                 p99_at_level[measurement_idx] = p.p99;
                 max_at_level[measurement_idx] = p.max;
@@ -191,7 +265,7 @@ fn run_buildup_benchmark<O: OrderbookTrait>(seed: u64) -> BuildupResults {
         } else {
             // Just add the order without measuring
             let order = Order::new(
-                Price::define(price_value),
+                Price::define(resting_price(price_value, side)),
                 Quantity::define(100),
                 side,
                 &mut id_counter,
@@ -317,3 +391,34 @@ fn print_warmup_analysis(
     println!("  - Positive change = slower when full (data structure pressure)");
     println!("  - Near zero = consistent performance regardless of fill level");
 }
+
+/// Max latency per fill level without and with reserve(), implementations in
+/// the order Fixed-Tick, SoA, Hybrid, Tree
+fn print_reserve_comparison(plain: &[&BuildupResults; 4], reserved: &[&BuildupResults; 4]) {
+    println!(
+        "{:<8} | {:>21} | {:>21} | {:>21} | {:>21}",
+        "Fill %", "Fixed-Tick", "SoA", "Hybrid", "Tree"
+    );
+    println!("{:<8} | {:>21} | {:>21} | {:>21} | {:>21}", "", "plain / reserved", "", "", "");
+    println!("{:-<105}", "");
+
+    for (i, &pct) in MEASUREMENT_POINTS.iter().enumerate() {
+        let cells: Vec<String> = plain
+            .iter()
+            .zip(reserved)
+            .map(|(p, r)| format!("{:>9} / {:>9}", p.max_at_level[i], r.max_at_level[i]))
+            .collect();
+        println!(
+            "{:<8} | {:>21} | {:>21} | {:>21} | {:>21}",
+            format!("{}%", pct),
+            cells[0],
+            cells[1],
+            cells[2],
+            cells[3]
+        );
+    }
+
+    println!("\nInterpretation:");
+    println!("  - Reserved max below plain = growth reallocations were the spikes");
+    println!("  - Fixed-Tick/SoA pay for reserve up front across every slot");
+}
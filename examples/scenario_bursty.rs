@@ -8,6 +8,7 @@ use orderbook::orderbook::fixed_tick::orderbook::Orderbook as FixedTickOrderbook
 use orderbook::orderbook::hybrid::orderbook::Orderbook as HybridOrderbook;
 use orderbook::orderbook::tree::orderbook::Orderbook as TreeOrderbook;
 use orderbook::orderbook::OrderbookTrait;
+use orderbook::orderbook::command::Command;
 use orderbook::orderbook::SoA::orderbook::Orderbook as SoAOrderbook;
 use orderbook::perf::latency::{LatencyTracker, Percentiles};
 use orderbook::perf::{cycles_to_ns, get_cpu_frequency};
@@ -15,17 +16,13 @@ use orderbook::types::order::{IdCounter, Order, Side};
 use orderbook::types::price::Price;
 use orderbook::types::quantity::Quantity;
 use orderbook::analysis::{CsvExporter, ResultRow};
+use orderbook::workload::{BurstyGenerator, WorkloadGenerator};
 use rand::prelude::*;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 
-const MID_PRICE: u32 = 5_000;
-
-// Burst parameters
-const BURST_SIZE: usize = 500; // Orders per burst
-const BURST_PRICE_RANGE: u32 = 20; // Tight range during burst (±10 ticks)
-const QUIET_SIZE: usize = 50; // Orders during quiet period
-const QUIET_PRICE_RANGE: u32 = 2000; // Wide range during quiet (±1000 ticks)
+const BURST_SIZE: usize = BurstyGenerator::BURST_SIZE;
+const QUIET_SIZE: usize = BurstyGenerator::QUIET_SIZE;
 const NUM_CYCLES: usize = 10; // Number of burst-quiet cycles
 
 // ============================================================================
@@ -94,8 +91,8 @@ fn main() {
     }
 
     println!("\nPattern: {} cycles of [BURST({}) -> QUIET({})]", NUM_CYCLES, BURST_SIZE, QUIET_SIZE);
-    println!("  Burst: {} orders in ±{} ticks", BURST_SIZE, BURST_PRICE_RANGE / 2);
-    println!("  Quiet: {} orders in ±{} ticks", QUIET_SIZE, QUIET_PRICE_RANGE / 2);
+    println!("  Burst: {} orders in ±{} ticks", BURST_SIZE, BurstyGenerator::BURST_PRICE_RANGE / 2);
+    println!("  Quiet: {} orders in ±{} ticks", QUIET_SIZE, BurstyGenerator::QUIET_PRICE_RANGE / 2);
     println!("  Total: {} orders\n", NUM_CYCLES * (BURST_SIZE + QUIET_SIZE));
 
     let seed: u64 = 42;
@@ -167,53 +164,21 @@ fn scenario_bursty<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
     let mut market_tracker = LatencyTracker::new(200);
 
     let mut book = O::new();
-    let mut id_counter = IdCounter::new();
     let mut order_ids = Vec::with_capacity(total_orders);
+    let mut generator = BurstyGenerator::new(seed);
 
     // Phase 1: Add orders in burst-quiet cycles
-    for cycle in 0..NUM_CYCLES {
-        // BURST phase: tight clustering around mid
-        let burst_center = MID_PRICE + (cycle as u32 * 10) % 100; // Slight drift each cycle
-        for i in 0..BURST_SIZE {
-            let side = if i % 2 == 0 { Side::Bid } else { Side::Ask };
-            let offset = rng.random_range(0..BURST_PRICE_RANGE);
-            let price_value = (burst_center - BURST_PRICE_RANGE / 2 + offset).clamp(1, 9999);
-
-            let order = Order::new(
-                Price::define(price_value),
-                Quantity::define(100),
-                side,
-                &mut id_counter,
-            );
-            let order_id = order.id();
-
-            add_tracker.record(|| {
-                book.add_order(order).expect("Failed to add order");
-            });
-
-            order_ids.push(order_id);
-        }
+    for command in generator.commands().take(total_orders) {
+        let Command::Add(order) = command else {
+            unreachable!("workload generators only add");
+        };
+        let order_id = order.id();
+
+        add_tracker.record(|| {
+            book.add_order(order).expect("Failed to add order");
+        });
 
-        // QUIET phase: wide spread, sparse
-        for i in 0..QUIET_SIZE {
-            let side = if i % 2 == 0 { Side::Bid } else { Side::Ask };
-            let offset = rng.random_range(0..QUIET_PRICE_RANGE);
-            let price_value = (MID_PRICE - QUIET_PRICE_RANGE / 2 + offset).clamp(1, 9999);
-
-            let order = Order::new(
-                Price::define(price_value),
-                Quantity::define(100),
-                side,
-                &mut id_counter,
-            );
-            let order_id = order.id();
-
-            add_tracker.record(|| {
-                book.add_order(order).expect("Failed to add order");
-            });
-
-            order_ids.push(order_id);
-        }
+        order_ids.push(order_id);
     }
 
     // Phase 2: Cancel in random order (simulates chaotic cancellation patterns)
@@ -229,9 +194,10 @@ fn scenario_bursty<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
     let mut book = O::new();
     let mut id_counter = IdCounter::new();
 
-    // Populate with burst-like pattern
+    // Populate with burst-like pattern: the first cycle's burst
+    let mut generator = BurstyGenerator::new(seed);
     for _ in 0..200 {
-        let price_value = MID_PRICE - 10 + rng.random_range(0..20);
+        let price_value = generator.next_price();
         let order = Order::new(
             Price::define(price_value),
             Quantity::define(100),
@@ -242,18 +208,19 @@ fn scenario_bursty<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
     }
 
     for _ in 0..100 {
+        let taker_id = id_counter.next();
         market_tracker.record(|| {
-            let _ = book.execute_market_order(Side::Bid, Quantity::define(100));
+            let _ = book.execute_market_order(Side::Bid, Quantity::define(100), taker_id);
         });
     }
 
     ScenarioResults {
-        add_order: add_tracker.precentiles().expect("No add_order samples"),
+        add_order: add_tracker.percentiles().expect("No add_order samples"),
         cancel_order: cancel_tracker
-            .precentiles()
+            .percentiles()
             .expect("No cancel_order samples"),
         market_order: market_tracker
-            .precentiles()
+            .percentiles()
             .expect("No market_order samples"),
     }
 }
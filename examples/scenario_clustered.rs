@@ -8,6 +8,7 @@ use orderbook::orderbook::fixed_tick::orderbook::Orderbook as FixedTickOrderbook
 use orderbook::orderbook::hybrid::orderbook::Orderbook as HybridOrderbook;
 use orderbook::orderbook::tree::orderbook::Orderbook as TreeOrderbook;
 use orderbook::orderbook::OrderbookTrait;
+use orderbook::orderbook::command::Command;
 use orderbook::orderbook::SoA::orderbook::Orderbook as SoAOrderbook;
 use orderbook::perf::latency::{LatencyTracker, Percentiles};
 use orderbook::perf::{cycles_to_ns, get_cpu_frequency};
@@ -15,14 +16,12 @@ use orderbook::types::order::{IdCounter, Order, Side};
 use orderbook::types::price::Price;
 use orderbook::types::quantity::Quantity;
 use orderbook::analysis::{CsvExporter, ResultRow};
+use orderbook::workload::{ClusteredGenerator, MID_PRICE, WorkloadGenerator};
 use rand::prelude::*;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 
 const NUM_SAMPLES: usize = 10_000;
-const MID_PRICE: u32 = 5_000;
-const CLUSTER_RADIUS: u32 = 10; // ±10 ticks from mid
-const CLUSTER_PROBABILITY: f64 = 0.90; // 90% within cluster
 
 // ============================================================================
 // Scenario 4.1b: Clustered Around Mid Distribution
@@ -79,8 +78,8 @@ fn main() {
 
     println!("\nParameters:");
     println!("  Mid price: {}", MID_PRICE);
-    println!("  Cluster radius: ±{} ticks", CLUSTER_RADIUS);
-    println!("  Cluster probability: {}%", CLUSTER_PROBABILITY * 100.0);
+    println!("  Cluster radius: ±{} ticks", ClusteredGenerator::CLUSTER_RADIUS);
+    println!("  Cluster probability: {}%", ClusteredGenerator::CLUSTER_PROBABILITY * 100.0);
     println!("  Samples: {}\n", NUM_SAMPLES);
 
     let seed: u64 = 42;
@@ -140,20 +139,6 @@ struct ScenarioResults {
     market_order: Percentiles,
 }
 
-/// Generate a price following the clustered distribution:
-/// - 90% chance: within ±10 ticks of mid (4990-5010)
-/// - 10% chance: anywhere in full range (1-9999)
-fn generate_clustered_price(rng: &mut impl Rng) -> u32 {
-    if rng.random_bool(CLUSTER_PROBABILITY) {
-        // Clustered: mid ± radius
-        let offset = rng.random_range(0..=CLUSTER_RADIUS * 2);
-        (MID_PRICE - CLUSTER_RADIUS + offset).clamp(1, 9999)
-    } else {
-        // Scattered: full range
-        rng.random_range(1..10000)
-    }
-}
-
 fn scenario_clustered_mid<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
     let mut rng = StdRng::seed_from_u64(seed);
 
@@ -163,19 +148,13 @@ fn scenario_clustered_mid<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
 
     // Phase 1: Benchmark add_order with clustered prices
     let mut book = O::new();
-    let mut id_counter = IdCounter::new();
     let mut order_ids = Vec::with_capacity(NUM_SAMPLES);
+    let mut generator = ClusteredGenerator::new(seed);
 
-    for i in 0..NUM_SAMPLES {
-        let side = if i % 2 == 0 { Side::Bid } else { Side::Ask };
-        let price_value = generate_clustered_price(&mut rng);
-
-        let order = Order::new(
-            Price::define(price_value),
-            Quantity::define(100),
-            side,
-            &mut id_counter,
-        );
+    for command in generator.commands().take(NUM_SAMPLES) {
+        let Command::Add(order) = command else {
+            unreachable!("workload generators only add");
+        };
         let order_id = order.id();
 
         add_tracker.record(|| {
@@ -200,7 +179,7 @@ fn scenario_clustered_mid<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
 
     // Populate with clustered asks
     for _ in 0..200 {
-        let price_value = generate_clustered_price(&mut rng);
+        let price_value = generator.next_price();
         let order = Order::new(
             Price::define(price_value),
             Quantity::define(100),
@@ -211,18 +190,19 @@ fn scenario_clustered_mid<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
     }
 
     for _ in 0..100 {
+        let taker_id = id_counter.next();
         market_tracker.record(|| {
-            let _ = book.execute_market_order(Side::Bid, Quantity::define(100));
+            let _ = book.execute_market_order(Side::Bid, Quantity::define(100), taker_id);
         });
     }
 
     ScenarioResults {
-        add_order: add_tracker.precentiles().expect("No add_order samples"),
+        add_order: add_tracker.percentiles().expect("No add_order samples"),
         cancel_order: cancel_tracker
-            .precentiles()
+            .percentiles()
             .expect("No cancel_order samples"),
         market_order: market_tracker
-            .precentiles()
+            .percentiles()
             .expect("No market_order samples"),
     }
 }
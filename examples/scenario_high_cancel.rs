@@ -4,6 +4,7 @@
 /// Stresses cancel/update performance
 ///
 /// Run with: cargo run --release --example scenario_high_cancel
+use orderbook::orderbook::arena::orderbook::Orderbook as ArenaOrderbook;
 use orderbook::orderbook::fixed_tick::orderbook::Orderbook as FixedTickOrderbook;
 use orderbook::orderbook::hybrid::orderbook::Orderbook as HybridOrderbook;
 use orderbook::orderbook::tree::orderbook::Orderbook as TreeOrderbook;
@@ -122,11 +123,15 @@ fn main() {
     let tree = scenario_high_cancel::<TreeOrderbook>(seed);
     print_results(&tree, cpu_ghz);
 
+    println!("\n--- Fixed-Tick with Order Arena ---");
+    let arena = scenario_high_cancel::<ArenaOrderbook>(seed);
+    print_results(&arena, cpu_ghz);
+
     println!("\n--- Comparison (p50 latency in cycles) ---");
-    print_comparison(&fixed, &soa, &hybrid, &tree);
+    print_comparison(&fixed, &soa, &hybrid, &tree, &arena);
 
     println!("\n--- Cancel Performance Focus (most critical for HFT) ---");
-    print_cancel_focus(&fixed, &soa, &hybrid, &tree, cpu_ghz);
+    print_cancel_focus(&fixed, &soa, &hybrid, &tree, &arena, cpu_ghz);
 
     // Export results to CSV
     let scenario_name = "scenario_high_cancel";
@@ -135,6 +140,7 @@ fn main() {
         ("soa", &soa),
         ("hybrid", &hybrid),
         ("tree", &tree),
+        ("arena", &arena),
     ];
     match CsvExporter::create(scenario_name) {
         Ok(mut csv) => {
@@ -223,25 +229,27 @@ fn scenario_high_cancel<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
         // Execute market orders to clear them
         for _ in 0..TRADES_PER_ROUND / 2 {
             // Buy (takes from asks)
+            let taker_id = id_counter.next();
             market_tracker.record(|| {
-                let _ = book.execute_market_order(Side::Bid, Quantity::define(100));
+                let _ = book.execute_market_order(Side::Bid, Quantity::define(100), taker_id);
             });
         }
         for _ in 0..TRADES_PER_ROUND / 2 {
             // Sell (takes from bids)
+            let taker_id = id_counter.next();
             market_tracker.record(|| {
-                let _ = book.execute_market_order(Side::Ask, Quantity::define(100));
+                let _ = book.execute_market_order(Side::Ask, Quantity::define(100), taker_id);
             });
         }
     }
 
     ScenarioResults {
-        add_order: add_tracker.precentiles().expect("No add_order samples"),
+        add_order: add_tracker.percentiles().expect("No add_order samples"),
         cancel_order: cancel_tracker
-            .precentiles()
+            .percentiles()
             .expect("No cancel_order samples"),
         market_order: market_tracker
-            .precentiles()
+            .percentiles()
             .expect("No market_order samples"),
     }
 }
@@ -304,35 +312,39 @@ fn print_comparison(
     soa: &ScenarioResults,
     hybrid: &ScenarioResults,
     tree: &ScenarioResults,
+    arena: &ScenarioResults,
 ) {
     println!(
-        "{:<15} | {:>12} | {:>12} | {:>12} | {:>12}",
-        "Operation", "Fixed-Tick", "SoA", "Hybrid", "Tree"
+        "{:<15} | {:>12} | {:>12} | {:>12} | {:>12} | {:>12}",
+        "Operation", "Fixed-Tick", "SoA", "Hybrid", "Tree", "Arena"
     );
-    println!("{:-<75}", "");
+    println!("{:-<90}", "");
     println!(
-        "{:<15} | {:>10} cy | {:>10} cy | {:>10} cy | {:>10} cy",
+        "{:<15} | {:>10} cy | {:>10} cy | {:>10} cy | {:>10} cy | {:>10} cy",
         "add_order",
         fixed.add_order.p50,
         soa.add_order.p50,
         hybrid.add_order.p50,
-        tree.add_order.p50
+        tree.add_order.p50,
+        arena.add_order.p50
     );
     println!(
-        "{:<15} | {:>10} cy | {:>10} cy | {:>10} cy | {:>10} cy",
+        "{:<15} | {:>10} cy | {:>10} cy | {:>10} cy | {:>10} cy | {:>10} cy",
         "cancel_order",
         fixed.cancel_order.p50,
         soa.cancel_order.p50,
         hybrid.cancel_order.p50,
-        tree.cancel_order.p50
+        tree.cancel_order.p50,
+        arena.cancel_order.p50
     );
     println!(
-        "{:<15} | {:>10} cy | {:>10} cy | {:>10} cy | {:>10} cy",
+        "{:<15} | {:>10} cy | {:>10} cy | {:>10} cy | {:>10} cy | {:>10} cy",
         "market_order",
         fixed.market_order.p50,
         soa.market_order.p50,
         hybrid.market_order.p50,
-        tree.market_order.p50
+        tree.market_order.p50,
+        arena.market_order.p50
     );
 }
 
@@ -341,36 +353,40 @@ fn print_cancel_focus(
     soa: &ScenarioResults,
     hybrid: &ScenarioResults,
     tree: &ScenarioResults,
+    arena: &ScenarioResults,
     cpu_ghz: f64,
 ) {
     println!(
-        "{:<12} | {:>10} | {:>10} | {:>10} | {:>10}",
-        "Metric", "Fixed-Tick", "SoA", "Hybrid", "Tree"
+        "{:<12} | {:>10} | {:>10} | {:>10} | {:>10} | {:>10}",
+        "Metric", "Fixed-Tick", "SoA", "Hybrid", "Tree", "Arena"
     );
-    println!("{:-<70}", "");
+    println!("{:-<83}", "");
     println!(
-        "{:<12} | {:>8} cy | {:>8} cy | {:>8} cy | {:>8} cy",
+        "{:<12} | {:>8} cy | {:>8} cy | {:>8} cy | {:>8} cy | {:>8} cy",
         "p50",
         fixed.cancel_order.p50,
         soa.cancel_order.p50,
         hybrid.cancel_order.p50,
-        tree.cancel_order.p50
+        tree.cancel_order.p50,
+        arena.cancel_order.p50
     );
     println!(
-        "{:<12} | {:>8} cy | {:>8} cy | {:>8} cy | {:>8} cy",
+        "{:<12} | {:>8} cy | {:>8} cy | {:>8} cy | {:>8} cy | {:>8} cy",
         "p99",
         fixed.cancel_order.p99,
         soa.cancel_order.p99,
         hybrid.cancel_order.p99,
-        tree.cancel_order.p99
+        tree.cancel_order.p99,
+        arena.cancel_order.p99
     );
     println!(
-        "{:<12} | {:>7.0} ns | {:>7.0} ns | {:>7.0} ns | {:>7.0} ns",
+        "{:<12} | {:>7.0} ns | {:>7.0} ns | {:>7.0} ns | {:>7.0} ns | {:>7.0} ns",
         "p50 (ns)",
         cycles_to_ns(fixed.cancel_order.p50, cpu_ghz),
         cycles_to_ns(soa.cancel_order.p50, cpu_ghz),
         cycles_to_ns(hybrid.cancel_order.p50, cpu_ghz),
         cycles_to_ns(tree.cancel_order.p50, cpu_ghz),
+        cycles_to_ns(arena.cancel_order.p50, cpu_ghz),
     );
 
 }
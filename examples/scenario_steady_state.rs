@@ -14,6 +14,7 @@ use orderbook::perf::{cycles_to_ns, get_cpu_frequency};
 use orderbook::types::order::{IdCounter, Order, OrderId, Side};
 use orderbook::types::price::Price;
 use orderbook::types::quantity::Quantity;
+use orderbook::workload::resting_price;
 use orderbook::analysis::{CsvExporter, ResultRow};
 use rand::prelude::*;
 use rand::rngs::StdRng;
@@ -175,6 +176,7 @@ fn run_steady_state<O: OrderbookTrait>(seed: u64) -> SteadyStateResults {
         let side = if rng.random_bool(0.5) { Side::Bid } else { Side::Ask };
         let offset = rng.random_range(0..PRICE_SPREAD);
         let price_value = (MID_PRICE - PRICE_SPREAD / 2 + offset).clamp(1, 9999);
+        let price_value = resting_price(price_value, side);
 
         let order = Order::new(
             Price::define(price_value),
@@ -204,6 +206,7 @@ fn run_steady_state<O: OrderbookTrait>(seed: u64) -> SteadyStateResults {
             let side = if rng.random_bool(0.5) { Side::Bid } else { Side::Ask };
             let offset = rng.random_range(0..PRICE_SPREAD);
             let price_value = (MID_PRICE - PRICE_SPREAD / 2 + offset).clamp(1, 9999);
+            let price_value = resting_price(price_value, side);
 
             let order = Order::new(
                 Price::define(price_value),
@@ -232,19 +235,20 @@ fn run_steady_state<O: OrderbookTrait>(seed: u64) -> SteadyStateResults {
             // Market order
             let side = if rng.random_bool(0.5) { Side::Bid } else { Side::Ask };
 
+            let taker_id = id_counter.next();
             market_tracker.record(|| {
-                let _ = book.execute_market_order(side, Quantity::define(100));
+                let _ = book.execute_market_order(side, Quantity::define(100), taker_id);
             });
         }
     }
 
     SteadyStateResults {
-        add_order: add_tracker.precentiles().expect("No add_order samples"),
+        add_order: add_tracker.percentiles().expect("No add_order samples"),
         cancel_order: cancel_tracker
-            .precentiles()
+            .percentiles()
             .expect("No cancel_order samples"),
         market_order: market_tracker
-            .precentiles()
+            .percentiles()
             .expect("No market_order samples"),
     }
 }
@@ -14,7 +14,7 @@ use orderbook::perf::{cycles_to_ns, get_cpu_frequency};
 use orderbook::types::order::{IdCounter, Order, Side};
 use orderbook::types::price::Price;
 use orderbook::analysis::{CsvExporter, ResultRow};
-use orderbook::types::quantity::Quantity;
+use orderbook::types::quantity::{QtyValue, Quantity};
 
 const MID_PRICE: u32 = 5_000;
 
@@ -188,7 +188,7 @@ fn populate_book<O: OrderbookTrait>(book: &mut O, id_counter: &mut IdCounter, si
         for _ in 0..ORDERS_PER_LEVEL {
             let order = Order::new(
                 Price::define(price_value),
-                Quantity::define(QTY_PER_ORDER),
+                Quantity::define(QTY_PER_ORDER as QtyValue),
                 side,
                 id_counter,
             );
@@ -202,51 +202,58 @@ fn run_sweep_benchmark<O: OrderbookTrait>() -> SweepResults {
     let mut medium_tracker = LatencyTracker::new(NUM_SWEEPS);
     let mut large_tracker = LatencyTracker::new(NUM_SWEEPS);
 
+    // One book for every trial: clear() empties it but keeps the level
+    // allocations, so trials do not pay for (or measure) a fresh book
+    let mut book = O::new();
+
     // Small sweeps
     for _ in 0..NUM_SWEEPS {
-        let mut book = O::new();
+        book.clear();
         let mut id_counter = IdCounter::new();
         populate_book(&mut book, &mut id_counter, Side::Ask);
 
         let sweep_qty = SMALL_SWEEP_LEVELS * QTY_PER_ORDER;
+        let taker_id = id_counter.next();
         small_tracker.record(|| {
-            book.execute_market_order(Side::Bid, Quantity::define(sweep_qty))
-                .expect("Failed to execute sweep");
+            let result = book.execute_market_order(Side::Bid, Quantity::define(sweep_qty as QtyValue), taker_id);
+            assert!(result.is_fully_filled(), "Failed to execute sweep");
         });
     }
 
     // Medium sweeps
     for _ in 0..NUM_SWEEPS {
-        let mut book = O::new();
+        book.clear();
         let mut id_counter = IdCounter::new();
         populate_book(&mut book, &mut id_counter, Side::Ask);
 
         let sweep_qty = MEDIUM_SWEEP_LEVELS * QTY_PER_ORDER;
+        let taker_id = id_counter.next();
         medium_tracker.record(|| {
-            book.execute_market_order(Side::Bid, Quantity::define(sweep_qty))
-                .expect("Failed to execute sweep");
+            let result = book.execute_market_order(Side::Bid, Quantity::define(sweep_qty as QtyValue), taker_id);
+            assert!(result.is_fully_filled(), "Failed to execute sweep");
         });
     }
 
     // Large sweeps
     for _ in 0..NUM_SWEEPS {
-        let mut book = O::new();
+        book.clear();
         let mut id_counter = IdCounter::new();
         populate_book(&mut book, &mut id_counter, Side::Ask);
 
         let sweep_qty = LARGE_SWEEP_LEVELS * QTY_PER_ORDER;
+        let taker_id = id_counter.next();
         large_tracker.record(|| {
-            book.execute_market_order(Side::Bid, Quantity::define(sweep_qty))
-                .expect("Failed to execute sweep");
+            let result = book.execute_market_order(Side::Bid, Quantity::define(sweep_qty as QtyValue), taker_id);
+            assert!(result.is_fully_filled(), "Failed to execute sweep");
         });
     }
 
     SweepResults {
-        small_sweep: small_tracker.precentiles().expect("No small sweep samples"),
+        small_sweep: small_tracker.percentiles().expect("No small sweep samples"),
         medium_sweep: medium_tracker
-            .precentiles()
+            .percentiles()
             .expect("No medium sweep samples"),
-        large_sweep: large_tracker.precentiles().expect("No large sweep samples"),
+        large_sweep: large_tracker.percentiles().expect("No large sweep samples"),
     }
 }
 
@@ -2,6 +2,7 @@ use orderbook::orderbook::fixed_tick::orderbook::Orderbook as FixedTickOrderbook
 use orderbook::orderbook::hybrid::orderbook::Orderbook as HybridOrderbook;
 use orderbook::orderbook::tree::orderbook::Orderbook as TreeOrderbook;
 use orderbook::orderbook::OrderbookTrait;
+use orderbook::orderbook::command::Command;
 use orderbook::orderbook::SoA::orderbook::Orderbook as SoAOrderbook;
 use orderbook::perf::latency::{LatencyTracker, Percentiles};
 use orderbook::perf::{cycles_to_ns, get_cpu_frequency};
@@ -9,14 +10,12 @@ use orderbook::types::order::{IdCounter, Order, Side};
 use orderbook::types::price::Price;
 use orderbook::types::quantity::Quantity;
 use orderbook::analysis::{CsvExporter, ResultRow};
+use orderbook::workload::{UniformGenerator, WorkloadGenerator};
 use rand::prelude::*;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 
 const NUM_SAMPLES: usize = 10_000;
-const PRICE_RANGE_MIN: u32 = 1;
-const PRICE_RANGE_MAX: u32 = 10_000;
-
 
 fn main() {
     println!("=== Scenario 4.1a: Uniform Random Distribution ===\n");
@@ -148,21 +147,13 @@ fn scenario_uniform_random<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
 
     // Phase 1: Benchmark add_order with uniform random prices
     let mut book = O::new();
-    let mut id_counter = IdCounter::new();
     let mut order_ids = Vec::with_capacity(NUM_SAMPLES);
+    let mut generator = UniformGenerator::new(seed);
 
-    for i in 0..NUM_SAMPLES {
-        let side = if i % 2 == 0 { Side::Bid } else { Side::Ask };
-
-        // Uniform random price across full range
-        let price_value = rng.random_range(PRICE_RANGE_MIN..PRICE_RANGE_MAX);
-
-        let order = Order::new(
-            Price::define(price_value),
-            Quantity::define(100),
-            side,
-            &mut id_counter,
-        );
+    for command in generator.commands().take(NUM_SAMPLES) {
+        let Command::Add(order) = command else {
+            unreachable!("workload generators only add");
+        };
         let order_id = order.id();
 
         add_tracker.record(|| {
@@ -190,7 +181,7 @@ fn scenario_uniform_random<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
     let mut id_counter = IdCounter::new();
 
     for _ in 0..200 {
-        let price_value = rng.random_range(PRICE_RANGE_MIN..PRICE_RANGE_MAX);
+        let price_value = generator.next_price();
         let order = Order::new(
             Price::define(price_value),
             Quantity::define(100),
@@ -202,18 +193,19 @@ fn scenario_uniform_random<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
 
     // Execute market orders - request exactly 100 to match one order fully
     for _ in 0..100 {
+        let taker_id = id_counter.next();
         market_tracker.record(|| {
-            let _ = book.execute_market_order(Side::Bid, Quantity::define(100));
+            let _ = book.execute_market_order(Side::Bid, Quantity::define(100), taker_id);
         });
     }
 
     ScenarioResults {
-        add_order: add_tracker.precentiles().expect("No add_order samples"),
+        add_order: add_tracker.percentiles().expect("No add_order samples"),
         cancel_order: cancel_tracker
-            .precentiles()
+            .percentiles()
             .expect("No cancel_order samples"),
         market_order: market_tracker
-            .precentiles()
+            .percentiles()
             .expect("No market_order samples"),
     }
 }
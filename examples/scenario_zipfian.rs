@@ -8,6 +8,7 @@ use orderbook::orderbook::fixed_tick::orderbook::Orderbook as FixedTickOrderbook
 use orderbook::orderbook::hybrid::orderbook::Orderbook as HybridOrderbook;
 use orderbook::orderbook::tree::orderbook::Orderbook as TreeOrderbook;
 use orderbook::orderbook::OrderbookTrait;
+use orderbook::orderbook::command::Command;
 use orderbook::orderbook::SoA::orderbook::Orderbook as SoAOrderbook;
 use orderbook::perf::latency::{LatencyTracker, Percentiles};
 use orderbook::perf::{cycles_to_ns, get_cpu_frequency};
@@ -15,15 +16,12 @@ use orderbook::types::order::{IdCounter, Order, Side};
 use orderbook::types::price::Price;
 use orderbook::types::quantity::Quantity;
 use orderbook::analysis::{CsvExporter, ResultRow};
+use orderbook::workload::{ZipfianGenerator, MID_PRICE, WorkloadGenerator};
 use rand::prelude::*;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
-use rand_distr::Zipf;
 
 const NUM_SAMPLES: usize = 10_000;
-const MID_PRICE: u32 = 5_000;
-const NUM_PRICE_LEVELS: f64 = 200.0; // Number of distinct price levels around mid
-const ZIPF_EXPONENT: f64 = 1.0; // Classic Zipf distribution (s=1)
 
 // ============================================================================
 // Scenario 4.1c: Zipfian Distribution
@@ -88,13 +86,13 @@ fn main() {
 
     println!("\nParameters:");
     println!("  Mid price: {}", MID_PRICE);
-    println!("  Price levels: {} (around mid)", NUM_PRICE_LEVELS);
-    println!("  Zipf exponent: {} (classic Zipf)", ZIPF_EXPONENT);
+    println!("  Price levels: {} (around mid)", ZipfianGenerator::PRICE_LEVELS);
+    println!("  Zipf exponent: {} (classic Zipf)", ZipfianGenerator::EXPONENT);
     println!("  Samples: {}", NUM_SAMPLES);
 
     // Show distribution preview
     println!("\nDistribution preview (expected hits per rank):");
-    let num_levels = NUM_PRICE_LEVELS as u64;
+    let num_levels = ZipfianGenerator::PRICE_LEVELS as u64;
     let total_weight: f64 = (1..=num_levels).map(|k| 1.0 / (k as f64)).sum();
     for rank in [1u64, 2, 5, 10, 50, 100, 200].iter() {
         if *rank <= num_levels {
@@ -162,36 +160,8 @@ struct ScenarioResults {
     market_order: Percentiles,
 }
 
-/// Generate a price following Zipfian distribution around mid-price
-/// Rank 1 = mid price, Rank 2 = mid±1, etc.
-fn generate_zipfian_price(rng: &mut impl Rng, zipf: &Zipf<f64>) -> u32 {
-    // Sample a rank (1 to NUM_PRICE_LEVELS)
-    let rank = zipf.sample(rng) as u32;
-
-    // Convert rank to price offset from mid
-    // Rank 1 -> offset 0 (mid price)
-    // Rank 2 -> offset 1 (mid + 1)
-    // Rank 3 -> offset -1 (mid - 1)
-    // Rank 4 -> offset 2 (mid + 2)
-    // etc. (alternating sides)
-    let offset = if rank == 1 {
-        0i32
-    } else {
-        let half = ((rank - 1) / 2 + 1) as i32;
-        if rank % 2 == 0 {
-            half
-        } else {
-            -half
-        }
-    };
-
-    let price = (MID_PRICE as i32 + offset).clamp(1, 9999) as u32;
-    price
-}
-
 fn scenario_zipfian<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
     let mut rng = StdRng::seed_from_u64(seed);
-    let zipf = Zipf::new(NUM_PRICE_LEVELS, ZIPF_EXPONENT).expect("Invalid Zipf parameters");
 
     let mut add_tracker = LatencyTracker::new(NUM_SAMPLES);
     let mut cancel_tracker = LatencyTracker::new(NUM_SAMPLES);
@@ -199,19 +169,13 @@ fn scenario_zipfian<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
 
     // Phase 1: Benchmark add_order with Zipfian prices
     let mut book = O::new();
-    let mut id_counter = IdCounter::new();
     let mut order_ids = Vec::with_capacity(NUM_SAMPLES);
+    let mut generator = ZipfianGenerator::new(seed);
 
-    for i in 0..NUM_SAMPLES {
-        let side = if i % 2 == 0 { Side::Bid } else { Side::Ask };
-        let price_value = generate_zipfian_price(&mut rng, &zipf);
-
-        let order = Order::new(
-            Price::define(price_value),
-            Quantity::define(100),
-            side,
-            &mut id_counter,
-        );
+    for command in generator.commands().take(NUM_SAMPLES) {
+        let Command::Add(order) = command else {
+            unreachable!("workload generators only add");
+        };
         let order_id = order.id();
 
         add_tracker.record(|| {
@@ -235,7 +199,7 @@ fn scenario_zipfian<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
     let mut id_counter = IdCounter::new();
 
     for _ in 0..200 {
-        let price_value = generate_zipfian_price(&mut rng, &zipf);
+        let price_value = generator.next_price();
         let order = Order::new(
             Price::define(price_value),
             Quantity::define(100),
@@ -246,18 +210,19 @@ fn scenario_zipfian<O: OrderbookTrait>(seed: u64) -> ScenarioResults {
     }
 
     for _ in 0..100 {
+        let taker_id = id_counter.next();
         market_tracker.record(|| {
-            let _ = book.execute_market_order(Side::Bid, Quantity::define(100));
+            let _ = book.execute_market_order(Side::Bid, Quantity::define(100), taker_id);
         });
     }
 
     ScenarioResults {
-        add_order: add_tracker.precentiles().expect("No add_order samples"),
+        add_order: add_tracker.percentiles().expect("No add_order samples"),
         cancel_order: cancel_tracker
-            .precentiles()
+            .percentiles()
             .expect("No cancel_order samples"),
         market_order: market_tracker
-            .precentiles()
+            .percentiles()
             .expect("No market_order samples"),
     }
 }
@@ -0,0 +1,87 @@
+//! Drives one book implementation with a byte-encoded command stream and
+//! checks its invariants after every command
+//!
+//! Layout: byte 0 picks the implementation, byte 1 the STP and match modes,
+//! then each 8-byte chunk is one command:
+//!   [kind, flags, price lo, price hi, qty (4 bytes, little endian)]
+//! kind % 4: 0/1 limit order, 2 cancel, 3 market order
+//! flags: bit 0 side, bits 1-2 time in force, bit 3 price near the middle
+//! of the grid (so the windowed book accepts it), bits 4-7 account
+//! A trailing partial chunk is ignored.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use orderbook::orderbook::arena::orderbook::Orderbook as Arena;
+use orderbook::orderbook::fixed_tick::orderbook::Orderbook as FixedTick;
+use orderbook::orderbook::hybrid::orderbook::Orderbook as Hybrid;
+use orderbook::orderbook::tree::orderbook::Orderbook as Tree;
+use orderbook::orderbook::windowed::WindowedArrayOrderbook as Windowed;
+use orderbook::orderbook::SoA::orderbook::Orderbook as SoA;
+use orderbook::orderbook::{BookConfig, MatchMode, OrderbookTrait, StpMode};
+use orderbook::types::order::{Order, OrderId, Side, TimeInForce};
+use orderbook::types::price::Price;
+use orderbook::types::quantity::Quantity;
+
+const TAKER_ID: OrderId = u64::MAX;
+
+fuzz_target!(|data: &[u8]| {
+    let [implementation, modes, commands @ ..] = data else {
+        return;
+    };
+    let config = BookConfig {
+        stp: [StpMode::Off, StpMode::CancelResting, StpMode::Skip][(modes % 3) as usize],
+        match_mode: if modes & 0x80 == 0 { MatchMode::Fifo } else { MatchMode::ProRata },
+        ..BookConfig::default()
+    };
+
+    match implementation % 6 {
+        0 => run::<Tree>(config, commands),
+        1 => run::<FixedTick>(config, commands),
+        2 => run::<SoA>(config, commands),
+        3 => run::<Hybrid>(config, commands),
+        4 => run::<Arena>(config, commands),
+        _ => run::<Windowed>(config, commands),
+    }
+});
+
+fn run<O: OrderbookTrait>(config: BookConfig, commands: &[u8]) {
+    let mut book = O::with_config(config).unwrap();
+    let mut next_id: OrderId = 0;
+    // Ids handed out so far, resting or not: cancels may name dead orders
+    let mut issued: Vec<OrderId> = Vec::new();
+
+    for chunk in commands.chunks_exact(8) {
+        let [kind, flags, price_lo, price_hi, qty @ ..] = chunk else {
+            unreachable!()
+        };
+        let side = if flags & 1 == 0 { Side::Bid } else { Side::Ask };
+        let raw_price = u16::from_le_bytes([*price_lo, *price_hi]) as u32;
+        // Zero and off-grid values stay in on purpose: they must be rejected
+        let price = if flags & 0b1000 == 0 { raw_price % 10_001 } else { 4_900 + raw_price % 200 };
+        let quantity = Quantity::define(u32::from_le_bytes(qty.try_into().unwrap()));
+
+        match kind % 4 {
+            0 | 1 => {
+                let time_in_force = [TimeInForce::GTC, TimeInForce::IOC, TimeInForce::FOK, TimeInForce::GTC]
+                    [((flags >> 1) & 0b11) as usize];
+                let order = Order::from_parts(next_id, Price::define(price), quantity, side)
+                    .with_time_in_force(time_in_force)
+                    .with_account((flags >> 4) as u32);
+                issued.push(next_id);
+                next_id += 1;
+                let _ = book.add_order(order);
+            }
+            2 if !issued.is_empty() => {
+                let _ = book.cancel_order(issued[raw_price as usize % issued.len()]);
+            }
+            2 => {}
+            _ => {
+                book.execute_market_order(side, quantity, TAKER_ID);
+            }
+        }
+
+        if let Err(problems) = book.validate() {
+            panic!("invariants broken after {:?}: {:#?}", chunk, problems);
+        }
+    }
+}
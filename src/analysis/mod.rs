@@ -1,4 +1,8 @@
+use crate::orderbook::{BookSnapshot, Fill};
 use crate::perf::{cycles_to_ns, latency::Percentiles};
+use crate::types::order::Side;
+use crate::types::price::Price;
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::{BufWriter, Write};
 
@@ -57,3 +61,424 @@ impl CsvExporter {
         )
     }
 }
+
+/// Linear histogram of `samples`: up to `bucket_count` equal-width buckets from
+/// the smallest sample to the largest.
+///
+/// Returns `(upper_bound, count)` pairs in ascending order, upper bounds
+/// inclusive, empty buckets included so the shape plots as-is. Raw samples
+/// come from `LatencyTracker::samples`.
+pub fn histogram(samples: &[u64], bucket_count: usize) -> Vec<(u64, usize)> {
+    let (Some(&min), Some(&max)) = (samples.iter().min(), samples.iter().max()) else {
+        return Vec::new();
+    };
+    if bucket_count == 0 {
+        return Vec::new();
+    }
+
+    let width = (max - min + 1).div_ceil(bucket_count as u64);
+    let used = (max - min + 1).div_ceil(width) as usize;
+    let mut buckets: Vec<(u64, usize)> = (0..used as u64)
+        .map(|i| ((min + (i + 1) * width - 1).min(max), 0))
+        .collect();
+    for &sample in samples {
+        buckets[((sample - min) / width) as usize].1 += 1;
+    }
+    buckets
+}
+
+/// Histogram with bucket bounds growing geometrically from the smallest
+/// sample to the largest, so a long tail stays readable next to the body.
+///
+/// Same `(upper_bound, count)` output as `histogram`; buckets that would round
+/// to the same bound are merged, so there can be fewer than `bucket_count`.
+pub fn log_histogram(samples: &[u64], bucket_count: usize) -> Vec<(u64, usize)> {
+    let (Some(&min), Some(&max)) = (samples.iter().min(), samples.iter().max()) else {
+        return Vec::new();
+    };
+    if bucket_count == 0 {
+        return Vec::new();
+    }
+
+    let low = min.max(1) as f64;
+    let ratio = (max as f64 / low).powf(1.0 / bucket_count as f64);
+    let mut bounds: Vec<u64> = (1..bucket_count)
+        .map(|i| (low * ratio.powi(i as i32)).ceil() as u64)
+        .filter(|&bound| bound < max)
+        .collect();
+    bounds.push(max);
+    bounds.dedup();
+
+    let mut buckets: Vec<(u64, usize)> = bounds.into_iter().map(|bound| (bound, 0)).collect();
+    for &sample in samples {
+        let i = buckets.partition_point(|&(bound, _)| bound < sample);
+        buckets[i].1 += 1;
+    }
+    buckets
+}
+
+/// Write histogram buckets as `upper_bound,count` CSV rows, header first, for
+/// gnuplot or pandas.
+pub fn histogram_to_csv(mut writer: impl Write, buckets: &[(u64, usize)]) -> std::io::Result<()> {
+    writeln!(writer, "upper_bound,count")?;
+    for (upper_bound, count) in buckets {
+        writeln!(writer, "{},{}", upper_bound, count)?;
+    }
+    writer.flush()
+}
+
+/// Outcome of a Mann-Whitney U test between two sets of latency samples.
+///
+/// The test asks whether one sample tends to be larger than the other without
+/// assuming any distribution, which suits skewed, long-tailed latencies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    pub median_a: f64,
+    pub median_b: f64,
+    /// U statistic for `a`: how many (a, b) pairs have a > b, ties counting half
+    pub u: f64,
+    /// Standard score of `u` under the null hypothesis, tie-corrected
+    pub z: f64,
+    /// Two-sided p-value from the normal approximation
+    pub p_value: f64,
+}
+
+impl Comparison {
+    /// Is the difference unlikely to be noise at significance level `alpha` (e.g. 0.05)?
+    pub fn is_significant(&self, alpha: f64) -> bool {
+        self.p_value < alpha
+    }
+}
+
+/// Mann-Whitney U test of `a` against `b`.
+///
+/// Uses the normal approximation, which is accurate once each side has a few
+/// dozen samples; benchmark runs have thousands. Both slices must be non-empty.
+pub fn compare(a: &[u64], b: &[u64]) -> Comparison {
+    assert!(!a.is_empty() && !b.is_empty(), "Both sides need samples to compare");
+
+    // Rank the pooled samples, ties sharing the average of their ranks
+    let mut pooled: Vec<(u64, bool)> = a.iter().map(|&s| (s, true)).chain(b.iter().map(|&s| (s, false))).collect();
+    pooled.sort_unstable_by_key(|&(s, _)| s);
+
+    let n = pooled.len() as f64;
+    let mut rank_sum_a = 0.0;
+    let mut tie_term = 0.0;
+    let mut start = 0;
+    while start < pooled.len() {
+        let end = start + pooled[start..].iter().take_while(|&&(s, _)| s == pooled[start].0).count();
+        let ties = (end - start) as f64;
+        let average_rank = (start + end + 1) as f64 / 2.0;
+        let from_a = pooled[start..end].iter().filter(|&&(_, is_a)| is_a).count() as f64;
+        rank_sum_a += average_rank * from_a;
+        tie_term += ties.powi(3) - ties;
+        start = end;
+    }
+
+    let (n_a, n_b) = (a.len() as f64, b.len() as f64);
+    let u = rank_sum_a - n_a * (n_a + 1.0) / 2.0;
+    let mean = n_a * n_b / 2.0;
+    let variance = n_a * n_b / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+
+    // Every sample equal: nothing to tell apart
+    let (z, p_value) = if variance > 0.0 {
+        let z = (u - mean) / variance.sqrt();
+        (z, (2.0 * (1.0 - standard_normal_cdf(z.abs()))).min(1.0))
+    } else {
+        (0.0, 1.0)
+    };
+
+    Comparison { median_a: median(a), median_b: median(b), u, z, p_value }
+}
+
+fn median(samples: &[u64]) -> f64 {
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) as f64 / 2.0
+    } else {
+        sorted[mid] as f64
+    }
+}
+
+/// One way two L2 snapshots disagree, as reported by `diff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelDiff {
+    /// The best price on `side` differs; `None` is an empty side.
+    Best { side: Side, a: Option<Price>, b: Option<Price> },
+    /// Both snapshots have the level, with different total quantities.
+    Quantity { side: Side, price: Price, a: u64, b: u64 },
+    /// Only `a` has a level at `price`.
+    OnlyInA { side: Side, price: Price, qty: u64 },
+    /// Only `b` has a level at `price`.
+    OnlyInB { side: Side, price: Price, qty: u64 },
+}
+
+/// Every difference between snapshots `a` and `b`; empty when they match.
+///
+/// Bids are reported before asks. Each side starts with a `Best` entry if the
+/// best prices differ, then lists its mismatched levels best price first.
+/// Take both snapshots at the same depth: a level one of them cut off shows
+/// up as present in only the other.
+pub fn diff(a: &BookSnapshot, b: &BookSnapshot) -> Vec<LevelDiff> {
+    let mut diffs = Vec::new();
+    for (side, a_levels, b_levels) in [(Side::Bid, &a.bids, &b.bids), (Side::Ask, &a.asks, &b.asks)] {
+        let (best_a, best_b) = (a_levels.first().map(|&(p, _)| p), b_levels.first().map(|&(p, _)| p));
+        if best_a != best_b {
+            diffs.push(LevelDiff::Best { side, a: best_a, b: best_b });
+        }
+
+        let mut levels: BTreeMap<Price, (Option<u64>, Option<u64>)> = BTreeMap::new();
+        for &(price, qty) in a_levels {
+            levels.entry(price).or_default().0 = Some(qty);
+        }
+        for &(price, qty) in b_levels {
+            levels.entry(price).or_default().1 = Some(qty);
+        }
+
+        let mismatches = levels.into_iter().filter_map(|(price, quantities)| match quantities {
+            (Some(a), Some(b)) if a == b => None,
+            (Some(a), Some(b)) => Some(LevelDiff::Quantity { side, price, a, b }),
+            (Some(qty), None) => Some(LevelDiff::OnlyInA { side, price, qty }),
+            (None, Some(qty)) => Some(LevelDiff::OnlyInB { side, price, qty }),
+            (None, None) => None,
+        });
+        // Best first: bids run from the highest price down
+        match side {
+            Side::Bid => diffs.extend(mismatches.rev()),
+            Side::Ask => diffs.extend(mismatches),
+        }
+    }
+    diffs
+}
+
+/// Combine per-shard L2 snapshots of one instrument into a single book.
+///
+/// Quantities at the same price on the same side are summed, so shards may
+/// overlap; each side of the result is re-sorted best first. A price resting
+/// as a bid in one shard and an ask in another cannot come from a consistent
+/// book and is an error. Snapshots taken at a limited depth merge into the
+/// union of their levels, which may miss levels a deeper snapshot would show.
+pub fn merge_snapshots(snapshots: &[BookSnapshot]) -> Result<BookSnapshot, String> {
+    let mut bids: BTreeMap<Price, u64> = BTreeMap::new();
+    let mut asks: BTreeMap<Price, u64> = BTreeMap::new();
+    for snapshot in snapshots {
+        for &(price, qty) in &snapshot.bids {
+            *bids.entry(price).or_default() += qty;
+        }
+        for &(price, qty) in &snapshot.asks {
+            *asks.entry(price).or_default() += qty;
+        }
+    }
+
+    if let Some(price) = bids.keys().find(|price| asks.contains_key(price)) {
+        return Err(format!("Price {} rests on both sides across shards", price.value()));
+    }
+
+    Ok(BookSnapshot {
+        bids: bids.into_iter().rev().collect(),
+        asks: asks.into_iter().collect(),
+    })
+}
+
+/// Price impact of a fill sequence, as measured by `price_impact`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImpactReport {
+    /// Buyer-initiated volume minus seller-initiated volume.
+    pub signed_volume: i64,
+    /// Last fill price minus the first, in price units.
+    pub price_change: i64,
+    /// `price_change / signed_volume`: the realized move per unit of net
+    /// volume. `None` when the flow nets to zero.
+    pub impact_per_unit: Option<f64>,
+    /// Kyle's lambda: least-squares slope, through the origin, of each fill's
+    /// price change since the previous fill against its signed quantity.
+    /// `None` with fewer than two fills.
+    pub lambda: Option<f64>,
+}
+
+/// Regress price changes against signed volume over `fills`, in execution
+/// order.
+///
+/// A fill's sign comes from its aggressor: a buyer lifting the offer pushes
+/// volume up, a seller hitting the bid pushes it down. A positive coefficient
+/// means prices follow order flow, as they should when takers move the book.
+pub fn price_impact(fills: &[Fill]) -> ImpactReport {
+    let signed = |fill: &Fill| match fill.aggressor_side {
+        Side::Bid => fill.quantity.value() as i64,
+        Side::Ask => -(fill.quantity.value() as i64),
+    };
+    let price = |fill: &Fill| fill.price.value() as i64;
+
+    let signed_volume: i64 = fills.iter().map(signed).sum();
+    let price_change = match (fills.first(), fills.last()) {
+        (Some(first), Some(last)) => price(last) - price(first),
+        _ => 0,
+    };
+    let impact_per_unit = (signed_volume != 0).then(|| price_change as f64 / signed_volume as f64);
+
+    // Σ Δp·q / Σ q² over consecutive pairs; every fill has a non-zero quantity
+    let (covariance, variance) = fills.windows(2).fold((0.0, 0.0), |(cov, var), pair| {
+        let (dp, q) = ((price(&pair[1]) - price(&pair[0])) as f64, signed(&pair[1]) as f64);
+        (cov + dp * q, var + q * q)
+    });
+    let lambda = (variance > 0.0).then(|| covariance / variance);
+
+    ImpactReport { signed_volume, price_change, impact_per_unit, lambda }
+}
+
+/// Φ(x) via the Abramowitz–Stegun 7.1.26 erf approximation (error < 1.5e-7)
+fn standard_normal_cdf(x: f64) -> f64 {
+    let t = x.abs() / std::f64::consts::SQRT_2;
+    let k = 1.0 / (1.0 + 0.3275911 * t);
+    let poly = k * (0.254829592 + k * (-0.284496736 + k * (1.421413741 + k * (-1.453152027 + k * 1.061405429))));
+    let erf = 1.0 - poly * (-t * t).exp();
+    if x >= 0.0 { 0.5 * (1.0 + erf) } else { 0.5 * (1.0 - erf) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::quantity::{QtyValue, Quantity};
+
+    /// Number of separate runs of non-empty buckets.
+    fn populated_regions(buckets: &[(u64, usize)]) -> usize {
+        let mut regions = 0;
+        let mut in_region = false;
+        for &(_, count) in buckets {
+            if count > 0 && !in_region {
+                regions += 1;
+            }
+            in_region = count > 0;
+        }
+        regions
+    }
+
+    #[test]
+    fn test_histograms_of_bimodal_samples() {
+        // Fast path around 100 cycles, slow path around 10_000
+        let samples: Vec<u64> =
+            (0..1_000).map(|i| if i % 4 == 0 { 10_000 + i % 200 } else { 100 + i % 20 }).collect();
+
+        for buckets in [histogram(&samples, 50), log_histogram(&samples, 50)] {
+            assert_eq!(buckets.iter().map(|&(_, count)| count).sum::<usize>(), samples.len());
+            assert_eq!(populated_regions(&buckets), 2, "{:?}", buckets);
+            assert!(buckets.windows(2).all(|w| w[0].0 < w[1].0));
+            assert_eq!(buckets.last().unwrap().0, *samples.iter().max().unwrap());
+        }
+        assert_eq!(histogram(&samples, 50).len(), 50);
+        assert!(histogram(&[], 10).is_empty());
+
+        // One distinct value: one bucket holding everything
+        assert_eq!(histogram(&[7; 5], 10), vec![(7, 5)]);
+        assert_eq!(log_histogram(&[7; 5], 10), vec![(7, 5)]);
+
+        let mut csv = Vec::new();
+        histogram_to_csv(&mut csv, &[(10, 3), (20, 0)]).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "upper_bound,count\n10,3\n20,0\n");
+    }
+
+    #[test]
+    fn test_compare_separates_distinct_distributions() {
+        // Same shape, b shifted up by 20 cycles
+        let a: Vec<u64> = (0..500).map(|i| 100 + i % 37).collect();
+        let b: Vec<u64> = a.iter().map(|s| s + 20).collect();
+
+        let result = compare(&a, &b);
+        assert!(result.is_significant(0.05), "{:?}", result);
+        assert!(result.p_value < 1e-6);
+        assert!(result.median_a < result.median_b);
+        assert!(result.z < 0.0);
+    }
+
+    #[test]
+    fn test_compare_identical_is_not_significant() {
+        let a: Vec<u64> = (0..500).map(|i| 100 + (i * 7) % 37).collect();
+        let result = compare(&a, &a);
+        assert!(!result.is_significant(0.05), "{:?}", result);
+        assert!(result.p_value > 0.99);
+
+        // Constant samples have no spread at all
+        let flat = compare(&[5; 10], &[5; 10]);
+        assert_eq!(flat.p_value, 1.0);
+    }
+
+    fn snapshot(bids: &[(u32, u64)], asks: &[(u32, u64)]) -> BookSnapshot {
+        let levels = |side: &[(u32, u64)]| side.iter().map(|&(p, q)| (Price::define(p), q)).collect();
+        BookSnapshot { bids: levels(bids), asks: levels(asks) }
+    }
+
+    #[test]
+    fn test_diff_reports_each_mismatch() {
+        let a = snapshot(&[(4999, 100), (4998, 50)], &[(5001, 200), (5002, 75)]);
+        assert!(diff(&a, &a).is_empty());
+
+        // One level's quantity differs
+        let b = snapshot(&[(4999, 100), (4998, 50)], &[(5001, 200), (5002, 70)]);
+        assert_eq!(
+            diff(&a, &b),
+            vec![LevelDiff::Quantity { side: Side::Ask, price: Price::define(5002), a: 75, b: 70 }]
+        );
+
+        // A missing best bid and an extra ask, reported best first
+        let c = snapshot(&[(4998, 50)], &[(5001, 200), (5002, 75), (5003, 10)]);
+        assert_eq!(
+            diff(&a, &c),
+            vec![
+                LevelDiff::Best { side: Side::Bid, a: Some(Price::define(4999)), b: Some(Price::define(4998)) },
+                LevelDiff::OnlyInA { side: Side::Bid, price: Price::define(4999), qty: 100 },
+                LevelDiff::OnlyInB { side: Side::Ask, price: Price::define(5003), qty: 10 },
+            ]
+        );
+        assert_eq!(
+            diff(&snapshot(&[], &[]), &snapshot(&[], &[(5001, 1)]))[0],
+            LevelDiff::Best { side: Side::Ask, a: None, b: Some(Price::define(5001)) }
+        );
+    }
+
+    #[test]
+    fn test_merge_snapshots_of_two_shards() {
+        // Shards split at 5000: the lower one holds the bids, the upper one
+        // the asks plus a deep bid the lower shard also quotes
+        let lower = snapshot(&[(4999, 100), (4995, 20)], &[]);
+        let upper = snapshot(&[(4995, 30)], &[(5003, 40), (5001, 200)]);
+
+        let merged = merge_snapshots(&[lower.clone(), upper]).unwrap();
+        assert_eq!(merged, snapshot(&[(4999, 100), (4995, 50)], &[(5001, 200), (5003, 40)]));
+        assert_eq!(merged.bids.first().map(|&(p, _)| p), Some(Price::define(4999)));
+        assert_eq!(merged.asks.first().map(|&(p, _)| p), Some(Price::define(5001)));
+
+        assert_eq!(merge_snapshots(std::slice::from_ref(&lower)).unwrap(), lower);
+        assert_eq!(merge_snapshots(&[]).unwrap(), BookSnapshot::default());
+        assert!(merge_snapshots(&[lower, snapshot(&[], &[(4999, 1)])]).is_err());
+    }
+
+    fn fill(price: u32, qty: QtyValue, aggressor_side: Side) -> Fill {
+        Fill {
+            price: Price::define(price),
+            quantity: Quantity::define(qty),
+            maker_order_id: 1,
+            taker_order_id: 2,
+            aggressor_side,
+            seq: 0,
+        }
+    }
+
+    #[test]
+    fn test_price_impact_of_a_monotonic_sweep() {
+        // A buyer walking up five ask levels, 100 at each
+        let sweep: Vec<Fill> = (5001..=5005).map(|price| fill(price, 100, Side::Bid)).collect();
+        let report = price_impact(&sweep);
+        assert_eq!((report.signed_volume, report.price_change), (500, 4));
+        assert_eq!(report.impact_per_unit, Some(4.0 / 500.0));
+        assert!(report.lambda.unwrap() > 0.0);
+
+        // The mirror-image sell sweep moves price down on negative volume
+        let sell: Vec<Fill> = (4995..=4999).rev().map(|price| fill(price, 100, Side::Ask)).collect();
+        assert_eq!(price_impact(&sell).lambda, report.lambda);
+
+        assert_eq!(price_impact(&sweep[..1]).lambda, None);
+        assert_eq!(price_impact(&[]).impact_per_unit, None);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::perf::latency::Percentiles;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Latency percentiles per operation name, as one benchmark run measured them
+pub type Results = BTreeMap<String, Percentiles>;
+
+/// An operation whose latency grew past the tolerance
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub operation: String,
+    /// "p50" or "p99"
+    pub metric: &'static str,
+    pub baseline: u64,
+    pub current: u64,
+    /// Increase over the baseline, in percent
+    pub change_pct: f64,
+}
+
+/// Every operation in both runs whose p50 or p99 is more than `tolerance_pct`
+/// percent above the baseline
+/// Operations only one side measured are not compared
+pub fn compare_to_baseline(current: &Results, baseline: &Results, tolerance_pct: f64) -> Vec<Regression> {
+    let mut regressions = Vec::new();
+    for (operation, now) in current {
+        let Some(before) = baseline.get(operation) else {
+            continue;
+        };
+        for (metric, baseline, current) in [("p50", before.p50, now.p50), ("p99", before.p99, now.p99)] {
+            // A zero baseline would make any change infinite; skip it
+            if baseline == 0 {
+                continue;
+            }
+            let change_pct = (current as f64 - baseline as f64) / baseline as f64 * 100.0;
+            if change_pct > tolerance_pct {
+                regressions.push(Regression {
+                    operation: operation.clone(),
+                    metric,
+                    baseline,
+                    current,
+                    change_pct,
+                });
+            }
+        }
+    }
+    regressions
+}
+
+/// Write `results` as a JSON object keyed by operation
+pub fn save_baseline(path: impl AsRef<Path>, results: &Results) -> std::io::Result<()> {
+    let operations: Map<String, Value> = results
+        .iter()
+        .map(|(operation, p)| {
+            let fields = json!({
+                "min": p.min,
+                "max": p.max,
+                "mean": p.mean,
+                "p50": p.p50,
+                "p95": p.p95,
+                "p99": p.p99,
+                "p999": p.p999,
+                "p9999": p.p9999,
+                "std_dev": p.std_dev,
+                "cv": p.cv,
+                "ops_per_sec": p.ops_per_sec,
+            });
+            (operation.clone(), fields)
+        })
+        .collect();
+    let text = serde_json::to_string_pretty(&Value::Object(operations)).map_err(std::io::Error::other)?;
+    fs::write(path, text)
+}
+
+/// Read a baseline save_baseline wrote
+pub fn load_baseline(path: impl AsRef<Path>) -> Result<Results, String> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let value: Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let operations = value
+        .as_object()
+        .ok_or_else(|| format!("{}: expected an object keyed by operation", path.display()))?;
+
+    operations
+        .iter()
+        .map(|(operation, fields)| {
+            let int = |name: &str| {
+                fields[name]
+                    .as_u64()
+                    .ok_or_else(|| format!("{}: {}.{} is not an integer", path.display(), operation, name))
+            };
+            let float = |name: &str| {
+                fields[name]
+                    .as_f64()
+                    .ok_or_else(|| format!("{}: {}.{} is not a number", path.display(), operation, name))
+            };
+            let percentiles = Percentiles {
+                min: int("min")?,
+                max: int("max")?,
+                mean: float("mean")?,
+                p50: int("p50")?,
+                p95: int("p95")?,
+                p99: int("p99")?,
+                p999: int("p999")?,
+                p9999: int("p9999")?,
+                std_dev: float("std_dev")?,
+                cv: float("cv")?,
+                ops_per_sec: float("ops_per_sec")?,
+            };
+            Ok((operation.clone(), percentiles))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perf::latency::LatencyTracker;
+
+    fn results(operation: &str, scale: f64) -> Results {
+        let samples = (1..=1_000).map(|i| (100.0 * scale) as u64 + i % 50).collect();
+        let percentiles = LatencyTracker::from_samples(samples).percentiles().unwrap();
+        Results::from([(operation.to_string(), percentiles)])
+    }
+
+    #[test]
+    fn test_ten_percent_slowdown() {
+        let baseline = results("add_order", 1.0);
+        let current = results("add_order", 1.1);
+
+        let flagged = compare_to_baseline(&current, &baseline, 5.0);
+        assert!(flagged.iter().any(|r| r.operation == "add_order" && r.metric == "p50"));
+        assert!(flagged.iter().all(|r| r.change_pct > 5.0 && r.change_pct < 20.0));
+        assert!(compare_to_baseline(&current, &baseline, 20.0).is_empty());
+
+        // Getting faster is never a regression
+        assert!(compare_to_baseline(&baseline, &current, 0.0).is_empty());
+    }
+
+    #[test]
+    fn test_baseline_round_trip() {
+        let baseline = results("cancel_order", 1.0);
+        let path = std::env::temp_dir().join(format!("orderbook_baseline_{}.json", std::process::id()));
+        save_baseline(&path, &baseline).unwrap();
+        let loaded = load_baseline(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let (before, after) = (&baseline["cancel_order"], &loaded["cancel_order"]);
+        assert_eq!((before.p50, before.p99, before.max), (after.p50, after.p99, after.max));
+        assert_eq!(before.mean, after.mean);
+    }
+}
@@ -1 +1,110 @@
+// Shared setup for the criterion suite in benches/, and the regression gate
+mod baseline;
+mod replay;
 
+pub use baseline::{Regression, Results, compare_to_baseline, load_baseline, save_baseline};
+pub use replay::{OpTallies, ReplayRun, check_replay_determinism, replay, replay_commands};
+
+use crate::orderbook::OrderbookTrait;
+use crate::types::order::{IdCounter, Order, Side};
+use crate::types::price::Price;
+use crate::types::quantity::Quantity;
+use crate::workload::{
+    ClusteredGenerator, ORDER_QUANTITY, UniformGenerator, WorkloadGenerator, ZipfianGenerator,
+};
+use std::time::{Duration, Instant};
+
+/// Orders resting in every benchmarked book before timing starts
+pub const BASE_ORDERS: usize = 10_000;
+/// Most timed operations run back to back before the book is restored
+pub const BATCH: usize = 1_000;
+
+/// Price distribution a benchmarked book is built from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distribution {
+    Uniform,
+    Clustered,
+    Zipfian,
+}
+
+impl Distribution {
+    pub const ALL: [Distribution; 3] = [Distribution::Uniform, Distribution::Clustered, Distribution::Zipfian];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Distribution::Uniform => "uniform",
+            Distribution::Clustered => "clustered",
+            Distribution::Zipfian => "zipfian",
+        }
+    }
+
+    pub fn generator(self, seed: u64) -> Box<dyn WorkloadGenerator> {
+        match self {
+            Distribution::Uniform => Box::new(UniformGenerator::new(seed)),
+            Distribution::Clustered => Box::new(ClusteredGenerator::new(seed)),
+            Distribution::Zipfian => Box::new(ZipfianGenerator::new(seed)),
+        }
+    }
+}
+
+/// A populated book plus orders to add and cancel against it
+/// Every order rests (bids below the mid, asks at or above) and is
+/// ORDER_QUANTITY, so a market order of that size takes exactly one
+pub struct Fixture<O> {
+    pub book: O,
+    /// The BASE_ORDERS resting in `book`, bid first then alternating
+    pub resting: Vec<Order>,
+    /// BATCH more orders from the same distribution, not in `book`
+    pub spare: Vec<Order>,
+    pub ids: IdCounter,
+}
+
+impl<O: OrderbookTrait> Fixture<O> {
+    pub fn new(distribution: Distribution, seed: u64) -> Self {
+        let mut generator = distribution.generator(seed);
+        let mut ids = IdCounter::new();
+        let mut orders = |count: usize, ids: &mut IdCounter| -> Vec<Order> {
+            (0..count)
+                .map(|i| {
+                    let side = if i % 2 == 0 { Side::Bid } else { Side::Ask };
+                    let price = Price::define(generator.next_resting_price(side));
+                    Order::new(price, Quantity::define(ORDER_QUANTITY), side, ids)
+                })
+                .collect()
+        };
+
+        let resting = orders(BASE_ORDERS, &mut ids);
+        let spare = orders(BATCH, &mut ids);
+        let mut book = O::new();
+        for &order in &resting {
+            book.add_order(order).expect("Generated orders are valid and rest");
+        }
+
+        Self { book, resting, spare, ids }
+    }
+}
+
+/// Time `iters` calls of `op` for a custom criterion measurement
+/// Calls run in batches of at most BATCH; after each, `restore` puts the
+/// state back untimed. `op` is passed its position in the batch and
+/// `restore` the batch length
+pub fn time_in_batches<S>(
+    iters: u64,
+    state: &mut S,
+    mut op: impl FnMut(&mut S, usize),
+    mut restore: impl FnMut(&mut S, usize),
+) -> Duration {
+    let mut elapsed = Duration::ZERO;
+    let mut left = iters as usize;
+    while left > 0 {
+        let batch = left.min(BATCH);
+        let start = Instant::now();
+        for i in 0..batch {
+            op(state, i);
+        }
+        elapsed += start.elapsed();
+        restore(state, batch);
+        left -= batch;
+    }
+    elapsed
+}
@@ -0,0 +1,210 @@
+use super::Distribution;
+use crate::orderbook::command::{Command, EngineEvent};
+use crate::orderbook::{BookSnapshot, Fill, OrderbookTrait};
+use crate::perf::latency::LatencyTracker;
+use crate::types::order::{IdCounter, Order, OrderId, Side};
+use crate::types::price::Price;
+use crate::types::quantity::Quantity;
+use crate::workload::{MAX_PRICE, ORDER_QUANTITY};
+use rand::SeedableRng;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+/// How many commands of each outcome a replay produced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpTallies {
+    pub added: usize,
+    pub canceled: usize,
+    pub reduced: usize,
+    pub executed: usize,
+    pub rejected: usize,
+}
+
+/// Everything one replay produced
+/// Only `latency` may differ between replays of the same commands
+pub struct ReplayRun {
+    /// Fills from adds and market orders, in execution order
+    pub fills: Vec<Fill>,
+    /// Every level on both sides once the commands ran
+    pub snapshot: BookSnapshot,
+    /// Resting orders per side in price-time order, bids first
+    pub orders: Vec<(Price, Order)>,
+    pub tallies: OpTallies,
+    /// rdtsc cycles per command
+    pub latency: LatencyTracker,
+}
+
+impl ReplayRun {
+    /// The first logical result that differs from `other`, None if they agree
+    pub fn first_difference(&self, other: &ReplayRun) -> Option<String> {
+        if self.tallies != other.tallies {
+            return Some(format!("tallies {:?} vs {:?}", self.tallies, other.tallies));
+        }
+        if let Some(i) = (0..self.fills.len().max(other.fills.len()))
+            .find(|&i| self.fills.get(i) != other.fills.get(i))
+        {
+            return Some(format!("fill {}: {:?} vs {:?}", i, self.fills.get(i), other.fills.get(i)));
+        }
+        if self.snapshot != other.snapshot {
+            return Some(format!("snapshots {:?} vs {:?}", self.snapshot, other.snapshot));
+        }
+        if let Some(i) = (0..self.orders.len().max(other.orders.len()))
+            .find(|&i| self.orders.get(i) != other.orders.get(i))
+        {
+            return Some(format!(
+                "resting order {}: {:?} vs {:?}",
+                i,
+                self.orders.get(i),
+                other.orders.get(i)
+            ));
+        }
+        None
+    }
+}
+
+/// A seeded mix of commands: 60% adds (some crossing), 10% cancels, 10%
+/// reduces and 20% market orders sweeping up to five levels' worth
+/// Cancels and reduces pick any id issued so far, so some hit filled orders
+/// and are rejected
+pub fn replay_commands(distribution: Distribution, seed: u64, count: usize) -> Vec<Command> {
+    let mut generator = distribution.generator(seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut ids = IdCounter::new();
+    let mut issued: Vec<OrderId> = Vec::new();
+
+    (0..count)
+        .map(|_| {
+            let side = if rng.random_bool(0.5) { Side::Bid } else { Side::Ask };
+            let roll = rng.random_range(0..10);
+            match roll {
+                // Raw prices, not folded onto the resting side, so adds cross
+                0..=5 => {
+                    let price = Price::define(generator.next_price().min(MAX_PRICE));
+                    let qty = Quantity::define(rng.random_range(1..=ORDER_QUANTITY));
+                    let order = Order::new(price, qty, side, &mut ids);
+                    issued.push(order.id());
+                    Command::Add(order)
+                }
+                6 | 7 if !issued.is_empty() => {
+                    let order = issued[rng.random_range(0..issued.len())];
+                    if roll == 6 {
+                        Command::Cancel(order)
+                    } else {
+                        let qty = Quantity::define(rng.random_range(1..=ORDER_QUANTITY / 2));
+                        Command::Reduce { order, qty }
+                    }
+                }
+                _ => {
+                    let qty = Quantity::define(rng.random_range(1..=5 * ORDER_QUANTITY));
+                    Command::Market { side, qty, taker: ids.next() }
+                }
+            }
+        })
+        .collect()
+}
+
+/// Apply `commands` to a fresh book, timing each with rdtsc
+pub fn replay<O: OrderbookTrait>(commands: &[Command]) -> ReplayRun {
+    let mut book = O::new();
+    let mut latency = LatencyTracker::new(commands.len());
+    let mut fills = Vec::new();
+    let mut tallies = OpTallies::default();
+
+    for &command in commands {
+        match latency.record(|| book.apply_command(command)) {
+            EngineEvent::Added(_, result) => {
+                tallies.added += 1;
+                fills.extend(result.fills);
+            }
+            EngineEvent::Canceled(_) => tallies.canceled += 1,
+            EngineEvent::Reduced(..) => tallies.reduced += 1,
+            EngineEvent::Executed(_, result) => {
+                tallies.executed += 1;
+                fills.extend(result.fills);
+            }
+            EngineEvent::Rejected(..) => tallies.rejected += 1,
+        }
+    }
+
+    let orders = book.iter_orders(Side::Bid).chain(book.iter_orders(Side::Ask)).collect();
+    ReplayRun {
+        fills,
+        snapshot: book.book_snapshot(usize::MAX),
+        orders,
+        tallies,
+        latency,
+    }
+}
+
+/// Run the same seeded workload on two fresh books and require identical
+/// fills, final book and tallies; cycle counts are free to differ
+/// Each book gets its own randomly keyed HashMaps, so behavior that leaks
+/// hash iteration order shows up as a mismatch. Returns both runs so their
+/// timings can be compared
+pub fn check_replay_determinism<O: OrderbookTrait>(
+    distribution: Distribution,
+    seed: u64,
+    count: usize,
+) -> Result<(ReplayRun, ReplayRun), String> {
+    let commands = replay_commands(distribution, seed, count);
+    let first = replay::<O>(&commands);
+    let second = replay::<O>(&commands);
+    match first.first_difference(&second) {
+        Some(difference) => Err(format!(
+            "{} workload, seed {}: replays diverge at {}",
+            distribution.name(),
+            seed,
+            difference
+        )),
+        None => Ok((first, second)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::SoA::orderbook::Orderbook as SoA;
+    use crate::orderbook::arena::orderbook::Orderbook as Arena;
+    use crate::orderbook::fixed_tick::orderbook::Orderbook as FixedTick;
+    use crate::orderbook::hybrid::orderbook::Orderbook as Hybrid;
+    use crate::orderbook::tree::orderbook::Orderbook as Tree;
+    use crate::orderbook::windowed::WindowedArrayOrderbook as Windowed;
+
+    fn check<O: OrderbookTrait>() {
+        for distribution in Distribution::ALL {
+            let (first, second) = check_replay_determinism::<O>(distribution, 7, 5_000).unwrap();
+            assert!(!first.fills.is_empty());
+            assert_eq!(first.latency.len(), second.latency.len());
+        }
+    }
+
+    #[test]
+    fn test_replays_are_identical() {
+        check::<Tree>();
+        check::<FixedTick>();
+        check::<SoA>();
+        check::<Hybrid>();
+        check::<Arena>();
+        check::<Windowed>();
+    }
+
+    #[test]
+    fn test_hybrid_cold_cleanup_is_deterministic() {
+        // Uniform prices land mostly outside the hot zone; the sweeps empty
+        // cold levels mid-walk and drag the mid far enough to recenter, so
+        // levels are parked, pulled back and cleaned up all run long
+        let commands = replay_commands(Distribution::Uniform, 42, 20_000);
+        let first = replay::<Hybrid>(&commands);
+        assert!(first.tallies.executed > 1_000 && first.tallies.rejected > 0);
+        for _ in 0..3 {
+            assert_eq!(first.first_difference(&replay::<Hybrid>(&commands)), None);
+        }
+    }
+
+    #[test]
+    fn test_difference_is_reported() {
+        let first = replay::<Tree>(&replay_commands(Distribution::Clustered, 1, 1_000));
+        let second = replay::<Tree>(&replay_commands(Distribution::Clustered, 2, 1_000));
+        assert!(first.first_difference(&second).is_some());
+    }
+}
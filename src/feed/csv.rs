@@ -0,0 +1,138 @@
+use crate::orderbook::command::Command;
+use crate::types::order::{Order, Side};
+use crate::types::price::Price;
+use crate::types::quantity::{QtyValue, Quantity};
+use std::io::{BufRead, BufReader, Read, Write};
+
+const HEADER: &str = "op,side,price,qty,id";
+
+/// Load an order tape: one `op,side,price,qty,id` row per command
+///
+/// - `add,B,5000,100,7` rests or matches order 7
+/// - `cancel,,,,7` cancels order 7
+/// - `market,S,,250,8` sells 250 at market as taker 8
+/// - `reduce,,,40,7` takes 40 off order 7
+///
+/// Unused fields are left empty. A header row and blank lines are skipped.
+/// Added orders are GTC with no account: the tape does not carry either
+pub fn load_tape(reader: impl Read) -> Result<Vec<Command>, String> {
+    let mut commands = Vec::new();
+
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let row = i + 1;
+        let line = line.map_err(|e| format!("Row {}: read failed: {}", row, e))?;
+        let line = line.trim();
+        if line.is_empty() || (row == 1 && line == HEADER) {
+            continue;
+        }
+
+        commands.push(parse_row(line).map_err(|e| format!("Row {}: {}", row, e))?);
+    }
+
+    Ok(commands)
+}
+
+/// Write `commands` as a tape load_tape reads back, header first
+pub fn dump_tape(mut writer: impl Write, commands: &[Command]) -> std::io::Result<()> {
+    writeln!(writer, "{}", HEADER)?;
+    for command in commands {
+        match command {
+            Command::Add(order) => writeln!(
+                writer,
+                "add,{},{},{},{}",
+                order.side(),
+                order.price().value(),
+                order.quantity().value(),
+                order.id()
+            )?,
+            Command::Cancel(id) => writeln!(writer, "cancel,,,,{}", id)?,
+            Command::Market { side, qty, taker } => {
+                writeln!(writer, "market,{},,{},{}", side, qty.value(), taker)?
+            }
+            Command::Reduce { order, qty } => writeln!(writer, "reduce,,,{},{}", qty.value(), order)?,
+        }
+    }
+    writer.flush()
+}
+
+fn parse_row(line: &str) -> Result<Command, String> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    let [op, side, price, qty, id] = fields[..] else {
+        return Err(format!("expected 5 fields ({}), found {}", HEADER, fields.len()));
+    };
+
+    let number = |name: &str, value: &str| -> Result<u64, String> {
+        value
+            .parse::<u64>()
+            .map_err(|_| format!("{} {:?} is not a non-negative integer", name, value))
+    };
+    let small = |name: &str, value: &str| -> Result<u32, String> {
+        u32::try_from(number(name, value)?).map_err(|_| format!("{} {} does not fit in u32", name, value))
+    };
+    let quantity = |value: &str| -> Result<Quantity, String> {
+        QtyValue::try_from(number("qty", value)?)
+            .map(Quantity::define)
+            .map_err(|_| format!("qty {} is too large for a quantity", value))
+    };
+    let side = || side.parse::<Side>();
+
+    match op {
+        "add" => Ok(Command::Add(Order::from_parts(
+            number("id", id)?,
+            Price::define(small("price", price)?),
+            quantity(qty)?,
+            side()?,
+        ))),
+        "cancel" => Ok(Command::Cancel(number("id", id)?)),
+        "market" => Ok(Command::Market {
+            side: side()?,
+            qty: quantity(qty)?,
+            taker: number("id", id)?,
+        }),
+        "reduce" => Ok(Command::Reduce {
+            order: number("id", id)?,
+            qty: quantity(qty)?,
+        }),
+        other => Err(format!("unknown op {:?} (expected add, cancel, market or reduce)", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::order::IdCounter;
+
+    #[test]
+    fn test_tape_round_trip() {
+        let mut counter = IdCounter::new();
+        let mut commands = Vec::new();
+        for i in 0..50u32 {
+            let side = if i % 2 == 0 { Side::Bid } else { Side::Ask };
+            let order = Order::new(Price::define(4990 + i), Quantity::define(10 + i as QtyValue), side, &mut counter);
+            commands.push(Command::Add(order));
+            match i % 5 {
+                0 => commands.push(Command::Cancel(order.id())),
+                1 => commands.push(Command::Market { side, qty: Quantity::define(i as QtyValue), taker: counter.next() }),
+                2 => commands.push(Command::Reduce { order: order.id(), qty: Quantity::define(3) }),
+                _ => {}
+            }
+        }
+
+        let mut tape = Vec::new();
+        dump_tape(&mut tape, &commands).unwrap();
+        assert_eq!(load_tape(tape.as_slice()).unwrap(), commands);
+    }
+
+    #[test]
+    fn test_malformed_rows_name_the_row() {
+        let tape = "op,side,price,qty,id\nadd,B,5000,100,1\nadd,X,5000,100,2\n";
+        let err = load_tape(tape.as_bytes()).unwrap_err();
+        assert!(err.starts_with("Row 3:"), "{}", err);
+
+        let err = load_tape("add,B,5000,100\n".as_bytes()).unwrap_err();
+        assert!(err.starts_with("Row 1: expected 5 fields"), "{}", err);
+
+        let err = load_tape("cancel,,,,\n".as_bytes()).unwrap_err();
+        assert!(err.contains("id \"\""), "{}", err);
+    }
+}
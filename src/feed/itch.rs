@@ -0,0 +1,237 @@
+use crate::orderbook::BookConfig;
+use crate::orderbook::command::Command;
+use crate::types::order::{Order, OrderId, Side};
+use crate::types::price::Price;
+use crate::types::quantity::{QtyValue, Quantity};
+use std::collections::HashSet;
+use std::io::Read;
+
+/// Offsets shared by every order message: type (1), stock locate (2),
+/// tracking number (2), timestamp (6), order reference number (8)
+const LOCATE: usize = 1;
+const ORDER_REF: usize = 11;
+
+/// Message lengths (excluding the 2-byte length prefix)
+const ADD_ORDER_LEN: usize = 36;
+const ORDER_EXECUTED_LEN: usize = 31;
+const ORDER_CANCEL_LEN: usize = 23;
+const ORDER_DELETE_LEN: usize = 19;
+
+/// How ITCH fields map onto this crate's book
+#[derive(Debug, Clone, Copy)]
+pub struct ItchConfig {
+    /// ITCH prices carry 4 implied decimals; each is divided by this to get
+    /// book ticks (e.g. 100 for a one-cent tick)
+    pub price_divisor: u32,
+    /// Shares per book quantity unit
+    pub shares_divisor: u32,
+    /// Keep only this instrument's messages; None keeps everything
+    pub stock_locate: Option<u16>,
+    /// Prices that do not land on this book's grid are skipped
+    pub book: BookConfig,
+}
+
+impl Default for ItchConfig {
+    fn default() -> Self {
+        Self {
+            price_divisor: 1,
+            shares_divisor: 1,
+            stock_locate: None,
+            book: BookConfig::default(),
+        }
+    }
+}
+
+/// An order message that produced no command, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedMessage {
+    pub order_ref: OrderId,
+    pub reason: String,
+}
+
+/// Commands decoded from a stream, in stream order
+#[derive(Debug, Default)]
+pub struct ItchBatch {
+    pub commands: Vec<Command>,
+    pub skipped: Vec<SkippedMessage>,
+}
+
+/// Decode a stream of length-prefixed ITCH 5.0 messages (big-endian u16 length
+/// before each message, as in NASDAQ's BinaryFILE captures)
+///
+/// Add Order ('A') becomes Add, Order Delete ('D') becomes Cancel, and Order
+/// Cancel ('X') and Order Executed ('E') become Reduce: the execution happened
+/// on the exchange, so the maker just loses quantity here. Other message types
+/// are ignored. Orders with prices off the book's grid are skipped, as are
+/// later messages for them. Only a truncated stream is an error.
+pub fn parse(bytes: &[u8], config: &ItchConfig) -> Result<ItchBatch, String> {
+    let mut batch = ItchBatch::default();
+    let mut skipped_refs = HashSet::new();
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let header = bytes
+            .get(offset..offset + 2)
+            .ok_or_else(|| format!("Truncated length prefix at byte {}", offset))?;
+        let len = u16::from_be_bytes([header[0], header[1]]) as usize;
+        let message = bytes
+            .get(offset + 2..offset + 2 + len)
+            .ok_or_else(|| format!("Truncated message at byte {}: {} bytes declared", offset, len))?;
+        offset += 2 + len;
+
+        let Some(&kind) = message.first() else {
+            continue;
+        };
+        let expected_len = match kind {
+            b'A' => ADD_ORDER_LEN,
+            b'E' => ORDER_EXECUTED_LEN,
+            b'X' => ORDER_CANCEL_LEN,
+            b'D' => ORDER_DELETE_LEN,
+            _ => continue,
+        };
+        if len < expected_len {
+            return Err(format!(
+                "'{}' message at byte {} is {} bytes, expected {}",
+                kind as char,
+                offset - 2 - len,
+                len,
+                expected_len
+            ));
+        }
+        if config.stock_locate.is_some_and(|locate| locate != be_u16(message, LOCATE)) {
+            continue;
+        }
+
+        let order_ref = be_u64(message, ORDER_REF);
+        if kind != b'A' && skipped_refs.contains(&order_ref) {
+            continue;
+        }
+
+        let decoded = match kind {
+            b'A' => decode_add(message, order_ref, config),
+            b'D' => Ok(Command::Cancel(order_ref)),
+            // Executed and canceled shares sit at the same offset
+            _ => Ok(Command::Reduce {
+                order: order_ref,
+                qty: Quantity::define((be_u32(message, 19) / config.shares_divisor) as QtyValue),
+            }),
+        };
+        match decoded {
+            Ok(command) => batch.commands.push(command),
+            Err(reason) => {
+                skipped_refs.insert(order_ref);
+                batch.skipped.push(SkippedMessage { order_ref, reason });
+            }
+        }
+    }
+
+    Ok(batch)
+}
+
+/// parse() over everything `reader` yields
+pub fn parse_reader(mut reader: impl Read, config: &ItchConfig) -> Result<ItchBatch, String> {
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read ITCH stream: {}", e))?;
+    parse(&bytes, config)
+}
+
+/// Add Order: side at 19, shares at 20, stock at 24, price at 32
+fn decode_add(message: &[u8], order_ref: OrderId, config: &ItchConfig) -> Result<Command, String> {
+    let side = match message[19] {
+        b'B' => Side::Bid,
+        b'S' => Side::Ask,
+        other => return Err(format!("Unknown buy/sell indicator {:?}", other as char)),
+    };
+    let shares = be_u32(message, 20);
+    let raw_price = be_u32(message, 32);
+
+    if !raw_price.is_multiple_of(config.price_divisor) {
+        return Err(format!(
+            "Price {} is not a multiple of the divisor {}",
+            raw_price, config.price_divisor
+        ));
+    }
+    let price = Price::define(raw_price / config.price_divisor);
+    let quantity = Quantity::define((shares / config.shares_divisor) as QtyValue);
+    config.book.validate_order(price, quantity)?;
+
+    Ok(Command::Add(Order::from_parts(order_ref, price, quantity, side)))
+}
+
+fn be_u16(message: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([message[at], message[at + 1]])
+}
+
+fn be_u32(message: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(message[at..at + 4].try_into().unwrap())
+}
+
+fn be_u64(message: &[u8], at: usize) -> u64 {
+    u64::from_be_bytes(message[at..at + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Length-prefixed message with the common header filled in
+    fn message(kind: u8, order_ref: u64, body: &[u8]) -> Vec<u8> {
+        let mut msg = vec![kind];
+        msg.extend(7u16.to_be_bytes()); // stock locate
+        msg.extend(0u16.to_be_bytes()); // tracking number
+        msg.extend([0u8; 6]); // timestamp
+        msg.extend(order_ref.to_be_bytes());
+        msg.extend(body);
+
+        let mut framed = (msg.len() as u16).to_be_bytes().to_vec();
+        framed.extend(msg);
+        framed
+    }
+
+    fn add(order_ref: u64, side: u8, shares: u32, price: u32) -> Vec<u8> {
+        let mut body = vec![side];
+        body.extend(shares.to_be_bytes());
+        body.extend(*b"AAPL    ");
+        body.extend(price.to_be_bytes());
+        message(b'A', order_ref, &body)
+    }
+
+    #[test]
+    fn test_parse_adds_and_delete() {
+        let mut stream = add(11, b'B', 300, 499_900);
+        stream.extend(add(12, b'S', 200, 500_100));
+        stream.extend(message(b'D', 11, &[]));
+
+        let config = ItchConfig { price_divisor: 100, ..ItchConfig::default() };
+        let batch = parse(&stream, &config).unwrap();
+
+        assert_eq!(
+            batch.commands,
+            vec![
+                Command::Add(Order::from_parts(11, Price::define(4999), Quantity::define(300), Side::Bid)),
+                Command::Add(Order::from_parts(12, Price::define(5001), Quantity::define(200), Side::Ask)),
+                Command::Cancel(11),
+            ]
+        );
+        assert!(batch.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_out_of_range_price_is_skipped() {
+        let mut stream = add(21, b'B', 100, 2_000_000);
+        stream.extend(message(b'X', 21, &50u32.to_be_bytes()));
+        stream.extend(add(22, b'S', 100, 500_100));
+
+        let config = ItchConfig { price_divisor: 100, ..ItchConfig::default() };
+        let batch = parse(&stream, &config).unwrap();
+
+        assert_eq!(batch.commands.len(), 1);
+        assert_eq!(batch.skipped.len(), 1);
+        assert_eq!(batch.skipped[0].order_ref, 21);
+
+        // Truncated input is an error, not a panic
+        assert!(parse(&stream[..stream.len() - 3], &config).is_err());
+    }
+}
@@ -0,0 +1,3 @@
+// Market-data and tape readers that turn recorded streams into book commands
+pub mod csv;
+pub mod itch;
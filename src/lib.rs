@@ -1,6 +1,8 @@
 pub mod analysis;
 pub mod benchmark;
+pub mod feed;
 pub mod optimization;
 pub mod orderbook;
 pub mod perf;
 pub mod types;
+pub mod workload;
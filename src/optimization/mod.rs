@@ -1 +1 @@
-
+pub mod slot_array;
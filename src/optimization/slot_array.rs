@@ -0,0 +1,131 @@
+use std::alloc::Layout;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+/// Size of a transparent huge page on x86-64 (and aarch64 with 4KB base pages)
+pub const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Fixed-length heap array behind the dense books' per-tick levels
+/// Derefs to a slice exactly like the Box<[T]> it usually wraps; the one
+/// difference is that it can also own a huge-page aligned allocation (see
+/// on_hugepages), which a Box cannot free correctly
+pub struct SlotArray<T> {
+    ptr: NonNull<T>,
+    len: usize,
+    // Some when allocated here rather than taken over from a Box
+    layout: Option<Layout>,
+}
+
+// Owns its elements like a Box<[T]> does
+unsafe impl<T: Send> Send for SlotArray<T> {}
+unsafe impl<T: Sync> Sync for SlotArray<T> {}
+
+impl<T> From<Box<[T]>> for SlotArray<T> {
+    fn from(boxed: Box<[T]>) -> Self {
+        let len = boxed.len();
+        let ptr = NonNull::new(Box::into_raw(boxed) as *mut T).expect("Box pointers are never null");
+        Self { ptr, len, layout: None }
+    }
+}
+
+#[cfg(feature = "hugepages")]
+impl<T: Default> SlotArray<T> {
+    /// `len` default elements, starting on a HUGE_PAGE_SIZE boundary
+    ///
+    /// On Linux the range is madvise(MADV_HUGEPAGE)'d before anything is
+    /// written, so the first touch can fault in 2MB pages: one TLB entry then
+    /// covers what would take 512 with 4KB pages. That needs transparent huge
+    /// pages in "madvise" or "always" mode; otherwise the hint is ignored and
+    /// this is an ordinary (if over-aligned) allocation. The pages are touched
+    /// here, so under the default first-touch NUMA policy they land on the
+    /// node of the constructing thread: build the book where it will run.
+    ///
+    /// On other platforms there is no madvise; the array is still aligned,
+    /// which lets an OS that promotes large pages on its own do so.
+    pub fn on_hugepages(len: usize) -> Self {
+        let bytes = (len * size_of::<T>()).next_multiple_of(HUGE_PAGE_SIZE).max(HUGE_PAGE_SIZE);
+        let layout = Layout::from_size_align(bytes, HUGE_PAGE_SIZE.max(align_of::<T>()))
+            .expect("Slot array size overflows");
+
+        // SAFETY: layout has a non-zero size
+        let raw = unsafe { std::alloc::alloc(layout) };
+        let Some(base) = NonNull::new(raw) else {
+            std::alloc::handle_alloc_error(layout);
+        };
+
+        #[cfg(target_os = "linux")]
+        // SAFETY: base..base+bytes is the allocation just made. A failure
+        // (e.g. a kernel built without THP) only means no huge pages
+        unsafe {
+            libc::madvise(base.as_ptr().cast(), bytes, libc::MADV_HUGEPAGE);
+        }
+
+        let ptr = base.cast::<T>();
+        for i in 0..len {
+            // SAFETY: i < len and the allocation holds at least len elements
+            unsafe { ptr.add(i).write(T::default()) };
+        }
+        Self { ptr, len, layout: Some(layout) }
+    }
+}
+
+impl<T> SlotArray<T> {
+    /// Whether on_hugepages allocated this array
+    pub fn is_huge_page_aligned(&self) -> bool {
+        self.layout.is_some()
+    }
+}
+
+impl<T> Deref for SlotArray<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // SAFETY: ptr holds len initialized elements for as long as self lives
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for SlotArray<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        // SAFETY: as in deref, and &mut self makes the access unique
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> Drop for SlotArray<T> {
+    fn drop(&mut self) {
+        let elements = std::ptr::slice_from_raw_parts_mut(self.ptr.as_ptr(), self.len);
+        match self.layout {
+            // SAFETY: the pointer came from Box::into_raw with this length
+            None => drop(unsafe { Box::from_raw(elements) }),
+            // SAFETY: on_hugepages initialized every element and allocated
+            // with exactly this layout
+            Some(layout) => unsafe {
+                std::ptr::drop_in_place(elements);
+                std::alloc::dealloc(self.ptr.as_ptr().cast(), layout);
+            },
+        }
+    }
+}
+
+#[cfg(all(test, feature = "hugepages"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_huge_page_array_is_aligned_and_dropped() {
+        let mut slots: SlotArray<Vec<u32>> = SlotArray::on_hugepages(10_000);
+        assert!(slots.is_huge_page_aligned());
+        assert_eq!(slots.as_ptr() as usize % HUGE_PAGE_SIZE, 0);
+        assert_eq!(slots.len(), 10_000);
+        assert!(slots.iter().all(Vec::is_empty));
+
+        // Elements own heap memory of their own, freed on drop
+        slots[9_999].extend([1, 2, 3]);
+        assert_eq!(slots[9_999], vec![1, 2, 3]);
+
+        let boxed = SlotArray::from(vec![7u8; 3].into_boxed_slice());
+        assert!(!boxed.is_huge_page_aligned());
+        assert_eq!(&*boxed, &[7, 7, 7]);
+    }
+}
@@ -1,28 +1,42 @@
-use crate::orderbook::{Fill, OrderbookTrait};
-use crate::types::order::{Order, OrderId, Side};
+use crate::optimization::slot_array::SlotArray;
+use crate::orderbook::level::PriceLevel;
+use crate::orderbook::error::OrderError;
+use crate::orderbook::{
+    check_invariants, fillable_quantity, hash_map_bytes, match_level, unindex_side, ArrivalSequence, BookConfig,
+    BookSnapshot, BookStats, ExpiryQueue, Fill, FillSequence, LevelQueue, LimitOrderResult, MarketOrderResult,
+    MemoryFootprint, OrderView, OrderbookTrait, SideTotals, StpMode, SweepQuote, Taker,
+};
+use crate::types::order::{AccountId, Order, OrderId, Side, TimeInForce};
 use crate::types::price::Price;
-use crate::types::quantity::Quantity;
+use crate::types::quantity::{QtyValue, Quantity};
 use std::collections::HashMap;
 
-/// Max price is represented in cents - $100 is max price
-const MAX_PRICE: u32 = 10000;
-const TICK_SIZE: u32 = 1;
-const LOT_SIZE: u32 = 1;
-const ELEMENT_NUM: usize = MAX_PRICE as usize / TICK_SIZE as usize;
-
 // Structure-of-Arrays (SoA) Orderbook
 // Same fixed-tick array structure, but each Level uses SoA instead of AoS
 pub struct Orderbook {
-    bids: Box<[LevelSoA; ELEMENT_NUM]>,
-    asks: Box<[LevelSoA; ELEMENT_NUM]>,
+    // One slot per tick, index = price / tick_size
+    bids: SlotArray<LevelSoA>,
+    asks: SlotArray<LevelSoA>,
     order_index: HashMap<OrderId, (Side, Price)>,
+    // Cached top-of-book indices, rescanned only when the best level empties
+    best_bid_idx: Option<usize>,
+    best_ask_idx: Option<usize>,
+    // Populated level counts, kept alongside the best-index caches
+    bid_levels: usize,
+    ask_levels: usize,
+    bid_totals: SideTotals,
+    ask_totals: SideTotals,
+    config: BookConfig,
+    fill_seq: FillSequence,
+    arrival_seq: ArrivalSequence,
+    expiries: ExpiryQueue,
 }
 
 /// Level using Structure-of-Arrays (SoA) approach
 /// Instead of Vec<Order> (AoS), we have separate arrays for each field
 ///
 /// Memory layout comparison:
-/// AoS: [id₁|side₁|price₁|qty₁|pad][id₂|side₂|price₂|qty₂|pad]... (24 bytes per order)
+/// AoS: [id₁|side₁|price₁|qty₁|pad][id₂|side₂|price₂|qty₂|pad]... (40 bytes per order)
 /// SoA: ids:[id₁|id₂|id₃...] sides:[s₁|s₂|s₃...] prices:[p₁|p₂|p₃...] quantities:[q₁|q₂|q₃...]
 ///
 /// Cache line utilization (64 bytes):
@@ -41,254 +55,739 @@ pub struct LevelSoA {
     prices: Vec<Price>,
     /// Vec header: 24 bytes, then N × 4 bytes for quantities
     quantities: Vec<Quantity>,
+    /// Vec header: 24 bytes, then N × 4 bytes for accounts (read only by STP)
+    accounts: Vec<AccountId>,
+    /// Vec header: 24 bytes, then N × 8 bytes for arrival seqs (never read by matching)
+    seqs: Vec<u64>,
+    /// Vec header: 24 bytes, then N × 8 bytes for expiries, 0 for none (read only by cancel)
+    expiries: Vec<u64>,
 }
 
 impl OrderbookTrait for Orderbook {
-    fn new() -> Self {
-        Self {
-            bids: Box::new(std::array::from_fn(|_| LevelSoA::default())),
-            asks: Box::new(std::array::from_fn(|_| LevelSoA::default())),
-            order_index: HashMap::new(),
+    fn with_config(config: BookConfig) -> Result<Self, String> {
+        Self::with_arrays(config, |slots| vec![LevelSoA::default(); slots].into_boxed_slice().into())
+    }
+
+    fn config(&self) -> &BookConfig {
+        &self.config
+    }
+
+    fn reserve(&mut self, orders_per_level: usize) {
+        for level in self.bids.iter_mut().chain(self.asks.iter_mut()) {
+            level.reserve(orders_per_level);
         }
     }
 
-    fn add_order(&mut self, order: Order) -> Result<(), String> {
+    fn reserve_orders(&mut self, additional: usize) {
+        self.order_index.reserve(additional);
+    }
+
+    fn add_order(&mut self, mut order: Order) -> Result<LimitOrderResult, OrderError> {
         let order_id = order.id();
         let side = order.side();
         let price_value = order.price().value();
-        let quantity_value = order.quantity().value();
 
-        // Validation 1: Price must be multiple of tick size
-        if price_value % TICK_SIZE != 0 {
-            return Err(format!(
-                "Price {} is not a valid tick (tick_size={})",
-                price_value, TICK_SIZE
-            ));
-        }
-
-        // Validation 2: Price must be in bounds
-        if price_value == 0 || price_value >= MAX_PRICE {
-            return Err(format!(
-                "Price {} out of bounds [1, {})",
-                price_value, MAX_PRICE
-            ));
+        self.config.validate_order(order.price(), order.quantity())?;
+        self.config.check_level_room(side, order.price(), self.level_len(side, price_value))?;
+        self.config.check_crossing(&order, || self.crosses(side, order.price()))?;
+
+        // Fill-or-kill: reject before anything trades unless the whole
+        // quantity is available at or better than the limit price
+        if order.time_in_force() == TimeInForce::FOK {
+            let available = fillable_quantity(self, &order);
+            if available < order.quantity() {
+                return Err(OrderError::FokUnfillable {
+                    order: order.id(),
+                    available: available.value(),
+                    requested: order.quantity().value(),
+                });
+            }
         }
 
-        // Validation 3: Quantity must be multiple of lot size
-        if quantity_value % LOT_SIZE != 0 {
-            return Err(format!(
-                "Quantity {} is not a valid lot (lot_size={})",
-                quantity_value, LOT_SIZE
-            ));
+        // Marketable limit order: take liquidity up to the limit price first
+        let mut remaining_qty = order.quantity();
+        let mut fills = Vec::new();
+        if self.crosses(side, order.price()) {
+            let taker = Taker::limit_order(&order, &self.config);
+            self.match_incoming(&mut remaining_qty, taker, &mut |fill| fills.push(fill.clone()));
         }
 
-        // Validation 4: Quantity must be positive
-        if quantity_value == 0 {
-            return Err("Quantity cannot be zero".to_string());
+        let filled_qty = order.quantity().saturating_sub(remaining_qty);
+
+        // Nothing left, IOC/FOK, or a remainder that would rest crossed against
+        // orders STP skipped: the unfilled remainder is canceled, not rested
+        if remaining_qty.is_zero()
+            || order.time_in_force() != TimeInForce::GTC
+            || (self.config.stp == StpMode::Skip && self.crosses(side, order.price()))
+        {
+            return Ok(LimitOrderResult {
+                fills,
+                filled_qty,
+                rested_qty: Quantity::define(0),
+                canceled_qty: remaining_qty,
+            });
         }
+        order.set_quantity(remaining_qty);
+        self.arrival_seq.stamp(&mut order);
+        self.expiries.track(&order);
 
-        let i = (price_value / TICK_SIZE) as usize;
+        let i = self.config.index_of(price_value);
 
         match side {
-            Side::Bid => self.bids[i].add_order(order),
-            Side::Ask => self.asks[i].add_order(order),
+            Side::Bid => {
+                self.bid_levels += self.bids[i].is_empty() as usize;
+                self.bids[i].add(order);
+                self.best_bid_idx = Some(self.best_bid_idx.map_or(i, |b| b.max(i)));
+                self.bid_totals.rested(remaining_qty);
+            }
+            Side::Ask => {
+                self.ask_levels += self.asks[i].is_empty() as usize;
+                self.asks[i].add(order);
+                self.best_ask_idx = Some(self.best_ask_idx.map_or(i, |a| a.min(i)));
+                self.ask_totals.rested(remaining_qty);
+            }
         }
 
         self.order_index.insert(order_id, (side, order.price()));
 
-        Ok(())
+        Ok(LimitOrderResult {
+            fills,
+            filled_qty,
+            rested_qty: remaining_qty,
+            canceled_qty: Quantity::define(0),
+        })
     }
 
-    fn cancel_order(&mut self, order_id: OrderId) -> Result<(), String> {
+    fn cancel_order(&mut self, order_id: OrderId) -> Result<Order, OrderError> {
         let (side, price) = self
             .order_index
             .remove(&order_id)
-            .ok_or_else(|| format!("Order {} not found", order_id))?;
+            .ok_or(OrderError::NotFound(order_id))?;
 
-        let i = (price.value() / TICK_SIZE) as usize;
+        let i = self.config.index_of(price.value());
+
+        let level = match side {
+            Side::Bid => &mut self.bids[i],
+            Side::Ask => &mut self.asks[i],
+        };
+        let order = level.cancel(order_id).ok_or(OrderError::IndexDesync(order_id))?;
 
         match side {
-            Side::Bid => self.bids[i].cancel_order(order_id),
-            Side::Ask => self.asks[i].cancel_order(order_id),
+            Side::Bid => {
+                self.bid_totals.removed(order.quantity());
+                if self.bids[i].is_empty() {
+                    self.bid_levels -= 1;
+                    if self.best_bid_idx == Some(i) {
+                        self.best_bid_idx = self.next_bid_at_or_below(i);
+                    }
+                }
+            }
+            Side::Ask => {
+                self.ask_totals.removed(order.quantity());
+                if self.asks[i].is_empty() {
+                    self.ask_levels -= 1;
+                    if self.best_ask_idx == Some(i) {
+                        self.best_ask_idx = self.next_ask_at_or_above(i);
+                    }
+                }
+            }
         };
 
+        Ok(order)
+    }
+
+    fn cancel_all_for_side(&mut self, side: Side) -> usize {
+        // Everything populated sits at or behind the cached best index
+        let (levels, range) = match side {
+            Side::Bid => {
+                (self.bid_levels, self.bid_totals) = (0, SideTotals::default());
+                (&mut self.bids, self.best_bid_idx.take().map(|best| 0..best + 1))
+            }
+            Side::Ask => {
+                (self.ask_levels, self.ask_totals) = (0, SideTotals::default());
+                let len = self.asks.len();
+                (&mut self.asks, self.best_ask_idx.take().map(|best| best..len))
+            }
+        };
+
+        let mut removed = 0;
+        for level in &mut levels[range.unwrap_or_default()] {
+            removed += level.ids.len();
+            level.clear();
+        }
+        unindex_side(&mut self.order_index, side, removed);
+
+        removed
+    }
+
+    fn take_due_expiries(&mut self, now: u64) -> Vec<OrderId> {
+        self.expiries.take_due(now)
+    }
+
+    fn clear(&mut self) {
+        self.cancel_all();
+        self.fill_seq = FillSequence::default();
+        self.arrival_seq = ArrivalSequence::default();
+        self.expiries.clear();
+    }
+
+    fn contains_order(&self, order_id: OrderId) -> bool {
+        self.order_index.contains_key(&order_id)
+    }
+
+    fn get_order(&self, order_id: OrderId) -> Option<OrderView> {
+        let &(side, price) = self.order_index.get(&order_id)?;
+        let i = self.config.index_of(price.value());
+        let level = match side {
+            Side::Bid => &self.bids[i],
+            Side::Ask => &self.asks[i],
+        };
+
+        // Position comes from the ID array alone, then one quantity read
+        let queue_position = level.position(order_id)?;
+
+        Some(OrderView {
+            price,
+            side,
+            remaining_qty: level.quantities[queue_position],
+            queue_position,
+        })
+    }
+
+    fn amend_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_qty: Option<Quantity>,
+    ) -> Result<(), String> {
+        let (side, price) = *self
+            .order_index
+            .get(&order_id)
+            .ok_or_else(|| format!("Order {} not found", order_id))?;
+
+        let i = self.config.index_of(price.value());
+        let (level, totals) = match side {
+            Side::Bid => (&mut self.bids[i], &mut self.bid_totals),
+            Side::Ask => (&mut self.asks[i], &mut self.ask_totals),
+        };
+        let pos = level
+            .position(order_id)
+            .ok_or_else(|| format!("Order {} found in index but not in level", order_id))?;
+
+        let target_price = new_price.unwrap_or(price);
+        let target_qty = new_qty.unwrap_or(level.quantities[pos]);
+
+        // Pure size reduction: only the quantity array is touched, queue position kept
+        if target_price == price && target_qty <= level.quantities[pos] {
+            self.config.validate_order(price, target_qty)?;
+            totals.reduced(level.quantities[pos].saturating_sub(target_qty));
+            level.quantities[pos] = target_qty;
+            return Ok(());
+        }
+
+        // Anything else loses time priority; validate up front so a rejected
+        // amend leaves the resting order untouched
+        self.config.validate_order(target_price, target_qty)?;
+        if target_price != price {
+            self.config.check_level_room(side, target_price, self.level_len(side, target_price.value()))?;
+        }
+        if self.crosses(side, target_price) {
+            return Err(format!(
+                "Amended price {} would cross the book",
+                target_price.value()
+            ));
+        }
+
+        let mut order = self.cancel_order(order_id)?;
+        order.set_price(target_price);
+        order.set_quantity(target_qty);
+        self.arrival_seq.stamp(&mut order);
+        self.expiries.track(&order);
+
+        // Re-append to the back of the new level with a fresh seq
+        let j = self.config.index_of(target_price.value());
+        match side {
+            Side::Bid => {
+                self.bid_levels += self.bids[j].is_empty() as usize;
+                self.bids[j].add(order);
+                self.best_bid_idx = Some(self.best_bid_idx.map_or(j, |b| b.max(j)));
+                self.bid_totals.rested(target_qty);
+            }
+            Side::Ask => {
+                self.ask_levels += self.asks[j].is_empty() as usize;
+                self.asks[j].add(order);
+                self.best_ask_idx = Some(self.best_ask_idx.map_or(j, |a| a.min(j)));
+                self.ask_totals.rested(target_qty);
+            }
+        }
+        self.order_index.insert(order_id, (side, target_price));
+
         Ok(())
     }
 
-    fn execute_market_order(
+    fn execute_market_order_with(
         &mut self,
         side: Side,
         mut quantity: Quantity,
-    ) -> Result<Vec<Fill>, String> {
-        let mut fills = Vec::new();
+        taker_id: OrderId,
+        mut on_fill: impl FnMut(&Fill),
+    ) -> MarketOrderResult {
+        let requested = quantity;
+        let taker = Taker::market(side, taker_id, &self.config);
+        self.match_incoming(&mut quantity, taker, &mut on_fill);
+
+        MarketOrderResult {
+            fills: Vec::new(),
+            filled_qty: requested.saturating_sub(quantity),
+            remaining_qty: quantity,
+        }
+    }
+
+    fn available_liquidity(&self, side: Side, max: Quantity, limit: Option<Price>) -> Quantity {
+        let cap = max.as_u64();
+        let mut total: u64 = 0;
+
+        // Same traversal order as execute_market_order, without mutating
+        let levels = self.levels(side.opposite());
+
+        for (price_value, level) in levels {
+            let beyond_limit = limit.is_some_and(|l| match side {
+                Side::Bid => price_value > l.value(),
+                Side::Ask => price_value < l.value(),
+            });
+            if total >= cap || beyond_limit {
+                break;
+            }
+            total += level.total_quantity();
+        }
+
+        Quantity::define(total.min(cap) as QtyValue)
+    }
+
+    fn sweep_cost(&self, side: Side, quantity: Quantity) -> Option<SweepQuote> {
+        // Same traversal order as execute_market_order, without mutating
+        let levels = self.levels(side.opposite());
+
+        let level_quantities = levels.map(|(price_value, level)| (price_value, level.total_quantity()));
+        SweepQuote::walk(quantity, level_quantities)
+    }
+
+    fn best_price(&self, side: Side) -> Option<Price> {
+        let best = match side {
+            Side::Bid => self.best_bid_idx,
+            Side::Ask => self.best_ask_idx,
+        };
+        best.map(|i| Price::define(self.config.price_at(i)))
+    }
+
+    fn worst_price(&self, side: Side) -> Option<Price> {
+        // Only the best end is cached: scan in from the far end of the grid,
+        // stopping at the best level at the latest
+        let worst = match side {
+            Side::Bid => self.best_bid_idx.and_then(|best| (0..=best).find(|&i| !self.bids[i].is_empty())),
+            Side::Ask => self
+                .best_ask_idx
+                .and_then(|best| (best..self.asks.len()).rev().find(|&i| !self.asks[i].is_empty())),
+        };
+        worst.map(|i| Price::define(self.config.price_at(i)))
+    }
+
+    fn last_trade(&self) -> Option<(Price, Quantity)> {
+        self.fill_seq.last_trade()
+    }
+
+    fn depth_at_price(&self, price: Price, side: Side) -> u64 {
+        let price_value = price.value();
+
+        if !self.config.is_valid_price(price_value) {
+            return 0;
+        }
+
+        let index = self.config.index_of(price_value);
 
         match side {
+            Side::Bid => self.bids[index].total_quantity(),
+            Side::Ask => self.asks[index].total_quantity(),
+        }
+    }
+
+    fn order_count_at_price(&self, price: Price, side: Side) -> u32 {
+        if !self.config.is_valid_price(price.value()) {
+            return 0;
+        }
+        // Only the id array's length
+        self.level_len(side, price.value()) as u32
+    }
+
+    fn depth_in_range(&self, side: Side, from: Price, to: Price) -> u64 {
+        let Some(slots) = self.config.slot_range(from, to) else {
+            return 0;
+        };
+
+        let levels = match side {
+            Side::Bid => &self.bids[slots],
+            Side::Ask => &self.asks[slots],
+        };
+        levels.iter().map(|level| level.total_quantity()).sum()
+    }
+
+    fn stats(&self) -> BookStats {
+        BookStats::from_totals(self.bid_levels, self.ask_levels, self.bid_totals, self.ask_totals)
+    }
+
+    fn memory_footprint(&self) -> MemoryFootprint {
+        let slots = self.bids.len() + self.asks.len();
+        let arrays: usize = self.bids.iter().chain(self.asks.iter()).map(LevelSoA::heap_bytes).sum();
+        MemoryFootprint {
+            fixed_bytes: size_of::<Self>() + slots * size_of::<LevelSoA>(),
+            heap_bytes: arrays + hash_map_bytes(&self.order_index),
+            orders: self.bid_totals.orders + self.ask_totals.orders,
+        }
+    }
+
+    fn best_n_prices(&self, side: Side, n: usize) -> Vec<Price> {
+        self.levels(side).take(n).map(|(price_value, _)| Price::define(price_value)).collect()
+    }
+
+    fn book_snapshot(&self, depth: usize) -> BookSnapshot {
+        let top = |side| {
+            self.levels(side)
+                .take(depth)
+                .map(|(price_value, level)| (Price::define(price_value), level.total_quantity()))
+                .collect()
+        };
+
+        BookSnapshot {
+            bids: top(Side::Bid),
+            asks: top(Side::Ask),
+        }
+    }
+
+    fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = check_invariants(self, &self.order_index);
+
+        // The best-price caches must point at populated levels
+        for (side, best, levels) in [
+            (Side::Bid, self.best_bid_idx, &self.bids),
+            (Side::Ask, self.best_ask_idx, &self.asks),
+        ] {
+            if best.is_some_and(|i| levels[i].is_empty()) {
+                problems.push(format!("Best {:?} index points at an empty level", side));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    fn iter_orders(&self, side: Side) -> impl Iterator<Item = (Price, Order)> {
+        self.levels(side).flat_map(|(price_value, level)| {
+            level.orders().map(move |order| (Price::define(price_value), order))
+        })
+    }
+}
+
+impl Orderbook {
+    /// with_config, with the per-tick level arrays on huge pages
+    /// See SlotArray::on_hugepages for what that takes and the non-Linux fallback
+    #[cfg(feature = "hugepages")]
+    pub fn with_hugepages(config: BookConfig) -> Result<Self, String> {
+        Self::with_arrays(config, SlotArray::on_hugepages)
+    }
+
+    /// A book whose bid and ask arrays `alloc` builds, given the slot count
+    fn with_arrays(config: BookConfig, alloc: impl Fn(usize) -> SlotArray<LevelSoA>) -> Result<Self, String> {
+        config.validate()?;
+
+        let slots = config.slots();
+        Ok(Self {
+            bids: alloc(slots),
+            asks: alloc(slots),
+            order_index: HashMap::new(),
+            best_bid_idx: None,
+            best_ask_idx: None,
+            bid_levels: 0,
+            ask_levels: 0,
+            bid_totals: SideTotals::default(),
+            ask_totals: SideTotals::default(),
+            config,
+            fill_seq: FillSequence::default(),
+            arrival_seq: ArrivalSequence::default(),
+            expiries: ExpiryQueue::default(),
+        })
+    }
+
+    /// Orders resting at an on-grid `price_value`
+    fn level_len(&self, side: Side, price_value: u32) -> usize {
+        let index = self.config.index_of(price_value);
+        match side {
+            Side::Bid => self.bids[index].ids.len(),
+            Side::Ask => self.asks[index].ids.len(),
+        }
+    }
+
+    /// Populated levels on `side`, best price first
+    /// Bounded scan: starts at the cached best index, skips empty slots
+    fn levels(&self, side: Side) -> Box<dyn Iterator<Item = (u32, &LevelSoA)> + '_> {
+        let config = self.config;
+        match side {
+            Side::Bid => Box::new(
+                self.best_bid_idx
+                    .into_iter()
+                    .flat_map(|best| (0..=best).rev())
+                    .filter(|&i| !self.bids[i].is_empty())
+                    .map(move |i| (config.price_at(i), &self.bids[i])),
+            ),
+            Side::Ask => Box::new(
+                self.best_ask_idx
+                    .into_iter()
+                    .flat_map(|best| best..self.asks.len())
+                    .filter(|&i| !self.asks[i].is_empty())
+                    .map(move |i| (config.price_at(i), &self.asks[i])),
+            ),
+        }
+    }
+
+    fn crosses(&self, side: Side, price: Price) -> bool {
+        match side {
+            Side::Bid => self.best_ask().is_some_and(|ask| ask.value() <= price.value()),
+            Side::Ask => self.best_bid().is_some_and(|bid| bid.value() >= price.value()),
+        }
+    }
+
+    /// Walk the opposite side for an incoming order on `side`, best price first
+    /// Stops once `remaining_qty` is zero or the next level is beyond `limit`
+    /// Each fill is sequenced, then handed to `on_fill`
+    fn match_incoming(
+        &mut self,
+        remaining_qty: &mut Quantity,
+        taker: Taker,
+        on_fill: &mut impl FnMut(&Fill),
+    ) {
+        let fill_seq = &mut self.fill_seq;
+        let mut on_fill = |mut fill: Fill| {
+            fill_seq.stamp(&mut fill);
+            on_fill(&fill);
+        };
+
+        match taker.side {
             Side::Bid => {
-                for i in 0..ELEMENT_NUM {
-                    if quantity.value() == 0 {
+                let Some(start) = self.best_ask_idx else {
+                    return;
+                };
+                for i in start..self.asks.len() {
+                    if remaining_qty.is_zero() {
+                        break;
+                    }
+                    let price = Price::define(self.config.price_at(i));
+                    if taker.limit.is_some_and(|l| price.value() > l.value()) {
                         break;
                     }
                     if self.asks[i].is_empty() {
                         continue;
                     }
-                    let price = Price::define((i as u32) * TICK_SIZE);
-                    let level_fills =
-                        self.asks[i].match_orders(&mut quantity, price, &mut self.order_index);
-                    fills.extend(level_fills);
+                    self.asks[i].match_orders(
+                        remaining_qty,
+                        price,
+                        taker,
+                        &mut self.order_index,
+                        &mut self.ask_totals,
+                        &mut on_fill,
+                    );
+                    self.ask_levels -= self.asks[i].is_empty() as usize;
                 }
+                self.best_ask_idx = self.next_ask_at_or_above(start);
             }
             Side::Ask => {
-                for i in (0..ELEMENT_NUM).rev() {
-                    if quantity.value() == 0 {
+                let Some(start) = self.best_bid_idx else {
+                    return;
+                };
+                for i in (0..=start).rev() {
+                    if remaining_qty.is_zero() {
+                        break;
+                    }
+                    let price = Price::define(self.config.price_at(i));
+                    if taker.limit.is_some_and(|l| price.value() < l.value()) {
                         break;
                     }
                     if self.bids[i].is_empty() {
                         continue;
                     }
-                    let price = Price::define((i as u32) * TICK_SIZE);
-                    let level_fills =
-                        self.bids[i].match_orders(&mut quantity, price, &mut self.order_index);
-                    fills.extend(level_fills);
+                    self.bids[i].match_orders(
+                        remaining_qty,
+                        price,
+                        taker,
+                        &mut self.order_index,
+                        &mut self.bid_totals,
+                        &mut on_fill,
+                    );
+                    self.bid_levels -= self.bids[i].is_empty() as usize;
                 }
+                self.best_bid_idx = self.next_bid_at_or_below(start);
             }
         }
+    }
 
-        if quantity.value() > 0 {
-            return Err(format!(
-                "Market order partially filled: {} remaining",
-                quantity.value()
-            ));
-        }
+    fn next_bid_at_or_below(&self, i: usize) -> Option<usize> {
+        (0..=i).rev().find(|&j| !self.bids[j].is_empty())
+    }
 
-        Ok(fills)
+    fn next_ask_at_or_above(&self, i: usize) -> Option<usize> {
+        (i..self.asks.len()).find(|&j| !self.asks[j].is_empty())
     }
+}
 
-    fn best_bid(&self) -> Option<Price> {
-        for i in (0..ELEMENT_NUM).rev() {
-            if !self.bids[i].is_empty() {
-                return Some(Price::define((i as u32) * TICK_SIZE));
-            }
-        }
-        None
+impl LevelSoA {
+    /// Grow every array so the level holds at least `orders` without reallocating
+    pub fn reserve(&mut self, orders: usize) {
+        let additional = orders.saturating_sub(self.ids.len());
+        self.ids.reserve(additional);
+        self.sides.reserve(additional);
+        self.prices.reserve(additional);
+        self.quantities.reserve(additional);
+        self.accounts.reserve(additional);
+        self.seqs.reserve(additional);
+        self.expiries.reserve(additional);
     }
 
-    fn best_ask(&self) -> Option<Price> {
-        for i in 0..ELEMENT_NUM {
-            if !self.asks[i].is_empty() {
-                return Some(Price::define((i as u32) * TICK_SIZE));
-            }
-        }
-        None
+    /// Bytes allocated across the seven arrays
+    pub fn heap_bytes(&self) -> usize {
+        self.ids.capacity() * size_of::<u64>()
+            + self.sides.capacity() * size_of::<Side>()
+            + self.prices.capacity() * size_of::<Price>()
+            + self.quantities.capacity() * size_of::<Quantity>()
+            + self.accounts.capacity() * size_of::<AccountId>()
+            + self.seqs.capacity() * size_of::<u64>()
+            + self.expiries.capacity() * size_of::<u64>()
     }
 
-    fn depth_at_price(&self, price: Price, side: Side) -> u32 {
-        let price_value = price.value();
+    /// Queue position of an order - only searches the ID array
+    fn position(&self, order_id: OrderId) -> Option<usize> {
+        self.ids.iter().position(|&id| id == order_id)
+    }
 
-        if price_value == 0 || price_value >= MAX_PRICE {
-            return 0;
-        }
+    /// Drop every order, keeping the arrays' capacity
+    fn clear(&mut self) {
+        self.ids.clear();
+        self.sides.clear();
+        self.prices.clear();
+        self.quantities.clear();
+        self.accounts.clear();
+        self.seqs.clear();
+        self.expiries.clear();
+    }
 
-        if price_value % TICK_SIZE != 0 {
-            return 0;
-        }
+    /// Rebuild each queued order in FIFO order (front of the arrays first)
+    pub fn orders(&self) -> impl Iterator<Item = Order> + '_ {
+        (0..self.ids.len()).map(|i| {
+            let mut order = Order::from_parts(self.ids[i], self.prices[i], self.quantities[i], self.sides[i])
+                .with_account(self.accounts[i])
+                .with_expiry(self.expiries[i]);
+            order.set_seq(self.seqs[i]);
+            order
+        })
+    }
 
-        let index = (price_value / TICK_SIZE) as usize;
+    /// Match orders FIFO - consumes liquidity from this level
+    /// THIS IS WHERE AoS WINS: Need all fields, so 4 separate array accesses
+    /// vs AoS: 1 array access gets all fields
+    pub(crate) fn match_orders(
+        &mut self,
+        remaining_qty: &mut Quantity,
+        price: Price,
+        taker: Taker,
+        order_index: &mut HashMap<OrderId, (Side, Price)>,
+        totals: &mut SideTotals,
+        on_fill: &mut impl FnMut(Fill),
+    ) {
+        let mut queue = SoaQueue { level: self, order_index, totals };
+        match_level(&mut queue, remaining_qty, price, taker, on_fill)
+    }
 
-        match side {
-            Side::Bid => self.bids[index].total_quantity(),
-            Side::Ask => self.asks[index].total_quantity(),
-        }
+    /// Remove the order at `idx` from all arrays and from `order_index`
+    fn remove_at(&mut self, idx: usize, order_index: &mut HashMap<OrderId, (Side, Price)>) {
+        let removed_id = self.ids.remove(idx);
+        self.sides.remove(idx);
+        self.prices.remove(idx);
+        self.quantities.remove(idx);
+        self.accounts.remove(idx);
+        self.seqs.remove(idx);
+        self.expiries.remove(idx);
+        order_index.remove(&removed_id);
     }
 }
 
-impl LevelSoA {
+impl PriceLevel for LevelSoA {
     /// Add order to this level - appends to all arrays
-    pub fn add_order(&mut self, order: Order) {
+    fn add(&mut self, order: Order) {
         self.ids.push(order.id());
         self.sides.push(order.side());
         self.prices.push(order.price());
         self.quantities.push(order.quantity());
+        self.accounts.push(order.account());
+        self.seqs.push(order.seq());
+        self.expiries.push(order.expiry().unwrap_or(0));
     }
 
     /// Cancel order by ID - requires searching all IDs
     /// THIS IS WHERE SoA WINS: Only loads ID array (8 IDs per cache line)
     /// vs AoS: loads full Order structs (2-3 per cache line)
-    pub fn cancel_order(&mut self, order_id: OrderId) -> Option<Order> {
+    fn cancel(&mut self, order_id: OrderId) -> Option<Order> {
         // Find position - only searches ID array (better cache utilization!)
         let pos = self.ids.iter().position(|&id| id == order_id)?;
 
         // Remove from all arrays
-        let _id = self.ids.remove(pos);
+        let id = self.ids.remove(pos);
         let side = self.sides.remove(pos);
         let price = self.prices.remove(pos);
         let quantity = self.quantities.remove(pos);
-
-        // Reconstruct Order for return
-        Some(Order::new(
-            price,
-            quantity,
-            side,
-            &mut crate::types::order::IdCounter::new(),
-        ))
+        let account = self.accounts.remove(pos);
+        let seq = self.seqs.remove(pos);
+        let expiry = self.expiries.remove(pos);
+
+        // Reconstruct Order for return, keeping its real id
+        let mut order = Order::from_parts(id, price, quantity, side).with_account(account).with_expiry(expiry);
+        order.set_seq(seq);
+        Some(order)
     }
 
     /// Total quantity at this level
     /// THIS IS WHERE SoA WINS BIG: Only loads quantity array (16 per cache line)
     /// vs AoS: loads full Order structs (2-3 per cache line) = ~6x worse
-    pub fn total_quantity(&self) -> u32 {
-        self.quantities.iter().map(|q| q.value()).sum()
+    fn total_quantity(&self) -> u64 {
+        // Accumulate in u64: many large orders can exceed u32::MAX
+        self.quantities.iter().map(|q| q.as_u64()).sum()
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.ids.is_empty()
+    fn order_count(&self) -> usize {
+        self.ids.len()
     }
+}
 
-    /// Match orders FIFO - consumes liquidity from this level
-    /// THIS IS WHERE AoS WINS: Need all fields, so 4 separate array accesses
-    /// vs AoS: 1 array access gets all fields
-    pub fn match_orders(
-        &mut self,
-        remaining_qty: &mut Quantity,
-        price: Price,
-        order_index: &mut HashMap<OrderId, (Side, Price)>,
-    ) -> Vec<Fill> {
-        let mut fills = Vec::new();
-        let mut orders_to_remove = Vec::new();
-
-        for idx in 0..self.ids.len() {
-            if remaining_qty.value() == 0 {
-                break;
-            }
-
-            // SoA: Need to access 3 separate arrays (id, quantity, ...)
-            let order_id = self.ids[idx];
-            let order_qty = self.quantities[idx].value();
-            let fill_qty = remaining_qty.value().min(order_qty);
-
-            fills.push(Fill {
-                price,
-                quantity: Quantity::define(fill_qty),
-                maker_order_id: order_id,
-            });
-
-            *remaining_qty = Quantity::define(remaining_qty.value() - fill_qty);
+/// LevelQueue over a SoA level: reads touch only the id, quantity and account
+/// arrays; a removal shifts all seven
+struct SoaQueue<'a> {
+    level: &'a mut LevelSoA,
+    order_index: &'a mut HashMap<OrderId, (Side, Price)>,
+    totals: &'a mut SideTotals,
+}
 
-            if fill_qty == order_qty {
-                orders_to_remove.push(idx);
-            } else {
-                panic!("Partial fills of resting orders not yet implemented");
-            }
-        }
+impl LevelQueue for SoaQueue<'_> {
+    fn maker(&self, i: usize) -> Option<(OrderId, Quantity, AccountId)> {
+        let level = &*self.level;
+        Some((*level.ids.get(i)?, level.quantities[i], level.accounts[i]))
+    }
 
-        // Remove filled orders from all arrays
-        for &idx in orders_to_remove.iter().rev() {
-            let removed_id = self.ids.remove(idx);
-            self.sides.remove(idx);
-            self.prices.remove(idx);
-            self.quantities.remove(idx);
-            order_index.remove(&removed_id);
-        }
+    fn remove(&mut self, i: usize) {
+        self.totals.removed(self.level.quantities[i]);
+        self.level.remove_at(i, self.order_index);
+    }
 
-        fills
+    fn reduce(&mut self, i: usize, qty: Quantity) {
+        self.totals.reduced(qty);
+        self.level.quantities[i] = self.level.quantities[i].saturating_sub(qty);
     }
 }
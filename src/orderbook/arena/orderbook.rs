@@ -0,0 +1,808 @@
+use crate::orderbook::fixed_tick::bitmap::OccupancyBitmap;
+use crate::orderbook::error::OrderError;
+use crate::orderbook::{
+    check_invariants, fillable_quantity, hash_map_bytes, match_level, ArrivalSequence, BookConfig,
+    BookSnapshot, BookStats, ExpiryQueue, Fill, FillSequence, LevelQueue, LimitOrderResult, MarketOrderResult,
+    MemoryFootprint, OrderView, OrderbookTrait, SideTotals, StpMode, SweepQuote, Taker,
+};
+use crate::types::order::{AccountId, Order, OrderId, Side, TimeInForce};
+use crate::types::price::Price;
+use crate::types::quantity::{QtyValue, Quantity};
+use std::cell::Cell;
+use std::collections::HashMap;
+
+/// Link to no slot: the end of a list
+const NIL: u32 = u32::MAX;
+
+/// Fixed-tick book that keeps every resting order in one arena
+///
+/// Each price level is a doubly linked list threaded through the arena by
+/// u32 slot index. Adding is a push (or a reused free slot) plus a link;
+/// cancelling finds the slot through `order_index` and unlinks it in O(1),
+/// where the VecDeque levels scan the queue and shift what follows. Freed
+/// slots go on a free list and are reused before the arena grows, so a
+/// cancel-heavy flow stops allocating once the arena has warmed up.
+pub struct Orderbook {
+    // One slot per tick, index = price / tick_size
+    bids: Box<[Level]>,
+    asks: Box<[Level]>,
+    arena: Arena,
+    // Order id -> arena slot; side and price are read from the slot
+    order_index: HashMap<OrderId, u32>,
+    bid_occupancy: OccupancyBitmap,
+    ask_occupancy: OccupancyBitmap,
+    bid_totals: SideTotals,
+    ask_totals: SideTotals,
+    config: BookConfig,
+    fill_seq: FillSequence,
+    arrival_seq: ArrivalSequence,
+    expiries: ExpiryQueue,
+}
+
+/// Ends of one level's list in the arena, with its order count and total
+/// Level Memory: 24 bytes, however many orders it holds
+#[derive(Debug, Clone, Copy)]
+pub struct Level {
+    head: u32,
+    tail: u32,
+    len: u32,
+    total_qty: u64,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self { head: NIL, tail: NIL, len: 0, total_qty: 0 }
+    }
+}
+
+impl Level {
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Cached sum of the level's quantities, O(1)
+    pub fn total_quantity(&self) -> u64 {
+        self.total_qty
+    }
+}
+
+/// An order and its neighbours on its level
+/// A free slot keeps its last order; `next` then chains the free list
+#[derive(Debug, Clone, Copy)]
+struct OrderSlot {
+    order: Order,
+    prev: u32,
+    next: u32,
+}
+
+/// Every resting order of a book, addressed by slot index
+struct Arena {
+    slots: Vec<OrderSlot>,
+    // Head of the free list
+    free: u32,
+}
+
+impl Arena {
+    fn new() -> Self {
+        Self { slots: Vec::new(), free: NIL }
+    }
+
+    fn order(&self, slot: u32) -> &Order {
+        &self.slots[slot as usize].order
+    }
+
+    /// Queue `order` at the back of `level`, returning its slot
+    fn push_back(&mut self, level: &mut Level, order: Order) -> u32 {
+        let entry = OrderSlot { order, prev: level.tail, next: NIL };
+        let slot = match self.free {
+            NIL => {
+                self.slots.push(entry);
+                (self.slots.len() - 1) as u32
+            }
+            free => {
+                self.free = self.slots[free as usize].next;
+                self.slots[free as usize] = entry;
+                free
+            }
+        };
+
+        match level.tail {
+            NIL => level.head = slot,
+            tail => self.slots[tail as usize].next = slot,
+        }
+        level.tail = slot;
+        level.len += 1;
+        level.total_qty += order.quantity().as_u64();
+        slot
+    }
+
+    /// Take `slot` off `level` and onto the free list
+    fn unlink(&mut self, level: &mut Level, slot: u32) -> Order {
+        let OrderSlot { order, prev, next } = self.slots[slot as usize];
+        match prev {
+            NIL => level.head = next,
+            prev => self.slots[prev as usize].next = next,
+        }
+        match next {
+            NIL => level.tail = prev,
+            next => self.slots[next as usize].prev = prev,
+        }
+        level.len -= 1;
+        level.total_qty -= order.quantity().as_u64();
+
+        self.slots[slot as usize].next = self.free;
+        self.free = slot;
+        order
+    }
+
+    /// The order in `slot`, on `level`, traded or was amended down by `qty`
+    fn reduce(&mut self, level: &mut Level, slot: u32, qty: Quantity) {
+        let order = &mut self.slots[slot as usize].order;
+        order.set_quantity(order.quantity().saturating_sub(qty));
+        level.total_qty -= qty.as_u64();
+    }
+
+    /// Slots of `level`, front of the queue first
+    fn slots_of(&self, level: &Level) -> impl Iterator<Item = u32> + '_ {
+        let head = (level.head != NIL).then_some(level.head);
+        std::iter::successors(head, |&slot| {
+            let next = self.slots[slot as usize].next;
+            (next != NIL).then_some(next)
+        })
+    }
+}
+
+impl OrderbookTrait for Orderbook {
+    fn with_config(config: BookConfig) -> Result<Self, String> {
+        config.validate()?;
+
+        let slots = config.slots();
+        Ok(Self {
+            bids: vec![Level::default(); slots].into_boxed_slice(),
+            asks: vec![Level::default(); slots].into_boxed_slice(),
+            arena: Arena::new(),
+            order_index: HashMap::new(),
+            bid_occupancy: OccupancyBitmap::new(slots),
+            ask_occupancy: OccupancyBitmap::new(slots),
+            bid_totals: SideTotals::default(),
+            ask_totals: SideTotals::default(),
+            config,
+            fill_seq: FillSequence::default(),
+            arrival_seq: ArrivalSequence::default(),
+            expiries: ExpiryQueue::default(),
+        })
+    }
+
+    fn config(&self) -> &BookConfig {
+        &self.config
+    }
+
+    /// Levels own no storage here; the arena gets room for one full level
+    fn reserve(&mut self, orders_per_level: usize) {
+        self.arena.slots.reserve(orders_per_level);
+    }
+
+    fn reserve_orders(&mut self, additional: usize) {
+        self.order_index.reserve(additional);
+        self.arena.slots.reserve(additional);
+    }
+
+    fn add_order(&mut self, mut order: Order) -> Result<LimitOrderResult, OrderError> {
+        let order_id = order.id();
+        let side = order.side();
+        let price_value = order.price().value();
+
+        self.config.validate_order(order.price(), order.quantity())?;
+        let resting = self.level(side, price_value).map_or(0, Level::len);
+        self.config.check_level_room(side, order.price(), resting)?;
+        self.config.check_crossing(&order, || self.crosses(side, order.price()))?;
+
+        // Fill-or-kill: reject before anything trades unless the whole
+        // quantity is available at or better than the limit price
+        if order.time_in_force() == TimeInForce::FOK {
+            let available = fillable_quantity(self, &order);
+            if available < order.quantity() {
+                return Err(OrderError::FokUnfillable {
+                    order: order.id(),
+                    available: available.value(),
+                    requested: order.quantity().value(),
+                });
+            }
+        }
+
+        // Marketable limit order: take liquidity up to the limit price first
+        let mut remaining_qty = order.quantity();
+        let mut fills = Vec::new();
+        if self.crosses(side, order.price()) {
+            let taker = Taker::limit_order(&order, &self.config);
+            self.match_incoming(&mut remaining_qty, taker, &mut |fill| fills.push(fill.clone()));
+        }
+
+        let filled_qty = order.quantity().saturating_sub(remaining_qty);
+
+        // Nothing left, IOC/FOK, or a remainder that would rest crossed against
+        // orders STP skipped: the unfilled remainder is canceled, not rested
+        if remaining_qty.is_zero()
+            || order.time_in_force() != TimeInForce::GTC
+            || (self.config.stp == StpMode::Skip && self.crosses(side, order.price()))
+        {
+            return Ok(LimitOrderResult {
+                fills,
+                filled_qty,
+                rested_qty: Quantity::define(0),
+                canceled_qty: remaining_qty,
+            });
+        }
+        order.set_quantity(remaining_qty);
+        self.arrival_seq.stamp(&mut order);
+        self.expiries.track(&order);
+
+        let i = self.config.index_of(price_value);
+        let slot = match side {
+            Side::Bid => {
+                self.bid_occupancy.set(i);
+                self.bid_totals.rested(remaining_qty);
+                self.arena.push_back(&mut self.bids[i], order)
+            }
+            Side::Ask => {
+                self.ask_occupancy.set(i);
+                self.ask_totals.rested(remaining_qty);
+                self.arena.push_back(&mut self.asks[i], order)
+            }
+        };
+
+        self.order_index.insert(order_id, slot);
+
+        Ok(LimitOrderResult {
+            fills,
+            filled_qty,
+            rested_qty: remaining_qty,
+            canceled_qty: Quantity::define(0),
+        })
+    }
+
+    fn cancel_order(&mut self, order_id: OrderId) -> Result<Order, OrderError> {
+        let slot = self
+            .order_index
+            .remove(&order_id)
+            .ok_or(OrderError::NotFound(order_id))?;
+
+        let order = *self.arena.order(slot);
+        let i = self.config.index_of(order.price().value());
+
+        let (level, occupancy, totals) = match order.side() {
+            Side::Bid => (&mut self.bids[i], &mut self.bid_occupancy, &mut self.bid_totals),
+            Side::Ask => (&mut self.asks[i], &mut self.ask_occupancy, &mut self.ask_totals),
+        };
+        // O(1): the slot knows its neighbours
+        self.arena.unlink(level, slot);
+        totals.removed(order.quantity());
+        if level.is_empty() {
+            occupancy.clear(i);
+        }
+
+        Ok(order)
+    }
+
+    fn cancel_all_for_side(&mut self, side: Side) -> usize {
+        let (levels, occupancy, totals) = match side {
+            Side::Bid => (&mut self.bids, &mut self.bid_occupancy, &mut self.bid_totals),
+            Side::Ask => (&mut self.asks, &mut self.ask_occupancy, &mut self.ask_totals),
+        };
+        *totals = SideTotals::default();
+
+        // Only populated slots are visited, via the occupancy bitmap; their
+        // orders go back on the free list, keeping the arena's capacity
+        let mut removed = 0;
+        let mut next = occupancy.first();
+        while let Some(i) = next {
+            while levels[i].head != NIL {
+                let slot = levels[i].head;
+                let order = self.arena.unlink(&mut levels[i], slot);
+                self.order_index.remove(&order.id());
+                removed += 1;
+            }
+            occupancy.clear(i);
+            next = occupancy.next_at_or_above(i + 1);
+        }
+
+        removed
+    }
+
+    fn take_due_expiries(&mut self, now: u64) -> Vec<OrderId> {
+        self.expiries.take_due(now)
+    }
+
+    fn clear(&mut self) {
+        self.cancel_all();
+        self.fill_seq = FillSequence::default();
+        self.arrival_seq = ArrivalSequence::default();
+        self.expiries.clear();
+    }
+
+    fn contains_order(&self, order_id: OrderId) -> bool {
+        self.order_index.contains_key(&order_id)
+    }
+
+    fn get_order(&self, order_id: OrderId) -> Option<OrderView> {
+        let &slot = self.order_index.get(&order_id)?;
+        let order = self.arena.order(slot);
+
+        // O(n) walk back to the head for the queue position
+        let queue_position = std::iter::successors(Some(slot), |&s| {
+            let prev = self.arena.slots[s as usize].prev;
+            (prev != NIL).then_some(prev)
+        })
+        .count()
+            - 1;
+
+        Some(OrderView {
+            price: order.price(),
+            side: order.side(),
+            remaining_qty: order.quantity(),
+            queue_position,
+        })
+    }
+
+    fn amend_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_qty: Option<Quantity>,
+    ) -> Result<(), String> {
+        let slot = *self
+            .order_index
+            .get(&order_id)
+            .ok_or_else(|| format!("Order {} not found", order_id))?;
+
+        let order = *self.arena.order(slot);
+        let (side, price) = (order.side(), order.price());
+        let target_price = new_price.unwrap_or(price);
+        let target_qty = new_qty.unwrap_or(order.quantity());
+
+        // Pure size reduction: amend in place and keep queue position
+        if target_price == price && target_qty <= order.quantity() {
+            self.config.validate_order(price, target_qty)?;
+            let i = self.config.index_of(price.value());
+            let (level, totals) = match side {
+                Side::Bid => (&mut self.bids[i], &mut self.bid_totals),
+                Side::Ask => (&mut self.asks[i], &mut self.ask_totals),
+            };
+            let reduction = order.quantity().saturating_sub(target_qty);
+            self.arena.reduce(level, slot, reduction);
+            totals.reduced(reduction);
+            return Ok(());
+        }
+
+        // Anything else loses time priority; validate up front so a rejected
+        // amend leaves the resting order untouched
+        let mut replacement = order;
+        replacement.set_price(target_price);
+        replacement.set_quantity(target_qty);
+
+        self.config.validate_order(target_price, target_qty)?;
+        if target_price != price {
+            let resting = self.level(side, target_price.value()).map_or(0, Level::len);
+            self.config.check_level_room(side, target_price, resting)?;
+        }
+        if self.crosses(side, target_price) {
+            return Err(format!(
+                "Amended price {} would cross the book",
+                target_price.value()
+            ));
+        }
+
+        self.cancel_order(order_id)?;
+        self.add_order(replacement)?;
+        Ok(())
+    }
+
+    fn best_price(&self, side: Side) -> Option<Price> {
+        let best = match side {
+            Side::Bid => self.bid_occupancy.last(),
+            Side::Ask => self.ask_occupancy.first(),
+        };
+        best.map(|i| Price::define(self.config.price_at(i)))
+    }
+
+    fn worst_price(&self, side: Side) -> Option<Price> {
+        let worst = match side {
+            Side::Bid => self.bid_occupancy.first(),
+            Side::Ask => self.ask_occupancy.last(),
+        };
+        worst.map(|i| Price::define(self.config.price_at(i)))
+    }
+
+    fn execute_market_order_with(
+        &mut self,
+        side: Side,
+        mut remaining_qty: Quantity,
+        taker_id: OrderId,
+        mut on_fill: impl FnMut(&Fill),
+    ) -> MarketOrderResult {
+        let requested = remaining_qty;
+        let taker = Taker::market(side, taker_id, &self.config);
+        self.match_incoming(&mut remaining_qty, taker, &mut on_fill);
+
+        MarketOrderResult {
+            fills: Vec::new(),
+            filled_qty: requested.saturating_sub(remaining_qty),
+            remaining_qty,
+        }
+    }
+
+    fn available_liquidity(&self, side: Side, max: Quantity, limit: Option<Price>) -> Quantity {
+        let cap = max.as_u64();
+        let mut total: u64 = 0;
+
+        // Same traversal order as execute_market_order, without mutating
+        for (price_value, level) in self.levels(side.opposite()) {
+            let beyond_limit = limit.is_some_and(|l| match side {
+                Side::Bid => price_value > l.value(),
+                Side::Ask => price_value < l.value(),
+            });
+            if total >= cap || beyond_limit {
+                break;
+            }
+            total += level.total_quantity();
+        }
+
+        Quantity::define(total.min(cap) as QtyValue)
+    }
+
+    fn sweep_cost(&self, side: Side, quantity: Quantity) -> Option<SweepQuote> {
+        // Same traversal order as execute_market_order, without mutating
+        let levels = self.levels(side.opposite());
+
+        let level_quantities = levels.map(|(price_value, level)| (price_value, level.total_quantity()));
+        SweepQuote::walk(quantity, level_quantities)
+    }
+
+    fn last_trade(&self) -> Option<(Price, Quantity)> {
+        self.fill_seq.last_trade()
+    }
+
+    fn depth_at_price(&self, price: Price, side: Side) -> u64 {
+        if !self.config.is_valid_price(price.value()) {
+            return 0;
+        }
+        self.level(side, price.value()).map_or(0, Level::total_quantity)
+    }
+
+    fn order_count_at_price(&self, price: Price, side: Side) -> u32 {
+        if !self.config.is_valid_price(price.value()) {
+            return 0;
+        }
+        self.level(side, price.value()).map_or(0, Level::len) as u32
+    }
+
+    fn depth_in_range(&self, side: Side, from: Price, to: Price) -> u64 {
+        let Some(slots) = self.config.slot_range(from, to) else {
+            return 0;
+        };
+
+        let levels = match side {
+            Side::Bid => &self.bids[slots],
+            Side::Ask => &self.asks[slots],
+        };
+        levels.iter().map(Level::total_quantity).sum()
+    }
+
+    fn stats(&self) -> BookStats {
+        BookStats::from_totals(
+            self.bid_occupancy.len(),
+            self.ask_occupancy.len(),
+            self.bid_totals,
+            self.ask_totals,
+        )
+    }
+
+    fn memory_footprint(&self) -> MemoryFootprint {
+        let slots = self.bids.len() + self.asks.len();
+        MemoryFootprint {
+            fixed_bytes: size_of::<Self>()
+                + slots * size_of::<Level>()
+                + self.bid_occupancy.heap_bytes()
+                + self.ask_occupancy.heap_bytes(),
+            heap_bytes: self.arena.slots.capacity() * size_of::<OrderSlot>()
+                + hash_map_bytes(&self.order_index),
+            orders: self.bid_totals.orders + self.ask_totals.orders,
+        }
+    }
+
+    fn best_n_prices(&self, side: Side, n: usize) -> Vec<Price> {
+        self.levels(side).take(n).map(|(price_value, _)| Price::define(price_value)).collect()
+    }
+
+    fn book_snapshot(&self, depth: usize) -> BookSnapshot {
+        let top = |side| {
+            self.levels(side)
+                .take(depth)
+                .map(|(price_value, level)| (Price::define(price_value), level.total_quantity()))
+                .collect()
+        };
+
+        BookSnapshot {
+            bids: top(Side::Bid),
+            asks: top(Side::Ask),
+        }
+    }
+
+    /// Also walks every level's links: each must point back at its
+    /// neighbour, and the cached count and total must match the list
+    fn validate(&self) -> Result<(), Vec<String>> {
+        let located: HashMap<OrderId, (Side, Price)> = self
+            .order_index
+            .iter()
+            .map(|(&id, &slot)| {
+                let order = self.arena.order(slot);
+                (id, (order.side(), order.price()))
+            })
+            .collect();
+        let mut problems = check_invariants(self, &located);
+
+        for side in Side::all() {
+            for (price_value, level) in self.levels(side) {
+                let (mut len, mut total, mut prev) = (0, 0, NIL);
+                for slot in self.arena.slots_of(level) {
+                    let entry = &self.arena.slots[slot as usize];
+                    if entry.prev != prev {
+                        problems.push(format!(
+                            "{:?} level {} slot {} links back to {} instead of {}",
+                            side, price_value, slot, entry.prev, prev
+                        ));
+                    }
+                    if self.order_index.get(&entry.order.id()) != Some(&slot) {
+                        problems.push(format!(
+                            "Order {} in slot {} is not indexed to it",
+                            entry.order.id(),
+                            slot
+                        ));
+                    }
+                    len += 1;
+                    total += entry.order.quantity().as_u64();
+                    prev = slot;
+                }
+                if level.tail != prev || level.len != len || level.total_qty != total {
+                    problems.push(format!(
+                        "{:?} level {} caches {} orders / {} total but holds {} / {}",
+                        side, price_value, level.len, level.total_qty, len, total
+                    ));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    fn iter_orders(&self, side: Side) -> impl Iterator<Item = (Price, Order)> {
+        let arena = &self.arena;
+        self.levels(side).flat_map(move |(price_value, level)| {
+            arena
+                .slots_of(level)
+                .map(move |slot| (Price::define(price_value), *arena.order(slot)))
+        })
+    }
+}
+
+impl Orderbook {
+    /// Level slot at `price_value` on `side`
+    fn level(&self, side: Side, price_value: u32) -> Option<&Level> {
+        let i = self.config.index_of(price_value);
+        match side {
+            Side::Bid => self.bids.get(i),
+            Side::Ask => self.asks.get(i),
+        }
+    }
+
+    /// Populated levels on `side`, best price first
+    /// Jumps between populated slots via the occupancy bitmap
+    fn levels(&self, side: Side) -> Box<dyn Iterator<Item = (u32, &Level)> + '_> {
+        let config = self.config;
+        match side {
+            Side::Bid => Box::new(
+                std::iter::successors(self.bid_occupancy.last(), |&i| {
+                    i.checked_sub(1)
+                        .and_then(|j| self.bid_occupancy.prev_at_or_below(j))
+                })
+                .map(move |i| (config.price_at(i), &self.bids[i])),
+            ),
+            Side::Ask => Box::new(
+                std::iter::successors(self.ask_occupancy.first(), |&i| {
+                    self.ask_occupancy.next_at_or_above(i + 1)
+                })
+                .map(move |i| (config.price_at(i), &self.asks[i])),
+            ),
+        }
+    }
+
+    /// Does a limit order at `price` on `side` reach the opposite best price?
+    fn crosses(&self, side: Side, price: Price) -> bool {
+        match side {
+            Side::Bid => self.best_ask().is_some_and(|ask| ask.value() <= price.value()),
+            Side::Ask => self.best_bid().is_some_and(|bid| bid.value() >= price.value()),
+        }
+    }
+
+    /// Walk the opposite side for an incoming order on `side`, best price first
+    /// Stops once `remaining_qty` is zero or the next level is beyond `limit`
+    /// Each fill is sequenced, then handed to `on_fill`
+    fn match_incoming(
+        &mut self,
+        remaining_qty: &mut Quantity,
+        taker: Taker,
+        on_fill: &mut impl FnMut(&Fill),
+    ) {
+        let fill_seq = &mut self.fill_seq;
+        let mut on_fill = |mut fill: Fill| {
+            fill_seq.stamp(&mut fill);
+            on_fill(&fill);
+        };
+
+        match taker.side {
+            // BUY: take liquidity from asks, lowest price upward
+            Side::Bid => {
+                let mut next = self.ask_occupancy.first();
+                while let Some(i) = next {
+                    if remaining_qty.is_zero() {
+                        break;
+                    }
+
+                    let price = Price::define(self.config.price_at(i));
+                    if taker.limit.is_some_and(|l| price.value() > l.value()) {
+                        break;
+                    }
+
+                    let mut queue = ArenaQueue::new(
+                        &mut self.asks[i],
+                        &mut self.arena,
+                        &mut self.order_index,
+                        &mut self.ask_totals,
+                    );
+                    match_level(&mut queue, remaining_qty, price, taker, &mut on_fill);
+
+                    if self.asks[i].is_empty() {
+                        self.ask_occupancy.clear(i);
+                    }
+                    next = self.ask_occupancy.next_at_or_above(i + 1);
+                }
+            }
+
+            // SELL: take liquidity from bids, highest price downward
+            Side::Ask => {
+                let mut next = self.bid_occupancy.last();
+                while let Some(i) = next {
+                    if remaining_qty.is_zero() {
+                        break;
+                    }
+
+                    let price = Price::define(self.config.price_at(i));
+                    if taker.limit.is_some_and(|l| price.value() < l.value()) {
+                        break;
+                    }
+
+                    let mut queue = ArenaQueue::new(
+                        &mut self.bids[i],
+                        &mut self.arena,
+                        &mut self.order_index,
+                        &mut self.bid_totals,
+                    );
+                    match_level(&mut queue, remaining_qty, price, taker, &mut on_fill);
+
+                    if self.bids[i].is_empty() {
+                        self.bid_occupancy.clear(i);
+                    }
+                    next = i.checked_sub(1).and_then(|j| self.bid_occupancy.prev_at_or_below(j));
+                }
+            }
+        }
+    }
+}
+
+/// LevelQueue over one arena level, keeping the book's index and running
+/// totals in step
+struct ArenaQueue<'a> {
+    level: &'a mut Level,
+    arena: &'a mut Arena,
+    order_index: &'a mut HashMap<OrderId, u32>,
+    totals: &'a mut SideTotals,
+    // Last position looked up and its slot. The matcher reads positions in
+    // order (and pro-rata removes them back to front), so each lookup is a
+    // step or two along the list rather than a walk from the head
+    cursor: Cell<Option<(usize, u32)>>,
+}
+
+impl<'a> ArenaQueue<'a> {
+    fn new(
+        level: &'a mut Level,
+        arena: &'a mut Arena,
+        order_index: &'a mut HashMap<OrderId, u32>,
+        totals: &'a mut SideTotals,
+    ) -> Self {
+        Self { level, arena, order_index, totals, cursor: Cell::new(None) }
+    }
+
+    /// Slot at queue position `i`, walking from the cursor or the head
+    fn slot_at(&self, i: usize) -> Option<u32> {
+        if i >= self.level.len() {
+            return None;
+        }
+        let (mut position, mut slot) = self.cursor.get().unwrap_or((0, self.level.head));
+        while position < i {
+            slot = self.arena.slots[slot as usize].next;
+            position += 1;
+        }
+        while position > i {
+            slot = self.arena.slots[slot as usize].prev;
+            position -= 1;
+        }
+        self.cursor.set(Some((i, slot)));
+        Some(slot)
+    }
+}
+
+impl LevelQueue for ArenaQueue<'_> {
+    fn maker(&self, i: usize) -> Option<(OrderId, Quantity, AccountId)> {
+        let order = self.arena.order(self.slot_at(i)?);
+        Some((order.id(), order.quantity(), order.account()))
+    }
+
+    fn remove(&mut self, i: usize) {
+        let Some(slot) = self.slot_at(i) else {
+            return;
+        };
+        // Re-anchor on the predecessor, which keeps its position
+        let prev = self.arena.slots[slot as usize].prev;
+        self.cursor.set((prev != NIL).then(|| (i - 1, prev)));
+
+        let order = self.arena.unlink(self.level, slot);
+        self.totals.removed(order.quantity());
+        self.order_index.remove(&order.id());
+    }
+
+    fn reduce(&mut self, i: usize, qty: Quantity) {
+        if let Some(slot) = self.slot_at(i) {
+            self.arena.reduce(self.level, slot, qty);
+            self.totals.reduced(qty);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::order::IdCounter;
+
+    #[test]
+    fn test_freed_slots_are_reused() {
+        let mut book = Orderbook::new();
+        let mut counter = IdCounter::new();
+        let mut ids = Vec::new();
+        for price in [4990, 4995, 4999] {
+            let bid = Order::new(Price::define(price), Quantity::define(10), Side::Bid, &mut counter);
+            ids.push(bid.id());
+            book.add_order(bid).unwrap();
+        }
+        assert_eq!(book.arena.slots.len(), 3);
+
+        book.cancel_order(ids[1]).unwrap();
+        book.cancel_order(ids[0]).unwrap();
+        for _ in 0..2 {
+            let ask = Order::new(Price::define(5010), Quantity::define(10), Side::Ask, &mut counter);
+            book.add_order(ask).unwrap();
+        }
+
+        // Both new orders landed in freed slots
+        assert_eq!(book.arena.slots.len(), 3);
+        assert_eq!(book.arena.free, NIL);
+        assert_eq!(book.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,285 @@
+use crate::orderbook::error::OrderError;
+use crate::orderbook::hybrid::orderbook::Orderbook as HybridOrderbook;
+use crate::orderbook::tree::orderbook::Orderbook as TreeOrderbook;
+use crate::orderbook::{
+    BookConfig, BookSnapshot, BookStats, Fill, LimitOrderResult, MarketOrderResult, MemoryFootprint,
+    OrderView, OrderbookTrait, SweepQuote,
+};
+use crate::types::order::{Order, OrderId, Side};
+use crate::types::price::Price;
+use crate::types::quantity::Quantity;
+
+/// Adds per locality sample
+const WINDOW: usize = 1024;
+
+/// Cold-zone adds within one window that trigger the move to the tree
+/// At half the window, most adds are missing the hybrid's arrays
+const MIGRATE_COLD_ADDS: usize = WINDOW / 2;
+
+/// Representation an AutoOrderbook is currently using
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoMode {
+    Hybrid,
+    Tree,
+}
+
+enum Book {
+    Hybrid(HybridOrderbook),
+    Tree(TreeOrderbook),
+}
+
+/// Run `$body` against whichever book is live, bound as `$book`
+macro_rules! with_book {
+    ($book_enum:expr, $book:ident => $body:expr) => {
+        match $book_enum {
+            Book::Hybrid($book) => $body,
+            Book::Tree($book) => $body,
+        }
+    };
+}
+
+/// An orderbook that picks its own representation
+///
+/// Starts as the hybrid book, which is fastest while activity stays near the
+/// mid. Every WINDOW adds it checks how many landed outside the hot zone; past
+/// MIGRATE_COLD_ADDS the access pattern is sparse and wide, so the resting
+/// orders are replayed into a tree book in queue order. The move is one-way:
+/// a tree has no hot zone to fall out of, so there is nothing to measure.
+///
+/// Fill sequence numbers continue across the move.
+pub struct AutoOrderbook {
+    book: Book,
+    window_adds: usize,
+    window_cold_adds: usize,
+    // Highest seq handed out, and what the current book's seqs are shifted by
+    last_seq: u64,
+    // Kept here rather than read from the book: a fresh tree has not traded
+    last_trade: Option<(Price, Quantity)>,
+    seq_offset: u64,
+    level_capacity: Option<usize>,
+}
+
+impl AutoOrderbook {
+    pub fn mode(&self) -> AutoMode {
+        match self.book {
+            Book::Hybrid(_) => AutoMode::Hybrid,
+            Book::Tree(_) => AutoMode::Tree,
+        }
+    }
+
+    /// Count an add at `price` against the window, migrating once it fills
+    /// with too many cold-zone hits
+    fn record_add(&mut self, price: Price) {
+        let Book::Hybrid(hybrid) = &self.book else {
+            return;
+        };
+
+        self.window_adds += 1;
+        if !hybrid.is_in_hot_zone(price.value()) {
+            self.window_cold_adds += 1;
+        }
+        if self.window_adds < WINDOW {
+            return;
+        }
+
+        if self.window_cold_adds >= MIGRATE_COLD_ADDS {
+            self.migrate_to_tree();
+        }
+        self.window_adds = 0;
+        self.window_cold_adds = 0;
+    }
+
+    fn migrate_to_tree(&mut self) {
+        let Book::Hybrid(hybrid) = &self.book else {
+            return;
+        };
+
+        let mut tree = TreeOrderbook::with_config(*hybrid.config())
+            .expect("Config already validated by the hybrid book");
+        if let Some(orders_per_level) = self.level_capacity {
+            tree.reserve(orders_per_level);
+        }
+        // Best price first and FIFO within a level, so queues come out identical
+        // (seqs are renumbered, but in the same relative order)
+        for (_, order) in hybrid.iter_orders(Side::Bid).chain(hybrid.iter_orders(Side::Ask)) {
+            tree.add_order(order)
+                .expect("A resting order is valid on the same config and cannot cross");
+        }
+
+        self.book = Book::Tree(tree);
+        self.seq_offset = self.last_seq;
+    }
+
+    /// Shift seqs from the current book past everything already handed out
+    fn restamp(&mut self, fills: &mut [Fill]) {
+        for fill in fills {
+            fill.seq += self.seq_offset;
+            self.last_seq = fill.seq;
+            self.last_trade = Some((fill.price, fill.quantity));
+        }
+    }
+}
+
+impl OrderbookTrait for AutoOrderbook {
+    fn with_config(config: BookConfig) -> Result<Self, String> {
+        Ok(Self {
+            book: Book::Hybrid(HybridOrderbook::with_config(config)?),
+            window_adds: 0,
+            window_cold_adds: 0,
+            last_seq: 0,
+            last_trade: None,
+            seq_offset: 0,
+            level_capacity: None,
+        })
+    }
+
+    fn config(&self) -> &BookConfig {
+        with_book!(&self.book, book => book.config())
+    }
+
+    fn reserve(&mut self, orders_per_level: usize) {
+        self.level_capacity = Some(orders_per_level);
+        with_book!(&mut self.book, book => book.reserve(orders_per_level))
+    }
+
+    fn reserve_orders(&mut self, additional: usize) {
+        with_book!(&mut self.book, book => book.reserve_orders(additional))
+    }
+
+    fn add_order(&mut self, order: Order) -> Result<LimitOrderResult, OrderError> {
+        let mut result = with_book!(&mut self.book, book => book.add_order(order))?;
+        self.restamp(&mut result.fills);
+        self.record_add(order.price());
+        Ok(result)
+    }
+
+    fn cancel_order(&mut self, order_id: OrderId) -> Result<Order, OrderError> {
+        with_book!(&mut self.book, book => book.cancel_order(order_id))
+    }
+
+    fn cancel_all_for_side(&mut self, side: Side) -> usize {
+        with_book!(&mut self.book, book => book.cancel_all_for_side(side))
+    }
+
+    fn take_due_expiries(&mut self, now: u64) -> Vec<OrderId> {
+        with_book!(&mut self.book, book => book.take_due_expiries(now))
+    }
+
+    /// Also goes back to the hybrid book: a cleared book has no access
+    /// pattern yet. From tree mode that is a new hybrid book, so nothing the
+    /// tree allocated is kept; only the reserve carries over
+    fn clear(&mut self) {
+        let config = *self.config();
+        let level_capacity = self.level_capacity;
+        match &mut self.book {
+            Book::Hybrid(book) => book.clear(),
+            Book::Tree(_) => {
+                *self = Self::with_config(config).expect("Config already validated");
+                if let Some(orders_per_level) = level_capacity {
+                    self.reserve(orders_per_level);
+                }
+            }
+        }
+        self.window_adds = 0;
+        self.window_cold_adds = 0;
+        self.last_seq = 0;
+        self.last_trade = None;
+        self.seq_offset = 0;
+    }
+
+    fn contains_order(&self, order_id: OrderId) -> bool {
+        with_book!(&self.book, book => book.contains_order(order_id))
+    }
+
+    fn get_order(&self, order_id: OrderId) -> Option<OrderView> {
+        with_book!(&self.book, book => book.get_order(order_id))
+    }
+
+    fn amend_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_qty: Option<Quantity>,
+    ) -> Result<(), String> {
+        with_book!(&mut self.book, book => book.amend_order(order_id, new_price, new_qty))
+    }
+
+    fn execute_market_order_with(
+        &mut self,
+        side: Side,
+        quantity: Quantity,
+        taker_id: OrderId,
+        mut on_fill: impl FnMut(&Fill),
+    ) -> MarketOrderResult {
+        let (seq_offset, mut last_seq, mut last_trade) = (self.seq_offset, self.last_seq, self.last_trade);
+        let restamp = |fill: &Fill| {
+            let fill = Fill { seq: fill.seq + seq_offset, ..fill.clone() };
+            last_seq = fill.seq;
+            last_trade = Some((fill.price, fill.quantity));
+            on_fill(&fill);
+        };
+        let result = with_book!(&mut self.book, book => book.execute_market_order_with(side, quantity, taker_id, restamp));
+        (self.last_seq, self.last_trade) = (last_seq, last_trade);
+        result
+    }
+
+    fn available_liquidity(&self, side: Side, max: Quantity, limit: Option<Price>) -> Quantity {
+        with_book!(&self.book, book => book.available_liquidity(side, max, limit))
+    }
+
+    fn sweep_cost(&self, side: Side, quantity: Quantity) -> Option<SweepQuote> {
+        with_book!(&self.book, book => book.sweep_cost(side, quantity))
+    }
+
+    fn best_price(&self, side: Side) -> Option<Price> {
+        with_book!(&self.book, book => book.best_price(side))
+    }
+
+    fn worst_price(&self, side: Side) -> Option<Price> {
+        with_book!(&self.book, book => book.worst_price(side))
+    }
+
+    fn last_trade(&self) -> Option<(Price, Quantity)> {
+        self.last_trade
+    }
+
+    fn depth_at_price(&self, price: Price, side: Side) -> u64 {
+        with_book!(&self.book, book => book.depth_at_price(price, side))
+    }
+
+    fn order_count_at_price(&self, price: Price, side: Side) -> u32 {
+        with_book!(&self.book, book => book.order_count_at_price(price, side))
+    }
+
+    fn depth_in_range(&self, side: Side, from: Price, to: Price) -> u64 {
+        with_book!(&self.book, book => book.depth_in_range(side, from, to))
+    }
+
+    fn stats(&self) -> BookStats {
+        with_book!(&self.book, book => book.stats())
+    }
+
+    fn memory_footprint(&self) -> MemoryFootprint {
+        with_book!(&self.book, book => book.memory_footprint())
+    }
+
+    fn best_n_prices(&self, side: Side, n: usize) -> Vec<Price> {
+        with_book!(&self.book, book => book.best_n_prices(side, n))
+    }
+
+    fn book_snapshot(&self, depth: usize) -> BookSnapshot {
+        with_book!(&self.book, book => book.book_snapshot(depth))
+    }
+
+    fn validate(&self) -> Result<(), Vec<String>> {
+        with_book!(&self.book, book => book.validate())
+    }
+
+    fn iter_orders(&self, side: Side) -> impl Iterator<Item = (Price, Order)> {
+        let orders: Box<dyn Iterator<Item = (Price, Order)> + '_> = match &self.book {
+            Book::Hybrid(book) => Box::new(book.iter_orders(side)),
+            Book::Tree(book) => Box::new(book.iter_orders(side)),
+        };
+        orders
+    }
+}
@@ -0,0 +1,132 @@
+use crate::orderbook::{LimitOrderResult, MarketOrderResult};
+use crate::types::order::{Order, OrderId, Side};
+use crate::types::quantity::Quantity;
+use std::cell::{Cell, UnsafeCell};
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A mutating operation on a book, as carried by queues, journals and feeds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Command {
+    Add(Order),
+    Cancel(OrderId),
+    Market { side: Side, qty: Quantity, taker: OrderId },
+    /// Take `qty` off a resting order without trading here: a partial
+    /// cancel, or an execution reported by an external feed
+    Reduce { order: OrderId, qty: Quantity },
+}
+
+/// What applying one Command did
+#[derive(Debug, Clone)]
+pub enum EngineEvent {
+    Added(OrderId, LimitOrderResult),
+    Canceled(OrderId),
+    /// Quantity the order still has after a Reduce; zero means it is gone
+    Reduced(OrderId, Quantity),
+    Executed(OrderId, MarketOrderResult),
+    /// The command was refused and left the book untouched
+    Rejected(OrderId, String),
+}
+
+impl EngineEvent {
+    /// Id of the order the originating command was about
+    pub fn order_id(&self) -> OrderId {
+        match self {
+            EngineEvent::Added(id, _)
+            | EngineEvent::Canceled(id)
+            | EngineEvent::Reduced(id, _)
+            | EngineEvent::Executed(id, _)
+            | EngineEvent::Rejected(id, _) => *id,
+        }
+    }
+}
+
+/// Fixed-capacity ring shared by one CommandSender and one CommandQueue
+/// `tail` is only advanced by the sender, `head` only by the queue
+struct Ring {
+    slots: Box<[UnsafeCell<MaybeUninit<Command>>]>,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+// Slots in [head, tail) belong to the consumer, the rest to the producer, and
+// the Release/Acquire pairs on head and tail hand them over
+unsafe impl Sync for Ring {}
+
+/// Producing end of a single-producer single-consumer command queue
+/// Not Clone, and push takes &mut self, so there is exactly one producer
+pub struct CommandSender {
+    ring: Arc<Ring>,
+}
+
+/// Consuming end of a single-producer single-consumer command queue
+/// Lock-free: the matching thread drains it without contending with the
+/// producer. It can move to another thread but not be shared (it is !Sync),
+/// so there is exactly one consumer
+pub struct CommandQueue {
+    ring: Arc<Ring>,
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl CommandQueue {
+    /// A queue holding up to `capacity` commands, with its producer handle
+    pub fn with_capacity(capacity: usize) -> (CommandSender, CommandQueue) {
+        assert!(capacity > 0, "CommandQueue capacity must be positive");
+        let ring = Arc::new(Ring {
+            slots: (0..capacity).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        });
+
+        let sender = CommandSender { ring: Arc::clone(&ring) };
+        let queue = CommandQueue {
+            ring,
+            _not_sync: PhantomData,
+        };
+        (sender, queue)
+    }
+
+    /// Take the oldest command, None if the queue is empty
+    pub fn pop(&self) -> Option<Command> {
+        let ring = &*self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        if head == ring.tail.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let slot = &ring.slots[head % ring.slots.len()];
+        // Published by the sender's Release store of tail
+        let command = unsafe { (*slot.get()).assume_init() };
+        ring.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(command)
+    }
+
+    pub fn len(&self) -> usize {
+        let ring = &*self.ring;
+        ring.tail.load(Ordering::Acquire).wrapping_sub(ring.head.load(Ordering::Relaxed))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CommandSender {
+    /// Enqueue a command; hands it back if the queue is full
+    pub fn push(&mut self, command: Command) -> Result<(), Command> {
+        let ring = &*self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        if tail.wrapping_sub(ring.head.load(Ordering::Acquire)) == ring.slots.len() {
+            return Err(command);
+        }
+
+        let slot = &ring.slots[tail % ring.slots.len()];
+        // Released by the consumer's Release store of head
+        unsafe { (*slot.get()).write(command) };
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+}
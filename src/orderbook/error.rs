@@ -0,0 +1,68 @@
+use crate::types::order::{OrderId, Side};
+use crate::types::quantity::QtyValue;
+use std::fmt;
+
+/// Why add_order or cancel_order refused an order
+/// Built without allocating, so a flood of rejections costs no more than
+/// the checks; Display gives the human-readable message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderError {
+    /// Price is not a multiple of the book's tick_size
+    PriceNotTick { price: u32, tick: u32 },
+    /// Price is zero or at or above max_price
+    PriceOutOfBounds { price: u32, max: u32 },
+    /// Quantity is not a multiple of the book's lot_size
+    QtyNotLot { qty: QtyValue, lot: u32 },
+    ZeroQty,
+    /// The level already holds max_orders_per_level orders
+    LevelFull { side: Side, price: u32, max: usize },
+    /// A GTC order would trade on arrival and the book has reject_crossing set
+    WouldCross { order: OrderId, price: u32 },
+    /// A FOK order finds less than its quantity within its limit
+    FokUnfillable { order: OrderId, available: QtyValue, requested: QtyValue },
+    /// Price falls outside a windowed book's [lower, upper)
+    OutsideWindow { price: u32, lower: u32, upper: u32 },
+    NotFound(OrderId),
+    /// The id index and the levels disagree about where the order rests
+    IndexDesync(OrderId),
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            OrderError::PriceNotTick { price, tick } => {
+                write!(f, "Price {} is not a valid tick (tick_size={})", price, tick)
+            }
+            OrderError::PriceOutOfBounds { price, max } => write!(f, "Price {} out of bounds [1, {})", price, max),
+            OrderError::QtyNotLot { qty, lot } => {
+                write!(f, "Quantity {} is not a valid lot (lot_size={})", qty, lot)
+            }
+            OrderError::ZeroQty => write!(f, "Quantity cannot be zero"),
+            OrderError::LevelFull { side, price, max } => {
+                write!(f, "{:?} level {} is full ({} orders)", side, price, max)
+            }
+            OrderError::WouldCross { order, price } => write!(f, "Order {} at {} would cross the book", order, price),
+            OrderError::FokUnfillable { order, available, requested } => write!(
+                f,
+                "FOK order {} cannot be fully filled: {} available, {} requested",
+                order, available, requested
+            ),
+            OrderError::OutsideWindow { price, lower, upper } => {
+                write!(f, "Price {} is outside the window [{}, {}); recenter first", price, lower, upper)
+            }
+            OrderError::NotFound(order) => write!(f, "Order {} not found", order),
+            OrderError::IndexDesync(order) => {
+                write!(f, "Order {} found in index but not in book (data inconsistency)", order)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OrderError {}
+
+// The rest of the API still reports String errors; `?` bridges the two
+impl From<OrderError> for String {
+    fn from(error: OrderError) -> Self {
+        error.to_string()
+    }
+}
@@ -0,0 +1,87 @@
+use crate::orderbook::Fill;
+use crate::types::order::Side;
+
+/// Exchange fees per side of a fill, in basis points of notional
+/// Positive charges the participant, negative pays a rebate (typically the
+/// maker side)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FeeSchedule {
+    pub maker_bps: i32,
+    pub taker_bps: i32,
+}
+
+impl FeeSchedule {
+    /// Rate for the participant on `participant` side of a fill whose
+    /// aggressor was `aggressor`: the taker is the aggressor, the maker the
+    /// opposite side
+    pub fn bps_for(&self, participant: Side, aggressor: Side) -> i32 {
+        if participant == aggressor {
+            self.taker_bps
+        } else {
+            self.maker_bps
+        }
+    }
+}
+
+// Fees are computed after the fact from fills; matching never sees them
+impl Fill {
+    /// Price × quantity, in price units (cents on the default grid)
+    pub fn notional(&self) -> u64 {
+        self.price.value() as u64 * self.quantity.as_u64()
+    }
+
+    /// Signed fee owed by the participant on `participant` side of this fill,
+    /// in the same units as notional (negative = rebate received)
+    /// Rounded toward zero, so a fractional amount is never charged
+    pub fn fee(&self, participant: Side, schedule: &FeeSchedule) -> i64 {
+        let bps = schedule.bps_for(participant, self.aggressor_side) as i128;
+        (self.notional() as i128 * bps / 10_000) as i64
+    }
+}
+
+/// Net fees for the participant on `participant` side of every fill
+pub fn total_fees(fills: &[Fill], participant: Side, schedule: &FeeSchedule) -> i64 {
+    fills.iter().map(|fill| fill.fee(participant, schedule)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::price::Price;
+    use crate::types::quantity::{QtyValue, Quantity};
+
+    fn fill(price: u32, qty: QtyValue, aggressor_side: Side) -> Fill {
+        Fill {
+            price: Price::define(price),
+            quantity: Quantity::define(qty),
+            maker_order_id: 0,
+            taker_order_id: 1,
+            aggressor_side,
+            seq: 0,
+        }
+    }
+
+    #[test]
+    fn test_taker_pays_and_maker_is_rebated() {
+        let schedule = FeeSchedule { maker_bps: -1, taker_bps: 2 };
+
+        // $1000 notional in cents: 20 lots at $50.00
+        let buy = fill(5000, 20, Side::Bid);
+        assert_eq!(buy.notional(), 100_000);
+        assert_eq!(buy.fee(Side::Bid, &schedule), 20);
+        assert_eq!(buy.fee(Side::Ask, &schedule), -10);
+
+        // Roles follow the aggressor, not the side
+        let sell = fill(5000, 20, Side::Ask);
+        assert_eq!(sell.fee(Side::Ask, &schedule), 20);
+        assert_eq!(sell.fee(Side::Bid, &schedule), -10);
+
+        // 0.9 and -0.45 round toward zero
+        let small = fill(4500, 1, Side::Bid);
+        assert_eq!((small.fee(Side::Bid, &schedule), small.fee(Side::Ask, &schedule)), (0, 0));
+
+        assert_eq!(total_fees(&[buy, sell, small], Side::Bid, &schedule), 10);
+        assert_eq!(total_fees(&[], Side::Bid, &schedule), 0);
+    }
+}
@@ -0,0 +1,185 @@
+/// Two-level occupancy bitmap over the fixed-tick price slots
+///
+/// Level 0 (`words`): one bit per price slot, set = level has resting orders
+/// Level 1 (`summary`): one bit per level-0 word, set = that word is non-zero
+///
+/// 10,000 slots → 157 words → 3 summary words (1.3 KB total per side)
+/// Finding the next populated level is a couple of trailing_zeros /
+/// leading_zeros instructions instead of a walk over up to 10,000 Vec headers
+pub struct OccupancyBitmap {
+    slots: usize,
+    words: Vec<u64>,
+    summary: Vec<u64>,
+    /// Number of set slots, so counting populated levels needs no scan
+    len: usize,
+}
+
+impl OccupancyBitmap {
+    pub fn new(slots: usize) -> Self {
+        let words = slots.div_ceil(64);
+        Self {
+            slots,
+            words: vec![0; words],
+            summary: vec![0; words.div_ceil(64)],
+            len: 0,
+        }
+    }
+
+    /// Mark slot `i` as populated
+    #[inline]
+    pub fn set(&mut self, i: usize) {
+        let w = i / 64;
+        let bit = 1 << (i % 64);
+        self.len += (self.words[w] & bit == 0) as usize;
+        self.words[w] |= bit;
+        self.summary[w / 64] |= 1 << (w % 64);
+    }
+
+    /// Mark slot `i` as empty; drops the summary bit once its word is empty
+    #[inline]
+    pub fn clear(&mut self, i: usize) {
+        let w = i / 64;
+        let bit = 1 << (i % 64);
+        self.len -= (self.words[w] & bit != 0) as usize;
+        self.words[w] &= !bit;
+        if self.words[w] == 0 {
+            self.summary[w / 64] &= !(1 << (w % 64));
+        }
+    }
+
+    /// Number of populated slots
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Bytes allocated for both bitmap levels
+    pub fn heap_bytes(&self) -> usize {
+        (self.words.capacity() + self.summary.capacity()) * size_of::<u64>()
+    }
+
+    pub fn is_set(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    /// Lowest populated slot
+    pub fn first(&self) -> Option<usize> {
+        self.next_at_or_above(0)
+    }
+
+    /// Highest populated slot
+    pub fn last(&self) -> Option<usize> {
+        self.prev_at_or_below(self.slots.checked_sub(1)?)
+    }
+
+    /// Lowest populated slot >= `i`
+    pub fn next_at_or_above(&self, i: usize) -> Option<usize> {
+        if i >= self.slots {
+            return None;
+        }
+
+        // Same word first, then jump straight to the next non-empty word
+        let w = i / 64;
+        let masked = self.words[w] & (!0u64 << (i % 64));
+        if masked != 0 {
+            return Some(w * 64 + masked.trailing_zeros() as usize);
+        }
+
+        let next_w = scan_up(&self.summary, w + 1)?;
+        Some(next_w * 64 + self.words[next_w].trailing_zeros() as usize)
+    }
+
+    /// Highest populated slot <= `i`
+    pub fn prev_at_or_below(&self, i: usize) -> Option<usize> {
+        let i = i.min(self.slots.checked_sub(1)?);
+
+        let w = i / 64;
+        let masked = self.words[w] & (!0u64 >> (63 - i % 64));
+        if masked != 0 {
+            return Some(w * 64 + 63 - masked.leading_zeros() as usize);
+        }
+
+        let prev_w = scan_down(&self.summary, w.checked_sub(1)?)?;
+        Some(prev_w * 64 + 63 - self.words[prev_w].leading_zeros() as usize)
+    }
+}
+
+/// Lowest set bit >= `i` in a plain bit array (used on the short summary)
+fn scan_up(bits: &[u64], i: usize) -> Option<usize> {
+    let mut w = i / 64;
+    let mut masked = *bits.get(w)? & (!0u64 << (i % 64));
+
+    loop {
+        if masked != 0 {
+            return Some(w * 64 + masked.trailing_zeros() as usize);
+        }
+        w += 1;
+        masked = *bits.get(w)?;
+    }
+}
+
+/// Highest set bit <= `i` in a plain bit array (used on the short summary)
+fn scan_down(bits: &[u64], i: usize) -> Option<usize> {
+    let mut w = i / 64;
+    let mut masked = bits[w] & (!0u64 >> (63 - i % 64));
+
+    loop {
+        if masked != 0 {
+            return Some(w * 64 + 63 - masked.leading_zeros() as usize);
+        }
+        w = w.checked_sub(1)?;
+        masked = bits[w];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_bitmap() {
+        let bitmap = OccupancyBitmap::new(10_000);
+        assert_eq!(bitmap.first(), None);
+        assert_eq!(bitmap.last(), None);
+        assert_eq!(bitmap.next_at_or_above(5_000), None);
+        assert_eq!(bitmap.prev_at_or_below(5_000), None);
+    }
+
+    #[test]
+    fn test_next_and_prev_across_words() {
+        let mut bitmap = OccupancyBitmap::new(10_000);
+        for i in [3, 64, 4_095, 4_096, 9_999] {
+            bitmap.set(i);
+        }
+
+        assert_eq!(bitmap.first(), Some(3));
+        assert_eq!(bitmap.last(), Some(9_999));
+        assert_eq!(bitmap.next_at_or_above(4), Some(64));
+        assert_eq!(bitmap.next_at_or_above(65), Some(4_095));
+        assert_eq!(bitmap.next_at_or_above(4_097), Some(9_999));
+        assert_eq!(bitmap.prev_at_or_below(4_094), Some(64));
+        assert_eq!(bitmap.prev_at_or_below(63), Some(3));
+        assert_eq!(bitmap.prev_at_or_below(2), None);
+        assert_eq!(bitmap.next_at_or_above(10_000), None);
+    }
+
+    #[test]
+    fn test_clear_drops_summary_bit() {
+        let mut bitmap = OccupancyBitmap::new(10_000);
+        bitmap.set(100);
+        bitmap.set(101);
+        bitmap.set(8_000);
+
+        bitmap.clear(100);
+        assert!(bitmap.is_set(101));
+        assert_eq!(bitmap.first(), Some(101));
+
+        bitmap.clear(101);
+        assert!(!bitmap.is_set(101));
+        assert_eq!(bitmap.first(), Some(8_000));
+        assert_eq!(bitmap.prev_at_or_below(7_999), None);
+    }
+}
@@ -1,307 +1,617 @@
-use crate::orderbook::{Fill, OrderbookTrait};
+use crate::optimization::slot_array::SlotArray;
+use crate::orderbook::fixed_tick::bitmap::OccupancyBitmap;
+use crate::orderbook::level::PriceLevel;
+use crate::orderbook::error::OrderError;
+use crate::orderbook::{
+    check_invariants, deque_bytes, fillable_quantity, hash_map_bytes, unindex_side, ArrivalSequence, BookConfig,
+    BookSnapshot, BookStats, ExpiryQueue, Fill, FillSequence, LimitOrderResult, MarketOrderResult, MemoryFootprint,
+    OrderView, OrderbookTrait, SideTotals, StpMode, SweepQuote, Taker,
+};
 use crate::types::order::Order;
 use crate::types::order::OrderId;
 use crate::types::order::Side;
+use crate::types::order::TimeInForce;
 use crate::types::price::Price;
-use crate::types::quantity::Quantity;
+use crate::types::quantity::{QtyValue, Quantity};
 use std::collections::HashMap;
 
-/// Max price is represented in cents - $100 is max price
-const MAX_PRICE: u32 = 10000;
-const TICK_SIZE: u32 = 1;
-const LOT_SIZE: u32 = 1;
-const ELEMENT_NUM: usize = MAX_PRICE as usize / TICK_SIZE as usize;
+// The array-of-structs level, shared with the hybrid and windowed books
+pub use crate::orderbook::level::Level;
 
-// Empty Orderbook:
+// Empty Orderbook (default config, 10,000 slots = max_price / tick_size):
 // -Bids and Asks: 10,000 * 2 * 24(VH)  =  480,000 bytes or 480 KB
 // -Order Index: 48 bytes(HMH)
+// -Occupancy bitmaps: 2 * (157 + 3) * 8 = 2,560 bytes
 pub struct Orderbook {
-    bids: Box<[Level; ELEMENT_NUM]>,
-    asks: Box<[Level; ELEMENT_NUM]>,
+    // One slot per tick, index = price / tick_size
+    bids: SlotArray<Level>,
+    asks: SlotArray<Level>,
     // entry: OrderId: 8b + Value(S+P): 5b (padded to 8b) = 16b
     // HashMap overhead per entry: 24-32 bytes
     // all together: 40 -48 bytes per entry
     order_index: HashMap<OrderId, (Side, Price)>,
-}
-
-/// Level Memory: H(24) + N * 24
-#[derive(Default, Clone)]
-pub struct Level {
-    /// Vec of 24 bytes per element
-    /// Vec header (ptr: *mut Order: 8bytes, len: usize(8bytes), cap: usize(8bytes))
-    /// usize on 64-bit system is 8 bytes because its addresses are pointer sized
-    pub orders: Vec<Order>,
+    // One bit per price slot marking non-empty levels
+    // Best price and next-best recovery jump via leading/trailing zeros
+    // instead of scanning empty slots
+    bid_occupancy: OccupancyBitmap,
+    ask_occupancy: OccupancyBitmap,
+    bid_totals: SideTotals,
+    ask_totals: SideTotals,
+    config: BookConfig,
+    fill_seq: FillSequence,
+    arrival_seq: ArrivalSequence,
+    expiries: ExpiryQueue,
 }
 
 impl OrderbookTrait for Orderbook {
-    fn new() -> Self {
-        Self {
-            bids: Box::new(std::array::from_fn(|_| Level::default())),
-            asks: Box::new(std::array::from_fn(|_| Level::default())),
-            order_index: HashMap::new(),
+    fn with_config(config: BookConfig) -> Result<Self, String> {
+        Self::with_arrays(config, |slots| vec![Level::default(); slots].into_boxed_slice().into())
+    }
+
+    fn config(&self) -> &BookConfig {
+        &self.config
+    }
+
+    fn reserve(&mut self, orders_per_level: usize) {
+        for level in self.bids.iter_mut().chain(self.asks.iter_mut()) {
+            level.reserve(orders_per_level);
         }
     }
 
-    fn add_order(&mut self, order: Order) -> Result<(), String> {
+    fn reserve_orders(&mut self, additional: usize) {
+        self.order_index.reserve(additional);
+    }
+
+    fn add_order(&mut self, mut order: Order) -> Result<LimitOrderResult, OrderError> {
         let order_id = order.id();
         let side = order.side();
         let price_value = order.price().value();
-        let quantity_value = order.quantity().value();
-
-        // Validation 1: Price must be multiple of tick size
-        if price_value % TICK_SIZE as u32 != 0 {
-            return Err(format!(
-                "Price {} is not a valid tick (tick_size={})",
-                price_value, TICK_SIZE
-            ));
-        };
 
-        // Validation 2: Price must be in bounds
-        if price_value == 0 || price_value >= MAX_PRICE {
-            return Err(format!(
-                "Price {} out of bounds [1, {})",
-                price_value, MAX_PRICE
-            ));
+        self.config.validate_order(order.price(), order.quantity())?;
+        let resting = self.level(side, price_value).map_or(0, |level| level.orders.len());
+        self.config.check_level_room(side, order.price(), resting)?;
+        self.config.check_crossing(&order, || self.crosses(side, order.price()))?;
+
+        // Fill-or-kill: reject before anything trades unless the whole
+        // quantity is available at or better than the limit price
+        if order.time_in_force() == TimeInForce::FOK {
+            let available = fillable_quantity(self, &order);
+            if available < order.quantity() {
+                return Err(OrderError::FokUnfillable {
+                    order: order.id(),
+                    available: available.value(),
+                    requested: order.quantity().value(),
+                });
+            }
         }
 
-        // Validation 3: Quantity must be multiple of lot size
-        if quantity_value % LOT_SIZE as u32 != 0 {
-            return Err(format!(
-                "Quantity {} is not a valid lot (lot_size={})",
-                quantity_value, LOT_SIZE
-            ));
-        };
+        // Marketable limit order: take liquidity up to the limit price first
+        let mut remaining_qty = order.quantity();
+        let mut fills = Vec::new();
+        if self.crosses(side, order.price()) {
+            let taker = Taker::limit_order(&order, &self.config);
+            self.match_incoming(&mut remaining_qty, taker, &mut |fill| fills.push(fill.clone()));
+        }
 
-        // Validation 4: Quantity must be positive
-        if quantity_value == 0 {
-            return Err("Quantity cannot be zero".to_string());
-        };
+        let filled_qty = order.quantity().saturating_sub(remaining_qty);
+
+        // Nothing left, IOC/FOK, or a remainder that would rest crossed against
+        // orders STP skipped: the unfilled remainder is canceled, not rested
+        if remaining_qty.is_zero()
+            || order.time_in_force() != TimeInForce::GTC
+            || (self.config.stp == StpMode::Skip && self.crosses(side, order.price()))
+        {
+            return Ok(LimitOrderResult {
+                fills,
+                filled_qty,
+                rested_qty: Quantity::define(0),
+                canceled_qty: remaining_qty,
+            });
+        }
+        order.set_quantity(remaining_qty);
+        self.arrival_seq.stamp(&mut order);
+        self.expiries.track(&order);
 
-        let i = (price_value / TICK_SIZE) as usize;
+        let i = self.config.index_of(price_value);
 
         match side {
             // O(1) array access: CPU calculates base_address + (i × 24 bytes) in hardware
-            Side::Bid => self.bids[i].add_order(order),
-            Side::Ask => self.asks[i].add_order(order),
+            Side::Bid => {
+                self.bids[i].add(order);
+                self.bid_occupancy.set(i);
+                self.bid_totals.rested(remaining_qty);
+            }
+            Side::Ask => {
+                self.asks[i].add(order);
+                self.ask_occupancy.set(i);
+                self.ask_totals.rested(remaining_qty);
+            }
         }
 
         self.order_index.insert(order_id, (side, order.price()));
 
-        Ok(())
+        Ok(LimitOrderResult {
+            fills,
+            filled_qty,
+            rested_qty: remaining_qty,
+            canceled_qty: Quantity::define(0),
+        })
     }
 
-    fn cancel_order(&mut self, order_id: OrderId) -> Result<(), String> {
+    fn cancel_order(&mut self, order_id: OrderId) -> Result<Order, OrderError> {
         let (side, price) = self
             .order_index
             .remove(&order_id)
-            .ok_or_else(|| format!("Order {} not found", order_id))?;
+            .ok_or(OrderError::NotFound(order_id))?;
 
-        let i = (price.value() / TICK_SIZE) as usize;
+        let i = self.config.index_of(price.value());
 
-        match side {
-            Side::Bid => self.bids[i].cancel_order(order_id),
-            Side::Ask => self.asks[i].cancel_order(order_id),
+        let (level, occupancy, totals) = match side {
+            Side::Bid => (&mut self.bids[i], &mut self.bid_occupancy, &mut self.bid_totals),
+            Side::Ask => (&mut self.asks[i], &mut self.ask_occupancy, &mut self.ask_totals),
         };
+        let order = level.cancel(order_id).ok_or(OrderError::IndexDesync(order_id))?;
+        totals.removed(order.quantity());
+        if level.is_empty() {
+            occupancy.clear(i);
+        }
 
-        Ok(())
+        Ok(order)
     }
 
-    // Best bid and Best ask are O(n) in worst case -> VERY BAD
-    // That is a tradeoff for adding and canceling order being O(1)
+    fn cancel_all_for_side(&mut self, side: Side) -> usize {
+        let (levels, occupancy, totals) = match side {
+            Side::Bid => (&mut self.bids, &mut self.bid_occupancy, &mut self.bid_totals),
+            Side::Ask => (&mut self.asks, &mut self.ask_occupancy, &mut self.ask_totals),
+        };
+        *totals = SideTotals::default();
+
+        // Only populated slots are visited, via the occupancy bitmap
+        let mut removed = 0;
+        let mut next = occupancy.first();
+        while let Some(i) = next {
+            removed += levels[i].orders.len();
+            levels[i].orders.clear();
+            occupancy.clear(i);
+            next = occupancy.next_at_or_above(i + 1);
+        }
+        unindex_side(&mut self.order_index, side, removed);
 
-    fn best_bid(&self) -> Option<Price> {
-        // O(n)
+        removed
+    }
 
-        // Scan from highest price (end of array) downward
-        for i in (0..ELEMENT_NUM).rev() {
-            if !self.bids[i].is_empty() {
-                // Convert index back to price: i * TICK_SIZE
-                return Some(Price::define((i as u32) * TICK_SIZE));
-            }
-        }
-        None
+    fn take_due_expiries(&mut self, now: u64) -> Vec<OrderId> {
+        self.expiries.take_due(now)
     }
 
-    fn best_ask(&self) -> Option<Price> {
-        // Scan from lowest price (start of array) upward
-        for i in 0..ELEMENT_NUM {
-            if !self.asks[i].is_empty() {
-                // Convert index back to price: i * TICK_SIZE
-                return Some(Price::define((i as u32) * TICK_SIZE));
-            }
+    fn clear(&mut self) {
+        self.cancel_all();
+        self.fill_seq = FillSequence::default();
+        self.arrival_seq = ArrivalSequence::default();
+        self.expiries.clear();
+    }
+
+    fn contains_order(&self, order_id: OrderId) -> bool {
+        self.order_index.contains_key(&order_id)
+    }
+
+    fn get_order(&self, order_id: OrderId) -> Option<OrderView> {
+        let &(side, price) = self.order_index.get(&order_id)?;
+
+        // O(n) scan of the level for position and current quantity
+        let (queue_position, order) = self
+            .level(side, price.value())?
+            .orders
+            .iter()
+            .enumerate()
+            .find(|(_, o)| o.id() == order_id)?;
+
+        Some(OrderView {
+            price,
+            side,
+            remaining_qty: order.quantity(),
+            queue_position,
+        })
+    }
+
+    fn amend_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_qty: Option<Quantity>,
+    ) -> Result<(), String> {
+        let (side, price) = *self
+            .order_index
+            .get(&order_id)
+            .ok_or_else(|| format!("Order {} not found", order_id))?;
+
+        let i = self.config.index_of(price.value());
+        let (level, totals) = match side {
+            Side::Bid => (&mut self.bids[i], &mut self.bid_totals),
+            Side::Ask => (&mut self.asks[i], &mut self.ask_totals),
+        };
+        let order = level
+            .orders
+            .iter_mut()
+            .find(|o| o.id() == order_id)
+            .ok_or_else(|| format!("Order {} found in index but not in level", order_id))?;
+
+        let target_price = new_price.unwrap_or(price);
+        let target_qty = new_qty.unwrap_or(order.quantity());
+
+        // Pure size reduction: amend in place and keep queue position
+        if target_price == price && target_qty <= order.quantity() {
+            self.config.validate_order(price, target_qty)?;
+            totals.reduced(order.quantity().saturating_sub(target_qty));
+            order.set_quantity(target_qty);
+            return Ok(());
+        }
+
+        // Anything else loses time priority; validate up front so a rejected
+        // amend leaves the resting order untouched
+        let mut replacement = *order;
+        replacement.set_price(target_price);
+        replacement.set_quantity(target_qty);
+
+        self.config.validate_order(target_price, target_qty)?;
+        if target_price != price {
+            let resting = self.level(side, target_price.value()).map_or(0, |level| level.orders.len());
+            self.config.check_level_room(side, target_price, resting)?;
         }
-        None
+        if self.crosses(side, target_price) {
+            return Err(format!(
+                "Amended price {} would cross the book",
+                target_price.value()
+            ));
+        }
+
+        self.cancel_order(order_id)?;
+        self.add_order(replacement)?;
+        Ok(())
+    }
+
+    // Best and worst prices come from the occupancy bitmaps:
+    // a few summary words + one leading/trailing zeros, no slot scan
+
+    fn best_price(&self, side: Side) -> Option<Price> {
+        // Convert index back to price: i * tick_size
+        let best = match side {
+            Side::Bid => self.bid_occupancy.last(),
+            Side::Ask => self.ask_occupancy.first(),
+        };
+        best.map(|i| Price::define(self.config.price_at(i)))
+    }
+
+    fn worst_price(&self, side: Side) -> Option<Price> {
+        let worst = match side {
+            Side::Bid => self.bid_occupancy.first(),
+            Side::Ask => self.ask_occupancy.last(),
+        };
+        worst.map(|i| Price::define(self.config.price_at(i)))
     }
 
     /// Execute a market order by consuming liquidity from the book
-    /// Returns a vector of fills (trades that occurred)
+    /// Returns the fills (trades that occurred) and any unfilled remainder
     ///
     /// Market BUY: consumes asks (starting from lowest price, walking up)
     /// Market SELL: consumes bids (starting from highest price, walking down)
-    fn execute_market_order(
+    fn execute_market_order_with(
         &mut self,
         side: Side,
         mut remaining_qty: Quantity,
-    ) -> Result<Vec<Fill>, String> {
-        let mut fills = Vec::new();
-
-        match side {
-            // Market BUY: take liquidity from asks (sell side)
-            Side::Bid => {
-                // Walk asks from lowest price upward
-                for i in 0..ELEMENT_NUM {
-                    if remaining_qty.value() == 0 {
-                        break; // Fully filled
-                    }
+        taker_id: OrderId,
+        mut on_fill: impl FnMut(&Fill),
+    ) -> MarketOrderResult {
+        let requested = remaining_qty;
+        let taker = Taker::market(side, taker_id, &self.config);
+        self.match_incoming(&mut remaining_qty, taker, &mut on_fill);
+
+        MarketOrderResult {
+            fills: Vec::new(),
+            filled_qty: requested.saturating_sub(remaining_qty),
+            remaining_qty,
+        }
+    }
 
-                    if self.asks[i].is_empty() {
-                        continue; // No liquidity at this level
-                    }
+    fn available_liquidity(&self, side: Side, max: Quantity, limit: Option<Price>) -> Quantity {
+        let cap = max.as_u64();
+        let mut total: u64 = 0;
 
-                    let price = Price::define((i as u32) * TICK_SIZE);
+        // Same traversal order as execute_market_order, without mutating
+        let levels = self.levels(side.opposite());
 
-                    // Consume orders at this price level (FIFO)
-                    let level_fills =
-                        self.asks[i].match_orders(&mut remaining_qty, price, &mut self.order_index);
-                    fills.extend(level_fills);
-                }
+        for (price_value, level) in levels {
+            let beyond_limit = limit.is_some_and(|l| match side {
+                Side::Bid => price_value > l.value(),
+                Side::Ask => price_value < l.value(),
+            });
+            if total >= cap || beyond_limit {
+                break;
             }
+            total += level.total_quantity();
+        }
 
-            // Market SELL: take liquidity from bids (buy side)
-            Side::Ask => {
-                // Walk bids from highest price downward
-                for i in (0..ELEMENT_NUM).rev() {
-                    if remaining_qty.value() == 0 {
-                        break; // Fully filled
-                    }
+        Quantity::define(total.min(cap) as QtyValue)
+    }
 
-                    if self.bids[i].is_empty() {
-                        continue; // No liquidity at this level
-                    }
+    fn sweep_cost(&self, side: Side, quantity: Quantity) -> Option<SweepQuote> {
+        // Same traversal order as execute_market_order, without mutating
+        let levels = self.levels(side.opposite());
 
-                    let price = Price::define((i as u32) * TICK_SIZE);
+        let level_quantities = levels.map(|(price_value, level)| (price_value, level.total_quantity()));
+        SweepQuote::walk(quantity, level_quantities)
+    }
 
-                    // Consume orders at this price level (FIFO)
-                    let level_fills =
-                        self.bids[i].match_orders(&mut remaining_qty, price, &mut self.order_index);
-                    fills.extend(level_fills);
-                }
-            }
-        }
+    fn last_trade(&self) -> Option<(Price, Quantity)> {
+        self.fill_seq.last_trade()
+    }
 
-        if remaining_qty.value() > 0 {
-            return Err(format!(
-                "Market order partially filled: {} remaining (insufficient liquidity)",
-                remaining_qty.value()
-            ));
+    fn depth_at_price(&self, price: Price, side: Side) -> u64 {
+        let price_value = price.value();
+
+        // Check bounds and tick alignment
+        if !self.config.is_valid_price(price_value) {
+            return 0;
         }
 
-        Ok(fills)
-    }
+        let index = self.config.index_of(price_value);
 
-    fn depth_at_price(&self, price: Price, side: Side) -> u32 {
-        let price_value = price.value();
+        match side {
+            Side::Bid => self.bids[index].total_quantity(),
+            Side::Ask => self.asks[index].total_quantity(),
+        }
+    }
 
-        // Check bounds
-        if price_value == 0 || price_value >= MAX_PRICE {
+    fn order_count_at_price(&self, price: Price, side: Side) -> u32 {
+        if !self.config.is_valid_price(price.value()) {
             return 0;
         }
+        self.level(side, price.value()).map_or(0, |level| level.orders.len() as u32)
+    }
 
-        // Check tick alignment
-        if price_value % TICK_SIZE != 0 {
+    fn depth_in_range(&self, side: Side, from: Price, to: Price) -> u64 {
+        let Some(slots) = self.config.slot_range(from, to) else {
             return 0;
+        };
+
+        let levels = match side {
+            Side::Bid => &self.bids[slots],
+            Side::Ask => &self.asks[slots],
+        };
+        levels.iter().map(|level| level.total_quantity()).sum()
+    }
+
+    fn stats(&self) -> BookStats {
+        BookStats::from_totals(
+            self.bid_occupancy.len(),
+            self.ask_occupancy.len(),
+            self.bid_totals,
+            self.ask_totals,
+        )
+    }
+
+    fn memory_footprint(&self) -> MemoryFootprint {
+        let slots = self.bids.len() + self.asks.len();
+        let levels = self.bids.iter().chain(self.asks.iter());
+        let queues: usize = levels.map(|level| deque_bytes(&level.orders)).sum();
+        MemoryFootprint {
+            fixed_bytes: size_of::<Self>()
+                + slots * size_of::<Level>()
+                + self.bid_occupancy.heap_bytes()
+                + self.ask_occupancy.heap_bytes(),
+            heap_bytes: queues + hash_map_bytes(&self.order_index),
+            orders: self.bid_totals.orders + self.ask_totals.orders,
         }
+    }
+
+    fn best_n_prices(&self, side: Side, n: usize) -> Vec<Price> {
+        self.levels(side).take(n).map(|(price_value, _)| Price::define(price_value)).collect()
+    }
 
-        let index = (price_value / TICK_SIZE) as usize;
+    fn book_snapshot(&self, depth: usize) -> BookSnapshot {
+        let top = |side| {
+            self.levels(side)
+                .take(depth)
+                .map(|(price_value, level)| (Price::define(price_value), level.total_quantity()))
+                .collect()
+        };
 
-        match side {
-            Side::Bid => self.bids[index].total_quantity(),
-            Side::Ask => self.asks[index].total_quantity(),
+        BookSnapshot {
+            bids: top(Side::Bid),
+            asks: top(Side::Ask),
         }
     }
-}
 
-impl Level {
-    pub fn add_order(&mut self, order: Order) {
-        // O(1)
-        self.orders.push(order);
+    fn validate(&self) -> Result<(), Vec<String>> {
+        let problems = check_invariants(self, &self.order_index);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
     }
 
-    pub fn cancel_order(&mut self, order_id: u64) -> Option<Order> {
-        let i = self.orders.iter().position(|o| o.id() == order_id)?;
+    fn iter_orders(&self, side: Side) -> impl Iterator<Item = (Price, Order)> {
+        self.levels(side).flat_map(|(price_value, level)| {
+            level
+                .orders
+                .iter()
+                .map(move |&order| (Price::define(price_value), order))
+        })
+    }
+}
 
-        // O(n) - remove shifts elements after element is removed
-        Some(self.orders.remove(i))
+impl Orderbook {
+    /// with_config, with the per-tick level arrays on huge pages
+    /// See SlotArray::on_hugepages for what that takes and the non-Linux fallback
+    #[cfg(feature = "hugepages")]
+    pub fn with_hugepages(config: BookConfig) -> Result<Self, String> {
+        Self::with_arrays(config, SlotArray::on_hugepages)
     }
 
-    pub fn total_quantity(&self) -> u32 {
-        self.orders
-            .iter()
-            .map(|o| o.quantity().value())
-            .sum::<u32>()
+    /// A book whose bid and ask arrays `alloc` builds, given the slot count
+    fn with_arrays(config: BookConfig, alloc: impl Fn(usize) -> SlotArray<Level>) -> Result<Self, String> {
+        config.validate()?;
+
+        let slots = config.slots();
+        Ok(Self {
+            bids: alloc(slots),
+            asks: alloc(slots),
+            order_index: HashMap::new(),
+            bid_occupancy: OccupancyBitmap::new(slots),
+            ask_occupancy: OccupancyBitmap::new(slots),
+            bid_totals: SideTotals::default(),
+            ask_totals: SideTotals::default(),
+            config,
+            fill_seq: FillSequence::default(),
+            arrival_seq: ArrivalSequence::default(),
+            expiries: ExpiryQueue::default(),
+        })
     }
 
-    pub fn is_empty(&self) -> bool {
-        if self.orders.len() == 0 {
-            return true;
+    /// Level slot at `price_value` on `side`
+    fn level(&self, side: Side, price_value: u32) -> Option<&Level> {
+        let i = self.config.index_of(price_value);
+        match side {
+            Side::Bid => self.bids.get(i),
+            Side::Ask => self.asks.get(i),
         }
+    }
 
-        false
+    /// Populated levels on `side`, best price first
+    /// Jumps between populated slots via the occupancy bitmap
+    fn levels(&self, side: Side) -> Box<dyn Iterator<Item = (u32, &Level)> + '_> {
+        let config = self.config;
+        match side {
+            Side::Bid => Box::new(
+                std::iter::successors(self.bid_occupancy.last(), |&i| {
+                    i.checked_sub(1)
+                        .and_then(|j| self.bid_occupancy.prev_at_or_below(j))
+                })
+                .map(move |i| (config.price_at(i), &self.bids[i])),
+            ),
+            Side::Ask => Box::new(
+                std::iter::successors(self.ask_occupancy.first(), |&i| {
+                    self.ask_occupancy.next_at_or_above(i + 1)
+                })
+                .map(move |i| (config.price_at(i), &self.asks[i])),
+            ),
+        }
     }
 
-    pub fn first_order(&self) -> Option<&Order> {
-        self.orders.first()
+    /// Does a limit order at `price` on `side` reach the opposite best price?
+    fn crosses(&self, side: Side, price: Price) -> bool {
+        match side {
+            Side::Bid => self.best_ask().is_some_and(|ask| ask.value() <= price.value()),
+            Side::Ask => self.best_bid().is_some_and(|bid| bid.value() >= price.value()),
+        }
     }
 
-    /// Match incoming market order against this price level's orders (FIFO)
-    /// Modifies remaining_qty as orders are filled
-    /// Removes filled orders from the level and order_index
-    /// Returns vector of fills that occurred
-    pub fn match_orders(
+    /// Walk the opposite side for an incoming order on `side`, best price first
+    /// Stops once `remaining_qty` is zero or the next level is beyond `limit`
+    /// Each fill is sequenced, then handed to `on_fill`
+    fn match_incoming(
         &mut self,
         remaining_qty: &mut Quantity,
-        price: Price,
-        order_index: &mut HashMap<OrderId, (Side, Price)>,
-    ) -> Vec<Fill> {
-        let mut fills = Vec::new();
-        let mut orders_to_remove = Vec::new();
+        taker: Taker,
+        on_fill: &mut impl FnMut(&Fill),
+    ) {
+        let fill_seq = &mut self.fill_seq;
+        let mut on_fill = |mut fill: Fill| {
+            fill_seq.stamp(&mut fill);
+            on_fill(&fill);
+        };
 
-        // Process orders in FIFO order (first in Vec = earliest order due to push)
-        for (idx, order) in self.orders.iter().enumerate() {
-            if remaining_qty.value() == 0 {
-                break; // Market order fully filled
+        match taker.side {
+            // BUY: take liquidity from asks (sell side)
+            Side::Bid => {
+                // Walk populated asks from lowest price upward
+                let mut next = self.ask_occupancy.first();
+                while let Some(i) = next {
+                    if remaining_qty.is_zero() {
+                        break; // Fully filled
+                    }
+
+                    let price = Price::define(self.config.price_at(i));
+                    if taker.limit.is_some_and(|l| price.value() > l.value()) {
+                        break; // Beyond the limit price
+                    }
+
+                    // Consume orders at this price level (FIFO)
+                    self.asks[i].match_orders(
+                        remaining_qty,
+                        price,
+                        taker,
+                        &mut self.order_index,
+                        &mut self.ask_totals,
+                        &mut on_fill,
+                    );
+
+                    if self.asks[i].is_empty() {
+                        self.ask_occupancy.clear(i);
+                    }
+                    next = self.ask_occupancy.next_at_or_above(i + 1);
+                }
             }
 
-            let order_qty = order.quantity().value();
-            let fill_qty = remaining_qty.value().min(order_qty);
+            // SELL: take liquidity from bids (buy side)
+            Side::Ask => {
+                // Walk populated bids from highest price downward
+                let mut next = self.bid_occupancy.last();
+                while let Some(i) = next {
+                    if remaining_qty.is_zero() {
+                        break; // Fully filled
+                    }
 
-            // Create fill
-            fills.push(Fill {
-                price,
-                quantity: Quantity::define(fill_qty),
-                maker_order_id: order.id(),
-            });
+                    let price = Price::define(self.config.price_at(i));
+                    if taker.limit.is_some_and(|l| price.value() < l.value()) {
+                        break; // Beyond the limit price
+                    }
+
+                    // Consume orders at this price level (FIFO)
+                    self.bids[i].match_orders(
+                        remaining_qty,
+                        price,
+                        taker,
+                        &mut self.order_index,
+                        &mut self.bid_totals,
+                        &mut on_fill,
+                    );
 
-            // Update remaining quantity
-            *remaining_qty = Quantity::define(remaining_qty.value() - fill_qty);
-
-            // If order fully filled, mark for removal
-            if fill_qty == order_qty {
-                orders_to_remove.push(idx);
-            } else {
-                // Partial fill - would need to modify order quantity
-                // For now, we don't support partial fills of resting orders
-                // Real implementation would update the order's quantity
-                panic!("Partial fills of resting orders not yet implemented");
+                    if self.bids[i].is_empty() {
+                        self.bid_occupancy.clear(i);
+                    }
+                    next = i.checked_sub(1).and_then(|j| self.bid_occupancy.prev_at_or_below(j));
+                }
             }
         }
+    }
+}
 
-        // Remove filled orders in reverse order (to maintain indices)
-        for &idx in orders_to_remove.iter().rev() {
-            let removed_order = self.orders.remove(idx);
-            order_index.remove(&removed_order.id());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::order::IdCounter;
+
+    #[test]
+    fn test_clear_keeps_level_allocations() {
+        let mut book = Orderbook::new();
+        let mut counter = IdCounter::new();
+        for _ in 0..20 {
+            let bid = Order::new(Price::define(4999), Quantity::define(10), Side::Bid, &mut counter);
+            book.add_order(bid).unwrap();
         }
-
-        fills
+        let capacity = book.bids[4999].orders.capacity();
+        let index_capacity = book.order_index.capacity();
+
+        book.clear();
+        assert!(book.bids[4999].orders.is_empty());
+        assert!(book.order_index.is_empty());
+        assert_eq!(book.bids[4999].orders.capacity(), capacity);
+        assert_eq!(book.order_index.capacity(), index_capacity);
+
+        let bid = Order::new(Price::define(4999), Quantity::define(10), Side::Bid, &mut counter);
+        book.add_order(bid).unwrap();
+        assert_eq!(book.best_bid(), Some(Price::define(4999)));
     }
 }
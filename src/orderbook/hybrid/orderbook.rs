@@ -1,19 +1,16 @@
-use crate::orderbook::{Fill, OrderbookTrait};
-use crate::types::order::{Order, OrderId, Side};
+use crate::orderbook::level::{Level, PriceLevel};
+use crate::orderbook::error::OrderError;
+use crate::orderbook::{
+    btree_map_bytes, check_invariants, deque_bytes, fillable_quantity, hash_map_bytes,
+    unindex_side, ArrivalSequence, BookConfig, BookSnapshot, BookStats, ExpiryQueue, Fill, FillSequence,
+    LimitOrderResult, MarketOrderResult, MemoryFootprint, OrderView, OrderbookTrait, SideTotals,
+    StpMode, SweepQuote, Taker,
+};
+use crate::types::order::{Order, OrderId, Side, TimeInForce};
 use crate::types::price::Price;
-use crate::types::quantity::Quantity;
+use crate::types::quantity::{QtyValue, Quantity};
 use std::collections::{BTreeMap, HashMap};
-
-/// Hybrid orderbook: Hot zone uses fixed array, cold zone uses tree
-///
-/// Design:
-/// - Hot zone: Fixed array centered around mid-price (fast O(1) access)
-/// - Cold zone: BTreeMap for sparse far-from-market prices (dynamic)
-/// - Adaptive: Can shift hot zone as market moves
-
-const MAX_PRICE: u32 = 10000;
-const TICK_SIZE: u32 = 1;
-const LOT_SIZE: u32 = 1;
+use std::ops::Bound;
 
 /// Size of the hot zone array (e.g., 200 price levels = $2 range with 1 cent ticks)
 /// This covers typical intraday price movement
@@ -22,6 +19,16 @@ const HOT_ZONE_SIZE: usize = 200;
 /// Hot zone extends this many ticks above and below mid price
 const HOT_ZONE_RADIUS: u32 = (HOT_ZONE_SIZE / 2) as u32;
 
+/// How far the observed mid may drift from the center before the hot zone moves
+const HOT_ZONE_RECENTER_DRIFT: u32 = HOT_ZONE_RADIUS / 2;
+
+/// Hybrid orderbook: Hot zone uses fixed array, cold zone uses tree
+///
+/// Design:
+/// - Hot zone: Fixed array centered around mid-price (fast O(1) access)
+/// - Cold zone: BTreeMap for sparse far-from-market prices (dynamic)
+/// - Adaptive: Hot zone re-centers on the mid once it drifts more than
+///   HOT_ZONE_RECENTER_DRIFT ticks away (checked after market orders)
 pub struct Orderbook {
     // Hot zone: Fixed array for frequently-accessed prices near the spread
     hot_bids: Box<[Level; HOT_ZONE_SIZE]>,
@@ -31,62 +38,123 @@ pub struct Orderbook {
     cold_bids: BTreeMap<u32, Level>,
     cold_asks: BTreeMap<u32, Level>,
 
-    // Center of hot zone (in price value, not index)
+    // Center of hot zone (in price value, not index), always on the tick grid
     hot_zone_center: u32,
 
     // Order index for O(1) cancel lookups
     order_index: HashMap<OrderId, (Side, Price)>,
-}
 
-#[derive(Default, Clone)]
-pub struct Level {
-    pub orders: Vec<Order>,
+    // Running order/volume totals; level counts are derived (see stats)
+    bid_totals: SideTotals,
+    ask_totals: SideTotals,
+
+    // Queue capacity for cold levels created from here on (see reserve)
+    level_capacity: usize,
+
+    config: BookConfig,
+    fill_seq: FillSequence,
+    arrival_seq: ArrivalSequence,
+    expiries: ExpiryQueue,
 }
 
 impl OrderbookTrait for Orderbook {
-    fn new() -> Self {
-        Self {
+    fn with_config(config: BookConfig) -> Result<Self, String> {
+        config.validate()?;
+
+        // The hot window must fit inside the price range
+        if config.slots() < HOT_ZONE_SIZE {
+            return Err(format!(
+                "max_price {} spans fewer ticks than the hot zone ({})",
+                config.max_price, HOT_ZONE_SIZE
+            ));
+        }
+
+        Ok(Self {
             hot_bids: Box::new(std::array::from_fn(|_| Level::default())),
             hot_asks: Box::new(std::array::from_fn(|_| Level::default())),
             cold_bids: BTreeMap::new(),
             cold_asks: BTreeMap::new(),
-            hot_zone_center: MAX_PRICE / 2, // Start at mid-range
+            hot_zone_center: config.price_at(config.slots() / 2), // Start at mid-range
             order_index: HashMap::new(),
+            bid_totals: SideTotals::default(),
+            ask_totals: SideTotals::default(),
+            level_capacity: 0,
+            config,
+            fill_seq: FillSequence::default(),
+            arrival_seq: ArrivalSequence::default(),
+            expiries: ExpiryQueue::default(),
+        })
+    }
+
+    fn config(&self) -> &BookConfig {
+        &self.config
+    }
+
+    fn reserve(&mut self, orders_per_level: usize) {
+        self.level_capacity = orders_per_level;
+        let hot = self.hot_bids.iter_mut().chain(self.hot_asks.iter_mut());
+        let cold = self.cold_bids.values_mut().chain(self.cold_asks.values_mut());
+        for level in hot.chain(cold) {
+            level.reserve(orders_per_level);
         }
     }
 
-    fn add_order(&mut self, order: Order) -> Result<(), String> {
+    fn reserve_orders(&mut self, additional: usize) {
+        self.order_index.reserve(additional);
+    }
+
+    fn add_order(&mut self, mut order: Order) -> Result<LimitOrderResult, OrderError> {
         let side = order.side();
         let price_value = order.price().value();
-        let quantity_value = order.quantity().value();
 
-        // Validation 1: Price must be multiple of tick size
-        if price_value % TICK_SIZE != 0 {
-            return Err(format!(
-                "Price {} is not a valid tick (tick_size={})",
-                price_value, TICK_SIZE
-            ));
+        self.config.validate_order(order.price(), order.quantity())?;
+        let resting = self.level(side, price_value).map_or(0, |level| level.orders.len());
+        self.config.check_level_room(side, order.price(), resting)?;
+        self.config.check_crossing(&order, || self.crosses(side, order.price()))?;
+
+        // Fill-or-kill: reject before anything trades unless the whole
+        // quantity is available at or better than the limit price
+        if order.time_in_force() == TimeInForce::FOK {
+            let available = fillable_quantity(self, &order);
+            if available < order.quantity() {
+                return Err(OrderError::FokUnfillable {
+                    order: order.id(),
+                    available: available.value(),
+                    requested: order.quantity().value(),
+                });
+            }
         }
 
-        // Validation 2: Price must be in bounds
-        if price_value == 0 || price_value >= MAX_PRICE {
-            return Err(format!(
-                "Price {} out of bounds [1, {})",
-                price_value, MAX_PRICE
-            ));
+        // Marketable limit order: take liquidity up to the limit price first
+        let mut remaining_qty = order.quantity();
+        let mut fills = Vec::new();
+        if self.crosses(side, order.price()) {
+            let taker = Taker::limit_order(&order, &self.config);
+            self.match_incoming(&mut remaining_qty, taker, &mut |fill| fills.push(fill.clone()));
         }
 
-        // Validation 3: Quantity must be multiple of lot size
-        if quantity_value % LOT_SIZE != 0 {
-            return Err(format!(
-                "Quantity {} is not a valid lot (lot_size={})",
-                quantity_value, LOT_SIZE
-            ));
+        let filled_qty = order.quantity().saturating_sub(remaining_qty);
+
+        // Nothing left, IOC/FOK, or a remainder that would rest crossed against
+        // orders STP skipped: the unfilled remainder is canceled, not rested
+        if remaining_qty.is_zero()
+            || order.time_in_force() != TimeInForce::GTC
+            || (self.config.stp == StpMode::Skip && self.crosses(side, order.price()))
+        {
+            return Ok(LimitOrderResult {
+                fills,
+                filled_qty,
+                rested_qty: Quantity::define(0),
+                canceled_qty: remaining_qty,
+            });
         }
+        order.set_quantity(remaining_qty);
+        self.arrival_seq.stamp(&mut order);
+        self.expiries.track(&order);
 
-        // Validation 4: Quantity must be positive
-        if quantity_value == 0 {
-            return Err("Quantity cannot be zero".to_string());
+        match side {
+            Side::Bid => self.bid_totals.rested(remaining_qty),
+            Side::Ask => self.ask_totals.rested(remaining_qty),
         }
 
         // Determine if price is in hot or cold zone
@@ -94,229 +162,260 @@ impl OrderbookTrait for Orderbook {
             // Hot zone: O(1) array access
             let idx = self.hot_zone_index(price_value);
             match side {
-                Side::Bid => self.hot_bids[idx].orders.push(order),
-                Side::Ask => self.hot_asks[idx].orders.push(order),
+                Side::Bid => self.hot_bids[idx].add(order),
+                Side::Ask => self.hot_asks[idx].add(order),
             }
         } else {
             // Cold zone: O(log n) tree access
+            let capacity = self.level_capacity;
             match side {
                 Side::Bid => {
                     self.cold_bids
                         .entry(price_value)
-                        .or_insert_with(Level::default)
-                        .orders
-                        .push(order);
+                        .or_insert_with(|| Level::with_capacity(capacity))
+                        .add(order);
                 }
                 Side::Ask => {
                     self.cold_asks
                         .entry(price_value)
-                        .or_insert_with(Level::default)
-                        .orders
-                        .push(order);
+                        .or_insert_with(|| Level::with_capacity(capacity))
+                        .add(order);
                 }
             }
         }
 
         self.order_index.insert(order.id(), (side, order.price()));
-        Ok(())
+        Ok(LimitOrderResult {
+            fills,
+            filled_qty,
+            rested_qty: remaining_qty,
+            canceled_qty: Quantity::define(0),
+        })
     }
 
-    fn cancel_order(&mut self, order_id: OrderId) -> Result<(), String> {
+    fn cancel_order(&mut self, order_id: OrderId) -> Result<Order, OrderError> {
         let (side, price) = self
             .order_index
             .remove(&order_id)
-            .ok_or_else(|| format!("Order {} not found", order_id))?;
+            .ok_or(OrderError::NotFound(order_id))?;
 
         let price_value = price.value();
+        let hot_idx = self.is_in_hot_zone(price_value).then(|| self.hot_zone_index(price_value));
+        let (hot, cold, totals) = match side {
+            Side::Bid => (&mut self.hot_bids, &mut self.cold_bids, &mut self.bid_totals),
+            Side::Ask => (&mut self.hot_asks, &mut self.cold_asks, &mut self.ask_totals),
+        };
 
         // Check hot zone first (most likely)
-        if self.is_in_hot_zone(price_value) {
-            let idx = self.hot_zone_index(price_value);
-            let level = match side {
-                Side::Bid => &mut self.hot_bids[idx],
-                Side::Ask => &mut self.hot_asks[idx],
-            };
+        if let Some(idx) = hot_idx {
+            let level = &mut hot[idx];
 
-            if let Some(pos) = level.orders.iter().position(|o| o.id() == order_id) {
-                level.orders.remove(pos);
-                return Ok(());
+            if let Some(order) = level.cancel(order_id) {
+                totals.removed(order.quantity());
+                return Ok(order);
             }
         } else {
             // Cold zone: tree lookup
-            let tree = match side {
-                Side::Bid => &mut self.cold_bids,
-                Side::Ask => &mut self.cold_asks,
-            };
+            let tree = cold;
 
             if let Some(level) = tree.get_mut(&price_value) {
-                if let Some(pos) = level.orders.iter().position(|o| o.id() == order_id) {
-                    level.orders.remove(pos);
+                if let Some(order) = level.cancel(order_id) {
+                    totals.removed(order.quantity());
 
                     // Clean up empty levels in cold zone
                     if level.orders.is_empty() {
                         tree.remove(&price_value);
                     }
 
-                    return Ok(());
+                    return Ok(order);
                 }
             }
         }
 
-        Err(format!(
-            "Order {} found in index but not in book (data inconsistency)",
-            order_id
-        ))
+        Err(OrderError::IndexDesync(order_id))
     }
 
-    fn execute_market_order(
-        &mut self,
-        side: Side,
-        mut quantity: Quantity,
-    ) -> Result<Vec<Fill>, String> {
-        let mut fills = Vec::new();
+    fn cancel_all_for_side(&mut self, side: Side) -> usize {
+        let (hot, cold, totals) = match side {
+            Side::Bid => (&mut self.hot_bids, &mut self.cold_bids, &mut self.bid_totals),
+            Side::Ask => (&mut self.hot_asks, &mut self.cold_asks, &mut self.ask_totals),
+        };
+        *totals = SideTotals::default();
+
+        let mut removed: usize = cold.values().map(|level| level.orders.len()).sum();
+        cold.clear();
+        for level in hot.iter_mut() {
+            removed += level.orders.len();
+            level.orders.clear();
+        }
+        unindex_side(&mut self.order_index, side, removed);
 
-        match side {
-            // Market BUY: consume asks (lowest price first)
-            Side::Bid => {
-                // First, consume from hot zone
-                for i in 0..HOT_ZONE_SIZE {
-                    if quantity.value() == 0 {
-                        break;
-                    }
-                    if self.hot_asks[i].orders.is_empty() {
-                        continue;
-                    }
+        removed
+    }
 
-                    let price_value = self.hot_zone_center - HOT_ZONE_RADIUS as u32 + i as u32;
-                    let price = Price::define(price_value);
-                    let level_fills = Self::match_level(
-                        &mut self.hot_asks[i],
-                        &mut quantity,
-                        price,
-                        &mut self.order_index,
-                    );
-                    fills.extend(level_fills);
-                }
+    fn take_due_expiries(&mut self, now: u64) -> Vec<OrderId> {
+        self.expiries.take_due(now)
+    }
 
-                // Then, consume from cold zone if needed
-                if quantity.value() > 0 {
-                    let mut empty_levels = Vec::new();
-                    for (&price_value, level) in self.cold_asks.iter_mut() {
-                        if quantity.value() == 0 {
-                            break;
-                        }
-
-                        let price = Price::define(price_value);
-                        let level_fills =
-                            Self::match_level(level, &mut quantity, price, &mut self.order_index);
-                        fills.extend(level_fills);
-
-                        if level.orders.is_empty() {
-                            empty_levels.push(price_value);
-                        }
-                    }
+    fn clear(&mut self) {
+        self.cancel_all();
+        self.hot_zone_center = self.config.price_at(self.config.slots() / 2);
+        self.fill_seq = FillSequence::default();
+        self.arrival_seq = ArrivalSequence::default();
+        self.expiries.clear();
+    }
 
-                    // Clean up empty cold levels
-                    for price_value in empty_levels {
-                        self.cold_asks.remove(&price_value);
-                    }
-                }
-            }
+    fn contains_order(&self, order_id: OrderId) -> bool {
+        self.order_index.contains_key(&order_id)
+    }
 
-            // Market SELL: consume bids (highest price first)
-            Side::Ask => {
-                // First, consume from hot zone (highest first)
-                for i in (0..HOT_ZONE_SIZE).rev() {
-                    if quantity.value() == 0 {
-                        break;
-                    }
-                    if self.hot_bids[i].orders.is_empty() {
-                        continue;
-                    }
+    fn get_order(&self, order_id: OrderId) -> Option<OrderView> {
+        let &(side, price) = self.order_index.get(&order_id)?;
 
-                    let price_value = self.hot_zone_center - HOT_ZONE_RADIUS as u32 + i as u32;
-                    let price = Price::define(price_value);
-                    let level_fills = Self::match_level(
-                        &mut self.hot_bids[i],
-                        &mut quantity,
-                        price,
-                        &mut self.order_index,
-                    );
-                    fills.extend(level_fills);
-                }
+        // O(n) scan of the level for position and current quantity
+        let (queue_position, order) = self
+            .level(side, price.value())?
+            .orders
+            .iter()
+            .enumerate()
+            .find(|(_, o)| o.id() == order_id)?;
+
+        Some(OrderView {
+            price,
+            side,
+            remaining_qty: order.quantity(),
+            queue_position,
+        })
+    }
 
-                // Then, consume from cold zone if needed
-                if quantity.value() > 0 {
-                    let mut empty_levels = Vec::new();
-                    for (&price_value, level) in self.cold_bids.iter_mut().rev() {
-                        if quantity.value() == 0 {
-                            break;
-                        }
-
-                        let price = Price::define(price_value);
-                        let level_fills =
-                            Self::match_level(level, &mut quantity, price, &mut self.order_index);
-                        fills.extend(level_fills);
-
-                        if level.orders.is_empty() {
-                            empty_levels.push(price_value);
-                        }
-                    }
+    fn amend_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_qty: Option<Quantity>,
+    ) -> Result<(), String> {
+        let (side, price) = *self
+            .order_index
+            .get(&order_id)
+            .ok_or_else(|| format!("Order {} not found", order_id))?;
 
-                    // Clean up empty cold levels
-                    for price_value in empty_levels {
-                        self.cold_bids.remove(&price_value);
-                    }
-                }
-            }
+        let price_value = price.value();
+        let hot_idx = self.is_in_hot_zone(price_value).then(|| self.hot_zone_index(price_value));
+        let (hot, cold, totals) = match side {
+            Side::Bid => (&mut self.hot_bids, &mut self.cold_bids, &mut self.bid_totals),
+            Side::Ask => (&mut self.hot_asks, &mut self.cold_asks, &mut self.ask_totals),
+        };
+        let level = match hot_idx {
+            Some(idx) => Some(&mut hot[idx]),
+            None => cold.get_mut(&price_value),
+        };
+        let order = level
+            .and_then(|level| level.orders.iter_mut().find(|o| o.id() == order_id))
+            .ok_or_else(|| {
+                format!(
+                    "Order {} found in index but not in book (data inconsistency)",
+                    order_id
+                )
+            })?;
+
+        let target_price = new_price.unwrap_or(price);
+        let target_qty = new_qty.unwrap_or(order.quantity());
+
+        // Pure size reduction: amend in place and keep queue position
+        if target_price == price && target_qty <= order.quantity() {
+            self.config.validate_order(price, target_qty)?;
+            totals.reduced(order.quantity().saturating_sub(target_qty));
+            order.set_quantity(target_qty);
+            return Ok(());
         }
 
-        if quantity.value() > 0 {
+        // Anything else loses time priority; validate up front so a rejected
+        // amend leaves the resting order untouched
+        let mut replacement = *order;
+        replacement.set_price(target_price);
+        replacement.set_quantity(target_qty);
+
+        self.config.validate_order(target_price, target_qty)?;
+        if target_price != price {
+            let resting = self.level(side, target_price.value()).map_or(0, |level| level.orders.len());
+            self.config.check_level_room(side, target_price, resting)?;
+        }
+        if self.crosses(side, target_price) {
             return Err(format!(
-                "Market order partially filled: {} remaining",
-                quantity.value()
+                "Amended price {} would cross the book",
+                target_price.value()
             ));
         }
 
-        Ok(fills)
+        self.cancel_order(order_id)?;
+        self.add_order(replacement)?;
+        Ok(())
     }
 
-    fn best_bid(&self) -> Option<Price> {
-        // Best bid = highest bid across both zones.
-        let hot = (0..HOT_ZONE_SIZE).rev()
-            .find(|&i| !self.hot_bids[i].orders.is_empty())
-            .map(|i| self.hot_zone_center - HOT_ZONE_RADIUS as u32 + i as u32);
-        let cold = self.cold_bids.last_key_value().map(|(&p, _)| p);
-        match (hot, cold) {
-            (Some(h), Some(c)) => Some(Price::define(h.max(c))),
-            (Some(h), None)    => Some(Price::define(h)),
-            (None,    Some(c)) => Some(Price::define(c)),
-            (None,    None)    => None,
+    fn execute_market_order_with(
+        &mut self,
+        side: Side,
+        mut quantity: Quantity,
+        taker_id: OrderId,
+        mut on_fill: impl FnMut(&Fill),
+    ) -> MarketOrderResult {
+        let requested = quantity;
+        let taker = Taker::market(side, taker_id, &self.config);
+        self.match_incoming(&mut quantity, taker, &mut on_fill);
+
+        // Sweeps are what move the market; follow it with the hot zone
+        self.recenter_hot_zone();
+
+        MarketOrderResult {
+            fills: Vec::new(),
+            filled_qty: requested.saturating_sub(quantity),
+            remaining_qty: quantity,
         }
     }
 
-    fn best_ask(&self) -> Option<Price> {
-        // Best ask = lowest ask across both zones.
-        let hot = (0..HOT_ZONE_SIZE)
-            .find(|&i| !self.hot_asks[i].orders.is_empty())
-            .map(|i| self.hot_zone_center - HOT_ZONE_RADIUS as u32 + i as u32);
-        let cold = self.cold_asks.first_key_value().map(|(&p, _)| p);
-        match (hot, cold) {
-            (Some(h), Some(c)) => Some(Price::define(h.min(c))),
-            (Some(h), None)    => Some(Price::define(h)),
-            (None,    Some(c)) => Some(Price::define(c)),
-            (None,    None)    => None,
+    fn available_liquidity(&self, side: Side, max: Quantity, limit: Option<Price>) -> Quantity {
+        let cap = max.as_u64();
+        let mut total: u64 = 0;
+
+        // Same traversal order as execute_market_order, without mutating
+        let levels = self.levels(side.opposite());
+
+        for (price_value, level) in levels {
+            let beyond_limit = Self::beyond_limit(price_value, side == Side::Bid, limit);
+            if total >= cap || beyond_limit {
+                break;
+            }
+            total += level.total_quantity();
         }
+
+        Quantity::define(total.min(cap) as QtyValue)
     }
 
-    fn depth_at_price(&self, price: Price, side: Side) -> u32 {
-        let price_value = price.value();
+    fn sweep_cost(&self, side: Side, quantity: Quantity) -> Option<SweepQuote> {
+        // Same traversal order as execute_market_order, without mutating
+        let levels = self.levels(side.opposite());
 
-        if price_value == 0 || price_value >= MAX_PRICE {
-            return 0;
-        }
+        let level_quantities = levels.map(|(price_value, level)| (price_value, level.total_quantity()));
+        SweepQuote::walk(quantity, level_quantities)
+    }
 
-        if price_value % TICK_SIZE != 0 {
+    fn best_price(&self, side: Side) -> Option<Price> {
+        // Best bid = highest bid across both zones, best ask = lowest ask
+        self.edge_price(side, side == Side::Bid)
+    }
+
+    fn worst_price(&self, side: Side) -> Option<Price> {
+        self.edge_price(side, side == Side::Ask)
+    }
+
+    fn last_trade(&self) -> Option<(Price, Quantity)> {
+        self.fill_seq.last_trade()
+    }
+
+    fn depth_at_price(&self, price: Price, side: Side) -> u64 {
+        let price_value = price.value();
+
+        if !self.config.is_valid_price(price_value) {
             return 0;
         }
 
@@ -339,69 +438,490 @@ impl OrderbookTrait for Orderbook {
                 .unwrap_or(0)
         }
     }
+
+    fn order_count_at_price(&self, price: Price, side: Side) -> u32 {
+        if !self.config.is_valid_price(price.value()) {
+            return 0;
+        }
+        self.level(side, price.value()).map_or(0, |level| level.orders.len() as u32)
+    }
+
+    fn depth_in_range(&self, side: Side, from: Price, to: Price) -> u64 {
+        let Some(slots) = self.config.slot_range(from, to) else {
+            return 0;
+        };
+        let (low, high) = (self.config.price_at(*slots.start()), self.config.price_at(*slots.end()));
+        let (hot, cold) = match side {
+            Side::Bid => (&self.hot_bids, &self.cold_bids),
+            Side::Ask => (&self.hot_asks, &self.cold_asks),
+        };
+
+        // Cold levels never sit inside the hot window, so the two never overlap
+        let cold_depth: u64 = cold.range(low..=high).map(|(_, level)| level.total_quantity()).sum();
+
+        let (lower, upper) = self.hot_zone_bounds();
+        let hot_depth = if low < upper && high >= lower {
+            let first = self.hot_zone_index(low.max(lower));
+            let last = self.hot_zone_index(high.min(upper - self.config.tick_size));
+            hot[first..=last].iter().map(|level| level.total_quantity()).sum()
+        } else {
+            0
+        };
+
+        cold_depth + hot_depth
+    }
+
+    /// Cold levels come from the tree lengths; the hot zone is a fixed
+    /// HOT_ZONE_SIZE scan rather than a counter on every push and pop
+    fn stats(&self) -> BookStats {
+        let hot_levels = |hot: &[Level; HOT_ZONE_SIZE]| hot.iter().filter(|l| !l.orders.is_empty()).count();
+        BookStats::from_totals(
+            hot_levels(&self.hot_bids) + self.cold_bids.len(),
+            hot_levels(&self.hot_asks) + self.cold_asks.len(),
+            self.bid_totals,
+            self.ask_totals,
+        )
+    }
+
+    fn memory_footprint(&self) -> MemoryFootprint {
+        let hot = self.hot_bids.iter().chain(self.hot_asks.iter());
+        let cold = self.cold_bids.values().chain(self.cold_asks.values());
+        let queues: usize = hot.chain(cold).map(|level| deque_bytes(&level.orders)).sum();
+        MemoryFootprint {
+            fixed_bytes: size_of::<Self>() + 2 * size_of::<[Level; HOT_ZONE_SIZE]>(),
+            heap_bytes: btree_map_bytes(&self.cold_bids)
+                + btree_map_bytes(&self.cold_asks)
+                + queues
+                + hash_map_bytes(&self.order_index),
+            orders: self.bid_totals.orders + self.ask_totals.orders,
+        }
+    }
+
+    fn best_n_prices(&self, side: Side, n: usize) -> Vec<Price> {
+        self.levels(side).take(n).map(|(price_value, _)| Price::define(price_value)).collect()
+    }
+
+    fn book_snapshot(&self, depth: usize) -> BookSnapshot {
+        let top = |side| {
+            self.levels(side)
+                .take(depth)
+                .map(|(price_value, level)| (Price::define(price_value), level.total_quantity()))
+                .collect()
+        };
+
+        BookSnapshot {
+            bids: top(Side::Bid),
+            asks: top(Side::Ask),
+        }
+    }
+
+    fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = check_invariants(self, &self.order_index);
+
+        // Cold levels are removed once empty and never overlap the hot zone
+        for (side, cold) in [(Side::Bid, &self.cold_bids), (Side::Ask, &self.cold_asks)] {
+            for (&price_value, level) in cold {
+                if level.orders.is_empty() {
+                    problems.push(format!(
+                        "Empty {:?} level lingering at {} in the cold map",
+                        side, price_value
+                    ));
+                }
+                if self.is_in_hot_zone(price_value) {
+                    problems.push(format!(
+                        "Cold {:?} level at {} lies inside the hot zone",
+                        side, price_value
+                    ));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    fn iter_orders(&self, side: Side) -> impl Iterator<Item = (Price, Order)> {
+        self.levels(side).flat_map(|(price_value, level)| {
+            level
+                .orders
+                .iter()
+                .map(move |&order| (Price::define(price_value), order))
+        })
+    }
 }
 
 impl Orderbook {
+    /// Release memory after a burst: drop any empty cold level and shrink
+    /// every queue, hot slots included, back to what it holds or to the
+    /// reserved size if larger
+    pub fn compact(&mut self) {
+        let capacity = self.level_capacity;
+        for cold in [&mut self.cold_bids, &mut self.cold_asks] {
+            cold.retain(|_, level| !level.orders.is_empty());
+        }
+        let hot = self.hot_bids.iter_mut().chain(self.hot_asks.iter_mut());
+        let cold = self.cold_bids.values_mut().chain(self.cold_asks.values_mut());
+        for level in hot.chain(cold) {
+            level.shrink_to(capacity);
+        }
+    }
+
+    /// Level at `price_value` on `side`, from whichever zone holds it
+    fn level(&self, side: Side, price_value: u32) -> Option<&Level> {
+        if self.is_in_hot_zone(price_value) {
+            let idx = self.hot_zone_index(price_value);
+            match side {
+                Side::Bid => Some(&self.hot_bids[idx]),
+                Side::Ask => Some(&self.hot_asks[idx]),
+            }
+        } else {
+            match side {
+                Side::Bid => self.cold_bids.get(&price_value),
+                Side::Ask => self.cold_asks.get(&price_value),
+            }
+        }
+    }
+
+    /// Highest (or lowest) populated price on `side` across both zones
+    /// Cold levels are removed as soon as they empty, so the tree's end keys
+    /// are populated; the hot arrays are scanned from the matching end
+    fn edge_price(&self, side: Side, highest: bool) -> Option<Price> {
+        let (hot, cold) = match side {
+            Side::Bid => (&self.hot_bids, &self.cold_bids),
+            Side::Ask => (&self.hot_asks, &self.cold_asks),
+        };
+        let populated = |&i: &usize| !hot[i].orders.is_empty();
+        let (hot_edge, cold_edge) = if highest {
+            ((0..HOT_ZONE_SIZE).rev().find(populated), cold.last_key_value())
+        } else {
+            ((0..HOT_ZONE_SIZE).find(populated), cold.first_key_value())
+        };
+        let candidates = hot_edge
+            .map(|i| self.hot_zone_price(i))
+            .into_iter()
+            .chain(cold_edge.map(|(&p, _)| p));
+        let edge = if highest { candidates.max() } else { candidates.min() };
+        edge.map(Price::define)
+    }
+
+    /// Populated levels on `side`, best price first
+    /// Stitches cold levels beyond the hot zone, the hot arrays, then the
+    /// cold levels on the far side, so the walk stays in strict price order
+    fn levels(&self, side: Side) -> Box<dyn Iterator<Item = (u32, &Level)> + '_> {
+        let (lower, upper) = self.hot_zone_bounds();
+        let cold = |(&p, level)| (p, level);
+
+        match side {
+            Side::Bid => Box::new(
+                self.cold_bids
+                    .range(upper..)
+                    .rev()
+                    .map(cold)
+                    .chain((0..HOT_ZONE_SIZE).rev().map(move |i| (self.hot_zone_price(i), &self.hot_bids[i])))
+                    .chain(self.cold_bids.range(..lower).rev().map(cold))
+                    .filter(|(_, level)| !level.orders.is_empty()),
+            ),
+            Side::Ask => Box::new(
+                self.cold_asks
+                    .range(..lower)
+                    .map(cold)
+                    .chain((0..HOT_ZONE_SIZE).map(move |i| (self.hot_zone_price(i), &self.hot_asks[i])))
+                    .chain(self.cold_asks.range(upper..).map(cold))
+                    .filter(|(_, level)| !level.orders.is_empty()),
+            ),
+        }
+    }
+
+    /// Move the hot zone onto the observed mid when it has drifted too far
+    ///
+    /// Mid is (best_bid + best_ask) / 2, or the only populated side's best
+    /// price when the other side is empty. Every hot level is parked in the
+    /// cold trees, the center moves, then the cold levels inside the new window
+    /// are pulled into the arrays. Levels move as whole Vecs, orders are not
+    /// copied, and order_index keys on price so it needs no update
+    pub fn recenter_hot_zone(&mut self) {
+        let mid = match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => (bid.midpoint_half_ticks(ask) / 2) as u32,
+            (Some(only), None) | (None, Some(only)) => only.value(),
+            (None, None) => return,
+        };
+
+        // Snap to the tick grid and keep the whole window inside [0, max_price)
+        let tick = self.config.tick_size;
+        let radius = HOT_ZONE_RADIUS * tick;
+        let new_center = self.config.floor_to_tick(mid).clamp(radius, self.config.max_price - radius);
+        if new_center.abs_diff(self.hot_zone_center) <= HOT_ZONE_RECENTER_DRIFT * tick {
+            return;
+        }
+
+        // Park the current hot levels in the cold trees, leaving slots sized
+        // as reserve asked
+        let capacity = self.level_capacity;
+        for i in 0..HOT_ZONE_SIZE {
+            let price_value = self.hot_zone_price(i);
+            if !self.hot_bids[i].orders.is_empty() {
+                let level = std::mem::replace(&mut self.hot_bids[i], Level::with_capacity(capacity));
+                self.cold_bids.insert(price_value, level);
+            }
+            if !self.hot_asks[i].orders.is_empty() {
+                let level = std::mem::replace(&mut self.hot_asks[i], Level::with_capacity(capacity));
+                self.cold_asks.insert(price_value, level);
+            }
+        }
+
+        self.hot_zone_center = new_center;
+
+        // Pull the levels inside the new window back into the arrays
+        let (new_lower, new_upper) = self.hot_zone_bounds();
+        for (hot, cold) in [
+            (&mut self.hot_bids, &mut self.cold_bids),
+            (&mut self.hot_asks, &mut self.cold_asks),
+        ] {
+            let in_window: Vec<u32> = cold.range(new_lower..new_upper).map(|(&p, _)| p).collect();
+            for price_value in in_window {
+                if let Some(level) = cold.remove(&price_value) {
+                    hot[((price_value - new_lower) / tick) as usize] = level;
+                }
+            }
+        }
+    }
+
+    /// Price range [lower, upper) covered by the hot arrays
+    fn hot_zone_bounds(&self) -> (u32, u32) {
+        let radius = HOT_ZONE_RADIUS * self.config.tick_size;
+        (self.hot_zone_center - radius, self.hot_zone_center + radius)
+    }
+
     /// Check if a price is within the hot zone
-    fn is_in_hot_zone(&self, price_value: u32) -> bool {
-        let lower_bound = self.hot_zone_center.saturating_sub(HOT_ZONE_RADIUS);
-        let upper_bound = self.hot_zone_center + HOT_ZONE_RADIUS;
+    pub fn is_in_hot_zone(&self, price_value: u32) -> bool {
+        let (lower_bound, upper_bound) = self.hot_zone_bounds();
         price_value >= lower_bound && price_value < upper_bound
     }
 
-    /// Convert price to hot zone array index
+    /// Convert price to hot zone array index (one slot per tick)
     fn hot_zone_index(&self, price_value: u32) -> usize {
-        let offset = price_value - (self.hot_zone_center - HOT_ZONE_RADIUS);
-        offset as usize
+        let offset = price_value - self.hot_zone_bounds().0;
+        (offset / self.config.tick_size) as usize
     }
 
-    /// Match orders at a single price level (FIFO)
-    fn match_level(
-        level: &mut Level,
-        remaining_qty: &mut Quantity,
-        price: Price,
-        order_index: &mut HashMap<OrderId, (Side, Price)>,
-    ) -> Vec<Fill> {
-        let mut fills = Vec::new();
-        let mut orders_to_remove = Vec::new();
+    /// Convert hot zone array index back to price
+    fn hot_zone_price(&self, i: usize) -> u32 {
+        self.hot_zone_bounds().0 + self.config.price_at(i)
+    }
 
-        for (idx, order) in level.orders.iter().enumerate() {
-            if remaining_qty.value() == 0 {
-                break;
+    /// Does a limit order at `price` on `side` reach the opposite best price?
+    fn crosses(&self, side: Side, price: Price) -> bool {
+        match side {
+            Side::Bid => self.best_ask().is_some_and(|ask| ask.value() <= price.value()),
+            Side::Ask => self.best_bid().is_some_and(|bid| bid.value() >= price.value()),
+        }
+    }
+
+    /// Walk the opposite side for an incoming order on `side` in strict price priority
+    /// Cold levels can sit on either side of the hot zone, so a BUY walks
+    /// cold-below → hot → cold-above (and a SELL the mirror image)
+    /// Each fill is sequenced, then handed to `on_fill`
+    fn match_incoming(
+        &mut self,
+        quantity: &mut Quantity,
+        taker: Taker,
+        on_fill: &mut impl FnMut(&Fill),
+    ) {
+        let (lower, upper) = self.hot_zone_bounds();
+        let tick = self.config.tick_size;
+        let to_price = |i: usize| lower + (i as u32) * tick;
+        let below = (Bound::Unbounded, Bound::Excluded(lower));
+        let above = (Bound::Included(upper), Bound::Unbounded);
+        let fill_seq = &mut self.fill_seq;
+        let on_fill = &mut |mut fill: Fill| {
+            fill_seq.stamp(&mut fill);
+            on_fill(&fill);
+        };
+
+        match taker.side {
+            // BUY: consume asks (lowest price first)
+            Side::Bid => {
+                let (cold, hot, index) = (&mut self.cold_asks, &mut self.hot_asks, &mut self.order_index);
+                let totals = &mut self.ask_totals;
+                let mut done = Self::match_cold(cold, below, true, quantity, taker, index, totals, on_fill);
+                if !done {
+                    done = Self::match_hot(hot, to_price, true, quantity, taker, index, totals, on_fill);
+                }
+                if !done {
+                    Self::match_cold(cold, above, true, quantity, taker, index, totals, on_fill);
+                }
+            }
+
+            // SELL: consume bids (highest price first)
+            Side::Ask => {
+                let (cold, hot, index) = (&mut self.cold_bids, &mut self.hot_bids, &mut self.order_index);
+                let totals = &mut self.bid_totals;
+                let mut done = Self::match_cold(cold, above, false, quantity, taker, index, totals, on_fill);
+                if !done {
+                    done = Self::match_hot(hot, to_price, false, quantity, taker, index, totals, on_fill);
+                }
+                if !done {
+                    Self::match_cold(cold, below, false, quantity, taker, index, totals, on_fill);
+                }
+            }
+        }
+    }
+
+    /// Is `price_value` worse than the limit for a walk in this direction?
+    fn beyond_limit(price_value: u32, ascending: bool, limit: Option<Price>) -> bool {
+        limit.is_some_and(|l| {
+            if ascending {
+                price_value > l.value()
+            } else {
+                price_value < l.value()
             }
+        })
+    }
 
-            let order_qty = order.quantity().value();
-            let fill_qty = remaining_qty.value().min(order_qty);
+    /// Consume hot zone levels in price order
+    /// Returns true once the walk must stop (filled or past the limit)
+    #[allow(clippy::too_many_arguments)]
+    fn match_hot(
+        levels: &mut [Level; HOT_ZONE_SIZE],
+        to_price: impl Fn(usize) -> u32,
+        ascending: bool,
+        quantity: &mut Quantity,
+        taker: Taker,
+        order_index: &mut HashMap<OrderId, (Side, Price)>,
+        totals: &mut SideTotals,
+        on_fill: &mut impl FnMut(Fill),
+    ) -> bool {
+        for step in 0..HOT_ZONE_SIZE {
+            let i = if ascending { step } else { HOT_ZONE_SIZE - 1 - step };
+            let price_value = to_price(i);
+
+            if quantity.is_zero() || Self::beyond_limit(price_value, ascending, taker.limit) {
+                return true;
+            }
+            if levels[i].orders.is_empty() {
+                continue;
+            }
 
-            fills.push(Fill {
-                price,
-                quantity: Quantity::define(fill_qty),
-                maker_order_id: order.id(),
-            });
+            let price = Price::define(price_value);
+            levels[i].match_orders(quantity, price, taker, order_index, totals, on_fill);
+        }
 
-            *remaining_qty = Quantity::define(remaining_qty.value() - fill_qty);
+        false
+    }
 
-            if fill_qty == order_qty {
-                orders_to_remove.push(idx);
-            } else {
-                panic!("Partial fills of resting orders not yet implemented");
+    /// Consume cold zone levels within `range` in price order
+    /// Returns true once the walk must stop (filled or past the limit)
+    #[allow(clippy::too_many_arguments)]
+    fn match_cold(
+        tree: &mut BTreeMap<u32, Level>,
+        range: (Bound<u32>, Bound<u32>),
+        ascending: bool,
+        quantity: &mut Quantity,
+        taker: Taker,
+        order_index: &mut HashMap<OrderId, (Side, Price)>,
+        totals: &mut SideTotals,
+        on_fill: &mut impl FnMut(Fill),
+    ) -> bool {
+        let mut empty_levels = Vec::new();
+        let mut done = false;
+
+        let levels: Box<dyn Iterator<Item = (&u32, &mut Level)>> = if ascending {
+            Box::new(tree.range_mut(range))
+        } else {
+            Box::new(tree.range_mut(range).rev())
+        };
+
+        for (&price_value, level) in levels {
+            if quantity.is_zero() || Self::beyond_limit(price_value, ascending, taker.limit) {
+                done = true;
+                break;
+            }
+
+            let price = Price::define(price_value);
+            level.match_orders(quantity, price, taker, order_index, totals, on_fill);
+
+            if level.orders.is_empty() {
+                empty_levels.push(price_value);
             }
         }
 
-        for &idx in orders_to_remove.iter().rev() {
-            let removed_order = level.orders.remove(idx);
-            order_index.remove(&removed_order.id());
+        // Clean up empty cold levels, in the order the walk emptied them
+        for price_value in empty_levels {
+            tree.remove(&price_value);
         }
 
-        fills
+        done
     }
 }
 
-impl Level {
-    pub fn total_quantity(&self) -> u32 {
-        self.orders
-            .iter()
-            .map(|o| o.quantity().value())
-            .sum::<u32>()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::order::IdCounter;
+
+    #[test]
+    fn test_clear_keeps_hot_levels_and_index() {
+        let mut book = Orderbook::new();
+        let mut counter = IdCounter::new();
+        for price in [4999, 4999, 4999, 9000] {
+            let bid = Order::new(Price::define(price), Quantity::define(10), Side::Bid, &mut counter);
+            book.add_order(bid).unwrap();
+        }
+        let capacity = book.level(Side::Bid, 4999).unwrap().orders.capacity();
+        let index_capacity = book.order_index.capacity();
+
+        book.clear();
+        assert!(book.cold_bids.is_empty());
+        assert!(book.order_index.is_empty());
+        assert_eq!(book.level(Side::Bid, 4999).unwrap().orders.capacity(), capacity);
+        assert_eq!(book.order_index.capacity(), index_capacity);
+    }
+
+    #[test]
+    fn test_validate_reports_stale_cold_levels() {
+        let mut book = Orderbook::new();
+        let mut counter = IdCounter::new();
+        let ask = Order::new(Price::define(9000), Quantity::define(10), Side::Ask, &mut counter);
+        book.add_order(ask).unwrap();
+        assert_eq!(book.validate(), Ok(()));
+
+        let (lower, _) = book.hot_zone_bounds();
+        book.cold_asks.insert(9500, Level::default());
+        book.cold_bids.insert(lower, Level::default());
+
+        let problems = book.validate().unwrap_err();
+        assert!(problems.iter().any(|p| p == "Empty Ask level lingering at 9500 in the cold map"));
+        assert!(problems.iter().any(|p| p.contains("lies inside the hot zone")));
+    }
+
+    #[test]
+    fn test_compact_shrinks_a_drained_hot_level() {
+        let mut book = Orderbook::new();
+        let mut counter = IdCounter::new();
+        assert!(book.is_in_hot_zone(5000));
+
+        let mut bid = || Order::new(Price::define(5000), Quantity::define(10), Side::Bid, &mut counter);
+        let ids: Vec<OrderId> = (0..1000)
+            .map(|_| {
+                let order = bid();
+                book.add_order(order).unwrap();
+                order.id()
+            })
+            .collect();
+        for id in ids {
+            book.cancel_order(id).unwrap();
+        }
+        // The hot slot outlives its orders, queue capacity and all
+        book.add_order(bid()).unwrap();
+        let capacity = |book: &Orderbook| book.level(Side::Bid, 5000).unwrap().orders.capacity();
+        assert!(capacity(&book) >= 1000);
+
+        book.compact();
+        assert!(capacity(&book) < 16, "capacity {} after compact", capacity(&book));
+        assert_eq!(book.depth_at_price(Price::define(5000), Side::Bid), 10);
+        assert_eq!(book.validate(), Ok(()));
     }
 }
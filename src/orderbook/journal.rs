@@ -0,0 +1,123 @@
+use crate::orderbook::command::{Command, EngineEvent};
+use crate::orderbook::{LimitOrderResult, MarketOrderResult, OrderbookTrait};
+use crate::perf::rdtsc;
+use crate::types::order::{Order, OrderId, Side};
+use crate::types::quantity::Quantity;
+
+/// One mutating operation as it was applied to a book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JournalEntry {
+    /// Position in the journal, starting at 0
+    pub seq: u64,
+    /// rdtsc() when the operation was applied
+    pub timestamp_cycles: u64,
+    pub command: Command,
+}
+
+/// Destination for journal entries: memory for tests, a file in production
+pub trait EventJournal {
+    fn record(&mut self, entry: JournalEntry);
+}
+
+/// Journal that keeps every entry in memory
+#[derive(Debug, Default)]
+pub struct MemoryJournal {
+    entries: Vec<JournalEntry>,
+}
+
+impl MemoryJournal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+}
+
+impl EventJournal for MemoryJournal {
+    fn record(&mut self, entry: JournalEntry) {
+        self.entries.push(entry);
+    }
+}
+
+/// A book that journals every add, cancel and market order before applying it
+/// Queries go through book(); only the mutators are wrapped
+pub struct JournaledBook<O: OrderbookTrait, J: EventJournal> {
+    book: O,
+    journal: J,
+    next_seq: u64,
+}
+
+impl<O: OrderbookTrait, J: EventJournal> JournaledBook<O, J> {
+    pub fn new(book: O, journal: J) -> Self {
+        Self {
+            book,
+            journal,
+            next_seq: 0,
+        }
+    }
+
+    pub fn book(&self) -> &O {
+        &self.book
+    }
+
+    pub fn journal(&self) -> &J {
+        &self.journal
+    }
+
+    pub fn into_parts(self) -> (O, J) {
+        (self.book, self.journal)
+    }
+
+    /// Journal `command`, then apply it
+    /// Rejected commands are journaled too: replay rejects them the same way
+    pub fn apply_command(&mut self, command: Command) -> EngineEvent {
+        self.journal.record(JournalEntry {
+            seq: self.next_seq,
+            timestamp_cycles: rdtsc(),
+            command,
+        });
+        self.next_seq += 1;
+
+        self.book.apply_command(command)
+    }
+
+    pub fn add_order(&mut self, order: Order) -> Result<LimitOrderResult, String> {
+        match self.apply_command(Command::Add(order)) {
+            EngineEvent::Added(_, result) => Ok(result),
+            EngineEvent::Rejected(_, reason) => Err(reason),
+            event => unreachable!("add produced {:?}", event),
+        }
+    }
+
+    pub fn cancel_order(&mut self, order_id: OrderId) -> Result<(), String> {
+        match self.apply_command(Command::Cancel(order_id)) {
+            EngineEvent::Canceled(_) => Ok(()),
+            EngineEvent::Rejected(_, reason) => Err(reason),
+            event => unreachable!("cancel produced {:?}", event),
+        }
+    }
+
+    pub fn execute_market_order(
+        &mut self,
+        side: Side,
+        quantity: Quantity,
+        taker_id: OrderId,
+    ) -> MarketOrderResult {
+        match self.apply_command(Command::Market { side, qty: quantity, taker: taker_id }) {
+            EngineEvent::Executed(_, result) => result,
+            event => unreachable!("market order produced {:?}", event),
+        }
+    }
+}
+
+/// Rebuild a book by applying journaled commands in order to a fresh default book
+pub fn replay<'a, O: OrderbookTrait>(entries: impl IntoIterator<Item = &'a JournalEntry>) -> O {
+    let mut book = O::new();
+    for entry in entries {
+        book.apply_command(entry.command);
+    }
+    book
+}
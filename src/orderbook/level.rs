@@ -0,0 +1,198 @@
+use crate::orderbook::{match_level, CommitQueue, Fill, SideTotals, Taker};
+use crate::types::order::{Order, OrderId, Side};
+use crate::types::price::Price;
+use crate::types::quantity::Quantity;
+use std::collections::{HashMap, VecDeque};
+
+/// What a book needs from one price level, whatever its memory layout
+///
+/// Matching itself lives in match_level; each layout only says how to read,
+/// reduce and remove its makers (LevelQueue), so a level type cannot bring its
+/// own matching rules. The arena book's levels are lists threaded through a
+/// shared arena and cannot stand alone, so they sit outside this trait.
+pub trait PriceLevel {
+    /// Queue `order` behind everything already at this level
+    fn add(&mut self, order: Order);
+
+    /// Take `order_id` out of the queue, wherever it is
+    fn cancel(&mut self, order_id: OrderId) -> Option<Order>;
+
+    /// Open quantity across the level, in u64: many large orders can exceed
+    /// u32::MAX
+    fn total_quantity(&self) -> u64;
+
+    fn order_count(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.order_count() == 0
+    }
+}
+
+/// Array-of-structs level: whole orders queued front (oldest) to back
+/// Shared by the fixed-tick, hybrid and windowed books
+///
+/// Level Memory: H(24) + N * 40
+#[derive(Debug, Default, Clone)]
+pub struct Level {
+    /// VecDeque of 40 bytes per element
+    /// Header (ptr: 8 bytes, cap: 8 bytes, head: 8 bytes) + len: 8 bytes
+    pub orders: VecDeque<Order>,
+}
+
+impl Level {
+    /// Empty level with room for `orders` orders
+    pub fn with_capacity(orders: usize) -> Self {
+        Self { orders: VecDeque::with_capacity(orders) }
+    }
+
+    /// Grow the queue so it holds at least `orders` without reallocating
+    pub fn reserve(&mut self, orders: usize) {
+        self.orders.reserve(orders.saturating_sub(self.orders.len()));
+    }
+
+    /// Give back spare queue capacity, keeping room for at least `orders`
+    pub fn shrink_to(&mut self, orders: usize) {
+        self.orders.shrink_to(orders);
+    }
+
+    pub fn first_order(&self) -> Option<&Order> {
+        self.orders.front()
+    }
+
+    /// Match an incoming order against this price level (see match_level)
+    /// Removes filled orders from the level and order_index, keeps `totals`
+    /// in step and hands each fill to on_fill as it occurs
+    pub(crate) fn match_orders(
+        &mut self,
+        remaining_qty: &mut Quantity,
+        price: Price,
+        taker: Taker,
+        order_index: &mut HashMap<OrderId, (Side, Price)>,
+        totals: &mut SideTotals,
+        on_fill: &mut impl FnMut(Fill),
+    ) {
+        let mut queue = CommitQueue { orders: &mut self.orders, order_index, totals };
+        match_level(&mut queue, remaining_qty, price, taker, on_fill)
+    }
+}
+
+impl PriceLevel for Level {
+    fn add(&mut self, order: Order) {
+        // O(1)
+        self.orders.push_back(order);
+    }
+
+    fn cancel(&mut self, order_id: OrderId) -> Option<Order> {
+        let i = self.orders.iter().position(|o| o.id() == order_id)?;
+
+        // O(n) - remove shifts the shorter side of the ring buffer
+        self.orders.remove(i)
+    }
+
+    fn total_quantity(&self) -> u64 {
+        self.orders.iter().map(|o| o.quantity().as_u64()).sum()
+    }
+
+    fn order_count(&self) -> usize {
+        self.orders.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::tree::orderbook::Level as TreeLevel;
+    use crate::orderbook::{BookConfig, MatchMode};
+    use crate::types::quantity::QtyValue;
+    use crate::orderbook::SoA::orderbook::LevelSoA;
+
+    /// Each layout's match_orders, so one sequence can drive the shared
+    /// matcher through any of them
+    trait Matchable: PriceLevel + Default {
+        fn match_orders(
+            &mut self,
+            remaining_qty: &mut Quantity,
+            price: Price,
+            taker: Taker,
+            order_index: &mut HashMap<OrderId, (Side, Price)>,
+            totals: &mut SideTotals,
+            on_fill: &mut impl FnMut(Fill),
+        );
+    }
+
+    macro_rules! matchable {
+        ($($level:ty),*) => {$(
+            impl Matchable for $level {
+                fn match_orders(
+                    &mut self,
+                    remaining_qty: &mut Quantity,
+                    price: Price,
+                    taker: Taker,
+                    order_index: &mut HashMap<OrderId, (Side, Price)>,
+                    totals: &mut SideTotals,
+                    on_fill: &mut impl FnMut(Fill),
+                ) {
+                    <$level>::match_orders(self, remaining_qty, price, taker, order_index, totals, on_fill)
+                }
+            }
+        )*};
+    }
+
+    matchable!(Level, TreeLevel, LevelSoA);
+
+    type Outcome = (Vec<(OrderId, QtyValue)>, u64, usize, usize);
+
+    /// Run one sequence through a level: four asks, a cancel from the middle,
+    /// a buy of 25 matched in `mode`, then a cancel from the back
+    fn exercise<L: Matchable>(mode: MatchMode) -> Outcome {
+        let price = Price::define(5000);
+        let mut level = L::default();
+        let mut order_index = HashMap::new();
+        let mut totals = SideTotals::default();
+        for (id, qty) in [(1, 10), (2, 20), (3, 30), (4, 40)] {
+            level.add(Order::from_parts(id, price, Quantity::define(qty), Side::Ask));
+            order_index.insert(id, (Side::Ask, price));
+            totals.orders += 1;
+            totals.volume += u64::from(qty);
+        }
+
+        let canceled = level.cancel(2).expect("order 2 rests at this level");
+        assert_eq!(canceled.quantity(), Quantity::define(20));
+        order_index.remove(&2);
+        totals.orders -= 1;
+        totals.volume -= 20;
+        assert!(level.cancel(2).is_none());
+
+        let mut remaining = Quantity::define(25);
+        let config = BookConfig { match_mode: mode, ..BookConfig::default() };
+        let taker = Taker::market(Side::Bid, 99, &config);
+        let mut fills = Vec::new();
+        level.match_orders(&mut remaining, price, taker, &mut order_index, &mut totals, &mut |fill| {
+            fills.push(fill)
+        });
+        assert!(remaining.is_zero());
+        assert!(fills.iter().all(|f| f.price == price && f.taker_order_id == 99));
+        assert_eq!(totals.volume, level.total_quantity());
+        assert_eq!(totals.orders, level.order_count());
+
+        let unfilled = fills.iter().filter(|f| f.maker_order_id == 4).fold(40, |left, f| left - f.quantity.value());
+        assert_eq!(level.cancel(4).map(|o| o.quantity().value()), Some(unfilled));
+        order_index.remove(&4);
+
+        let fills = fills.iter().map(|f| (f.maker_order_id, f.quantity.value())).collect();
+        (fills, level.total_quantity(), level.order_count(), order_index.len())
+    }
+
+    #[test]
+    fn levels_agree_across_layouts() {
+        let expected = (vec![(1, 10), (3, 15)], 15, 1, 1);
+        assert_eq!(exercise::<Level>(MatchMode::Fifo), expected);
+        assert_eq!(exercise::<TreeLevel>(MatchMode::Fifo), expected);
+        assert_eq!(exercise::<LevelSoA>(MatchMode::Fifo), expected);
+
+        let expected = exercise::<Level>(MatchMode::ProRata);
+        assert_eq!(expected.0.iter().map(|&(_, qty)| qty).sum::<QtyValue>(), 25);
+        assert_eq!(exercise::<TreeLevel>(MatchMode::ProRata), expected);
+        assert_eq!(exercise::<LevelSoA>(MatchMode::ProRata), expected);
+    }
+}
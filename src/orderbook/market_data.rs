@@ -0,0 +1,181 @@
+use crate::orderbook::error::OrderError;
+use crate::orderbook::{LimitOrderResult, MarketOrderResult, OrderbookTrait, StpMode};
+use crate::types::order::{NO_ACCOUNT, Order, OrderId, Side};
+use crate::types::price::Price;
+use crate::types::quantity::Quantity;
+use std::collections::HashSet;
+
+/// New aggregate quantity at a level that changed since the last drain
+/// A quantity of 0 means the level is gone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LevelUpdate {
+    pub side: Side,
+    pub price: Price,
+    pub quantity: u64,
+}
+
+/// Best price and the aggregate quantity resting there, per side
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopOfBook {
+    pub bid: Option<(Price, u64)>,
+    pub ask: Option<(Price, u64)>,
+}
+
+impl TopOfBook {
+    pub fn of<O: OrderbookTrait>(book: &O) -> Self {
+        let best = |best: Option<Price>, side| best.map(|price| (price, book.depth_at_price(price, side)));
+        Self {
+            bid: best(book.best_bid(), Side::Bid),
+            ask: best(book.best_ask(), Side::Ask),
+        }
+    }
+}
+
+/// A book that remembers which levels its mutators touched, so a publisher
+/// can send incremental L2 updates instead of re-snapshotting, and tells a
+/// registered callback when the top of book moves
+/// Queries go through book(); only the mutators are wrapped
+pub struct MarketDataBook<O: OrderbookTrait> {
+    book: O,
+    dirty: HashSet<(Side, Price)>,
+    // Top as of the last mutation, to detect edges against
+    top: TopOfBook,
+    on_top_change: Option<Box<dyn FnMut(TopOfBook)>>,
+}
+
+impl<O: OrderbookTrait> MarketDataBook<O> {
+    pub fn new(book: O) -> Self {
+        Self {
+            top: TopOfBook::of(&book),
+            book,
+            dirty: HashSet::new(),
+            on_top_change: None,
+        }
+    }
+
+    /// Call `callback` with the new top whenever a mutation changes the best
+    /// price or the quantity at it on either side; deeper changes stay quiet
+    /// Replaces any earlier callback
+    pub fn on_top_of_book_change(&mut self, callback: impl FnMut(TopOfBook) + 'static) {
+        self.on_top_change = Some(Box::new(callback));
+    }
+
+    pub fn top_of_book(&self) -> TopOfBook {
+        self.top
+    }
+
+    pub fn book(&self) -> &O {
+        &self.book
+    }
+
+    pub fn into_inner(self) -> O {
+        self.book
+    }
+
+    /// The current quantity at every level touched since the last drain, bids
+    /// then asks, each in price order, and start a new interval
+    /// A level touched several times is reported once, with where it ended up
+    pub fn drain_dirty(&mut self) -> Vec<LevelUpdate> {
+        let mut touched: Vec<(Side, Price)> = self.dirty.drain().collect();
+        touched.sort_unstable_by_key(|&(side, price)| (side == Side::Ask, price));
+
+        touched
+            .into_iter()
+            .map(|(side, price)| LevelUpdate {
+                side,
+                price,
+                quantity: self.book.depth_at_price(price, side),
+            })
+            .collect()
+    }
+
+    /// Marks the order's own level and every level it traded against
+    pub fn add_order(&mut self, order: Order) -> Result<LimitOrderResult, OrderError> {
+        let opposite = order.side().opposite();
+        // STP may cancel same-account makers on levels that see no fill
+        if self.book.config().stp == StpMode::CancelResting && order.account() != NO_ACCOUNT {
+            let crossed = |price: Price| match order.side() {
+                Side::Bid => price <= order.price(),
+                Side::Ask => price >= order.price(),
+            };
+            let own: Vec<Price> = self
+                .book
+                .iter_orders(opposite)
+                .take_while(|&(price, _)| crossed(price))
+                .filter(|(_, resting)| resting.account() == order.account())
+                .map(|(price, _)| price)
+                .collect();
+            self.dirty.extend(own.into_iter().map(|price| (opposite, price)));
+        }
+
+        let result = self.book.add_order(order)?;
+        self.dirty.extend(result.fills.iter().map(|fill| (opposite, fill.price)));
+        if !result.rested_qty.is_zero() {
+            self.dirty.insert((order.side(), order.price()));
+        }
+        self.check_top();
+        Ok(result)
+    }
+
+    pub fn cancel_order(&mut self, order_id: OrderId) -> Result<Order, OrderError> {
+        let order = self.book.cancel_order(order_id)?;
+        self.dirty.insert((order.side(), order.price()));
+        self.check_top();
+        Ok(order)
+    }
+
+    /// Marks the level the order left and, after a price change, the one it joined
+    pub fn amend_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_qty: Option<Quantity>,
+    ) -> Result<(), String> {
+        let view = self
+            .book
+            .get_order(order_id)
+            .ok_or_else(|| format!("Order {} not found", order_id))?;
+        self.book.amend_order(order_id, new_price, new_qty)?;
+        self.dirty.insert((view.side, view.price));
+        self.dirty.insert((view.side, new_price.unwrap_or(view.price)));
+        self.check_top();
+        Ok(())
+    }
+
+    pub fn execute_market_order(
+        &mut self,
+        side: Side,
+        quantity: Quantity,
+        taker_id: OrderId,
+    ) -> MarketOrderResult {
+        let result = self.book.execute_market_order(side, quantity, taker_id);
+        self.dirty.extend(result.fills.iter().map(|fill| (side.opposite(), fill.price)));
+        self.check_top();
+        result
+    }
+
+    /// Marks every level that was on `side`
+    pub fn cancel_all_for_side(&mut self, side: Side) -> usize {
+        let levels = self.book.book_snapshot(usize::MAX);
+        let prices = match side {
+            Side::Bid => levels.bids,
+            Side::Ask => levels.asks,
+        };
+        self.dirty.extend(prices.into_iter().map(|(price, _)| (side, price)));
+        let canceled = self.book.cancel_all_for_side(side);
+        self.check_top();
+        canceled
+    }
+
+    fn check_top(&mut self) {
+        let top = TopOfBook::of(&self.book);
+        if top != self.top {
+            self.top = top;
+            if let Some(callback) = self.on_top_change.as_mut() {
+                callback(top);
+            }
+        }
+    }
+}
@@ -1,54 +1,1566 @@
-use crate::types::order::{Order, OrderId, Side};
-use crate::types::price::Price;
-use crate::types::quantity::Quantity;
+use crate::orderbook::command::{Command, CommandQueue, EngineEvent};
+use crate::orderbook::error::OrderError;
+use crate::types::order::{AccountId, IdCounter, NO_ACCOUNT, Order, OrderId, Side, TimeInForce};
+use crate::types::price::{Price, PriceScale};
+use crate::types::quantity::{QtyValue, Quantity};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, VecDeque};
+use std::ops::RangeInclusive;
+
+/// Self-trade prevention: what matching does when an incoming order meets a
+/// resting order from the same account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StpMode {
+    /// Same-account orders trade like any other
+    #[default]
+    Off,
+    /// Cancel the resting order and keep matching behind it
+    CancelResting,
+    /// Leave the resting order queued and match past it
+    /// A limit remainder that would then rest crossed is canceled instead
+    Skip,
+}
+
+/// How an incoming order's quantity is shared among the resting orders at a
+/// price level
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MatchMode {
+    /// Strict time priority: the oldest order fills first
+    #[default]
+    Fifo,
+    /// Every order at the level fills in proportion to its size; see
+    /// pro_rata_split for the rounding rule
+    ProRata,
+}
+
+/// Price grid, lot size and matching rules a book is built on
+/// Valid prices are multiples of `tick_size` in [tick_size, max_price);
+/// valid quantities are positive multiples of `lot_size`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BookConfig {
+    pub max_price: u32,
+    pub tick_size: u32,
+    pub lot_size: u32,
+    pub stp: StpMode,
+    pub match_mode: MatchMode,
+    /// Most orders one price level may hold; an add that would rest beyond it
+    /// is rejected. Bounds the linear scans cancel and matching do per level
+    pub max_orders_per_level: Option<usize>,
+    /// Reject a GTC order that would trade on arrival instead of matching it as
+    /// a marketable limit; IOC and FOK orders exist to take, so still match
+    /// Off by default. The book never rests crossed either way
+    pub reject_crossing: bool,
+}
+
+impl Default for BookConfig {
+    /// Integer ticks from 1 to 9999 (max price $100 in cents), lot size 1, no
+    /// STP, FIFO matching, levels unbounded, crossing orders matched
+    fn default() -> Self {
+        Self {
+            max_price: 10000,
+            tick_size: 1,
+            lot_size: 1,
+            stp: StpMode::Off,
+            match_mode: MatchMode::Fifo,
+            max_orders_per_level: None,
+            reject_crossing: false,
+        }
+    }
+}
+
+impl BookConfig {
+    /// Checked once when a book is constructed
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tick_size == 0 {
+            return Err("tick_size cannot be zero".to_string());
+        }
+
+        if self.lot_size == 0 {
+            return Err("lot_size cannot be zero".to_string());
+        }
+
+        // Keeps the top slot on the grid so array books size exactly
+        if !self.max_price.is_multiple_of(self.tick_size) {
+            return Err(format!(
+                "max_price {} is not a multiple of tick_size {}",
+                self.max_price, self.tick_size
+            ));
+        }
+
+        if self.max_orders_per_level == Some(0) {
+            return Err("max_orders_per_level cannot be zero".to_string());
+        }
+
+        if self.max_price <= self.tick_size {
+            return Err(format!(
+                "max_price {} leaves no valid price (tick_size={})",
+                self.max_price, self.tick_size
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Number of tick slots in [0, max_price): the length of a dense price array
+    pub fn slots(&self) -> usize {
+        (self.max_price / self.tick_size) as usize
+    }
+
+    /// Slot index of an on-grid price
+    pub fn index_of(&self, price_value: u32) -> usize {
+        (price_value / self.tick_size) as usize
+    }
+
+    /// Price of slot `i`
+    pub fn price_at(&self, i: usize) -> u32 {
+        (i as u32) * self.tick_size
+    }
+
+    /// Largest on-grid price at or below `price_value`
+    pub fn floor_to_tick(&self, price_value: u32) -> u32 {
+        price_value - price_value % self.tick_size
+    }
+
+    /// Slots of the valid prices between `a` and `b` inclusive, given in either
+    /// order; bounds off the grid or out of range are pulled inwards
+    /// None when no valid price lies between them
+    pub fn slot_range(&self, a: Price, b: Price) -> Option<RangeInclusive<usize>> {
+        let (low, high) = (a.value().min(b.value()), a.value().max(b.value()));
+        let first = low.div_ceil(self.tick_size).max(1) as usize;
+        let last = ((high / self.tick_size) as usize).min(self.slots() - 1);
+        (first <= last).then_some(first..=last)
+    }
+
+    /// Can one more order rest on the `side` level at `price`, which holds
+    /// `resting` orders now?
+    pub fn check_level_room(&self, side: Side, price: Price, resting: usize) -> Result<(), OrderError> {
+        match self.max_orders_per_level {
+            Some(max) if resting >= max => Err(OrderError::LevelFull { side, price: price.value(), max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Refuses a GTC order that `crosses` the opposite best when
+    /// reject_crossing is set; `crosses` is only asked then
+    pub fn check_crossing(&self, order: &Order, crosses: impl FnOnce() -> bool) -> Result<(), OrderError> {
+        if self.reject_crossing && order.time_in_force() == TimeInForce::GTC && crosses() {
+            return Err(OrderError::WouldCross { order: order.id(), price: order.price().value() });
+        }
+        Ok(())
+    }
+
+    /// In bounds and on the tick grid
+    pub fn is_valid_price(&self, price_value: u32) -> bool {
+        price_value != 0 && price_value < self.max_price && price_value.is_multiple_of(self.tick_size)
+    }
+
+    /// is_valid_price with the reason when it is not
+    pub fn check_price(&self, price: Price) -> Result<(), OrderError> {
+        let price_value = price.value();
+
+        // Validation 1: Price must be multiple of tick size
+        if !price_value.is_multiple_of(self.tick_size) {
+            return Err(OrderError::PriceNotTick { price: price_value, tick: self.tick_size });
+        }
+
+        // Validation 2: Price must be in bounds
+        if price_value == 0 || price_value >= self.max_price {
+            return Err(OrderError::PriceOutOfBounds { price: price_value, max: self.max_price });
+        }
+
+        Ok(())
+    }
+
+    /// Tick, bounds and lot checks shared by add_order and amend_order
+    pub fn validate_order(&self, price: Price, quantity: Quantity) -> Result<(), OrderError> {
+        self.check_price(price)?;
+        let quantity_value = quantity.value();
+
+        // Validation 3: Quantity must be multiple of lot size
+        if !quantity_value.is_multiple_of(self.lot_size as QtyValue) {
+            return Err(OrderError::QtyNotLot { qty: quantity_value, lot: self.lot_size });
+        }
+
+        // Validation 4: Quantity must be positive
+        if quantity_value == 0 {
+            return Err(OrderError::ZeroQty);
+        }
+
+        Ok(())
+    }
+
+    /// Checks a two-sided quote can pass before any of it is placed: bid
+    /// strictly below ask and both legs valid orders
+    pub fn validate_quote(&self, bid: (Price, Quantity), ask: (Price, Quantity)) -> Result<(), String> {
+        if bid.0 >= ask.0 {
+            return Err(format!(
+                "Quote is crossed: bid {} is not below ask {}",
+                bid.0.value(),
+                ask.0.value()
+            ));
+        }
+        self.validate_order(bid.0, bid.1)?;
+        self.validate_order(ask.0, ask.1)?;
+        Ok(())
+    }
+}
 
 /// Represents a trade execution (fill)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Fill {
     pub price: Price,
     pub quantity: Quantity,
     pub maker_order_id: OrderId,
+    pub taker_order_id: OrderId,
+    pub aggressor_side: Side,
+    /// Book-wide execution sequence number, strictly increasing across fills
+    pub seq: u64,
+}
+
+/// Source of fill sequence numbers, one per book
+/// Fills are stamped in execution order once a match completes, so it also
+/// sees the book's most recent execution
+#[derive(Debug, Default)]
+pub(crate) struct FillSequence {
+    seq: u64,
+    last_trade: Option<(Price, Quantity, u64)>,
+}
+
+impl FillSequence {
+    pub(crate) fn stamp(&mut self, fill: &mut Fill) {
+        self.seq += 1;
+        fill.seq = self.seq;
+        self.last_trade = Some((fill.price, fill.quantity, fill.seq));
+    }
+
+    pub(crate) fn last_trade(&self) -> Option<(Price, Quantity)> {
+        self.last_trade.map(|(price, quantity, _)| (price, quantity))
+    }
+}
+
+/// Source of order arrival sequence numbers, one per book
+/// Stamped on every order as it joins the back of a queue (a new order, or
+/// one an amend requeued), so queues stay sorted by seq front to back
+#[derive(Debug, Default)]
+pub(crate) struct ArrivalSequence(u64);
+
+impl ArrivalSequence {
+    pub(crate) fn stamp(&mut self, order: &mut Order) {
+        self.0 += 1;
+        order.set_seq(self.0);
+    }
+}
+
+/// Min-heap of (expiry, id) for resting orders that carry an expiry, one
+/// per book, so expire_orders pops what is due instead of walking levels
+/// Entries stay behind when an order fills or cancels; ids are unique, so
+/// the book skips a due id that no longer rests
+#[derive(Debug, Default)]
+pub(crate) struct ExpiryQueue(BinaryHeap<Reverse<(u64, OrderId)>>);
+
+impl ExpiryQueue {
+    /// Call as `order` comes to rest
+    pub(crate) fn track(&mut self, order: &Order) {
+        if let Some(expiry) = order.expiry() {
+            self.0.push(Reverse((expiry, order.id())));
+        }
+    }
+
+    /// Ids with an expiry at or before `now`, soonest first, dropped from the
+    /// queue
+    pub(crate) fn take_due(&mut self, now: u64) -> Vec<OrderId> {
+        let mut due = Vec::new();
+        while let Some(&Reverse((expiry, order_id))) = self.0.peek()
+            && expiry <= now
+        {
+            self.0.pop();
+            due.push(order_id);
+        }
+        due
+    }
+
+    /// Forget every entry, keeping the heap's allocation
+    pub(crate) fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// Outcome of a market order
+/// Partial execution is a normal result: whatever could not be filled is
+/// reported in `remaining_qty` instead of being treated as an error
+#[derive(Debug, Clone)]
+pub struct MarketOrderResult {
+    pub fills: Vec<Fill>,
+    pub filled_qty: Quantity,
+    pub remaining_qty: Quantity,
+}
+
+impl MarketOrderResult {
+    pub fn is_fully_filled(&self) -> bool {
+        self.remaining_qty.is_zero()
+    }
+
+    /// Volume-weighted average price of the fills; None if nothing filled
+    pub fn vwap(&self) -> Option<f64> {
+        let filled: u64 = self.fills.iter().map(|fill| fill.quantity.as_u64()).sum();
+        if filled == 0 {
+            return None;
+        }
+        let notional: u128 = self.fills.iter().map(|fill| fill.notional() as u128).sum();
+        Some(notional as f64 / filled as f64)
+    }
+}
+
+/// The incoming side of a match, as seen by the per-level matching loops
+#[derive(Debug, Clone, Copy)]
+pub struct Taker {
+    pub order_id: OrderId,
+    pub side: Side,
+    /// Worst price the taker accepts; None for market orders
+    pub limit: Option<Price>,
+    pub account: AccountId,
+    pub stp: StpMode,
+    pub mode: MatchMode,
+    /// Pro-rata shares are rounded to whole lots of this size
+    pub lot_size: u32,
+}
+
+impl Taker {
+    /// An incoming limit order: bounded by its price, tagged with its account
+    pub fn limit_order(order: &Order, config: &BookConfig) -> Self {
+        Self {
+            order_id: order.id(),
+            side: order.side(),
+            limit: Some(order.price()),
+            account: order.account(),
+            stp: config.stp,
+            mode: config.match_mode,
+            lot_size: config.lot_size,
+        }
+    }
+
+    /// A market order: no price bound and no account, so never a self-trade
+    pub fn market(side: Side, order_id: OrderId, config: &BookConfig) -> Self {
+        Self {
+            order_id,
+            side,
+            limit: None,
+            account: NO_ACCOUNT,
+            stp: StpMode::Off,
+            mode: config.match_mode,
+            lot_size: config.lot_size,
+        }
+    }
+
+    /// Fill of this taker against one maker; `seq` is stamped by the book
+    pub fn fill(&self, price: Price, quantity: Quantity, maker_order_id: OrderId) -> Fill {
+        Fill {
+            price,
+            quantity,
+            maker_order_id,
+            taker_order_id: self.order_id,
+            aggressor_side: self.side,
+            seq: 0,
+        }
+    }
+
+    /// Would filling against a maker from `maker_account` be a self-trade
+    /// that STP has to prevent?
+    pub fn self_trade(&self, maker_account: AccountId) -> bool {
+        self.stp != StpMode::Off && self.account != NO_ACCOUNT && self.account == maker_account
+    }
+}
+
+/// Quantity `order` could take right now, up to its own size and limit price
+/// Used by FOK's pre-check: with STP on, same-account makers do not count
+/// since matching will cancel or skip them instead of filling
+pub(crate) fn fillable_quantity<O: OrderbookTrait>(book: &O, order: &Order) -> Quantity {
+    let taker = Taker::limit_order(order, book.config());
+    if taker.stp == StpMode::Off || taker.account == NO_ACCOUNT {
+        return book.available_liquidity(order.side(), order.quantity(), taker.limit);
+    }
+
+    // Same-account orders have to be picked out one by one
+    let within_limit: fn(u32, u32) -> bool = match order.side() {
+        Side::Bid => |p, limit| p <= limit,
+        Side::Ask => |p, limit| p >= limit,
+    };
+    let cap = order.quantity().as_u64();
+    let mut total: u64 = 0;
+
+    for (price, maker) in book.iter_orders(order.side().opposite()) {
+        if total >= cap || !within_limit(price.value(), order.price().value()) {
+            break;
+        }
+        if !taker.self_trade(maker.account()) {
+            total += maker.quantity().as_u64();
+        }
+    }
+
+    Quantity::define(total.min(cap) as QtyValue)
+}
+
+/// Share `incoming` among resting orders of `sizes`, given in time priority
+///
+/// If the level cannot fill everything, each order first gets its
+/// proportional share rounded down to whole lots. The leftover is then handed
+/// out one lot at a time, earliest order first, cycling through the queue
+/// until none is left; the final piece may be a partial lot. An incoming size
+/// that covers the whole level simply fills every order.
+pub(crate) fn pro_rata_split(incoming: Quantity, sizes: &[Quantity], lot_size: u32) -> Vec<Quantity> {
+    let total: u64 = sizes.iter().map(|q| q.as_u64()).sum();
+    let incoming = incoming.as_u64();
+    if incoming >= total {
+        return sizes.to_vec();
+    }
+
+    let lot = lot_size as u64;
+    let incoming_lots = incoming / lot;
+    // The product can pass u64::MAX with wide quantities; the share itself
+    // is below the order's size, so it fits back in a u64
+    let mut shares: Vec<u64> = sizes
+        .iter()
+        .map(|q| (incoming_lots as u128 * q.as_u64() as u128 / total as u128) as u64 * lot)
+        .collect();
+
+    // Each floored share is strictly below its order's size, so every order
+    // has room for at least part of a lot and the loop always makes progress
+    let mut leftover = incoming - shares.iter().sum::<u64>();
+    while leftover > 0 {
+        for (share, size) in shares.iter_mut().zip(sizes) {
+            let extra = lot.min(leftover).min(size.as_u64() - *share);
+            *share += extra;
+            leftover -= extra;
+            if leftover == 0 {
+                break;
+            }
+        }
+    }
+
+    shares.into_iter().map(|s| Quantity::define(s as QtyValue)).collect()
+}
+
+/// One price level as the matcher sees it: resting orders by queue position
+/// Books implement this over their own level layout to commit a match; a
+/// SimulatedQueue implements it over a copy to dry-run one
+pub(crate) trait LevelQueue {
+    /// Id, open quantity and account of the order at queue position `i`
+    fn maker(&self, i: usize) -> Option<(OrderId, Quantity, AccountId)>;
+
+    /// Take order `i` out of the level (filled or canceled by STP); later
+    /// orders move up one position
+    fn remove(&mut self, i: usize);
+
+    /// Order `i` traded `qty` and keeps its place
+    fn reduce(&mut self, i: usize, qty: Quantity);
+}
+
+/// Match `taker` against one level, oldest order first or pro-rata per its
+/// mode, until `remaining_qty` runs out or the level does, handing each
+/// fill to `on_fill` as it is made
+/// The single copy of the per-level rules, so execution and
+/// simulate_market_order cannot drift apart
+pub(crate) fn match_level<Q: LevelQueue>(
+    queue: &mut Q,
+    remaining_qty: &mut Quantity,
+    price: Price,
+    taker: Taker,
+    on_fill: &mut impl FnMut(Fill),
+) {
+    if taker.mode == MatchMode::ProRata {
+        return match_level_pro_rata(queue, remaining_qty, price, taker, on_fill);
+    }
+
+    // `i` only moves past orders that STP skips; otherwise it stays at 0
+    let mut i = 0;
+    while !remaining_qty.is_zero()
+        && let Some((order_id, order_qty, account)) = queue.maker(i)
+    {
+        // Self-trade prevention: a same-account maker is never filled
+        if taker.self_trade(account) {
+            if taker.stp == StpMode::CancelResting {
+                queue.remove(i);
+            } else {
+                i += 1;
+            }
+            continue;
+        }
+
+        let fill_qty = (*remaining_qty).min(order_qty);
+        on_fill(taker.fill(price, fill_qty, order_id));
+        *remaining_qty = remaining_qty.saturating_sub(fill_qty);
+
+        if fill_qty == order_qty {
+            queue.remove(i);
+        } else {
+            // Partial fill: the resting order keeps its place
+            queue.reduce(i, fill_qty);
+        }
+    }
+}
+
+/// Pro-rata half of match_level (see pro_rata_split for the rounding)
+/// Self-trading makers are canceled or skipped per STP before the split
+fn match_level_pro_rata<Q: LevelQueue>(
+    queue: &mut Q,
+    remaining_qty: &mut Quantity,
+    price: Price,
+    taker: Taker,
+    on_fill: &mut impl FnMut(Fill),
+) {
+    let mut eligible = Vec::new();
+    let mut i = 0;
+    while let Some((order_id, order_qty, account)) = queue.maker(i) {
+        if !taker.self_trade(account) {
+            eligible.push((i, order_id, order_qty));
+        } else if taker.stp == StpMode::CancelResting {
+            queue.remove(i);
+            continue;
+        }
+        i += 1;
+    }
+
+    let sizes: Vec<Quantity> = eligible.iter().map(|&(_, _, qty)| qty).collect();
+    let shares = pro_rata_split(*remaining_qty, &sizes, taker.lot_size);
+
+    for (&(_, order_id, _), &share) in eligible.iter().zip(&shares) {
+        if !share.is_zero() {
+            on_fill(taker.fill(price, share, order_id));
+            *remaining_qty = remaining_qty.saturating_sub(share);
+        }
+    }
+
+    // Back to front, so a removal never shifts an order still to be applied
+    for (&(i, _, order_qty), &share) in eligible.iter().zip(&shares).rev() {
+        if share == order_qty {
+            queue.remove(i);
+        } else if !share.is_zero() {
+            queue.reduce(i, share);
+        }
+    }
+}
+
+/// LevelQueue over a VecDeque level, keeping the book's index and running
+/// totals in step; shared by the books whose levels are a queue of orders
+pub(crate) struct CommitQueue<'a> {
+    pub(crate) orders: &'a mut VecDeque<Order>,
+    pub(crate) order_index: &'a mut HashMap<OrderId, (Side, Price)>,
+    pub(crate) totals: &'a mut SideTotals,
+}
+
+impl LevelQueue for CommitQueue<'_> {
+    fn maker(&self, i: usize) -> Option<(OrderId, Quantity, AccountId)> {
+        self.orders.get(i).map(|o| (o.id(), o.quantity(), o.account()))
+    }
+
+    fn remove(&mut self, i: usize) {
+        // Front removals (the common case) are O(1) pops
+        if let Some(order) = self.orders.remove(i) {
+            self.totals.removed(order.quantity());
+            self.order_index.remove(&order.id());
+        }
+    }
+
+    fn reduce(&mut self, i: usize, qty: Quantity) {
+        let order = &mut self.orders[i];
+        self.totals.reduced(qty);
+        order.set_quantity(order.quantity().saturating_sub(qty));
+    }
+}
+
+/// LevelQueue over a read-only copy of a level
+/// Removals and reductions are only tracked as far as the matcher reads
+/// them back: it removes at its cursor and stops after a partial fill, so
+/// position `i` is original position `i + removed`
+struct SimulatedQueue<'a> {
+    orders: &'a [Order],
+    removed: usize,
+}
+
+impl LevelQueue for SimulatedQueue<'_> {
+    fn maker(&self, i: usize) -> Option<(OrderId, Quantity, AccountId)> {
+        self.orders.get(i + self.removed).map(|o| (o.id(), o.quantity(), o.account()))
+    }
+
+    fn remove(&mut self, _i: usize) {
+        self.removed += 1;
+    }
+
+    fn reduce(&mut self, _i: usize, _qty: Quantity) {}
+}
+
+/// Cost of sweeping the opposite side for a given size, without trading
+/// A book too thin to fill everything quotes the part it can, so
+/// `filled_qty` may be less than requested
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepQuote {
+    pub vwap: f64,
+    pub levels_consumed: usize,
+    pub filled_qty: Quantity,
+    /// Last (least favourable) price level touched
+    pub worst_price: Price,
+}
+
+impl SweepQuote {
+    /// Accumulate `(price, level quantity)` pairs, best price first, until
+    /// `quantity` is covered; None if nothing would fill
+    pub(crate) fn walk(
+        quantity: Quantity,
+        levels: impl Iterator<Item = (u32, u64)>,
+    ) -> Option<Self> {
+        let cap = quantity.as_u64();
+        let mut filled: u64 = 0;
+        let mut notional: u128 = 0;
+        let mut levels_consumed = 0;
+        let mut worst_price = 0;
+
+        for (price_value, level_qty) in levels {
+            if filled >= cap {
+                break;
+            }
+            let take = level_qty.min(cap - filled);
+            filled += take;
+            notional += price_value as u128 * take as u128;
+            levels_consumed += 1;
+            worst_price = price_value;
+        }
+
+        if filled == 0 {
+            return None;
+        }
+
+        Some(Self {
+            vwap: notional as f64 / filled as f64,
+            levels_consumed,
+            filled_qty: Quantity::define(filled as QtyValue),
+            worst_price: Price::define(worst_price),
+        })
+    }
+}
+
+/// Execution quality of one market order against the mid it arrived at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlippageReport {
+    /// (best bid + best ask) / 2 before executing, unrounded; None when
+    /// either side was empty
+    pub arrival_mid: Option<f64>,
+    /// None if nothing filled
+    pub vwap: Option<f64>,
+    /// How much worse than arrival_mid the order filled, in ticks: positive
+    /// is a cost for either side. None without both a mid and a fill
+    pub slippage_ticks: Option<f64>,
+}
+
+/// Invariants shared by every implementation, checked through the trait
+/// against the book's `order_index`; books add their own structural checks
+pub(crate) fn check_invariants<O: OrderbookTrait>(
+    book: &O,
+    order_index: &HashMap<OrderId, (Side, Price)>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    // get_order resolves through the index to the recorded level; walked in
+    // id order so the report does not follow the map's random hash order
+    let mut indexed: Vec<_> = order_index.iter().map(|(&id, &entry)| (id, entry)).collect();
+    indexed.sort_unstable_by_key(|&(id, _)| id);
+    for (id, (side, price)) in indexed {
+        if book.get_order(id).is_none() {
+            problems.push(format!(
+                "Order {} indexed at {:?} {} but not on that level",
+                id,
+                side,
+                price.value()
+            ));
+        }
+    }
+
+    let mut resting = 0;
+    for side in Side::all() {
+        let mut previous: Option<(Price, u64)> = None;
+        for (price, order) in book.iter_orders(side) {
+            resting += 1;
+            // Matching takes each level front first, so position must follow seq
+            if let Some((previous_price, previous_seq)) = previous
+                && previous_price == price
+                && previous_seq >= order.seq()
+            {
+                problems.push(format!(
+                    "Order {} (seq {}) queues behind seq {} at {:?} {}",
+                    order.id(),
+                    order.seq(),
+                    previous_seq,
+                    side,
+                    price.value()
+                ));
+            }
+            previous = Some((price, order.seq()));
+            if order.side() != side || order.price() != price {
+                problems.push(format!(
+                    "Order {} ({:?} {}) rests on the {:?} level at {}",
+                    order.id(),
+                    order.side(),
+                    order.price().value(),
+                    side,
+                    price.value()
+                ));
+            }
+            if order.quantity().is_zero() {
+                problems.push(format!("Order {} rests with zero quantity", order.id()));
+            }
+            if !order_index.contains_key(&order.id()) {
+                problems.push(format!(
+                    "Order {} rests at {:?} {} without an index entry",
+                    order.id(),
+                    side,
+                    price.value()
+                ));
+            }
+        }
+    }
+    if resting != order_index.len() {
+        problems.push(format!(
+            "{} resting orders but {} index entries",
+            resting,
+            order_index.len()
+        ));
+    }
+
+    let snapshot = book.book_snapshot(usize::MAX);
+    for (side, levels) in [(Side::Bid, &snapshot.bids), (Side::Ask, &snapshot.asks)] {
+        for (price, qty) in levels {
+            if *qty == 0 {
+                problems.push(format!("Empty {:?} level lingering at {}", side, price.value()));
+            }
+        }
+    }
+
+    // Running counters agree with a full walk
+    let walked = BookStats {
+        bid_levels: snapshot.bids.len(),
+        ask_levels: snapshot.asks.len(),
+        bid_orders: book.iter_orders(Side::Bid).count(),
+        ask_orders: book.iter_orders(Side::Ask).count(),
+        bid_volume: snapshot.bids.iter().map(|(_, qty)| qty).sum(),
+        ask_volume: snapshot.asks.iter().map(|(_, qty)| qty).sum(),
+    };
+    if book.stats() != walked {
+        problems.push(format!("stats() reports {:?} but the book holds {:?}", book.stats(), walked));
+    }
+
+    if let (Some(bid), Some(ask)) = (book.best_bid(), book.best_ask())
+        && bid >= ask
+    {
+        problems.push(format!(
+            "Book is crossed: best bid {} >= best ask {}",
+            bid.value(),
+            ask.value()
+        ));
+    }
+
+    problems
+}
+
+/// Outcome of a limit order
+/// `rested_qty` went on the book; `canceled_qty` is the unfilled part of an
+/// IOC order, dropped instead of resting
+#[derive(Debug, Clone)]
+pub struct LimitOrderResult {
+    pub fills: Vec<Fill>,
+    pub filled_qty: Quantity,
+    pub rested_qty: Quantity,
+    pub canceled_qty: Quantity,
+}
+
+/// Where a resting order currently stands
+/// `queue_position` is 0 for the order at the front of its level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderView {
+    pub price: Price,
+    pub side: Side,
+    pub remaining_qty: Quantity,
+    pub queue_position: usize,
+}
+
+/// Aggregated (L2) view of the top price levels on each side
+/// Both sides are ordered best to worst: bids descending, asks ascending
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BookSnapshot {
+    pub bids: Vec<(Price, u64)>,
+    pub asks: Vec<(Price, u64)>,
+}
+
+/// Book-wide counts per side, cheap enough to poll from a dashboard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BookStats {
+    pub bid_levels: usize,
+    pub ask_levels: usize,
+    pub bid_orders: usize,
+    pub ask_orders: usize,
+    pub bid_volume: u64,
+    pub ask_volume: u64,
+}
+
+impl BookStats {
+    pub(crate) fn from_totals(bid_levels: usize, ask_levels: usize, bids: SideTotals, asks: SideTotals) -> Self {
+        Self {
+            bid_levels,
+            ask_levels,
+            bid_orders: bids.orders,
+            ask_orders: asks.orders,
+            bid_volume: bids.volume,
+            ask_volume: asks.volume,
+        }
+    }
+}
+
+/// Estimated memory held by a book, in bytes
+/// `fixed_bytes` is allocated whatever the book holds (the struct itself and
+/// any per-tick arrays); `heap_bytes` grows with the orders (queue buffers,
+/// map entries). Both count capacity, not just what is in use
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryFootprint {
+    pub fixed_bytes: usize,
+    pub heap_bytes: usize,
+    /// Resting orders when measured
+    pub orders: usize,
+}
+
+impl MemoryFootprint {
+    pub fn total_bytes(&self) -> usize {
+        self.fixed_bytes + self.heap_bytes
+    }
+}
+
+/// Drop one side's `removed` orders from the id index
+/// clear() rather than retain() when nothing else is indexed: retain leaves
+/// tombstones that cost index capacity until the next rehash
+pub(crate) fn unindex_side(order_index: &mut HashMap<OrderId, (Side, Price)>, side: Side, removed: usize) {
+    if removed == order_index.len() {
+        order_index.clear();
+    } else {
+        order_index.retain(|_, (s, _)| *s != side);
+    }
+}
+
+/// Heap held by a HashMap: one slot plus one control byte per bucket
+pub(crate) fn hash_map_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+    map.capacity() * (size_of::<(K, V)>() + 1)
+}
+
+/// Heap held by a BTreeMap's entries, ignoring slack in part-full nodes
+pub(crate) fn btree_map_bytes<K, V>(map: &BTreeMap<K, V>) -> usize {
+    map.len() * (size_of::<K>() + size_of::<V>())
+}
+
+/// Heap held by a VecDeque's buffer
+pub(crate) fn deque_bytes<T>(queue: &VecDeque<T>) -> usize {
+    queue.capacity() * size_of::<T>()
+}
+
+/// Running order count and resting volume for one side of a book
+/// Every path that rests, shrinks or removes an order updates these, so
+/// stats() never walks the book
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SideTotals {
+    pub(crate) orders: usize,
+    pub(crate) volume: u64,
+}
+
+impl SideTotals {
+    /// An order came to rest with `qty`
+    pub(crate) fn rested(&mut self, qty: Quantity) {
+        self.orders += 1;
+        self.volume += qty.as_u64();
+    }
+
+    /// A resting order left the book with `qty` still open
+    pub(crate) fn removed(&mut self, qty: Quantity) {
+        self.orders -= 1;
+        self.volume -= qty.as_u64();
+    }
+
+    /// A resting order shrank by `qty` (partial fill or amend down)
+    pub(crate) fn reduced(&mut self, qty: Quantity) {
+        self.volume -= qty.as_u64();
+    }
+}
+
+/// Implementation-independent resting state of a book
+/// Orders are listed bids then asks, each best price first and FIFO within a
+/// level, so replaying them with add_order rebuilds identical queues in any
+/// implementation on the same `config`. `next_id` is the next id the
+/// counter that issued them would hand out; resume with IdCounter::starting_at
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BookState {
+    pub orders: Vec<Order>,
+    pub next_id: OrderId,
+    /// States saved before the config was recorded restore onto the default
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub config: BookConfig,
+}
+
+/// Ladder rendering behind format_ladder, with `label` formatting each price
+fn ladder<O: OrderbookTrait + ?Sized>(book: &O, depth: usize, label: impl Fn(Price) -> String) -> String {
+    let snapshot = book.book_snapshot(depth);
+    let mut out = format!("{:>12} {:>10} {:>12}\n", "BID QTY", "PRICE", "ASK QTY");
+
+    for (price, qty) in snapshot.asks.iter().rev() {
+        out += &format!("{:>12} {:>10} {:>12}\n", "", label(*price), qty);
+    }
+    let spread = match (book.best_bid(), book.best_ask()) {
+        (Some(bid), Some(ask)) => label(Price::define(bid.distance_to(ask))),
+        _ => "-".to_string(),
+    };
+    out += &format!("{:>12} {:>10} {:>12}\n", "", format!("({})", spread), "spread");
+    for (price, qty) in &snapshot.bids {
+        out += &format!("{:>12} {:>10} {:>12}\n", qty, label(*price), "");
+    }
+
+    out
 }
 
 /// Common trait that all orderbook implementations must implement
 /// This allows benchmarking different implementations uniformly
 pub trait OrderbookTrait {
-    /// Create a new empty orderbook
-    fn new() -> Self;
+    /// Create a new empty orderbook on the default price grid
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self::with_config(BookConfig::default()).expect("default BookConfig is valid")
+    }
+
+    /// Create a new empty orderbook on the price grid described by `config`
+    /// Returns error if the config is invalid for this implementation
+    fn with_config(config: BookConfig) -> Result<Self, String>
+    where
+        Self: Sized;
+
+    /// Create a new empty orderbook whose order id index already holds
+    /// `expected_orders`, so building up to that many resting orders never
+    /// rehashes it (see reserve_orders)
+    fn new_with_capacity(expected_orders: usize) -> Self
+    where
+        Self: Sized,
+    {
+        let mut book = Self::new();
+        book.reserve_orders(expected_orders);
+        book
+    }
+
+    /// The price grid this book validates against
+    fn config(&self) -> &BookConfig;
+
+    /// Pre-size level queues to hold `orders_per_level` orders, so a caller
+    /// that knows its depth sees no reallocation as levels fill
+    /// Array books size every slot now; tree and hybrid cold levels are
+    /// created on demand, so they remember the capacity for new levels
+    fn reserve(&mut self, orders_per_level: usize);
+
+    /// Make room for `additional` more resting orders in the order id index
+    /// (and any per-order storage) so they can be added without rehashing
+    fn reserve_orders(&mut self, additional: usize);
 
     /// Add a limit order to the book
+    /// A marketable order first trades against the opposite side up to its limit
+    /// price; any remainder rests (GTC) or is canceled (IOC). A FOK order that
+    /// cannot fill completely within its limit is rejected without trading
     /// Returns error if order is invalid (bad price/quantity, out of bounds, etc.)
-    fn add_order(&mut self, order: Order) -> Result<(), String>;
+    fn add_order(&mut self, order: Order) -> Result<LimitOrderResult, OrderError>;
+
+    /// Add a burst of limit orders, reserving index room for the whole batch
+    /// up front (from the iterator's size hint) instead of growing per order
+    /// Each order is applied as add_order would; a rejected order does not
+    /// stop the rest. Returns one result per order, in batch order
+    fn add_orders(&mut self, orders: impl IntoIterator<Item = Order>) -> Vec<Result<(), OrderError>> {
+        let orders = orders.into_iter();
+        let (batch_size, _) = orders.size_hint();
+        self.reserve_orders(batch_size);
 
-    /// Cancel an order by ID
+        let mut results = Vec::with_capacity(batch_size);
+        for order in orders {
+            results.push(self.add_order(order).map(|_| ()));
+        }
+        results
+    }
+
+    /// Cancel an order by ID, returning it as it rested (quantity net of
+    /// partial fills) for position and risk bookkeeping
     /// Returns error if order not found
-    fn cancel_order(&mut self, order_id: OrderId) -> Result<(), String>;
+    fn cancel_order(&mut self, order_id: OrderId) -> Result<Order, OrderError>;
+
+    /// Cancel every resting order on `side` in one pass over its levels,
+    /// without per-order lookups. Returns how many orders were removed
+    fn cancel_all_for_side(&mut self, side: Side) -> usize;
+
+    /// Remove every resting order whose expiry (see Order::with_expiry) is at
+    /// or before `now`, returning them as they rested, soonest expiry first
+    /// The book keeps expiring orders in a min-heap, so the cost follows the
+    /// number due rather than the size of the book
+    fn expire_orders(&mut self, now: u64) -> Vec<Order> {
+        // Due ids that already filled or canceled are no longer resting
+        let due = self.take_due_expiries(now);
+        due.into_iter().filter_map(|order_id| self.cancel_order(order_id).ok()).collect()
+    }
+
+    /// Pop every id due by `now` off the book's expiry heap, soonest first,
+    /// whether or not it still rests; expire_orders does the canceling
+    #[doc(hidden)]
+    fn take_due_expiries(&mut self, now: u64) -> Vec<OrderId>;
+
+    /// Cancel every resting order on both sides (kill switch, end of session)
+    /// Returns how many orders were removed
+    fn cancel_all(&mut self) -> usize {
+        self.cancel_all_for_side(Side::Bid) + self.cancel_all_for_side(Side::Ask)
+    }
+
+    /// Return to the state of a freshly built book on the same config, fill
+    /// sequence included, so a benchmark can reuse one book across trials
+    /// The id index keeps its allocation, as do array-backed levels and any
+    /// reserve on them; BTreeMap-keyed levels (the tree book, the hybrid's
+    /// cold zone) are freed, as they are whenever a level empties
+    fn clear(&mut self);
+
+    /// Whether `order_id` is resting, from the id index alone: unlike
+    /// get_order it never walks a level
+    fn contains_order(&self, order_id: OrderId) -> bool;
+
+    /// Look up a resting order: current (post partial fill) quantity and
+    /// queue position within its level. None if the id is not resting
+    fn get_order(&self, order_id: OrderId) -> Option<OrderView>;
+
+    /// Quantity queued ahead of a resting order at its level: what has to
+    /// trade or cancel before it fills. None if the id is not resting
+    /// Follows matching order, so a priority-keeping reduction stays in place
+    fn queue_ahead(&self, order_id: OrderId) -> Option<u64> {
+        let view = self.get_order(order_id)?;
+        let ahead = self
+            .iter_orders(view.side)
+            .skip_while(|&(price, _)| price != view.price)
+            .take_while(|(_, order)| order.id() != order_id)
+            .map(|(_, order)| order.quantity().as_u64())
+            .sum();
+        Some(ahead)
+    }
+
+    /// Amend a resting order's price and/or quantity (cancel-replace)
+    /// A pure quantity reduction keeps queue position; a price change or a
+    /// quantity increase moves the order to the back of its (new) level
+    /// Returns error if the order is not found, the new values are invalid,
+    /// or the new price would cross the book
+    fn amend_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_qty: Option<Quantity>,
+    ) -> Result<(), String>;
+
+    /// Shave a resting order down to `new_qty`, keeping its queue position
+    /// Level totals follow. An increase is refused: it has to go through
+    /// amend_order and lose priority
+    fn reduce_order(&mut self, order_id: OrderId, new_qty: Quantity) -> Result<(), String> {
+        let view = self
+            .get_order(order_id)
+            .ok_or_else(|| format!("Order {} not found", order_id))?;
+        if new_qty > view.remaining_qty {
+            return Err(format!(
+                "Cannot reduce order {} from {} to {}: use amend_order to increase",
+                order_id,
+                view.remaining_qty.value(),
+                new_qty.value()
+            ));
+        }
+        self.config().validate_order(view.price, new_qty)?;
+
+        self.amend_order(order_id, None, Some(new_qty))
+    }
+
+    /// Place a two-sided quote, bid first, with ids from `ids`
+    /// Returns the (bid, ask) ids. A leg that would trade against the book is
+    /// refused before anything is placed, as fills cannot be rolled back. If
+    /// the ask is rejected, the bid is canceled, so an Err leaves no half quote
+    fn quote(
+        &mut self,
+        bid: (Price, Quantity),
+        ask: (Price, Quantity),
+        ids: &mut IdCounter,
+    ) -> Result<(OrderId, OrderId), String> {
+        // Catch a bad or marketable leg before anything rests or trades
+        self.config().validate_quote(bid, ask)?;
+        if let Some(best_ask) = self.best_ask()
+            && bid.0 >= best_ask
+        {
+            return Err(format!(
+                "Quote bid {} would cross the best ask {}",
+                bid.0.value(),
+                best_ask.value()
+            ));
+        }
+        if let Some(best_bid) = self.best_bid()
+            && ask.0 <= best_bid
+        {
+            return Err(format!(
+                "Quote ask {} would cross the best bid {}",
+                ask.0.value(),
+                best_bid.value()
+            ));
+        }
+
+        let bid_order = Order::new(bid.0, bid.1, Side::Bid, ids);
+        let ask_order = Order::new(ask.0, ask.1, Side::Ask, ids);
+        self.add_order(bid_order)?;
+        if let Err(e) = self.add_order(ask_order) {
+            self.cancel_order(bid_order.id())
+                .expect("An uncrossed bid rests until the ask is placed");
+            return Err(e.into());
+        }
+
+        Ok((bid_order.id(), ask_order.id()))
+    }
+
+    /// Replace the quote `previous` (as returned by quote) with a new one
+    /// Parts of the old quote that have since filled are skipped. A quote
+    /// that fails validate_quote leaves the old orders untouched; one quote
+    /// rejects later (a leg crossing the book, a full level) puts the old
+    /// orders back as they were canceled, account and expiry included, at the
+    /// back of their levels, and returns the error
+    fn requote(
+        &mut self,
+        previous: (OrderId, OrderId),
+        bid: (Price, Quantity),
+        ask: (Price, Quantity),
+        ids: &mut IdCounter,
+    ) -> Result<(OrderId, OrderId), String> {
+        self.config().validate_quote(bid, ask)?;
+
+        // Not found if that leg has filled since
+        let resting: Vec<Order> =
+            [previous.0, previous.1].into_iter().filter_map(|id| self.cancel_order(id).ok()).collect();
+
+        match self.quote(bid, ask, ids) {
+            Ok(quoted) => Ok(quoted),
+            Err(e) => {
+                for order in resting {
+                    self.add_order(order)
+                        .expect("An order that was just resting fits back on its level");
+                }
+                Err(e)
+            }
+        }
+    }
 
     /// Execute a market order, consuming liquidity from the book
-    /// Returns fills that occurred, or error if insufficient liquidity
-    fn execute_market_order(&mut self, side: Side, quantity: Quantity)
-    -> Result<Vec<Fill>, String>;
+    /// `taker_id` identifies the market order on the fills it produces
+    /// Returns the fills that occurred plus any quantity left unfilled
+    /// when the opposite side runs out of liquidity
+    fn execute_market_order(
+        &mut self,
+        side: Side,
+        quantity: Quantity,
+        taker_id: OrderId,
+    ) -> MarketOrderResult {
+        let mut fills = Vec::new();
+        let result = self.execute_market_order_with(side, quantity, taker_id, |fill| fills.push(fill.clone()));
+        MarketOrderResult { fills, ..result }
+    }
+
+    /// execute_market_order without collecting: `on_fill` sees each fill,
+    /// sequenced, as it happens. The returned result's `fills` is empty
+    fn execute_market_order_with(
+        &mut self,
+        side: Side,
+        quantity: Quantity,
+        taker_id: OrderId,
+        on_fill: impl FnMut(&Fill),
+    ) -> MarketOrderResult;
+
+    /// Fills execute_market_order would produce right now, without touching
+    /// the book: same makers, prices and quantities in the same order
+    /// Nothing is executed, so every `seq` is 0
+    fn simulate_market_order(&self, side: Side, quantity: Quantity, taker_id: OrderId) -> Vec<Fill> {
+        let taker = Taker::market(side, taker_id, self.config());
+        let mut remaining_qty = quantity;
+        let mut fills = Vec::new();
+
+        // Levels come out of iter_orders best first, each in queue order
+        let mut orders = self.iter_orders(side.opposite()).peekable();
+        while !remaining_qty.is_zero()
+            && let Some(&(price, _)) = orders.peek()
+        {
+            let level: Vec<Order> = std::iter::from_fn(|| orders.next_if(|&(p, _)| p == price))
+                .map(|(_, order)| order)
+                .collect();
+            let mut queue = SimulatedQueue { orders: &level, removed: 0 };
+            match_level(&mut queue, &mut remaining_qty, price, taker, &mut |fill| fills.push(fill));
+        }
+
+        fills
+    }
+
+    /// Total opposite-side quantity an order on `side` could consume, capped
+    /// at `max` so the walk stops as soon as enough is found
+    /// With a `limit`, only levels at or better than that price count
+    fn available_liquidity(&self, side: Side, max: Quantity, limit: Option<Price>) -> Quantity;
+
+    /// Price a market order on `side` for `quantity` without touching the book
+    /// Walks the opposite side in the same order execute_market_order would
+    fn sweep_cost(&self, side: Side, quantity: Quantity) -> Option<SweepQuote>;
+
+    /// Fill-or-kill market order: executes only if the full quantity can be filled
+    /// Rejects without touching the book otherwise
+    fn execute_market_order_or_reject(
+        &mut self,
+        side: Side,
+        quantity: Quantity,
+        taker_id: OrderId,
+    ) -> Result<MarketOrderResult, String> {
+        let available = self.available_liquidity(side, quantity, None);
+        if available < quantity {
+            return Err(format!(
+                "Insufficient liquidity: {} available, {} requested",
+                available.value(),
+                quantity.value()
+            ));
+        }
+
+        Ok(self.execute_market_order(side, quantity, taker_id))
+    }
+
+    /// Market order with a slippage bound: takes liquidity on the opposite
+    /// side only at `limit` or better, then stops; whatever is left is
+    /// reported in `remaining_qty` and never rests (an IOC limit order)
+    /// Returns error, without trading, if `limit` or `quantity` is invalid
+    fn execute_limit_sweep(
+        &mut self,
+        side: Side,
+        quantity: Quantity,
+        limit: Price,
+        taker_id: OrderId,
+    ) -> Result<MarketOrderResult, String> {
+        let order = Order::from_parts(taker_id, limit, quantity, side).with_time_in_force(TimeInForce::IOC);
+        let result = self.add_order(order)?;
+
+        Ok(MarketOrderResult {
+            fills: result.fills,
+            filled_qty: result.filled_qty,
+            remaining_qty: result.canceled_qty,
+        })
+    }
+
+    /// Market order whose unfilled remainder, once the opposite side runs
+    /// out, rests as a new GTC limit at `rest_price` under an id from `ids`
+    /// Returns the sweep's fills and the resting order's id, None when the
+    /// sweep filled everything. The rest's price, lot and level room are
+    /// checked first, so an invalid `rest_price` is rejected without trading;
+    /// a rejection only the book itself knows about (a windowed book's range)
+    /// comes back after the sweep has traded
+    fn execute_market_then_rest(
+        &mut self,
+        side: Side,
+        quantity: Quantity,
+        taker_id: OrderId,
+        rest_price: Price,
+        ids: &mut IdCounter,
+    ) -> Result<(Vec<Fill>, Option<OrderId>), OrderError> {
+        self.config().validate_order(rest_price, quantity)?;
+        let resting = self.order_count_at_price(rest_price, side) as usize;
+        self.config().check_level_room(side, rest_price, resting)?;
+
+        let result = self.execute_market_order(side, quantity, taker_id);
+        if result.remaining_qty.is_zero() {
+            return Ok((result.fills, None));
+        }
+
+        // The sweep emptied the opposite side, so the remainder cannot cross
+        let order = Order::new(rest_price, result.remaining_qty, side, ids);
+        self.add_order(order)?;
+        Ok((result.fills, Some(order.id())))
+    }
+
+    /// execute_market_order plus its slippage against the arrival mid
+    /// An empty side still executes; it only leaves slippage unmeasured
+    fn execute_market_order_measured(
+        &mut self,
+        side: Side,
+        quantity: Quantity,
+        taker_id: OrderId,
+    ) -> (MarketOrderResult, SlippageReport) {
+        let arrival_mid = match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => Some((bid.value() as f64 + ask.value() as f64) / 2.0),
+            _ => None,
+        };
+        let result = self.execute_market_order(side, quantity, taker_id);
+        let vwap = result.vwap();
+
+        let tick = self.config().tick_size as f64;
+        let slippage_ticks = match (arrival_mid, vwap, side) {
+            (Some(mid), Some(vwap), Side::Bid) => Some((vwap - mid) / tick),
+            (Some(mid), Some(vwap), Side::Ask) => Some((mid - vwap) / tick),
+            _ => None,
+        };
+
+        (result, SlippageReport { arrival_mid, vwap, slippage_ticks })
+    }
+
+    /// Apply one command, reporting what it did
+    fn apply_command(&mut self, command: Command) -> EngineEvent {
+        match command {
+            Command::Add(order) => match self.add_order(order) {
+                Ok(result) => EngineEvent::Added(order.id(), result),
+                Err(reason) => EngineEvent::Rejected(order.id(), reason.to_string()),
+            },
+            Command::Cancel(order_id) => match self.cancel_order(order_id) {
+                Ok(_) => EngineEvent::Canceled(order_id),
+                Err(reason) => EngineEvent::Rejected(order_id, reason.to_string()),
+            },
+            Command::Market { side, qty, taker } => {
+                EngineEvent::Executed(taker, self.execute_market_order(side, qty, taker))
+            }
+            Command::Reduce { order, qty } => {
+                let Some(view) = self.get_order(order) else {
+                    return EngineEvent::Rejected(order, format!("Order {} not found", order));
+                };
+                let remaining = view.remaining_qty.saturating_sub(qty);
+                // A pure quantity reduction keeps the order's queue position
+                let outcome = if remaining.is_zero() {
+                    self.cancel_order(order).map(|_| ()).map_err(String::from)
+                } else {
+                    self.amend_order(order, None, Some(remaining))
+                };
+                match outcome {
+                    Ok(()) => EngineEvent::Reduced(order, remaining),
+                    Err(reason) => EngineEvent::Rejected(order, reason),
+                }
+            }
+        }
+    }
+
+    /// Drain up to `max` commands from `queue`, one event per command in
+    /// queue order; stops early once the queue is empty
+    fn apply_commands(&mut self, queue: &CommandQueue, max: usize) -> Vec<EngineEvent> {
+        let mut events = Vec::new();
+        while events.len() < max {
+            let Some(command) = queue.pop() else {
+                break;
+            };
+            events.push(self.apply_command(command));
+        }
+        events
+    }
+
+    /// Best resting price on `side`: the highest bid or the lowest ask
+    fn best_price(&self, side: Side) -> Option<Price>;
+
+    /// Resting price furthest from the touch on `side`: the lowest bid or
+    /// the highest ask. None if the side is empty
+    fn worst_price(&self, side: Side) -> Option<Price>;
 
     /// Get the best (highest) bid price
-    fn best_bid(&self) -> Option<Price>;
+    fn best_bid(&self) -> Option<Price> {
+        self.best_price(Side::Bid)
+    }
 
     /// Get the best (lowest) ask price
-    fn best_ask(&self) -> Option<Price>;
+    fn best_ask(&self) -> Option<Price> {
+        self.best_price(Side::Ask)
+    }
+
+    /// Price and size of the most recent fill, from a market order or a
+    /// crossing add. None until the first trade and again after clear
+    fn last_trade(&self) -> Option<(Price, Quantity)>;
 
     /// Get total quantity available at a specific price level
-    fn depth_at_price(&self, price: Price, side: Side) -> u32;
+    /// Summed in u64 so a deep level of large orders cannot wrap around
+    /// An invalid price reads as 0; see try_depth_at_price to tell them apart
+    fn depth_at_price(&self, price: Price, side: Side) -> u64;
+
+    /// How many orders rest at a price level: the queue a new order would
+    /// join, where depth_at_price is the volume ahead of it
+    /// An invalid price reads as 0, as in depth_at_price
+    fn order_count_at_price(&self, price: Price, side: Side) -> u32;
+
+    /// depth_at_price that rejects a price no order could rest at (off the
+    /// tick grid or out of bounds) instead of reporting it as empty
+    fn try_depth_at_price(&self, price: Price, side: Side) -> Result<u64, String> {
+        self.config().check_price(price)?;
+        Ok(self.depth_at_price(price, side))
+    }
+
+    /// Total quantity resting on `side` at prices between `from` and `to`
+    /// inclusive; reversed or out-of-range bounds are accepted (see
+    /// BookConfig::slot_range)
+    fn depth_in_range(&self, side: Side, from: Price, to: Price) -> u64;
+
+    /// Populated levels, resting orders and resting volume per side
+    /// Served from running counters rather than a walk of the book
+    fn stats(&self) -> BookStats;
+
+    /// Estimated bytes held, split into up-front and per-order allocations
+    /// Walks every level, so it is for reporting, not the hot path
+    fn memory_footprint(&self) -> MemoryFootprint;
+
+    /// Prices of up to `n` populated levels on `side`, best first
+    /// Skips summing quantities, unlike book_snapshot
+    fn best_n_prices(&self, side: Side, n: usize) -> Vec<Price>;
+
+    /// Up to `n` bid prices, highest first
+    fn best_n_bids(&self, n: usize) -> Vec<Price> {
+        self.best_n_prices(Side::Bid, n)
+    }
+
+    /// Up to `n` ask prices, lowest first
+    fn best_n_asks(&self, n: usize) -> Vec<Price> {
+        self.best_n_prices(Side::Ask, n)
+    }
+
+    /// Top `depth` populated levels per side with their aggregated quantity
+    /// A side with fewer populated levels returns fewer entries (never padded)
+    fn book_snapshot(&self, depth: usize) -> BookSnapshot;
+
+    /// Check internal consistency: every index entry resolves to an order on
+    /// its recorded level and vice versa, no empty levels linger and the book
+    /// is not crossed. Walks the whole book; meant for debugging and fuzzing
+    /// Problems are listed in the same order on every run with the same book
+    fn validate(&self) -> Result<(), Vec<String>>;
+
+    /// Every resting order on `side` (L3), best price first and FIFO within a level
+    /// Orders are yielded by value: SoA keeps no `Order` structs to borrow
+    fn iter_orders(&self, side: Side) -> impl Iterator<Item = (Price, Order)>;
+
+    /// Capture every resting order and the config as a BookState
+    /// `ids` is the counter this book's ids come from: ids of orders that
+    /// already filled or canceled may be higher than any still resting
+    fn to_snapshot(&self, ids: &IdCounter) -> BookState {
+        let orders: Vec<Order> = self
+            .iter_orders(Side::Bid)
+            .chain(self.iter_orders(Side::Ask))
+            .map(|(_, order)| order)
+            .collect();
+
+        BookState { orders, next_id: ids.peek(), config: *self.config() }
+    }
+
+    /// Rebuild a book (of any implementation) from a BookState, on its config
+    /// Orders are re-added in priority order, so queues are kept but seqs
+    /// are renumbered from 1
+    /// Rejects states whose orders are invalid for this book or would trade
+    fn from_snapshot(state: &BookState) -> Result<Self, String>
+    where
+        Self: Sized,
+    {
+        let mut book = Self::with_config(state.config)?;
+        for order in &state.orders {
+            let result = book.add_order(*order)?;
+            if !result.fills.is_empty() {
+                return Err(format!(
+                    "Snapshot is crossed: order {} traded on restore",
+                    order.id()
+                ));
+            }
+        }
 
-    /// Get the mid price (average of best bid and best ask)
+        Ok(book)
+    }
+
+    /// Best bid at or above best ask
+    /// Matching keeps every book uncrossed, so true means a bug; see check_invariants
+    fn is_crossed(&self) -> bool {
+        matches!((self.best_bid(), self.best_ask()), (Some(bid), Some(ask)) if bid >= ask)
+    }
+
+    /// Best ask minus best bid in raw price units, so a one-tick spread is
+    /// tick_size; 0 when locked
+    /// None when either side is empty or the book is crossed
+    fn spread(&self) -> Option<u32> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => bid.spread_to(ask),
+            _ => None,
+        }
+    }
+
+    /// Average of best bid and best ask, rounded down onto the tick grid
+    /// A spread of an odd number of ticks gives the tick just below the true
+    /// mid; see mid_price_half_ticks for the exact value
     fn mid_price(&self) -> Option<Price> {
+        let mid = (self.mid_price_half_ticks()? / 2) as u32;
+        Some(Price::define(self.config().floor_to_tick(mid)))
+    }
+
+    /// Twice the exact mid price (best bid + best ask), in raw units
+    fn mid_price_half_ticks(&self) -> Option<u64> {
         match (self.best_bid(), self.best_ask()) {
-            (Some(bid), Some(ask)) => Some(Price::define((bid.value() + ask.value()) / 2)),
+            (Some(bid), Some(ask)) => Some(bid.midpoint_half_ticks(ask)),
             _ => None,
         }
     }
+
+    /// Order-flow imbalance over the top `depth` levels per side, in [-1, 1]
+    /// (bid_vol - ask_vol) / (bid_vol + ask_vol): +1 with no asks, -1 with no
+    /// bids, None when both sides are empty
+    fn imbalance(&self, depth: usize) -> Option<f64> {
+        let snapshot = self.book_snapshot(depth);
+        let bid_vol: u64 = snapshot.bids.iter().map(|&(_, qty)| qty).sum();
+        let ask_vol: u64 = snapshot.asks.iter().map(|&(_, qty)| qty).sum();
+
+        let total = bid_vol + ask_vol;
+        if total == 0 {
+            return None;
+        }
+        Some((bid_vol as f64 - ask_vol as f64) / total as f64)
+    }
+
+    /// Book shape: the top `max_levels` populated levels on `side`, best
+    /// first, each with the volume resting from the best price out to it
+    /// The last entry is the side's total volume within `max_levels`
+    fn cumulative_depth(&self, side: Side, max_levels: usize) -> Vec<(Price, u64)> {
+        let snapshot = self.book_snapshot(max_levels);
+        let levels = match side {
+            Side::Bid => snapshot.bids,
+            Side::Ask => snapshot.asks,
+        };
+
+        let mut running = 0;
+        levels
+            .into_iter()
+            .map(|(price, qty)| {
+                running += qty;
+                (price, running)
+            })
+            .collect()
+    }
+
+    /// Top `depth` levels as a price ladder for debugging: asks above the
+    /// spread and bids below, both descending, with bid quantities on the
+    /// left and ask quantities on the right
+    fn format_ladder(&self, depth: usize) -> String {
+        ladder(self, depth, |price| price.value().to_string())
+    }
+
+    /// format_ladder with prices (and the spread) in currency under `scale`,
+    /// e.g. "$50.00" for 5000 in cents
+    fn format_ladder_decimal(&self, depth: usize, scale: PriceScale) -> String {
+        ladder(self, depth, |price| format!("${}", price.format_decimal(scale)))
+    }
 }
 
 #[allow(non_snake_case)]
 pub mod SoA;
+pub mod arena;
+pub mod auto;
+pub mod command;
+pub mod error;
+pub mod fees;
 pub mod fixed_tick;
 pub mod hybrid;
+pub mod journal;
+pub mod level;
+pub mod market_data;
+pub mod ohlc;
+pub mod shared;
 pub mod tree;
+pub mod windowed;
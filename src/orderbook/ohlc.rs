@@ -0,0 +1,161 @@
+use crate::orderbook::Fill;
+use crate::perf::rdtsc;
+use crate::types::price::Price;
+
+/// When an OhlcAggregator closes a candle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandleBucket {
+    /// After this many fills
+    Trades(usize),
+    /// On the first fill at least this many cycles after the candle opened
+    Cycles(u64),
+}
+
+/// Open/high/low/close and volume over one bucket of fills
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candle {
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    /// Total filled quantity
+    pub volume: u64,
+    pub trades: usize,
+    /// Cycle count of the first fill
+    pub opened_at: u64,
+}
+
+impl Candle {
+    fn open(fill: &Fill, now: u64) -> Self {
+        Self {
+            open: fill.price,
+            high: fill.price,
+            low: fill.price,
+            close: fill.price,
+            volume: fill.quantity.as_u64(),
+            trades: 1,
+            opened_at: now,
+        }
+    }
+
+    fn extend(&mut self, fill: &Fill) {
+        self.high = self.high.max(fill.price);
+        self.low = self.low.min(fill.price);
+        self.close = fill.price;
+        self.volume += fill.quantity.as_u64();
+        self.trades += 1;
+    }
+}
+
+/// Builds candles from a stream of fills, from any book
+/// Feed it the fills a book returns (in order); each push hands back the
+/// candle it completed, if any
+#[derive(Debug)]
+pub struct OhlcAggregator {
+    bucket: CandleBucket,
+    current: Option<Candle>,
+}
+
+impl OhlcAggregator {
+    pub fn new(bucket: CandleBucket) -> Self {
+        Self { bucket, current: None }
+    }
+
+    /// Add `fill`, timestamped now
+    pub fn push(&mut self, fill: &Fill) -> Option<Candle> {
+        self.push_at(fill, rdtsc())
+    }
+
+    /// Add `fill` as seen at cycle count `now`
+    pub fn push_at(&mut self, fill: &Fill, now: u64) -> Option<Candle> {
+        match self.bucket {
+            CandleBucket::Trades(trades) => {
+                match &mut self.current {
+                    Some(candle) => candle.extend(fill),
+                    None => self.current = Some(Candle::open(fill, now)),
+                }
+                if self.current.is_some_and(|candle| candle.trades >= trades) {
+                    self.current.take()
+                } else {
+                    None
+                }
+            }
+            CandleBucket::Cycles(cycles) => match &mut self.current {
+                Some(candle) if now.saturating_sub(candle.opened_at) < cycles => {
+                    candle.extend(fill);
+                    None
+                }
+                // The fill belongs to the next bucket: close this one first
+                _ => self.current.replace(Candle::open(fill, now)),
+            },
+        }
+    }
+
+    /// Add every fill in `fills`, returning the candles they completed
+    pub fn extend<'a>(&mut self, fills: impl IntoIterator<Item = &'a Fill>) -> Vec<Candle> {
+        fills.into_iter().filter_map(|fill| self.push(fill)).collect()
+    }
+
+    /// The candle still being built
+    pub fn current(&self) -> Option<&Candle> {
+        self.current.as_ref()
+    }
+
+    /// Close the open candle early, e.g. at the end of a run
+    pub fn flush(&mut self) -> Option<Candle> {
+        self.current.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::order::Side;
+    use crate::types::quantity::{QtyValue, Quantity};
+
+    fn fill(price: u32, qty: QtyValue) -> Fill {
+        Fill {
+            price: Price::define(price),
+            quantity: Quantity::define(qty),
+            maker_order_id: 0,
+            taker_order_id: 1,
+            aggressor_side: Side::Bid,
+            seq: 0,
+        }
+    }
+
+    #[test]
+    fn test_trade_buckets() {
+        let mut ohlc = OhlcAggregator::new(CandleBucket::Trades(4));
+        let fills = [fill(5001, 10), fill(5004, 5), fill(4998, 20), fill(5002, 15), fill(5003, 1)];
+
+        let candles = ohlc.extend(&fills);
+        assert_eq!(candles.len(), 1);
+        let candle = candles[0];
+        assert_eq!(candle.open, Price::define(5001));
+        assert_eq!(candle.high, Price::define(5004));
+        assert_eq!(candle.low, Price::define(4998));
+        assert_eq!(candle.close, Price::define(5002));
+        assert_eq!((candle.volume, candle.trades), (50, 4));
+
+        // The fifth fill opened the next candle
+        assert_eq!(ohlc.current().map(|c| c.open), Some(Price::define(5003)));
+        assert_eq!(ohlc.flush().map(|c| c.trades), Some(1));
+        assert!(ohlc.current().is_none());
+    }
+
+    #[test]
+    fn test_cycle_buckets() {
+        let mut ohlc = OhlcAggregator::new(CandleBucket::Cycles(100));
+
+        assert_eq!(ohlc.push_at(&fill(5000, 10), 1_000), None);
+        assert_eq!(ohlc.push_at(&fill(5005, 10), 1_050), None);
+        assert_eq!(ohlc.push_at(&fill(4995, 10), 1_099), None);
+
+        // 100 cycles after the open: closes the first candle
+        let candle = ohlc.push_at(&fill(5010, 10), 1_100).unwrap();
+        assert_eq!((candle.high, candle.low, candle.close), (Price::define(5005), Price::define(4995), Price::define(4995)));
+        assert_eq!(candle.trades, 3);
+        assert_eq!(ohlc.current().map(|c| c.opened_at), Some(1_100));
+    }
+}
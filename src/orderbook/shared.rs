@@ -0,0 +1,102 @@
+use crate::orderbook::error::OrderError;
+use crate::orderbook::{BookSnapshot, LimitOrderResult, MarketOrderResult, OrderView, OrderbookTrait};
+use crate::types::order::{Order, OrderId, Side};
+use crate::types::price::Price;
+use crate::types::quantity::Quantity;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Any orderbook behind one reader-writer lock, shareable across threads
+///
+/// Cloning hands out another handle to the same book. Mutations take the
+/// write lock and queries the read lock, so readers run concurrently with each
+/// other but never with a writer. This is coarse-grained locking of the whole
+/// book, not a lock-free structure: every writer serializes on it.
+pub struct SharedOrderbook<O: OrderbookTrait> {
+    book: Arc<RwLock<O>>,
+}
+
+impl<O: OrderbookTrait> Clone for SharedOrderbook<O> {
+    fn clone(&self) -> Self {
+        Self {
+            book: Arc::clone(&self.book),
+        }
+    }
+}
+
+impl<O: OrderbookTrait> SharedOrderbook<O> {
+    pub fn new(book: O) -> Self {
+        Self {
+            book: Arc::new(RwLock::new(book)),
+        }
+    }
+
+    // A poisoned lock means a writer panicked mid-update and the book may be
+    // inconsistent, so the panic is propagated rather than recovered
+    fn read(&self) -> RwLockReadGuard<'_, O> {
+        self.book.read().expect("orderbook lock poisoned")
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, O> {
+        self.book.write().expect("orderbook lock poisoned")
+    }
+
+    pub fn add_order(&self, order: Order) -> Result<LimitOrderResult, OrderError> {
+        self.write().add_order(order)
+    }
+
+    pub fn cancel_order(&self, order_id: OrderId) -> Result<Order, OrderError> {
+        self.write().cancel_order(order_id)
+    }
+
+    pub fn amend_order(
+        &self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_qty: Option<Quantity>,
+    ) -> Result<(), String> {
+        self.write().amend_order(order_id, new_price, new_qty)
+    }
+
+    pub fn execute_market_order(
+        &self,
+        side: Side,
+        quantity: Quantity,
+        taker_id: OrderId,
+    ) -> MarketOrderResult {
+        self.write().execute_market_order(side, quantity, taker_id)
+    }
+
+    pub fn best_bid(&self) -> Option<Price> {
+        self.read().best_bid()
+    }
+
+    pub fn best_ask(&self) -> Option<Price> {
+        self.read().best_ask()
+    }
+
+    pub fn contains_order(&self, order_id: OrderId) -> bool {
+        self.read().contains_order(order_id)
+    }
+
+    pub fn get_order(&self, order_id: OrderId) -> Option<OrderView> {
+        self.read().get_order(order_id)
+    }
+
+    pub fn depth_at_price(&self, price: Price, side: Side) -> u64 {
+        self.read().depth_at_price(price, side)
+    }
+
+    pub fn book_snapshot(&self, depth: usize) -> BookSnapshot {
+        self.read().book_snapshot(depth)
+    }
+
+    /// Run several queries against one consistent view of the book
+    pub fn with_read<R>(&self, f: impl FnOnce(&O) -> R) -> R {
+        f(&self.read())
+    }
+
+    /// Run several mutations with no reader seeing the book in between
+    pub fn with_write<R>(&self, f: impl FnOnce(&mut O) -> R) -> R {
+        f(&mut self.write())
+    }
+}
@@ -1,148 +1,546 @@
-use crate::orderbook::{Fill, OrderbookTrait};
-use crate::types::order::{Order, OrderId, Side};
+use crate::orderbook::level::PriceLevel;
+use crate::orderbook::error::OrderError;
+use crate::orderbook::{
+    btree_map_bytes, check_invariants, deque_bytes, fillable_quantity, hash_map_bytes, match_level,
+    unindex_side, ArrivalSequence, BookConfig, BookSnapshot, BookStats, ExpiryQueue, Fill, FillSequence, LevelQueue,
+    LimitOrderResult, MarketOrderResult, MemoryFootprint, OrderView, OrderbookTrait,
+    SideTotals, StpMode, SweepQuote, Taker,
+};
+use crate::types::order::{AccountId, Order, OrderId, Side, TimeInForce};
 use crate::types::price::Price;
-use crate::types::quantity::Quantity;
-use std::collections::{BTreeMap, HashMap};
+use crate::types::quantity::{QtyValue, Quantity};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 
-/// Max price is represented in cents - $100 is max price
-const MAX_PRICE: u32 = 10000;
-const TICK_SIZE: u32 = 1;
-const LOT_SIZE: u32 = 1;
 pub struct Orderbook {
     bids: BTreeMap<u32, Level>,
     asks: BTreeMap<u32, Level>,
     order_index: HashMap<OrderId, (Side, Price)>,
+    bid_totals: SideTotals,
+    ask_totals: SideTotals,
+    // Queue capacity for levels created from here on (see reserve)
+    level_capacity: usize,
+    config: BookConfig,
+    fill_seq: FillSequence,
+    arrival_seq: ArrivalSequence,
+    expiries: ExpiryQueue,
 }
 #[derive(Default, Clone)]
 pub struct Level {
-    pub orders: Vec<Order>,
+    orders: VecDeque<Order>,
+    // Sum of the queue's quantities, kept in step so depth reads are O(1)
+    total_qty: u64,
 }
 
 impl OrderbookTrait for Orderbook {
-    fn new() -> Self {
-        Self {
+    fn with_config(config: BookConfig) -> Result<Self, String> {
+        config.validate()?;
+
+        // Levels are keyed by price, so the grid only affects validation
+        Ok(Self {
             bids: BTreeMap::new(),
             asks: BTreeMap::new(),
             order_index: HashMap::new(),
+            bid_totals: SideTotals::default(),
+            ask_totals: SideTotals::default(),
+            level_capacity: 0,
+            config,
+            fill_seq: FillSequence::default(),
+            arrival_seq: ArrivalSequence::default(),
+            expiries: ExpiryQueue::default(),
+        })
+    }
+
+    fn config(&self) -> &BookConfig {
+        &self.config
+    }
+
+    fn reserve(&mut self, orders_per_level: usize) {
+        self.level_capacity = orders_per_level;
+        for level in self.bids.values_mut().chain(self.asks.values_mut()) {
+            level.reserve(orders_per_level);
         }
     }
 
-    fn add_order(&mut self, order: Order) -> Result<(), String> {
+    fn reserve_orders(&mut self, additional: usize) {
+        self.order_index.reserve(additional);
+    }
+
+    fn add_order(&mut self, mut order: Order) -> Result<LimitOrderResult, OrderError> {
         let side = order.side();
         let price_value = order.price().value();
-        let quantity_value = order.quantity().value();
 
-        // Validation 1: Price must be multiple of tick size
-        if price_value % TICK_SIZE != 0 {
-            return Err(format!(
-                "Price {} is not a valid tick (tick_size={})",
-                price_value, TICK_SIZE
-            ));
-        }
-
-        // Validation 2: Price must be in bounds
-        if price_value == 0 || price_value >= MAX_PRICE {
-            return Err(format!(
-                "Price {} out of bounds [1, {})",
-                price_value, MAX_PRICE
-            ));
+        self.config.validate_order(order.price(), order.quantity())?;
+        let resting = self.level(side, price_value).map_or(0, |level| level.orders.len());
+        self.config.check_level_room(side, order.price(), resting)?;
+        self.config.check_crossing(&order, || self.crosses(side, order.price()))?;
+
+        // Fill-or-kill: reject before anything trades unless the whole
+        // quantity is available at or better than the limit price
+        if order.time_in_force() == TimeInForce::FOK {
+            let available = fillable_quantity(self, &order);
+            if available < order.quantity() {
+                return Err(OrderError::FokUnfillable {
+                    order: order.id(),
+                    available: available.value(),
+                    requested: order.quantity().value(),
+                });
+            }
         }
 
-        // Validation 3: Quantity must be multiple of lot size
-        if quantity_value % LOT_SIZE != 0 {
-            return Err(format!(
-                "Quantity {} is not a valid lot (lot_size={})",
-                quantity_value, LOT_SIZE
-            ));
+        // Marketable limit order: take liquidity up to the limit price first
+        let mut remaining_qty = order.quantity();
+        let mut fills = Vec::new();
+        if self.crosses(side, order.price()) {
+            let taker = Taker::limit_order(&order, &self.config);
+            self.match_incoming(&mut remaining_qty, taker, &mut |fill| fills.push(fill.clone()));
         }
 
-        // Validation 4: Quantity must be positive
-        if quantity_value == 0 {
-            return Err("Quantity cannot be zero".to_string());
+        let filled_qty = order.quantity().saturating_sub(remaining_qty);
+
+        // Nothing left, IOC/FOK, or a remainder that would rest crossed against
+        // orders STP skipped: the unfilled remainder is canceled, not rested
+        if remaining_qty.is_zero()
+            || order.time_in_force() != TimeInForce::GTC
+            || (self.config.stp == StpMode::Skip && self.crosses(side, order.price()))
+        {
+            return Ok(LimitOrderResult {
+                fills,
+                filled_qty,
+                rested_qty: Quantity::define(0),
+                canceled_qty: remaining_qty,
+            });
         }
+        order.set_quantity(remaining_qty);
+        self.arrival_seq.stamp(&mut order);
+        self.expiries.track(&order);
 
-        // Add order to appropriate side
+        // Rest the remainder on the appropriate side
         // Use entry API to insert or modify in place
+        self.totals_mut(side).rested(remaining_qty);
+        let capacity = self.level_capacity;
         match side {
             Side::Bid => {
                 self.bids
                     .entry(price_value)
-                    .or_insert_with(Level::default)
-                    .orders
-                    .push(order);
+                    .or_insert_with(|| Level::with_capacity(capacity))
+                    .add(order);
             }
             Side::Ask => {
                 self.asks
                     .entry(price_value)
-                    .or_insert_with(Level::default)
-                    .orders
-                    .push(order);
+                    .or_insert_with(|| Level::with_capacity(capacity))
+                    .add(order);
             }
         }
 
         // Track order in index for O(1) lookup during cancellation
         self.order_index.insert(order.id(), (side, order.price()));
 
-        Ok(())
+        Ok(LimitOrderResult {
+            fills,
+            filled_qty,
+            rested_qty: remaining_qty,
+            canceled_qty: Quantity::define(0),
+        })
     }
 
-    fn cancel_order(&mut self, order_id: OrderId) -> Result<(), String> {
+    fn cancel_order(&mut self, order_id: OrderId) -> Result<Order, OrderError> {
         // O(1) lookup in HashMap to find price level
         let (side, price) = self
             .order_index
             .remove(&order_id)
-            .ok_or_else(|| format!("Order {} not found", order_id))?;
+            .ok_or(OrderError::NotFound(order_id))?;
 
         let price_value = price.value();
 
         // O(log n) lookup in BTreeMap to get the level
-        let tree = match side {
-            Side::Bid => &mut self.bids,
-            Side::Ask => &mut self.asks,
+        let (tree, totals) = match side {
+            Side::Bid => (&mut self.bids, &mut self.bid_totals),
+            Side::Ask => (&mut self.asks, &mut self.ask_totals),
         };
 
         if let Some(level) = tree.get_mut(&price_value) {
             // O(n) search within the level to find and remove the order
-            if let Some(pos) = level.orders.iter().position(|o| o.id() == order_id) {
-                level.orders.remove(pos);
+            if let Some(order) = level.cancel(order_id) {
+                totals.removed(order.quantity());
 
                 // Clean up empty price levels to keep tree sparse
                 if level.orders.is_empty() {
                     tree.remove(&price_value);
                 }
 
-                return Ok(());
+                return Ok(order);
             }
         }
 
         // Order was in index but not in tree (data inconsistency)
-        Err(format!(
-            "Order {} found in index but not in tree (data inconsistency)",
-            order_id
-        ))
+        Err(OrderError::IndexDesync(order_id))
     }
 
-    fn execute_market_order(
+    fn cancel_all_for_side(&mut self, side: Side) -> usize {
+        let levels = match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+        let removed = levels.values().map(|level| level.orders.len()).sum();
+        levels.clear();
+        unindex_side(&mut self.order_index, side, removed);
+        *self.totals_mut(side) = SideTotals::default();
+
+        removed
+    }
+
+    fn take_due_expiries(&mut self, now: u64) -> Vec<OrderId> {
+        self.expiries.take_due(now)
+    }
+
+    fn clear(&mut self) {
+        self.cancel_all();
+        self.fill_seq = FillSequence::default();
+        self.arrival_seq = ArrivalSequence::default();
+        self.expiries.clear();
+    }
+
+    fn contains_order(&self, order_id: OrderId) -> bool {
+        self.order_index.contains_key(&order_id)
+    }
+
+    fn get_order(&self, order_id: OrderId) -> Option<OrderView> {
+        let &(side, price) = self.order_index.get(&order_id)?;
+
+        // O(n) scan of the level for position and current quantity
+        let (queue_position, order) = self
+            .level(side, price.value())?
+            .orders
+            .iter()
+            .enumerate()
+            .find(|(_, o)| o.id() == order_id)?;
+
+        Some(OrderView {
+            price,
+            side,
+            remaining_qty: order.quantity(),
+            queue_position,
+        })
+    }
+
+    fn amend_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_qty: Option<Quantity>,
+    ) -> Result<(), String> {
+        let (side, price) = *self
+            .order_index
+            .get(&order_id)
+            .ok_or_else(|| format!("Order {} not found", order_id))?;
+
+        let (tree, totals) = match side {
+            Side::Bid => (&mut self.bids, &mut self.bid_totals),
+            Side::Ask => (&mut self.asks, &mut self.ask_totals),
+        };
+        let missing = || {
+            format!(
+                "Order {} found in index but not in tree (data inconsistency)",
+                order_id
+            )
+        };
+        let level = tree.get_mut(&price.value()).ok_or_else(missing)?;
+        let pos = level
+            .orders
+            .iter()
+            .position(|o| o.id() == order_id)
+            .ok_or_else(missing)?;
+        let order = level.orders[pos];
+
+        let target_price = new_price.unwrap_or(price);
+        let target_qty = new_qty.unwrap_or(order.quantity());
+
+        // Pure size reduction: amend in place and keep queue position
+        if target_price == price && target_qty <= order.quantity() {
+            self.config.validate_order(price, target_qty)?;
+            let reduction = order.quantity().saturating_sub(target_qty);
+            totals.reduced(reduction);
+            level.reduce(pos, reduction);
+            return Ok(());
+        }
+
+        // Anything else loses time priority; validate up front so a rejected
+        // amend leaves the resting order untouched
+        let mut replacement = order;
+        replacement.set_price(target_price);
+        replacement.set_quantity(target_qty);
+
+        self.config.validate_order(target_price, target_qty)?;
+        if target_price != price {
+            let resting = self.level(side, target_price.value()).map_or(0, |level| level.orders.len());
+            self.config.check_level_room(side, target_price, resting)?;
+        }
+        if self.crosses(side, target_price) {
+            return Err(format!(
+                "Amended price {} would cross the book",
+                target_price.value()
+            ));
+        }
+
+        self.cancel_order(order_id)?;
+        self.add_order(replacement)?;
+        Ok(())
+    }
+
+    fn execute_market_order_with(
         &mut self,
         side: Side,
         mut quantity: Quantity,
-    ) -> Result<Vec<Fill>, String> {
-        let mut fills = Vec::new();
-        let mut empty_levels = Vec::new();
+        taker_id: OrderId,
+        mut on_fill: impl FnMut(&Fill),
+    ) -> MarketOrderResult {
+        let requested = quantity;
+        let taker = Taker::market(side, taker_id, &self.config);
+        self.match_incoming(&mut quantity, taker, &mut on_fill);
+
+        MarketOrderResult {
+            fills: Vec::new(),
+            filled_qty: requested.saturating_sub(quantity),
+            remaining_qty: quantity,
+        }
+    }
+
+    fn available_liquidity(&self, side: Side, max: Quantity, limit: Option<Price>) -> Quantity {
+        let cap = max.as_u64();
+        let mut total: u64 = 0;
+
+        // Same traversal order as execute_market_order, without mutating
+        let levels = self.levels(side.opposite());
+
+        for (price_value, level) in levels {
+            let beyond_limit = limit.is_some_and(|l| match side {
+                Side::Bid => price_value > l.value(),
+                Side::Ask => price_value < l.value(),
+            });
+            if total >= cap || beyond_limit {
+                break;
+            }
+            total += level.total_quantity();
+        }
+
+        Quantity::define(total.min(cap) as QtyValue)
+    }
+
+    fn sweep_cost(&self, side: Side, quantity: Quantity) -> Option<SweepQuote> {
+        // Same traversal order as execute_market_order, without mutating
+        let levels = self.levels(side.opposite());
+
+        let level_quantities = levels.map(|(price_value, level)| (price_value, level.total_quantity()));
+        SweepQuote::walk(quantity, level_quantities)
+    }
+
+    fn best_price(&self, side: Side) -> Option<Price> {
+        // BTreeMap's first/last_key_value() return the end keys in O(log n)
+        let best = match side {
+            Side::Bid => self.bids.last_key_value(),
+            Side::Ask => self.asks.first_key_value(),
+        };
+        best.map(|(&price_value, _)| Price::define(price_value))
+    }
+
+    fn worst_price(&self, side: Side) -> Option<Price> {
+        let worst = match side {
+            Side::Bid => self.bids.first_key_value(),
+            Side::Ask => self.asks.last_key_value(),
+        };
+        worst.map(|(&price_value, _)| Price::define(price_value))
+    }
+
+    fn last_trade(&self) -> Option<(Price, Quantity)> {
+        self.fill_seq.last_trade()
+    }
+
+    fn depth_at_price(&self, price: Price, side: Side) -> u64 {
+        let price_value = price.value();
+
+        // Check bounds and tick alignment
+        if !self.config.is_valid_price(price_value) {
+            return 0;
+        }
+
+        // O(log n) lookup in BTreeMap
+        let tree = match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        };
+
+        tree.get(&price_value)
+            .map(|level| level.total_quantity())
+            .unwrap_or(0)
+    }
+
+    fn order_count_at_price(&self, price: Price, side: Side) -> u32 {
+        if !self.config.is_valid_price(price.value()) {
+            return 0;
+        }
+        self.level(side, price.value()).map_or(0, |level| level.orders.len() as u32)
+    }
+
+    fn depth_in_range(&self, side: Side, from: Price, to: Price) -> u64 {
+        let Some(slots) = self.config.slot_range(from, to) else {
+            return 0;
+        };
+        let prices = self.config.price_at(*slots.start())..=self.config.price_at(*slots.end());
+
+        let tree = match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        };
+        tree.range(prices).map(|(_, level)| level.total_quantity()).sum()
+    }
+
+    fn stats(&self) -> BookStats {
+        BookStats::from_totals(self.bids.len(), self.asks.len(), self.bid_totals, self.ask_totals)
+    }
+
+    fn memory_footprint(&self) -> MemoryFootprint {
+        let levels = self.bids.values().chain(self.asks.values());
+        let queues: usize = levels.map(|level| deque_bytes(&level.orders)).sum();
+        MemoryFootprint {
+            fixed_bytes: size_of::<Self>(),
+            heap_bytes: btree_map_bytes(&self.bids)
+                + btree_map_bytes(&self.asks)
+                + queues
+                + hash_map_bytes(&self.order_index),
+            orders: self.bid_totals.orders + self.ask_totals.orders,
+        }
+    }
+
+    fn best_n_prices(&self, side: Side, n: usize) -> Vec<Price> {
+        let prices: Box<dyn Iterator<Item = &u32>> = match side {
+            Side::Bid => Box::new(self.bids.keys().rev()),
+            Side::Ask => Box::new(self.asks.keys()),
+        };
+        prices.take(n).map(|&price_value| Price::define(price_value)).collect()
+    }
 
+    fn book_snapshot(&self, depth: usize) -> BookSnapshot {
+        let top = |side| {
+            self.levels(side)
+                .take(depth)
+                .map(|(price_value, level)| (Price::define(price_value), level.total_quantity()))
+                .collect()
+        };
+
+        BookSnapshot {
+            bids: top(Side::Bid),
+            asks: top(Side::Ask),
+        }
+    }
+
+    fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = check_invariants(self, &self.order_index);
+        for side in Side::all() {
+            for (price_value, level) in self.levels(side) {
+                let counted = level.counted_quantity();
+                if level.total_qty != counted {
+                    problems.push(format!(
+                        "{:?} level {} caches a total of {} but holds {}",
+                        side, price_value, level.total_qty, counted
+                    ));
+                }
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    fn iter_orders(&self, side: Side) -> impl Iterator<Item = (Price, Order)> {
+        self.levels(side).flat_map(|(price_value, level)| {
+            level
+                .orders
+                .iter()
+                .map(move |&order| (Price::define(price_value), order))
+        })
+    }
+}
+
+impl Orderbook {
+    /// Release memory after a burst: drop any empty level and shrink every
+    /// queue back to what it holds, or to the reserved size if larger
+    pub fn compact(&mut self) {
+        let capacity = self.level_capacity;
+        for tree in [&mut self.bids, &mut self.asks] {
+            tree.retain(|_, level| !level.orders.is_empty());
+            for level in tree.values_mut() {
+                level.shrink_to(capacity);
+            }
+        }
+    }
+
+    fn totals_mut(&mut self, side: Side) -> &mut SideTotals {
+        match side {
+            Side::Bid => &mut self.bid_totals,
+            Side::Ask => &mut self.ask_totals,
+        }
+    }
+
+    /// Level at `price_value` on `side`, if populated
+    fn level(&self, side: Side, price_value: u32) -> Option<&Level> {
+        match side {
+            Side::Bid => self.bids.get(&price_value),
+            Side::Ask => self.asks.get(&price_value),
+        }
+    }
+
+    /// Populated levels on `side`, best price first
+    fn levels(&self, side: Side) -> Box<dyn Iterator<Item = (u32, &Level)> + '_> {
+        // Empty levels are removed eagerly, so the tree holds only populated ones
+        match side {
+            Side::Bid => Box::new(self.bids.iter().rev().map(|(&p, level)| (p, level))),
+            Side::Ask => Box::new(self.asks.iter().map(|(&p, level)| (p, level))),
+        }
+    }
+
+    /// Does a limit order at `price` on `side` reach the opposite best price?
+    fn crosses(&self, side: Side, price: Price) -> bool {
         match side {
-            // Market BUY: consume asks (lowest price first)
+            Side::Bid => self.best_ask().is_some_and(|ask| ask.value() <= price.value()),
+            Side::Ask => self.best_bid().is_some_and(|bid| bid.value() >= price.value()),
+        }
+    }
+
+    /// Walk the opposite side for an incoming order on `side`, best price first
+    /// Stops once `quantity` is zero or the next level is beyond `limit`
+    /// Each fill is sequenced, then handed to `on_fill`
+    fn match_incoming(
+        &mut self,
+        quantity: &mut Quantity,
+        taker: Taker,
+        on_fill: &mut impl FnMut(&Fill),
+    ) {
+        let fill_seq = &mut self.fill_seq;
+        let mut on_fill = |mut fill: Fill| {
+            fill_seq.stamp(&mut fill);
+            on_fill(&fill);
+        };
+        // Levels emptied by the walk, in walk (price) order: the cleanup
+        // below never depends on hash order
+        let mut empty_levels = Vec::new();
+
+        match taker.side {
+            // BUY: consume asks (lowest price first)
             Side::Bid => {
                 // BTreeMap iter() returns keys in ascending order (lowest to highest)
                 for (&price_value, level) in self.asks.iter_mut() {
-                    if quantity.value() == 0 {
+                    if quantity.is_zero() {
+                        break;
+                    }
+                    if taker.limit.is_some_and(|l| price_value > l.value()) {
                         break;
                     }
 
                     let price = Price::define(price_value);
-                    let level_fills =
-                        Self::match_level(level, &mut quantity, price, &mut self.order_index);
-                    fills.extend(level_fills);
+                    level.match_orders(quantity, price, taker, &mut self.order_index, &mut self.ask_totals, &mut on_fill);
 
                     // Track empty levels for cleanup
                     if level.orders.is_empty() {
@@ -156,18 +554,19 @@ impl OrderbookTrait for Orderbook {
                 }
             }
 
-            // Market SELL: consume bids (highest price first)
+            // SELL: consume bids (highest price first)
             Side::Ask => {
                 // BTreeMap iter().rev() returns keys in descending order (highest to lowest)
                 for (&price_value, level) in self.bids.iter_mut().rev() {
-                    if quantity.value() == 0 {
+                    if quantity.is_zero() {
+                        break;
+                    }
+                    if taker.limit.is_some_and(|l| price_value < l.value()) {
                         break;
                     }
 
                     let price = Price::define(price_value);
-                    let level_fills =
-                        Self::match_level(level, &mut quantity, price, &mut self.order_index);
-                    fills.extend(level_fills);
+                    level.match_orders(quantity, price, taker, &mut self.order_index, &mut self.bid_totals, &mut on_fill);
 
                     // Track empty levels for cleanup
                     if level.orders.is_empty() {
@@ -181,114 +580,244 @@ impl OrderbookTrait for Orderbook {
                 }
             }
         }
+    }
+}
 
-        if quantity.value() > 0 {
-            return Err(format!(
-                "Market order partially filled: {} remaining",
-                quantity.value()
-            ));
-        }
+impl Level {
+    /// Empty level with room for `orders` orders
+    pub fn with_capacity(orders: usize) -> Self {
+        Self { orders: VecDeque::with_capacity(orders), total_qty: 0 }
+    }
 
-        Ok(fills)
+    /// Grow the queue so it holds at least `orders` without reallocating
+    pub fn reserve(&mut self, orders: usize) {
+        self.orders.reserve(orders.saturating_sub(self.orders.len()));
     }
 
-    fn best_bid(&self) -> Option<Price> {
-        // BTreeMap's last_key_value() returns highest key in O(log n)
-        self.bids
-            .last_key_value()
-            .map(|(&price_value, _)| Price::define(price_value))
+    /// Give back spare queue capacity, keeping room for at least `orders`
+    pub fn shrink_to(&mut self, orders: usize) {
+        self.orders.shrink_to(orders);
     }
 
-    fn best_ask(&self) -> Option<Price> {
-        // BTreeMap's first_key_value() returns lowest key in O(log n)
-        self.asks
-            .first_key_value()
-            .map(|(&price_value, _)| Price::define(price_value))
+    /// Orders queued at this level, front first
+    pub fn orders(&self) -> &VecDeque<Order> {
+        &self.orders
     }
 
-    fn depth_at_price(&self, price: Price, side: Side) -> u32 {
-        let price_value = price.value();
+    fn remove(&mut self, pos: usize) -> Option<Order> {
+        let order = self.orders.remove(pos)?;
+        self.total_qty -= order.quantity().as_u64();
+        Some(order)
+    }
 
-        // Check bounds
-        if price_value == 0 || price_value >= MAX_PRICE {
-            return 0;
-        }
+    /// Take `qty` off the order at `pos` without moving it
+    fn reduce(&mut self, pos: usize, qty: Quantity) {
+        let order = &mut self.orders[pos];
+        order.set_quantity(order.quantity().saturating_sub(qty));
+        self.total_qty -= qty.as_u64();
+    }
 
-        // Check tick alignment
-        if price_value % TICK_SIZE != 0 {
-            return 0;
-        }
+    /// Match an incoming order against this price level (see match_level)
+    /// Removes filled orders from the level and order_index, keeps `totals`
+    /// and the cached total in step and hands each fill to on_fill
+    pub(crate) fn match_orders(
+        &mut self,
+        remaining_qty: &mut Quantity,
+        price: Price,
+        taker: Taker,
+        order_index: &mut HashMap<OrderId, (Side, Price)>,
+        totals: &mut SideTotals,
+        on_fill: &mut impl FnMut(Fill),
+    ) {
+        let mut queue = TreeQueue { level: self, order_index, totals };
+        match_level(&mut queue, remaining_qty, price, taker, on_fill)
+    }
 
-        // O(log n) lookup in BTreeMap
-        let tree = match side {
-            Side::Bid => &self.bids,
-            Side::Ask => &self.asks,
-        };
+    /// Total quantity summed from the queue, ignoring the cache
+    fn counted_quantity(&self) -> u64 {
+        // Accumulate in u64: many large orders can exceed u32::MAX
+        self.orders
+            .iter()
+            .map(|o| o.quantity().as_u64())
+            .sum::<u64>()
+    }
+}
 
-        tree.get(&price_value)
-            .map(|level| level.total_quantity())
-            .unwrap_or(0)
+impl PriceLevel for Level {
+    fn add(&mut self, order: Order) {
+        self.total_qty += order.quantity().as_u64();
+        self.orders.push_back(order);
+    }
+
+    fn cancel(&mut self, order_id: OrderId) -> Option<Order> {
+        let pos = self.orders.iter().position(|o| o.id() == order_id)?;
+        self.remove(pos)
+    }
+
+    /// Total quantity at this price level, in O(1)
+    fn total_quantity(&self) -> u64 {
+        self.total_qty
+    }
+
+    fn order_count(&self) -> usize {
+        self.orders.len()
     }
 }
 
-impl Orderbook {
-    /// Match orders at a single price level (FIFO)
-    /// Modifies remaining_qty as orders are filled
-    /// Removes filled orders from the level and order_index
-    /// Returns vector of fills that occurred
-    fn match_level(
-        level: &mut Level,
-        remaining_qty: &mut Quantity,
-        price: Price,
-        order_index: &mut HashMap<OrderId, (Side, Price)>,
-    ) -> Vec<Fill> {
-        let mut fills = Vec::new();
-        let mut orders_to_remove = Vec::new();
+/// LevelQueue over a tree level, going through Level so its cached total
+/// stays in step with the queue
+struct TreeQueue<'a> {
+    level: &'a mut Level,
+    order_index: &'a mut HashMap<OrderId, (Side, Price)>,
+    totals: &'a mut SideTotals,
+}
 
-        // Process orders in FIFO order (first in Vec = earliest order)
-        for (idx, order) in level.orders.iter().enumerate() {
-            if remaining_qty.value() == 0 {
-                break; // Market order fully filled
-            }
+impl LevelQueue for TreeQueue<'_> {
+    fn maker(&self, i: usize) -> Option<(OrderId, Quantity, AccountId)> {
+        self.level.orders.get(i).map(|o| (o.id(), o.quantity(), o.account()))
+    }
 
-            let order_qty = order.quantity().value();
-            let fill_qty = remaining_qty.value().min(order_qty);
+    fn remove(&mut self, i: usize) {
+        if let Some(order) = self.level.remove(i) {
+            self.totals.removed(order.quantity());
+            self.order_index.remove(&order.id());
+        }
+    }
 
-            // Create fill
-            fills.push(Fill {
-                price,
-                quantity: Quantity::define(fill_qty),
-                maker_order_id: order.id(),
-            });
+    fn reduce(&mut self, i: usize, qty: Quantity) {
+        self.totals.reduced(qty);
+        self.level.reduce(i, qty);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::order::IdCounter;
+
+    #[test]
+    fn test_clear_keeps_index_allocation() {
+        let mut book = Orderbook::new();
+        let mut counter = IdCounter::new();
+        for price in [4990, 4999, 5001, 5005] {
+            let side = if price < 5000 { Side::Bid } else { Side::Ask };
+            book.add_order(Order::new(Price::define(price), Quantity::define(10), side, &mut counter)).unwrap();
+        }
+        let index_capacity = book.order_index.capacity();
 
-            // Update remaining quantity
-            *remaining_qty = Quantity::define(remaining_qty.value() - fill_qty);
+        book.clear();
+        assert!(book.bids.is_empty() && book.asks.is_empty());
+        assert!(book.order_index.is_empty());
+        assert_eq!(book.order_index.capacity(), index_capacity);
+    }
 
-            // If order fully filled, mark for removal
-            if fill_qty == order_qty {
-                orders_to_remove.push(idx);
-            } else {
-                // Partial fill of resting order - not implemented yet
-                panic!("Partial fills of resting orders not yet implemented");
+    #[test]
+    fn test_validate_reports_index_desync() {
+        let mut book = Orderbook::new();
+        let mut counter = IdCounter::new();
+        let bid = Order::new(Price::define(4999), Quantity::define(10), Side::Bid, &mut counter);
+        let ask = Order::new(Price::define(5001), Quantity::define(10), Side::Ask, &mut counter);
+        let (bid_id, ask_id) = (bid.id(), ask.id());
+        book.add_order(bid).unwrap();
+        book.add_order(ask).unwrap();
+        assert_eq!(book.validate(), Ok(()));
+
+        // Drop one entry, point another at the wrong level, leave an empty level
+        book.order_index.remove(&bid_id);
+        book.order_index.insert(ask_id, (Side::Ask, Price::define(5002)));
+        book.asks.insert(5003, Level::default());
+
+        let problems = book.validate().unwrap_err();
+        let reported = |needle: &str| problems.iter().any(|p| p.contains(needle));
+        assert!(reported(&format!("Order {} rests at Bid 4999 without an index entry", bid_id)));
+        assert!(reported(&format!("Order {} indexed at Ask 5002 but not on that level", ask_id)));
+        assert!(reported("Empty Ask level lingering at 5003"));
+    }
+
+    #[test]
+    fn test_validate_report_order_is_fixed() {
+        // Each book's index hashes with its own random keys, so only a sorted
+        // walk reports stale entries in the same order for both
+        let stale = |book: &mut Orderbook| {
+            for id in (100..164).rev() {
+                book.order_index.insert(id, (Side::Bid, Price::define(4000 + id as u32)));
             }
-        }
+            book.validate().unwrap_err()
+        };
+        let first = stale(&mut Orderbook::new());
+        assert_eq!(first, stale(&mut Orderbook::new()));
+        assert!(first[0].starts_with("Order 100 ") && first[63].starts_with("Order 163 "));
+    }
+
+    #[test]
+    fn test_reserve_sizes_existing_and_new_levels() {
+        let mut book = Orderbook::new();
+        let mut counter = IdCounter::new();
+        let bid = Order::new(Price::define(4999), Quantity::define(10), Side::Bid, &mut counter);
+        book.add_order(bid).unwrap();
+
+        book.reserve(64);
+        assert!(book.bids[&4999].orders.capacity() >= 64);
 
-        // Remove filled orders in reverse order (to maintain indices)
-        for &idx in orders_to_remove.iter().rev() {
-            let removed_order = level.orders.remove(idx);
-            order_index.remove(&removed_order.id());
+        // A level created after reserve starts at the reserved size
+        let ask = Order::new(Price::define(5001), Quantity::define(10), Side::Ask, &mut counter);
+        book.add_order(ask).unwrap();
+        assert!(book.asks[&5001].orders.capacity() >= 64);
+    }
+
+    #[test]
+    fn test_compact_shrinks_a_drained_level() {
+        let mut book = Orderbook::new();
+        let mut counter = IdCounter::new();
+        let mut ids = Vec::new();
+        for _ in 0..1000 {
+            let bid = Order::new(Price::define(5000), Quantity::define(10), Side::Bid, &mut counter);
+            ids.push(bid.id());
+            book.add_order(bid).unwrap();
         }
+        // Keep one order so the level, and its peak capacity, survive
+        for id in &ids[1..] {
+            book.cancel_order(*id).unwrap();
+        }
+        assert!(book.bids[&5000].orders.capacity() >= 1000);
 
-        fills
+        book.compact();
+        assert!(book.bids[&5000].orders.capacity() < 16);
+        assert_eq!(book.depth_at_price(Price::define(5000), Side::Bid), 10);
+        assert_eq!(book.validate(), Ok(()));
     }
-}
 
-impl Level {
-    /// Calculate total quantity at this price level
-    pub fn total_quantity(&self) -> u32 {
-        self.orders
-            .iter()
-            .map(|o| o.quantity().value())
-            .sum::<u32>()
+    #[test]
+    fn test_level_total_tracks_queue() {
+        let mut book = Orderbook::new();
+        let mut counter = IdCounter::new();
+        let assert_total = |book: &Orderbook, expected: u64| {
+            let level = &book.asks[&5002];
+            assert_eq!(level.total_quantity(), level.counted_quantity());
+            assert_eq!(level.total_quantity(), expected);
+        };
+
+        let mut ids = Vec::new();
+        for qty in [30, 50, 20] {
+            let order = Order::new(Price::define(5002), Quantity::define(qty), Side::Ask, &mut counter);
+            ids.push(order.id());
+            book.add_order(order).unwrap();
+        }
+        assert_total(&book, 100);
+
+        book.cancel_order(ids[1]).unwrap();
+        assert_total(&book, 50);
+
+        // Partial fill of the front order
+        book.execute_market_order(Side::Bid, Quantity::define(10), counter.next());
+        assert_total(&book, 40);
+
+        book.amend_order(ids[2], None, Some(Quantity::define(5))).unwrap();
+        assert_total(&book, 25);
+
+        // Fill through the front order and into the next
+        book.execute_market_order(Side::Bid, Quantity::define(22), counter.next());
+        assert_total(&book, 3);
+        assert_eq!(book.validate(), Ok(()));
     }
 }
@@ -0,0 +1,3 @@
+pub mod orderbook;
+
+pub use orderbook::Orderbook as WindowedArrayOrderbook;
@@ -0,0 +1,689 @@
+use crate::orderbook::fixed_tick::bitmap::OccupancyBitmap;
+use crate::orderbook::level::{Level, PriceLevel};
+use crate::orderbook::error::OrderError;
+use crate::orderbook::{
+    check_invariants, deque_bytes, fillable_quantity, hash_map_bytes, unindex_side, ArrivalSequence, BookConfig,
+    BookSnapshot, BookStats, ExpiryQueue, Fill, FillSequence, LimitOrderResult, MarketOrderResult, MemoryFootprint,
+    OrderView, OrderbookTrait, SideTotals, StpMode, SweepQuote, Taker,
+};
+use crate::types::order::{Order, OrderId, Side, TimeInForce};
+use crate::types::price::Price;
+use crate::types::quantity::{QtyValue, Quantity};
+use std::collections::HashMap;
+
+/// Window radius in ticks for with_config: 200 slots per side, 1/50 of the
+/// default 10,000-tick grid
+pub const DEFAULT_RADIUS: u32 = 100;
+
+/// Fixed-tick book that only allocates a window of 2 * radius slots
+///
+/// Same per-level queues and occupancy bitmaps as the fixed-tick book, but
+/// the arrays cover [lower, lower + 2 * radius ticks) instead of the whole
+/// grid, and there is no cold tree behind them as in the hybrid: an order
+/// priced outside the window is rejected. recenter moves the window when the
+/// market does, as long as every resting order stays inside it.
+pub struct Orderbook {
+    // One slot per tick in the window, index = price / tick_size - lower_slot
+    bids: Box<[Level]>,
+    asks: Box<[Level]>,
+    // Grid slot of window index 0
+    lower_slot: usize,
+    order_index: HashMap<OrderId, (Side, Price)>,
+    bid_occupancy: OccupancyBitmap,
+    ask_occupancy: OccupancyBitmap,
+    bid_totals: SideTotals,
+    ask_totals: SideTotals,
+    config: BookConfig,
+    fill_seq: FillSequence,
+    arrival_seq: ArrivalSequence,
+    expiries: ExpiryQueue,
+}
+
+impl OrderbookTrait for Orderbook {
+    /// A DEFAULT_RADIUS window centered on the middle of the price range
+    fn with_config(config: BookConfig) -> Result<Self, String> {
+        config.validate()?;
+        let center = Price::define(config.price_at(config.slots() / 2));
+        Self::with_window(config, center, DEFAULT_RADIUS)
+    }
+
+    fn config(&self) -> &BookConfig {
+        &self.config
+    }
+
+    fn reserve(&mut self, orders_per_level: usize) {
+        for level in self.bids.iter_mut().chain(self.asks.iter_mut()) {
+            level.reserve(orders_per_level);
+        }
+    }
+
+    fn reserve_orders(&mut self, additional: usize) {
+        self.order_index.reserve(additional);
+    }
+
+    fn add_order(&mut self, mut order: Order) -> Result<LimitOrderResult, OrderError> {
+        let order_id = order.id();
+        let side = order.side();
+
+        self.config.validate_order(order.price(), order.quantity())?;
+        let i = self.check_in_window(order.price())?;
+        let resting = self.level(side, order.price().value()).map_or(0, |level| level.orders.len());
+        self.config.check_level_room(side, order.price(), resting)?;
+        self.config.check_crossing(&order, || self.crosses(side, order.price()))?;
+
+        // Fill-or-kill: reject before anything trades unless the whole
+        // quantity is available at or better than the limit price
+        if order.time_in_force() == TimeInForce::FOK {
+            let available = fillable_quantity(self, &order);
+            if available < order.quantity() {
+                return Err(OrderError::FokUnfillable {
+                    order: order.id(),
+                    available: available.value(),
+                    requested: order.quantity().value(),
+                });
+            }
+        }
+
+        // Marketable limit order: take liquidity up to the limit price first
+        let mut remaining_qty = order.quantity();
+        let mut fills = Vec::new();
+        if self.crosses(side, order.price()) {
+            let taker = Taker::limit_order(&order, &self.config);
+            self.match_incoming(&mut remaining_qty, taker, &mut |fill| fills.push(fill.clone()));
+        }
+
+        let filled_qty = order.quantity().saturating_sub(remaining_qty);
+
+        // Nothing left, IOC/FOK, or a remainder that would rest crossed against
+        // orders STP skipped: the unfilled remainder is canceled, not rested
+        if remaining_qty.is_zero()
+            || order.time_in_force() != TimeInForce::GTC
+            || (self.config.stp == StpMode::Skip && self.crosses(side, order.price()))
+        {
+            return Ok(LimitOrderResult {
+                fills,
+                filled_qty,
+                rested_qty: Quantity::define(0),
+                canceled_qty: remaining_qty,
+            });
+        }
+        order.set_quantity(remaining_qty);
+        self.arrival_seq.stamp(&mut order);
+        self.expiries.track(&order);
+
+        match side {
+            Side::Bid => {
+                self.bids[i].add(order);
+                self.bid_occupancy.set(i);
+                self.bid_totals.rested(remaining_qty);
+            }
+            Side::Ask => {
+                self.asks[i].add(order);
+                self.ask_occupancy.set(i);
+                self.ask_totals.rested(remaining_qty);
+            }
+        }
+
+        self.order_index.insert(order_id, (side, order.price()));
+
+        Ok(LimitOrderResult {
+            fills,
+            filled_qty,
+            rested_qty: remaining_qty,
+            canceled_qty: Quantity::define(0),
+        })
+    }
+
+    fn cancel_order(&mut self, order_id: OrderId) -> Result<Order, OrderError> {
+        let (side, price) = self
+            .order_index
+            .remove(&order_id)
+            .ok_or(OrderError::NotFound(order_id))?;
+
+        // Indexed orders always lie inside the window: recenter keeps them there
+        let i = self.slot(price.value()).ok_or(OrderError::IndexDesync(order_id))?;
+        let (level, occupancy, totals) = match side {
+            Side::Bid => (&mut self.bids[i], &mut self.bid_occupancy, &mut self.bid_totals),
+            Side::Ask => (&mut self.asks[i], &mut self.ask_occupancy, &mut self.ask_totals),
+        };
+        let order = level.cancel(order_id).ok_or(OrderError::IndexDesync(order_id))?;
+        totals.removed(order.quantity());
+        if level.is_empty() {
+            occupancy.clear(i);
+        }
+
+        Ok(order)
+    }
+
+    fn cancel_all_for_side(&mut self, side: Side) -> usize {
+        let (levels, occupancy, totals) = match side {
+            Side::Bid => (&mut self.bids, &mut self.bid_occupancy, &mut self.bid_totals),
+            Side::Ask => (&mut self.asks, &mut self.ask_occupancy, &mut self.ask_totals),
+        };
+        *totals = SideTotals::default();
+
+        let mut removed = 0;
+        let mut next = occupancy.first();
+        while let Some(i) = next {
+            removed += levels[i].orders.len();
+            levels[i].orders.clear();
+            occupancy.clear(i);
+            next = occupancy.next_at_or_above(i + 1);
+        }
+        unindex_side(&mut self.order_index, side, removed);
+
+        removed
+    }
+
+    fn take_due_expiries(&mut self, now: u64) -> Vec<OrderId> {
+        self.expiries.take_due(now)
+    }
+
+    fn clear(&mut self) {
+        self.cancel_all();
+        self.fill_seq = FillSequence::default();
+        self.arrival_seq = ArrivalSequence::default();
+        self.expiries.clear();
+    }
+
+    fn contains_order(&self, order_id: OrderId) -> bool {
+        self.order_index.contains_key(&order_id)
+    }
+
+    fn get_order(&self, order_id: OrderId) -> Option<OrderView> {
+        let &(side, price) = self.order_index.get(&order_id)?;
+
+        let (queue_position, order) = self
+            .level(side, price.value())?
+            .orders
+            .iter()
+            .enumerate()
+            .find(|(_, o)| o.id() == order_id)?;
+
+        Some(OrderView {
+            price,
+            side,
+            remaining_qty: order.quantity(),
+            queue_position,
+        })
+    }
+
+    fn amend_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_qty: Option<Quantity>,
+    ) -> Result<(), String> {
+        let (side, price) = *self
+            .order_index
+            .get(&order_id)
+            .ok_or_else(|| format!("Order {} not found", order_id))?;
+
+        let i = self.slot(price.value()).ok_or_else(|| {
+            format!("Order {} indexed outside the window (data inconsistency)", order_id)
+        })?;
+        let (level, totals) = match side {
+            Side::Bid => (&mut self.bids[i], &mut self.bid_totals),
+            Side::Ask => (&mut self.asks[i], &mut self.ask_totals),
+        };
+        let order = level
+            .orders
+            .iter_mut()
+            .find(|o| o.id() == order_id)
+            .ok_or_else(|| format!("Order {} found in index but not in level", order_id))?;
+
+        let target_price = new_price.unwrap_or(price);
+        let target_qty = new_qty.unwrap_or(order.quantity());
+
+        // Pure size reduction: amend in place and keep queue position
+        if target_price == price && target_qty <= order.quantity() {
+            self.config.validate_order(price, target_qty)?;
+            totals.reduced(order.quantity().saturating_sub(target_qty));
+            order.set_quantity(target_qty);
+            return Ok(());
+        }
+
+        // Anything else loses time priority; validate up front so a rejected
+        // amend leaves the resting order untouched
+        let mut replacement = *order;
+        replacement.set_price(target_price);
+        replacement.set_quantity(target_qty);
+
+        self.config.validate_order(target_price, target_qty)?;
+        self.check_in_window(target_price)?;
+        if target_price != price {
+            let resting = self.level(side, target_price.value()).map_or(0, |level| level.orders.len());
+            self.config.check_level_room(side, target_price, resting)?;
+        }
+        if self.crosses(side, target_price) {
+            return Err(format!(
+                "Amended price {} would cross the book",
+                target_price.value()
+            ));
+        }
+
+        self.cancel_order(order_id)?;
+        self.add_order(replacement)?;
+        Ok(())
+    }
+
+    fn best_price(&self, side: Side) -> Option<Price> {
+        let best = match side {
+            Side::Bid => self.bid_occupancy.last(),
+            Side::Ask => self.ask_occupancy.first(),
+        };
+        best.map(|i| Price::define(self.price_at(i)))
+    }
+
+    fn worst_price(&self, side: Side) -> Option<Price> {
+        let worst = match side {
+            Side::Bid => self.bid_occupancy.first(),
+            Side::Ask => self.ask_occupancy.last(),
+        };
+        worst.map(|i| Price::define(self.price_at(i)))
+    }
+
+    fn execute_market_order_with(
+        &mut self,
+        side: Side,
+        mut remaining_qty: Quantity,
+        taker_id: OrderId,
+        mut on_fill: impl FnMut(&Fill),
+    ) -> MarketOrderResult {
+        let requested = remaining_qty;
+        let taker = Taker::market(side, taker_id, &self.config);
+        self.match_incoming(&mut remaining_qty, taker, &mut on_fill);
+
+        MarketOrderResult {
+            fills: Vec::new(),
+            filled_qty: requested.saturating_sub(remaining_qty),
+            remaining_qty,
+        }
+    }
+
+    fn available_liquidity(&self, side: Side, max: Quantity, limit: Option<Price>) -> Quantity {
+        let cap = max.as_u64();
+        let mut total: u64 = 0;
+
+        for (price_value, level) in self.levels(side.opposite()) {
+            let beyond_limit = limit.is_some_and(|l| match side {
+                Side::Bid => price_value > l.value(),
+                Side::Ask => price_value < l.value(),
+            });
+            if total >= cap || beyond_limit {
+                break;
+            }
+            total += level.total_quantity();
+        }
+
+        Quantity::define(total.min(cap) as QtyValue)
+    }
+
+    fn sweep_cost(&self, side: Side, quantity: Quantity) -> Option<SweepQuote> {
+        let levels = self.levels(side.opposite());
+        let level_quantities = levels.map(|(price_value, level)| (price_value, level.total_quantity()));
+        SweepQuote::walk(quantity, level_quantities)
+    }
+
+    fn last_trade(&self) -> Option<(Price, Quantity)> {
+        self.fill_seq.last_trade()
+    }
+
+    fn depth_at_price(&self, price: Price, side: Side) -> u64 {
+        if !self.config.is_valid_price(price.value()) {
+            return 0;
+        }
+        self.level(side, price.value()).map_or(0, PriceLevel::total_quantity)
+    }
+
+    fn order_count_at_price(&self, price: Price, side: Side) -> u32 {
+        if !self.config.is_valid_price(price.value()) {
+            return 0;
+        }
+        self.level(side, price.value()).map_or(0, |level| level.orders.len() as u32)
+    }
+
+    fn depth_in_range(&self, side: Side, from: Price, to: Price) -> u64 {
+        let Some(slots) = self.config.slot_range(from, to) else {
+            return 0;
+        };
+
+        // Clip the grid slots to the window
+        let first = slots.start().saturating_sub(self.lower_slot);
+        let last = (slots.end() + 1).saturating_sub(self.lower_slot).min(self.bids.len());
+        if first >= last {
+            return 0;
+        }
+        let levels = match side {
+            Side::Bid => &self.bids[first..last],
+            Side::Ask => &self.asks[first..last],
+        };
+        levels.iter().map(PriceLevel::total_quantity).sum()
+    }
+
+    fn stats(&self) -> BookStats {
+        BookStats::from_totals(
+            self.bid_occupancy.len(),
+            self.ask_occupancy.len(),
+            self.bid_totals,
+            self.ask_totals,
+        )
+    }
+
+    fn memory_footprint(&self) -> MemoryFootprint {
+        let slots = self.bids.len() + self.asks.len();
+        let levels = self.bids.iter().chain(self.asks.iter());
+        let queues: usize = levels.map(|level| deque_bytes(&level.orders)).sum();
+        MemoryFootprint {
+            fixed_bytes: size_of::<Self>()
+                + slots * size_of::<Level>()
+                + self.bid_occupancy.heap_bytes()
+                + self.ask_occupancy.heap_bytes(),
+            heap_bytes: queues + hash_map_bytes(&self.order_index),
+            orders: self.bid_totals.orders + self.ask_totals.orders,
+        }
+    }
+
+    fn best_n_prices(&self, side: Side, n: usize) -> Vec<Price> {
+        self.levels(side).take(n).map(|(price_value, _)| Price::define(price_value)).collect()
+    }
+
+    fn book_snapshot(&self, depth: usize) -> BookSnapshot {
+        let top = |side| {
+            self.levels(side)
+                .take(depth)
+                .map(|(price_value, level)| (Price::define(price_value), level.total_quantity()))
+                .collect()
+        };
+
+        BookSnapshot {
+            bids: top(Side::Bid),
+            asks: top(Side::Ask),
+        }
+    }
+
+    fn validate(&self) -> Result<(), Vec<String>> {
+        let problems = check_invariants(self, &self.order_index);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    fn iter_orders(&self, side: Side) -> impl Iterator<Item = (Price, Order)> {
+        self.levels(side).flat_map(|(price_value, level)| {
+            level
+                .orders
+                .iter()
+                .map(move |&order| (Price::define(price_value), order))
+        })
+    }
+}
+
+impl Orderbook {
+    /// Book whose window spans `radius` ticks either side of `center`
+    /// A center too close to either end of the price range is pulled in so
+    /// the whole window stays on the grid
+    pub fn with_window(config: BookConfig, center: Price, radius: u32) -> Result<Self, String> {
+        config.validate()?;
+        let slots = 2 * radius as usize;
+        if radius == 0 || slots > config.slots() {
+            return Err(format!(
+                "Window radius {} must be between 1 and {} ticks",
+                radius,
+                config.slots() / 2
+            ));
+        }
+
+        Ok(Self {
+            bids: vec![Level::default(); slots].into_boxed_slice(),
+            asks: vec![Level::default(); slots].into_boxed_slice(),
+            lower_slot: Self::window_start(&config, center, slots),
+            order_index: HashMap::new(),
+            bid_occupancy: OccupancyBitmap::new(slots),
+            ask_occupancy: OccupancyBitmap::new(slots),
+            bid_totals: SideTotals::default(),
+            ask_totals: SideTotals::default(),
+            config,
+            fill_seq: FillSequence::default(),
+            arrival_seq: ArrivalSequence::default(),
+            expiries: ExpiryQueue::default(),
+        })
+    }
+
+    /// Prices the window covers, [lower, upper)
+    pub fn window(&self) -> (Price, Price) {
+        (
+            Price::define(self.price_at(0)),
+            Price::define(self.price_at(self.bids.len())),
+        )
+    }
+
+    /// Move the window to be centered on `center` (pulled in at the ends of
+    /// the price range as in with_window)
+    /// Refused, leaving the book as it was, if any resting order would fall
+    /// outside the new window. Levels move in place, so nothing reallocates
+    pub fn recenter(&mut self, center: Price) -> Result<(), String> {
+        let len = self.bids.len();
+        let new_lower = Self::window_start(&self.config, center, len);
+
+        // Lowest and highest occupied window slot across both sides
+        let lowest = [self.bid_occupancy.first(), self.ask_occupancy.first()].into_iter().flatten().min();
+        let highest = [self.bid_occupancy.last(), self.ask_occupancy.last()].into_iter().flatten().max();
+        if let (Some(lowest), Some(highest)) = (lowest, highest) {
+            let (lowest, highest) = (self.lower_slot + lowest, self.lower_slot + highest);
+            if lowest < new_lower || highest >= new_lower + len {
+                return Err(format!(
+                    "Recentering on {} would strand orders resting between {} and {}",
+                    center.value(),
+                    self.config.price_at(lowest),
+                    self.config.price_at(highest)
+                ));
+            }
+        }
+
+        // Every occupied slot survives the shift, so what rotates around the
+        // end of the array is empty
+        let shift = new_lower.abs_diff(self.lower_slot);
+        if shift < len {
+            for levels in [&mut self.bids, &mut self.asks] {
+                if new_lower > self.lower_slot {
+                    levels.rotate_left(shift);
+                } else {
+                    levels.rotate_right(shift);
+                }
+            }
+        }
+        self.lower_slot = new_lower;
+
+        for (levels, occupancy) in [
+            (&self.bids, &mut self.bid_occupancy),
+            (&self.asks, &mut self.ask_occupancy),
+        ] {
+            *occupancy = OccupancyBitmap::new(len);
+            for (i, level) in levels.iter().enumerate() {
+                if !level.is_empty() {
+                    occupancy.set(i);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Grid slot of the first window slot for a `slots`-wide window on `center`
+    fn window_start(config: &BookConfig, center: Price, slots: usize) -> usize {
+        let center_slot = config.index_of(center.value());
+        center_slot.saturating_sub(slots / 2).min(config.slots() - slots)
+    }
+
+    /// Window slot of an on-grid price, None outside the window
+    fn slot(&self, price_value: u32) -> Option<usize> {
+        self.config
+            .index_of(price_value)
+            .checked_sub(self.lower_slot)
+            .filter(|&i| i < self.bids.len())
+    }
+
+    /// slot, with the reason when the price is outside the window
+    fn check_in_window(&self, price: Price) -> Result<usize, OrderError> {
+        self.slot(price.value()).ok_or_else(|| {
+            let (lower, upper) = self.window();
+            OrderError::OutsideWindow { price: price.value(), lower: lower.value(), upper: upper.value() }
+        })
+    }
+
+    /// Price of window slot `i`
+    fn price_at(&self, i: usize) -> u32 {
+        self.config.price_at(self.lower_slot + i)
+    }
+
+    /// Level slot at `price_value` on `side`; None outside the window
+    fn level(&self, side: Side, price_value: u32) -> Option<&Level> {
+        let i = self.slot(price_value)?;
+        match side {
+            Side::Bid => self.bids.get(i),
+            Side::Ask => self.asks.get(i),
+        }
+    }
+
+    /// Populated levels on `side`, best price first
+    fn levels(&self, side: Side) -> Box<dyn Iterator<Item = (u32, &Level)> + '_> {
+        match side {
+            Side::Bid => Box::new(
+                std::iter::successors(self.bid_occupancy.last(), |&i| {
+                    i.checked_sub(1)
+                        .and_then(|j| self.bid_occupancy.prev_at_or_below(j))
+                })
+                .map(move |i| (self.price_at(i), &self.bids[i])),
+            ),
+            Side::Ask => Box::new(
+                std::iter::successors(self.ask_occupancy.first(), |&i| {
+                    self.ask_occupancy.next_at_or_above(i + 1)
+                })
+                .map(move |i| (self.price_at(i), &self.asks[i])),
+            ),
+        }
+    }
+
+    /// Does a limit order at `price` on `side` reach the opposite best price?
+    fn crosses(&self, side: Side, price: Price) -> bool {
+        match side {
+            Side::Bid => self.best_ask().is_some_and(|ask| ask.value() <= price.value()),
+            Side::Ask => self.best_bid().is_some_and(|bid| bid.value() >= price.value()),
+        }
+    }
+
+    /// Walk the opposite side for an incoming order on `side`, best price first
+    /// Stops once `remaining_qty` is zero or the next level is beyond `limit`
+    /// Each fill is sequenced, then handed to `on_fill`
+    fn match_incoming(
+        &mut self,
+        remaining_qty: &mut Quantity,
+        taker: Taker,
+        on_fill: &mut impl FnMut(&Fill),
+    ) {
+        let fill_seq = &mut self.fill_seq;
+        let mut on_fill = |mut fill: Fill| {
+            fill_seq.stamp(&mut fill);
+            on_fill(&fill);
+        };
+        let (lower_slot, config) = (self.lower_slot, self.config);
+        let price_at = |i: usize| Price::define(config.price_at(lower_slot + i));
+
+        match taker.side {
+            // BUY: walk populated asks from the lowest price upward
+            Side::Bid => {
+                let mut next = self.ask_occupancy.first();
+                while let Some(i) = next {
+                    let price = price_at(i);
+                    if remaining_qty.is_zero() || taker.limit.is_some_and(|l| price > l) {
+                        break;
+                    }
+
+                    self.asks[i].match_orders(
+                        remaining_qty,
+                        price,
+                        taker,
+                        &mut self.order_index,
+                        &mut self.ask_totals,
+                        &mut on_fill,
+                    );
+
+                    if self.asks[i].is_empty() {
+                        self.ask_occupancy.clear(i);
+                    }
+                    next = self.ask_occupancy.next_at_or_above(i + 1);
+                }
+            }
+
+            // SELL: walk populated bids from the highest price downward
+            Side::Ask => {
+                let mut next = self.bid_occupancy.last();
+                while let Some(i) = next {
+                    let price = price_at(i);
+                    if remaining_qty.is_zero() || taker.limit.is_some_and(|l| price < l) {
+                        break;
+                    }
+
+                    self.bids[i].match_orders(
+                        remaining_qty,
+                        price,
+                        taker,
+                        &mut self.order_index,
+                        &mut self.bid_totals,
+                        &mut on_fill,
+                    );
+
+                    if self.bids[i].is_empty() {
+                        self.bid_occupancy.clear(i);
+                    }
+                    next = i.checked_sub(1).and_then(|j| self.bid_occupancy.prev_at_or_below(j));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::order::IdCounter;
+
+    #[test]
+    fn test_window_is_a_fraction_of_the_grid() {
+        let book = Orderbook::new();
+        assert_eq!(book.window(), (Price::define(4900), Price::define(5100)));
+        assert_eq!(book.bids.len(), 200);
+
+        let grid = crate::orderbook::fixed_tick::orderbook::Orderbook::new();
+        assert!(book.memory_footprint().fixed_bytes * 40 < grid.memory_footprint().fixed_bytes);
+
+        // Centers near the ends are pulled in; radii wider than the grid fail
+        let config = BookConfig::default();
+        let low = Orderbook::with_window(config, Price::define(10), 50).unwrap();
+        assert_eq!(low.window(), (Price::define(0), Price::define(100)));
+        assert!(Orderbook::with_window(config, Price::define(5000), 0).is_err());
+        assert!(Orderbook::with_window(config, Price::define(5000), 5001).is_err());
+    }
+
+    #[test]
+    fn test_recenter_moves_levels_with_their_prices() {
+        let mut book = Orderbook::new();
+        let mut counter = IdCounter::new();
+        let bid = Order::new(Price::define(4950), Quantity::define(10), Side::Bid, &mut counter);
+        let ask = Order::new(Price::define(5050), Quantity::define(20), Side::Ask, &mut counter);
+        book.add_order(bid).unwrap();
+        book.add_order(ask).unwrap();
+
+        // Both ways, levels land back on their own prices
+        for center in [5040, 4960, 5000] {
+            book.recenter(Price::define(center)).unwrap();
+            assert_eq!(book.best_bid(), Some(Price::define(4950)));
+            assert_eq!(book.get_order(ask.id()).map(|view| view.price), Some(Price::define(5050)));
+            assert_eq!(book.depth_at_price(Price::define(5050), Side::Ask), 20);
+            assert_eq!(book.validate(), Ok(()));
+        }
+    }
+}
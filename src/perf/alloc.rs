@@ -0,0 +1,146 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Heap traffic on one thread between two alloc_stats calls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// alloc, alloc_zeroed and realloc calls
+    pub allocations: u64,
+    pub deallocations: u64,
+    /// Bytes requested by those allocations; a realloc counts its new size
+    pub bytes_allocated: u64,
+}
+
+// Per thread, so parallel tests and other threads don't leak into a
+// bracketed operation. Const-initialized Cells have no destructor and never
+// allocate, which keeps the allocator from recursing into itself
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+    static DEALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+    static BYTES: Cell<u64> = const { Cell::new(0) };
+}
+
+/// System allocator that tallies every call on the calling thread
+/// Installed as the global allocator when the alloc-stats feature is on
+pub struct CountingAllocator;
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn record_allocation(bytes: usize) {
+    // try_with: a thread being torn down has no counters left to update
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+    let _ = BYTES.try_with(|total| total.set(total.get() + bytes as u64));
+}
+
+fn record_deallocation() {
+    let _ = DEALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+// SAFETY: every call is forwarded unchanged to System; the bookkeeping
+// touches only thread-local Cells and never allocates
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_allocation(layout.size());
+        // SAFETY: the caller's contract for alloc is passed through as is
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record_allocation(layout.size());
+        // SAFETY: as for alloc
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record_deallocation();
+        // SAFETY: ptr came from this allocator, i.e. from System, with layout
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record_allocation(new_size);
+        // SAFETY: as for dealloc; new_size is the caller's responsibility
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// Allocations on this thread since the previous call, which resets them
+/// Call once before and once after an operation to attribute its heap
+/// traffic; see count_allocations
+pub fn alloc_stats() -> AllocStats {
+    AllocStats {
+        allocations: ALLOCATIONS.with(|count| count.replace(0)),
+        deallocations: DEALLOCATIONS.with(|count| count.replace(0)),
+        bytes_allocated: BYTES.with(|total| total.replace(0)),
+    }
+}
+
+/// Run `operation` and report the allocations it made on this thread
+pub fn count_allocations<R>(operation: impl FnOnce() -> R) -> (R, AllocStats) {
+    alloc_stats();
+    let result = operation();
+    (result, alloc_stats())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::OrderbookTrait;
+    use crate::orderbook::tree::orderbook::Orderbook as Tree;
+    use crate::types::order::{IdCounter, Order, Side};
+    use crate::types::price::Price;
+    use crate::types::quantity::Quantity;
+
+    #[test]
+    fn test_counts_a_vec_allocation() {
+        let (vec, stats) = count_allocations(|| Vec::<u64>::with_capacity(32));
+        assert_eq!(stats.allocations, 1);
+        assert_eq!(stats.bytes_allocated, 256);
+        let ((), stats) = count_allocations(|| drop(vec));
+        assert_eq!(stats.deallocations, 1);
+    }
+
+    #[test]
+    fn test_new_tree_level_allocates_existing_level_does_not() {
+        let mut book = Tree::new();
+        book.reserve(8);
+        book.reserve_orders(16);
+        let mut ids = IdCounter::new();
+        let mut bid = |price| Order::new(Price::define(price), Quantity::define(10), Side::Bid, &mut ids);
+
+        let (first, second, third) = (bid(4990), bid(4980), bid(4990));
+        book.add_order(first).unwrap();
+
+        // A new price needs its own VecDeque, and the BTreeMap may grow a node
+        let (result, stats) = count_allocations(|| book.add_order(second));
+        result.unwrap();
+        assert!(stats.allocations > 0, "{:?}", stats);
+
+        // The level's queue and the id index already have room
+        let (result, stats) = count_allocations(|| book.add_order(third));
+        result.unwrap();
+        assert_eq!(stats, AllocStats::default());
+    }
+
+    #[test]
+    fn test_new_with_capacity_index_does_not_grow() {
+        const ORDERS: usize = 1_000;
+        // Every order joins one pre-sized level, so only the index can grow
+        let added_allocations = |mut book: Tree| {
+            book.reserve(ORDERS);
+            let mut ids = IdCounter::new();
+            let mut bid = || Order::new(Price::define(4990), Quantity::define(10), Side::Bid, &mut ids);
+            book.add_order(bid()).unwrap();
+            let ((), stats) = count_allocations(|| {
+                for _ in 1..ORDERS {
+                    book.add_order(bid()).unwrap();
+                }
+            });
+            stats.allocations
+        };
+
+        assert!(added_allocations(Tree::new()) > 0);
+        assert_eq!(added_allocations(Tree::new_with_capacity(ORDERS)), 0);
+    }
+}
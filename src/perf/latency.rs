@@ -1,4 +1,4 @@
-use super::rdtsc;
+use super::{rdtsc, rdtsc_serialized};
 
 /// Get CPU frequency from /proc/cpuinfo (Linux only)
 /// Returns frequency in GHz, or None if not available
@@ -21,36 +21,129 @@ pub fn get_cpu_frequency_from_proc() -> Option<f64> {
 }
 
 /// Get CPU frequency - tries /proc/cpuinfo first, falls back to estimation
+/// /proc is only used while it agrees with the measured counter rate; if
+/// the TSC is not invariant or the reported clock looks scaled, a warning
+/// goes to stderr (see timing_warning) and the measurement is returned
+/// On aarch64 this is the counter frequency instead, since rdtsc() reads the
+/// generic timer there rather than counting CPU cycles
 pub fn get_cpu_frequency() -> f64 {
-    #[cfg(target_os = "linux")]
+    #[cfg(target_arch = "aarch64")]
     {
-        if let Some(freq) = get_cpu_frequency_from_proc() {
-            return freq;
+        super::counter_frequency()
+    }
+
+    #[cfg(not(target_arch = "aarch64"))]
+    {
+        let estimate = get_cpu_frequency_calibrated(DEFAULT_SAMPLES, DEFAULT_SAMPLE_MS);
+        if let Some(warning) = timing_warning(&estimate) {
+            eprintln!("warning: {}", warning);
         }
+        match estimate.reported_ghz {
+            Some(reported) if !estimate.scaling_suspected => reported,
+            // Fallback: the rate rdtsc() was measured to tick at
+            _ => estimate.ghz,
+        }
+    }
+}
+
+/// Why nanoseconds converted from rdtsc() cycles cannot be trusted on this
+/// machine, given a calibration; None when they can
+pub fn timing_warning(estimate: &FrequencyEstimate) -> Option<String> {
+    if !super::tsc_is_invariant() {
+        return Some(
+            "TSC is not invariant: cycle counts do not map linearly to time, so nanosecond figures \
+             are unreliable"
+                .to_string(),
+        );
+    }
+    match estimate.reported_ghz {
+        Some(reported) if estimate.scaling_suspected => Some(format!(
+            "/proc/cpuinfo reports {:.3} GHz but the TSC ticks at {:.3} GHz: frequency scaling or \
+             turbo is active, converting with the measured rate",
+            reported, estimate.ghz
+        )),
+        _ => None,
     }
+}
 
-    // Fallback: estimate by measurement
-    estimate_cpu_frequency()
+/// Samples estimate_cpu_frequency takes, and how long each one sleeps
+const DEFAULT_SAMPLES: usize = 5;
+const DEFAULT_SAMPLE_MS: u64 = 2;
+
+/// Samples further than this fraction from the median are dropped
+const OUTLIER_TOLERANCE: f64 = 0.05;
+
+/// /proc/cpuinfo and the measurement may differ by this fraction before
+/// turbo or frequency scaling is suspected
+const SCALING_TOLERANCE: f64 = 0.10;
+
+/// A measured counter frequency, checked against what the kernel reports
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrequencyEstimate {
+    /// Median of the samples that survived outlier removal, in GHz
+    pub ghz: f64,
+    /// /proc/cpuinfo "cpu MHz" of the first core in GHz, when readable
+    pub reported_ghz: Option<f64>,
+    /// The reported and measured rates differ by more than SCALING_TOLERANCE:
+    /// the core is running off its nominal clock (turbo or power scaling),
+    /// so the two should not be used interchangeably
+    pub scaling_suspected: bool,
 }
 
 /// Estimate CPU frequency in GHz by measuring cycles over a known time period
+/// Median of DEFAULT_SAMPLES short samples (see get_cpu_frequency_calibrated)
 pub fn estimate_cpu_frequency() -> f64 {
-    use std::time::Instant;
+    measure_frequency(DEFAULT_SAMPLES, DEFAULT_SAMPLE_MS)
+}
 
-    let start_time = Instant::now();
-    let start_cycles = rdtsc();
+/// Measure the counter frequency from `samples` sleeps of `sleep_ms` each
+/// and compare it with /proc/cpuinfo. A sample the scheduler stretched or
+/// a scaling step skewed lands away from the rest and is discarded
+pub fn get_cpu_frequency_calibrated(samples: usize, sleep_ms: u64) -> FrequencyEstimate {
+    let ghz = measure_frequency(samples, sleep_ms);
 
-    // Sleep for 10ms to get a good measurement
-    std::thread::sleep(std::time::Duration::from_millis(10));
+    // On aarch64 the counter runs at its own fixed rate, never the CPU clock
+    #[cfg(all(target_os = "linux", not(target_arch = "aarch64")))]
+    let reported_ghz = get_cpu_frequency_from_proc();
+    #[cfg(not(all(target_os = "linux", not(target_arch = "aarch64"))))]
+    let reported_ghz = None;
 
-    let end_cycles = rdtsc();
-    let end_time = Instant::now();
+    let scaling_suspected = reported_ghz.is_some_and(|reported| (reported - ghz).abs() > SCALING_TOLERANCE * ghz);
+    FrequencyEstimate { ghz, reported_ghz, scaling_suspected }
+}
 
-    let elapsed_ns = end_time.duration_since(start_time).as_nanos() as f64;
-    let elapsed_cycles = (end_cycles - start_cycles) as f64;
+/// Median counter rate over `samples` timed sleeps, outliers removed
+fn measure_frequency(samples: usize, sleep_ms: u64) -> f64 {
+    use std::time::{Duration, Instant};
 
-    // GHz = (cycles / nanoseconds)
-    elapsed_cycles / elapsed_ns
+    let mut rates: Vec<f64> = (0..samples.max(1))
+        .map(|_| {
+            let start_time = Instant::now();
+            let start_cycles = rdtsc();
+            std::thread::sleep(Duration::from_millis(sleep_ms));
+            let end_cycles = rdtsc();
+            let elapsed_ns = start_time.elapsed().as_nanos() as f64;
+
+            // GHz = (cycles / nanoseconds)
+            (end_cycles - start_cycles) as f64 / elapsed_ns
+        })
+        .collect();
+    rates.sort_by(f64::total_cmp);
+
+    let median = median_of_sorted(&rates);
+    rates.retain(|rate| (rate - median).abs() <= OUTLIER_TOLERANCE * median);
+    median_of_sorted(&rates)
+}
+
+/// Middle value of a sorted, non-empty slice (mean of the two middle ones
+/// for an even length)
+fn median_of_sorted(sorted: &[f64]) -> f64 {
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
 }
 
 /// Convert CPU cycles to nanoseconds given a CPU frequency in GHz
@@ -58,31 +151,83 @@ pub fn cycles_to_ns(cycles: u64, cpu_ghz: f64) -> f64 {
     cycles as f64 / cpu_ghz
 }
 
+/// get_cpu_frequency, measured once per process
+/// Keeps throughput reporting from re-reading /proc or re-estimating
+fn cached_cpu_frequency() -> f64 {
+    static CPU_GHZ: std::sync::OnceLock<f64> = std::sync::OnceLock::new();
+    *CPU_GHZ.get_or_init(get_cpu_frequency)
+}
+
+/// Operations per second for a mean latency of `mean_cycles`
+fn ops_per_sec(mean_cycles: f64) -> f64 {
+    cached_cpu_frequency() * 1e9 / mean_cycles
+}
+
 pub struct LatencyTracker {
     samples: Vec<u64>,
+    serialized: bool,
+    /// Samples still to be discarded as warm-up
+    warmup_remaining: usize,
 }
 
 impl LatencyTracker {
     pub fn new(capacity: usize) -> Self {
+        Self::with_warmup(capacity, 0)
+    }
+
+    /// Tracker over already collected samples, e.g. timed elsewhere
+    pub fn from_samples(samples: Vec<u64>) -> Self {
+        Self {
+            samples,
+            serialized: false,
+            warmup_remaining: 0,
+        }
+    }
+
+    /// Discard the first `warmup_count` samples, so cold caches, page faults
+    /// and branch predictor training stay out of the percentiles
+    /// `capacity` is for the samples kept after warm-up
+    pub fn with_warmup(capacity: usize, warmup_count: usize) -> Self {
         Self {
             samples: Vec::with_capacity(capacity),
+            serialized: false,
+            warmup_remaining: warmup_count,
         }
     }
 
+    /// Time with rdtsc_serialized instead of bare rdtsc
+    /// Steadier for very short operations, but every sample includes the
+    /// fixed cost of the fences
+    pub fn with_serialized_rdtsc(mut self) -> Self {
+        self.serialized = true;
+        self
+    }
+
     #[inline(always)]
     pub fn record<F, R>(&mut self, op: F) -> R
     where
         F: FnOnce() -> R,
     {
-        let start = rdtsc();
+        let read = if self.serialized { rdtsc_serialized } else { rdtsc };
+        let start = read();
         let result = op();
-        let end = rdtsc();
+        let end = read();
 
-        self.samples.push(end - start);
+        self.record_cycles(end - start);
 
         result
     }
 
+    /// Record an externally measured sample
+    #[inline(always)]
+    pub fn record_cycles(&mut self, cycles: u64) {
+        if self.warmup_remaining > 0 {
+            self.warmup_remaining -= 1;
+            return;
+        }
+        self.samples.push(cycles);
+    }
+
     pub fn is_empty(&self) -> bool {
         self.samples.is_empty()
     }
@@ -91,9 +236,41 @@ impl LatencyTracker {
         self.samples.len()
     }
 
+    /// Recorded cycle counts, in recording order
+    pub fn samples(&self) -> &[u64] {
+        &self.samples
+    }
+
+    /// The recorded cycle counts, without copying
+    pub fn take_samples(self) -> Vec<u64> {
+        self.samples
+    }
+
     pub fn clear(&mut self) {
         self.samples.clear();
     }
+
+    /// Append another tracker's samples, e.g. to combine per-thread trackers
+    pub fn merge(&mut self, other: LatencyTracker) {
+        self.samples.extend(other.samples);
+    }
+
+    /// Operations per second if the recorded operations ran back to back
+    /// Summed per-op cycles miss any time spent between operations; see
+    /// wall_clock_throughput for the end-to-end rate
+    pub fn throughput(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let total: u64 = self.samples.iter().sum();
+        ops_per_sec(total as f64 / self.samples.len() as f64)
+    }
+
+    /// Operations per second over `elapsed`, an Instant-measured span around
+    /// the whole measurement loop
+    pub fn wall_clock_throughput(&self, elapsed: std::time::Duration) -> f64 {
+        self.samples.len() as f64 / elapsed.as_secs_f64()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -108,46 +285,253 @@ pub struct Percentiles {
     pub p99: u64,
     pub p999: u64,  // p99.9
     pub p9999: u64, // p99.99
+    /// Population standard deviation of the samples
+    pub std_dev: f64,
+    /// Coefficient of variation: std_dev / mean
+    pub cv: f64,
+    /// Throughput implied by the mean latency
+    pub ops_per_sec: f64,
 }
 
 impl LatencyTracker {
-    pub fn precentiles(&mut self) -> Option<Percentiles> {
+    /// Sorts a copy of the samples: samples() keeps recording order
+    pub fn percentiles(&self) -> Option<Percentiles> {
+        self.percentiles_trimmed(0.0, 1.0)
+    }
+
+    #[deprecated(note = "renamed to percentiles")]
+    pub fn precentiles(&self) -> Option<Percentiles> {
+        self.percentiles()
+    }
+
+    /// Percentiles with mean, std_dev and cv computed only over the samples
+    /// between the `lower_pct` and `upper_pct` percentiles (fractions, e.g.
+    /// 0.01 and 0.99); min, max and the percentiles themselves are untrimmed
+    /// This is for a stable central tendency when interrupts or SMIs throw
+    /// off a few samples, not for hiding tail latency: report p99+ and max
+    /// alongside it
+    pub fn percentiles_trimmed(&self, lower_pct: f64, upper_pct: f64) -> Option<Percentiles> {
+        assert!(
+            (0.0..=1.0).contains(&lower_pct) && lower_pct < upper_pct && upper_pct <= 1.0,
+            "Trim bounds must satisfy 0.0 <= lower < upper <= 1.0"
+        );
         if self.samples.is_empty() {
             return None;
         }
 
-        self.samples.sort_unstable();
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+
+        let len = sorted.len();
+        let min = sorted[0];
+        let max = sorted[len - 1];
 
-        let len = self.samples.len();
-        let min = self.samples[0];
-        let max = self.samples[len - 1];
-        let sum: u64 = self.samples.iter().sum();
-        let mean = sum as f64 / len as f64;
+        let kept_from = ((lower_pct * len as f64).floor() as usize).min(len - 1);
+        let kept_to = ((upper_pct * len as f64).ceil() as usize).max(kept_from + 1);
+        let kept = &sorted[kept_from..kept_to];
+
+        let sum: u64 = kept.iter().sum();
+        let mean = sum as f64 / kept.len() as f64;
+        // Second pass over the samples: no cancellation between large sums
+        let variance = kept
+            .iter()
+            .map(|&s| (s as f64 - mean).powi(2))
+            .sum::<f64>()
+            / kept.len() as f64;
+        let std_dev = variance.sqrt();
 
         Some(Percentiles {
             min,
             max,
             mean,
+            std_dev,
+            cv: std_dev / mean,
+            p50: percentile_at(&sorted, 0.50),
+            p95: percentile_at(&sorted, 0.95),
+            p99: percentile_at(&sorted, 0.99),
+            p999: percentile_at(&sorted, 0.999),
+            p9999: percentile_at(&sorted, 0.9999),
+            ops_per_sec: ops_per_sec(mean),
+        })
+    }
+
+    #[deprecated(note = "renamed to percentiles_trimmed")]
+    pub fn precentiles_trimmed(&self, lower_pct: f64, upper_pct: f64) -> Option<Percentiles> {
+        self.percentiles_trimmed(lower_pct, upper_pct)
+    }
+}
+
+/// Value at fraction `p` of the way through the ascending `sorted`
+fn percentile_at(sorted: &[u64], p: f64) -> u64 {
+    assert!(!sorted.is_empty(), "No samples to calculate percentile");
+    assert!(
+        (0.0..=1.0).contains(&p),
+        "Percentile must be between 0.0 and 1.0"
+    );
+
+    // Linear interpolation between the two nearest ranks ("type 7")
+    let rank = p * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let fraction = rank - lower as f64;
+
+    let (low, high) = (sorted[lower] as f64, sorted[upper] as f64);
+    (low + fraction * (high - low)).round() as u64
+}
+
+/// Sub-buckets per power of two in HdrLatencyTracker, as a bit count
+/// Values below 2^SUB_BUCKET_BITS are exact; above that a bucket spans at
+/// most 1/128 of its value (< 0.8% error)
+const SUB_BUCKET_BITS: u32 = 8;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+const HALF_SUB_BUCKETS: usize = SUB_BUCKETS / 2;
+const HDR_BUCKETS: usize = (64 - SUB_BUCKET_BITS as usize) * HALF_SUB_BUCKETS + SUB_BUCKETS;
+
+/// Latency tracker backed by a log-linear histogram
+/// Memory is fixed (~60 KB) regardless of sample count and recording never
+/// allocates, at the cost of bucket-resolution percentiles
+pub struct HdrLatencyTracker {
+    counts: Box<[u64]>,
+    count: u64,
+    sum: u128,
+    sum_sq: u128,
+    min: u64,
+    max: u64,
+}
+
+impl HdrLatencyTracker {
+    pub fn new() -> Self {
+        Self {
+            counts: vec![0; HDR_BUCKETS].into_boxed_slice(),
+            count: 0,
+            sum: 0,
+            sum_sq: 0,
+            min: u64::MAX,
+            max: 0,
+        }
+    }
+
+    #[inline(always)]
+    pub fn record<F, R>(&mut self, op: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let start = rdtsc();
+        let result = op();
+        let end = rdtsc();
+
+        self.record_cycles(end - start);
+
+        result
+    }
+
+    /// Record an externally measured sample
+    #[inline(always)]
+    pub fn record_cycles(&mut self, cycles: u64) {
+        self.counts[Self::bucket_of(cycles)] += 1;
+        self.count += 1;
+        self.sum += cycles as u128;
+        self.sum_sq += cycles as u128 * cycles as u128;
+        self.min = self.min.min(cycles);
+        self.max = self.max.max(cycles);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.fill(0);
+        self.count = 0;
+        self.sum = 0;
+        self.sum_sq = 0;
+        self.min = u64::MAX;
+        self.max = 0;
+    }
+
+    /// Add another tracker's bucket counts, e.g. to combine per-thread trackers
+    pub fn merge(&mut self, other: HdrLatencyTracker) {
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other_count;
+        }
+        self.count += other.count;
+        self.sum += other.sum;
+        self.sum_sq += other.sum_sq;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Same percentiles as LatencyTracker, each reported as the highest value
+    /// of its bucket (clamped to the observed max)
+    pub fn percentiles(&self) -> Option<Percentiles> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let mean = self.sum as f64 / self.count as f64;
+        // Samples are gone, so E[x²] - E[x]²; clamped against rounding
+        let variance = (self.sum_sq as f64 / self.count as f64 - mean * mean).max(0.0);
+        let std_dev = variance.sqrt();
+
+        Some(Percentiles {
+            min: self.min,
+            max: self.max,
+            mean,
+            std_dev,
+            cv: std_dev / mean,
             p50: self.percentile_at(0.50),
             p95: self.percentile_at(0.95),
             p99: self.percentile_at(0.99),
             p999: self.percentile_at(0.999),
             p9999: self.percentile_at(0.9999),
+            ops_per_sec: ops_per_sec(mean),
         })
     }
 
+    #[deprecated(note = "renamed to percentiles")]
+    pub fn precentiles(&self) -> Option<Percentiles> {
+        self.percentiles()
+    }
+
     fn percentile_at(&self, p: f64) -> u64 {
-        assert!(
-            !self.samples.is_empty(),
-            "No samples to calculate percentile"
-        );
-        assert!(
-            p >= 0.0 && p <= 1.0,
-            "Percentile must be between 0.0 and 1.0"
-        );
+        // Nearest lower rank; bucket resolution dwarfs interpolation
+        let rank = (p * (self.count - 1) as f64) as u64;
+        let mut seen = 0;
+
+        for (bucket, &n) in self.counts.iter().enumerate() {
+            seen += n;
+            if seen > rank {
+                return Self::highest_in(bucket).min(self.max);
+            }
+        }
+        self.max
+    }
 
-        let index = (p * (self.samples.len() - 1) as f64) as usize;
-        self.samples[index]
+    /// Values with `shift` bits past the first SUB_BUCKET_BITS share a bucket
+    /// with their top SUB_BUCKET_BITS bits
+    #[inline(always)]
+    fn bucket_of(value: u64) -> usize {
+        let shift = (64 - value.leading_zeros()).saturating_sub(SUB_BUCKET_BITS);
+        shift as usize * HALF_SUB_BUCKETS + (value >> shift) as usize
+    }
+
+    fn highest_in(bucket: usize) -> u64 {
+        if bucket < SUB_BUCKETS {
+            return bucket as u64;
+        }
+        let shift = bucket / HALF_SUB_BUCKETS - 1;
+        let mantissa = (bucket - shift * HALF_SUB_BUCKETS) as u64;
+        (mantissa << shift) + ((1u64 << shift) - 1)
+    }
+}
+
+impl Default for HdrLatencyTracker {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -155,6 +539,35 @@ impl LatencyTracker {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_calibrated_frequency_is_plausible_and_stable() {
+        let first = get_cpu_frequency_calibrated(5, 2);
+        assert!((0.5..=6.0).contains(&first.ghz), "{:?}", first);
+        assert_eq!(first.scaling_suspected, first.reported_ghz.is_some_and(|r| (r - first.ghz).abs() > 0.1 * first.ghz));
+
+        // The counter rate is fixed, so calibrations agree within a few percent
+        let second = get_cpu_frequency_calibrated(5, 2);
+        assert!((first.ghz - second.ghz).abs() < 0.05 * first.ghz, "{:?} vs {:?}", first, second);
+    }
+
+    #[test]
+    fn test_timing_warning_flags_scaling() {
+        let steady = FrequencyEstimate { ghz: 3.0, reported_ghz: Some(3.0), scaling_suspected: false };
+        let scaled = FrequencyEstimate { ghz: 3.0, reported_ghz: Some(1.2), scaling_suspected: true };
+        if crate::perf::tsc_is_invariant() {
+            assert_eq!(timing_warning(&steady), None);
+            assert!(timing_warning(&scaled).unwrap().contains("1.200 GHz"));
+        } else {
+            assert!(timing_warning(&steady).unwrap().contains("not invariant"));
+        }
+    }
+
+    #[test]
+    fn test_median_of_sorted() {
+        assert_eq!(median_of_sorted(&[3.0]), 3.0);
+        assert_eq!(median_of_sorted(&[1.0, 2.0, 4.0, 9.0]), 3.0);
+    }
+
     #[test]
     fn test_latency_tracker_basic() {
         let mut tracker = LatencyTracker::new(100);
@@ -176,6 +589,30 @@ mod tests {
         assert!(!tracker.is_empty());
     }
 
+    #[test]
+    fn test_samples_in_recording_order() {
+        let mut tracker = LatencyTracker::with_warmup(8, 1);
+        for cycles in [999, 30, 10, 20, 40, 5] {
+            tracker.record_cycles(cycles);
+        }
+
+        // Warm-up sample dropped, the rest exactly as recorded
+        assert_eq!(tracker.samples(), &[30, 10, 20, 40, 5]);
+        assert_eq!(tracker.take_samples(), vec![30, 10, 20, 40, 5]);
+    }
+
+    #[test]
+    fn test_percentiles_keep_recording_order() {
+        let tracker = LatencyTracker::from_samples(vec![50, 10, 40, 20, 30]);
+
+        let stats = tracker.percentiles().unwrap();
+        assert_eq!((stats.min, stats.p50, stats.max), (10, 30, 50));
+        assert_eq!(tracker.samples(), &[50, 10, 40, 20, 30]);
+
+        // Same answer the second time
+        assert_eq!(tracker.percentiles().unwrap().p50, 30);
+    }
+
     #[test]
     fn test_percentiles_calculation() {
         let mut tracker = LatencyTracker::new(1000);
@@ -197,7 +634,7 @@ mod tests {
         }
 
         // Calculate percentiles
-        let stats = tracker.precentiles().expect("Should have percentiles");
+        let stats = tracker.percentiles().expect("Should have percentiles");
 
         // Basic sanity checks
         println!("Min: {}", stats.min);
@@ -221,12 +658,146 @@ mod tests {
         assert_eq!(tracker.len(), 1000);
     }
 
+    #[test]
+    fn test_percentiles_interpolate_between_ranks() {
+        let mut tracker = LatencyTracker::new(100);
+        tracker.samples.extend(1..=100);
+
+        // p50 lands halfway between 50 and 51, p99 just past 99
+        let stats = tracker.percentiles().unwrap();
+        assert_eq!(stats.p50, 51); // 50.5 rounded
+        assert_eq!(stats.p99, 99); // 99.01 rounded
+        assert_eq!(stats.p9999, 100); // 99.9901 rounded
+        assert_eq!((stats.min, stats.max), (1, 100));
+    }
+
+    #[test]
+    fn test_serialized_rdtsc_never_runs_backwards() {
+        for _ in 0..100_000 {
+            let start = rdtsc_serialized();
+            let end = rdtsc_serialized();
+            assert!(end >= start, "{} < {}", end, start);
+        }
+
+        let mut tracker = LatencyTracker::new(10_000).with_serialized_rdtsc();
+        for _ in 0..10_000 {
+            tracker.record(|| ());
+        }
+        let stats = tracker.percentiles().unwrap();
+        assert!(stats.max < u64::MAX / 2, "wrapped sample: {}", stats.max);
+    }
+
+    #[test]
+    fn test_throughput() {
+        const OPS: usize = 2_000;
+        let mut tracker = LatencyTracker::new(OPS);
+
+        // ~1µs of work per op so loop overhead stays small next to it
+        let started = std::time::Instant::now();
+        for _ in 0..OPS {
+            tracker.record(|| {
+                let mut x = 0u64;
+                for i in 0..1_000 {
+                    x = std::hint::black_box(x.wrapping_add(i));
+                }
+                x
+            });
+        }
+        let elapsed = started.elapsed();
+
+        let summed = tracker.throughput();
+        let wall = tracker.wall_clock_throughput(elapsed);
+        assert!(summed > 0.0 && wall > 0.0);
+        // Gaps between ops and a nominal (not TSC) frequency both skew the
+        // two rates apart, but not by an order of magnitude
+        assert!(wall < summed * 10.0, "wall {} vs summed {}", wall, summed);
+        assert!(wall > summed / 10.0, "wall {} vs summed {}", wall, summed);
+
+        let stats = tracker.percentiles().unwrap();
+        assert!((stats.ops_per_sec - summed).abs() <= summed * 1e-9);
+    }
+
+    #[test]
+    fn test_std_dev_and_cv() {
+        let mut constant = LatencyTracker::new(100);
+        constant.samples.extend([250; 100]);
+        let stats = constant.percentiles().unwrap();
+        assert_eq!(stats.std_dev, 0.0);
+        assert_eq!(stats.cv, 0.0);
+
+        // Population std dev of 1..=n is sqrt((n² - 1) / 12)
+        let mut ramp = LatencyTracker::new(1000);
+        let mut hdr = HdrLatencyTracker::new();
+        ramp.samples.extend(1..=1000);
+        (1..=1000).for_each(|s| hdr.record_cycles(s));
+        let expected = ((1000.0f64 * 1000.0 - 1.0) / 12.0).sqrt();
+
+        let stats = ramp.percentiles().unwrap();
+        assert!((stats.std_dev - expected).abs() < 1e-9);
+        assert!((stats.cv - expected / 500.5).abs() < 1e-12);
+        assert!((hdr.percentiles().unwrap().std_dev - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_warmup_samples_are_discarded() {
+        let mut tracker = LatencyTracker::with_warmup(900, 100);
+        for _ in 0..100 {
+            tracker.record_cycles(1_000_000);
+        }
+        for i in 0..900 {
+            tracker.record_cycles(100 + i % 10);
+        }
+
+        assert_eq!(tracker.len(), 900);
+        let stats = tracker.percentiles().unwrap();
+        assert_eq!((stats.min, stats.max), (100, 109));
+        assert!(stats.p9999 <= 109);
+    }
+
+    #[test]
+    fn test_trimmed_mean_ignores_spikes() {
+        let mut tracker = LatencyTracker::new(1005);
+        tracker.samples.extend((0..1000).map(|i| 200 + i % 100));
+        tracker.samples.extend([50_000_000; 5]);
+
+        let raw = tracker.percentiles().unwrap();
+        let trimmed = tracker.percentiles_trimmed(0.0, 0.99).unwrap();
+        assert!(raw.mean > 10.0 * raw.p50 as f64);
+        assert!((trimmed.mean - raw.p50 as f64).abs() < raw.p50 as f64 * 0.05);
+        assert!(trimmed.std_dev < raw.std_dev);
+        assert_eq!((trimmed.max, trimmed.p9999), (raw.max, raw.p9999));
+    }
+
+    #[test]
+    fn test_merge_trackers() {
+        let mut low = LatencyTracker::from_samples((100..200).collect());
+        let high = LatencyTracker::from_samples((1000..1300).collect());
+        low.merge(high);
+
+        assert_eq!(low.len(), 400);
+        let stats = low.percentiles().unwrap();
+        assert_eq!((stats.min, stats.max), (100, 1299));
+        assert!(stats.p50 >= 1000);
+
+        let mut hdr_low = HdrLatencyTracker::new();
+        let mut hdr_high = HdrLatencyTracker::new();
+        (100..200).for_each(|s| hdr_low.record_cycles(s));
+        (1000..1300).for_each(|s| hdr_high.record_cycles(s));
+        hdr_low.merge(hdr_high);
+
+        assert_eq!(hdr_low.len(), 400);
+        let merged = hdr_low.percentiles().unwrap();
+        assert_eq!((merged.min, merged.max), (100, 1299));
+        assert!((merged.mean - stats.mean).abs() < 1e-9);
+        assert!(merged.p50.abs_diff(stats.p50) <= stats.p50 / 100);
+    }
+
     #[test]
     fn test_empty_tracker() {
-        let mut tracker = LatencyTracker::new(10);
+        let tracker = LatencyTracker::new(10);
 
         // Empty tracker should return None
-        assert!(tracker.precentiles().is_none());
+        assert!(tracker.percentiles().is_none());
         assert!(tracker.is_empty());
         assert_eq!(tracker.len(), 0);
     }
@@ -247,4 +818,40 @@ mod tests {
         assert!(tracker.is_empty());
         assert_eq!(tracker.len(), 0);
     }
+
+    #[test]
+    fn test_hdr_percentiles_match_exact() {
+        let mut exact = LatencyTracker::new(100_000);
+        let mut hdr = HdrLatencyTracker::new();
+
+        // Long-tailed synthetic latencies: mostly a few hundred cycles with
+        // rare spikes into the hundreds of thousands
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        for _ in 0..100_000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let cycles = 100 + (state % 400) + if state.is_multiple_of(1000) { state % 500_000 } else { 0 };
+            exact.samples.push(cycles);
+            hdr.record_cycles(cycles);
+        }
+
+        let e = exact.percentiles().unwrap();
+        let h = hdr.percentiles().unwrap();
+        assert_eq!((h.min, h.max), (e.min, e.max));
+        assert!((h.mean - e.mean).abs() < 1e-6);
+        for (approx, truth) in [
+            (h.p50, e.p50),
+            (h.p95, e.p95),
+            (h.p99, e.p99),
+            (h.p999, e.p999),
+            (h.p9999, e.p9999),
+        ] {
+            assert!(approx.abs_diff(truth) as f64 <= truth as f64 / 100.0, "{} vs {}", approx, truth);
+        }
+        assert_eq!(hdr.len(), 100_000);
+
+        hdr.clear();
+        assert!(hdr.percentiles().is_none());
+    }
 }
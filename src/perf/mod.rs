@@ -1,5 +1,14 @@
+#[cfg(feature = "alloc-stats")]
+pub mod alloc;
 pub mod latency;
 mod rdtsc;
 
-pub use latency::{cycles_to_ns, estimate_cpu_frequency, get_cpu_frequency};
-pub use rdtsc::rdtsc;
+#[cfg(feature = "alloc-stats")]
+pub use alloc::{AllocStats, alloc_stats, count_allocations};
+pub use latency::{
+    FrequencyEstimate, cycles_to_ns, estimate_cpu_frequency, get_cpu_frequency, get_cpu_frequency_calibrated,
+    timing_warning,
+};
+pub use rdtsc::{rdtsc, rdtsc_serialized, rdtscp, tsc_is_invariant};
+#[cfg(target_arch = "aarch64")]
+pub use rdtsc::counter_frequency;
@@ -2,6 +2,8 @@
 ///
 /// Returns the number of CPU cycles since processor reset.
 /// This is the fastest way to measure time on x86/x64.
+/// On aarch64 this reads the virtual counter (CNTVCT_EL0) instead, which
+/// ticks at the fixed rate given by counter_frequency(), not the CPU clock.
 #[inline(always)]
 pub fn rdtsc() -> u64 {
     #[cfg(target_arch = "x86_64")]
@@ -9,7 +11,16 @@ pub fn rdtsc() -> u64 {
         core::arch::x86_64::_rdtsc()
     }
 
-    #[cfg(not(target_arch = "x86_64"))]
+    #[cfg(target_arch = "aarch64")]
+    {
+        let ticks: u64;
+        unsafe {
+            core::arch::asm!("mrs {}, cntvct_el0", out(reg) ticks, options(nomem, nostack));
+        }
+        ticks
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
     {
         // Fallback for non-x86 platforms (uses std::time)
         use std::time::Instant;
@@ -18,3 +29,100 @@ pub fn rdtsc() -> u64 {
         start.elapsed().as_nanos() as u64
     }
 }
+
+/// Frequency of the aarch64 virtual counter in GHz, from CNTFRQ_EL0
+///
+/// Counter ticks divided by this give nanoseconds, exactly as CPU cycles
+/// divided by the clock rate do on x86.
+#[cfg(target_arch = "aarch64")]
+pub fn counter_frequency() -> f64 {
+    let hz: u64;
+    unsafe {
+        core::arch::asm!("mrs {}, cntfrq_el0", out(reg) hz, options(nomem, nostack));
+    }
+    hz as f64 / 1e9
+}
+
+/// Read the Time-Stamp Counter with RDTSCP
+///
+/// RDTSCP waits until all earlier instructions have executed before reading
+/// the counter, so the end of a measured region cannot be read early. Later
+/// instructions may still start before it.
+#[inline(always)]
+pub fn rdtscp() -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let mut aux = 0u32;
+        core::arch::x86_64::__rdtscp(&mut aux)
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        rdtsc()
+    }
+}
+
+/// Read the Time-Stamp Counter fenced by LFENCE on both sides
+///
+/// Neither earlier nor later instructions can be reordered across the read,
+/// so short regions are not skewed by out-of-order execution. The fences add
+/// a fixed cost (tens of cycles, CPU-dependent) to every measured interval;
+/// compare against a no-op baseline measured the same way.
+#[inline(always)]
+pub fn rdtsc_serialized() -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        use core::arch::x86_64::{_mm_lfence, _rdtsc};
+        _mm_lfence();
+        let tsc = _rdtsc();
+        _mm_lfence();
+        tsc
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        rdtsc()
+    }
+}
+
+/// Does the counter rdtsc() reads tick at a constant rate?
+///
+/// On x86_64 this is the invariant TSC flag (CPUID leaf 0x80000007, EDX bit
+/// 8): without it the TSC follows the core clock through frequency changes
+/// and sleep states, so cycle counts do not map linearly to time. The
+/// aarch64 generic timer and the Instant fallback always run at a fixed rate.
+pub fn tsc_is_invariant() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        use core::arch::x86_64::__cpuid;
+        // Leaf 0x80000000 reports the highest extended leaf available
+        let max_extended = __cpuid(0x8000_0000).eax;
+        max_extended >= 0x8000_0007 && __cpuid(0x8000_0007).edx & (1 << 8) != 0
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_is_monotonic() {
+        for _ in 0..100_000 {
+            let first = rdtsc();
+            let second = rdtsc();
+            assert!(second >= first, "{} < {}", second, first);
+        }
+    }
+
+    #[test]
+    fn test_tsc_is_invariant_answers() {
+        let invariant: bool = tsc_is_invariant();
+        // CPUID is stable for the life of the process
+        assert_eq!(invariant, tsc_is_invariant());
+    }
+}
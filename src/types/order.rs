@@ -1,29 +1,105 @@
 use crate::types::price::Price;
 use crate::types::quantity::Quantity;
+use std::fmt;
+use std::num::NonZeroU64;
+use std::str::FromStr;
 
 pub type OrderId = u64;
 
+/// Participant / account an order belongs to, used for self-trade prevention
+pub type AccountId = u32;
+
+/// Account of orders that were never tagged; they never count as self-trades
+pub const NO_ACCOUNT: AccountId = 0;
+
 #[repr(u8)]
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Side {
     Bid,
     Ask,
 }
 
-/// 17 Bytes
-/// Padded with additional 7 bytes due to the largest field alignment
-/// Order is 24 bytes
-#[derive(Clone, Copy)]
+impl Side {
+    /// The side an order on this side trades against
+    pub fn opposite(self) -> Side {
+        match self {
+            Side::Bid => Side::Ask,
+            Side::Ask => Side::Bid,
+        }
+    }
+
+    pub fn all() -> [Side; 2] {
+        [Side::Bid, Side::Ask]
+    }
+}
+
+/// "B" / "S", as used by feeds and the CLI tools
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Bid => write!(f, "B"),
+            Side::Ask => write!(f, "S"),
+        }
+    }
+}
+
+/// Accepts "B"/"S" as well as bid/buy and ask/sell, in any case
+impl FromStr for Side {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "B" | "BID" | "BUY" => Ok(Side::Bid),
+            "S" | "ASK" | "SELL" => Ok(Side::Ask),
+            _ => Err(format!("Unknown side: {:?}", s)),
+        }
+    }
+}
+
+/// How long an order may stay on the book
+/// IOC and FOK orders never rest, so every resting order is GTC
+#[repr(u8)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimeInForce {
+    /// Good-til-canceled: any unfilled remainder rests
+    #[default]
+    GTC,
+    /// Immediate-or-cancel: fill what crosses now, cancel the remainder
+    IOC,
+    /// Fill-or-kill: fill completely on arrival or reject without trading
+    FOK,
+}
+
+/// 38 Bytes
+/// Padded with additional 2 bytes due to the largest field alignment
+/// Order is 40 bytes (48 with the wide-quantity feature's u64 Quantity)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Order {
     // 8 byte
-    // Id serves as a sequencer
     id: OrderId,
+    // 8 byte
+    // Arrival sequence, stamped by the book when the order rests
+    #[cfg_attr(feature = "serde", serde(default))]
+    seq: u64,
     //1 byte
     side: Side,
     // 4 byte
     price: Price,
-    // 4 byte
+    // 4 byte (8 with wide-quantity)
     quantity: Quantity,
+    // 1 byte
+    #[cfg_attr(feature = "serde", serde(default))]
+    time_in_force: TimeInForce,
+    // 4 byte
+    #[cfg_attr(feature = "serde", serde(default))]
+    account: AccountId,
+    // 8 byte: NonZeroU64 gives None the zero niche
+    #[cfg_attr(feature = "serde", serde(default))]
+    expiry: Option<NonZeroU64>,
 }
 
 pub struct IdCounter(u64);
@@ -33,10 +109,40 @@ impl IdCounter {
         Self(0)
     }
 
+    /// Resume issuing ids from `next` (e.g. after restoring a BookState)
+    pub fn starting_at(next: u64) -> Self {
+        Self(next)
+    }
+
+    /// Issue the next id
+    /// Panics once every id has been issued rather than wrapping around and
+    /// handing out duplicates; use try_next to handle exhaustion instead
     pub fn next(&mut self) -> u64 {
+        self.try_next().expect("IdCounter exhausted")
+    }
+
+    /// Issue the next id, or Err once u64::MAX is reached
+    pub fn try_next(&mut self) -> Result<u64, String> {
         let current = self.0;
-        self.0 += 1;
-        current
+        self.0 = current
+            .checked_add(1)
+            .ok_or_else(|| format!("IdCounter exhausted at {}", current))?;
+        Ok(current)
+    }
+
+    /// The id the next call to next() will return, without consuming it
+    pub fn peek(&self) -> u64 {
+        self.0
+    }
+
+    /// The most recently issued id, None if none has been issued yet
+    pub fn current(&self) -> Option<u64> {
+        self.0.checked_sub(1)
+    }
+
+    /// Resume issuing ids from `next`, as starting_at does for a new counter
+    pub fn reset(&mut self, next: u64) {
+        self.0 = next;
     }
 }
 
@@ -46,11 +152,52 @@ impl Order {
 
         Order {
             id,
+            seq: 0,
+            price,
+            quantity,
+            side,
+            time_in_force: TimeInForce::GTC,
+            account: NO_ACCOUNT,
+            expiry: None,
+        }
+    }
+
+    /// Rebuild a resting (GTC) order from its stored fields, keeping the original id
+    /// Used when a book keeps order fields apart (e.g. SoA) and has to hand
+    /// back a whole `Order`
+    pub fn from_parts(id: OrderId, price: Price, quantity: Quantity, side: Side) -> Self {
+        Order {
+            id,
+            seq: 0,
             price,
             quantity,
             side,
+            time_in_force: TimeInForce::GTC,
+            account: NO_ACCOUNT,
+            expiry: None,
         }
     }
+
+    /// Same order with a different time in force (orders default to GTC)
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+
+    /// Same order tagged with the account that placed it
+    pub fn with_account(mut self, account: AccountId) -> Self {
+        self.account = account;
+        self
+    }
+
+    /// Same order set to expire at `expiry`, a caller-defined clock tick (a
+    /// timestamp, session number or sequence): expire_orders removes it
+    /// once called with a `now` at or past it. 0 means no expiry
+    pub fn with_expiry(mut self, expiry: u64) -> Self {
+        self.expiry = NonZeroU64::new(expiry);
+        self
+    }
+
     pub fn id(&self) -> u64 {
         self.id
     }
@@ -63,4 +210,79 @@ impl Order {
     pub fn side(&self) -> Side {
         self.side
     }
+    pub fn time_in_force(&self) -> TimeInForce {
+        self.time_in_force
+    }
+    pub fn account(&self) -> AccountId {
+        self.account
+    }
+    /// Tick at which a resting good-till-date or session order expires;
+    /// None for orders that rest until filled or canceled
+    pub fn expiry(&self) -> Option<u64> {
+        self.expiry.map(NonZeroU64::get)
+    }
+
+    /// Time priority within the book: the order's place in the sequence of
+    /// orders that came to rest, 0 until it rests
+    /// Unlike the id this changes when an amend sends the order to the back
+    /// of a queue, and within a level it increases from front to back
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Used by the books when a resting order is partially filled
+    pub(crate) fn set_quantity(&mut self, quantity: Quantity) {
+        self.quantity = quantity;
+    }
+
+    /// Used by the books when an amend moves a resting order to a new price
+    pub(crate) fn set_price(&mut self, price: Price) {
+        self.price = price;
+    }
+
+    /// Used by the books when an order takes its place in a queue
+    pub(crate) fn set_seq(&mut self, seq: u64) {
+        self.seq = seq;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id_counter_exhaustion() {
+        let mut counter = IdCounter::starting_at(u64::MAX - 2);
+        assert_eq!(counter.current(), Some(u64::MAX - 3));
+        assert_eq!(counter.peek(), u64::MAX - 2);
+        assert_eq!(counter.next(), u64::MAX - 2);
+        assert_eq!(counter.try_next(), Ok(u64::MAX - 1));
+
+        // No id is ever handed out twice
+        assert!(counter.try_next().is_err());
+        assert!(counter.try_next().is_err());
+        assert_eq!(counter.peek(), u64::MAX);
+
+        counter.reset(0);
+        assert_eq!(counter.current(), None);
+        assert_eq!(counter.next(), 0);
+        assert_eq!(counter.current(), Some(0));
+    }
+
+    #[test]
+    fn test_side_opposite_and_all() {
+        assert_eq!(Side::Bid.opposite(), Side::Ask);
+        assert_eq!(Side::Ask.opposite(), Side::Bid);
+        assert_eq!(Side::all(), [Side::Bid, Side::Ask]);
+    }
+
+    #[test]
+    fn test_side_display_round_trip() {
+        for side in Side::all() {
+            assert_eq!(side.to_string().parse::<Side>(), Ok(side));
+        }
+        assert_eq!("b".parse::<Side>(), Ok(Side::Bid));
+        assert_eq!("Sell".parse::<Side>(), Ok(Side::Ask));
+        assert!("X".parse::<Side>().is_err());
+    }
 }
@@ -1,6 +1,23 @@
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Price(u32);
 
+/// How raw prices map to decimals: one raw unit is 10^-decimals
+/// The default grid (prices in cents) is `PriceScale::CENTS`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PriceScale {
+    pub decimals: u32,
+}
+
+impl PriceScale {
+    pub const CENTS: PriceScale = PriceScale { decimals: 2 };
+
+    /// Raw units per whole currency unit
+    pub fn factor(&self) -> u32 {
+        10u32.pow(self.decimals)
+    }
+}
+
 impl Price {
     pub fn define(price: u32) -> Self {
         Self(price)
@@ -9,4 +26,140 @@ impl Price {
     pub fn value(&self) -> u32 {
         self.0
     }
+
+    /// Move up by `n` ticks of the raw price unit, None past u32::MAX
+    pub fn checked_add_ticks(self, n: u32) -> Option<Price> {
+        self.0.checked_add(n).map(Self)
+    }
+
+    /// Move down by `n` ticks of the raw price unit, None below zero
+    pub fn checked_sub_ticks(self, n: u32) -> Option<Price> {
+        self.0.checked_sub(n).map(Self)
+    }
+
+    /// Raw price units between two prices, whichever is higher
+    pub fn distance_to(self, other: Price) -> u32 {
+        self.0.abs_diff(other.0)
+    }
+
+    /// Raw price units from this bid up to `ask`: 0 when locked, None when
+    /// crossed
+    pub fn spread_to(self, ask: Price) -> Option<u32> {
+        ask.0.checked_sub(self.0)
+    }
+
+    /// Twice the exact midpoint of two prices, i.e. their sum, in raw units
+    /// Odd when the midpoint falls between two raw units
+    pub fn midpoint_half_ticks(self, other: Price) -> u64 {
+        self.0 as u64 + other.0 as u64
+    }
+
+    /// Decimal value under `scale`, e.g. 5000 -> 50.0 in cents
+    pub fn to_decimal(self, scale: PriceScale) -> f64 {
+        self.0 as f64 / scale.factor() as f64
+    }
+
+    /// Raw price for a decimal under `scale`, e.g. 50.0 -> 5000 in cents
+    /// None if the decimal is negative, out of range, or finer than one raw
+    /// unit (50.005 in cents)
+    pub fn from_decimal(decimal: f64, scale: PriceScale) -> Option<Price> {
+        let raw = decimal * scale.factor() as f64;
+        let units = raw.round();
+        // Allow for binary rounding of decimals like 0.1, nothing coarser
+        if !(0.0..=u32::MAX as f64).contains(&units) || (raw - units).abs() > 1e-6 {
+            return None;
+        }
+        Some(Self(units as u32))
+    }
+
+    /// Exact decimal rendering under `scale`, e.g. "50.00" for 5000 in cents
+    pub fn format_decimal(self, scale: PriceScale) -> String {
+        let factor = scale.factor();
+        if scale.decimals == 0 {
+            return self.0.to_string();
+        }
+        format!(
+            "{}.{:0width$}",
+            self.0 / factor,
+            self.0 % factor,
+            width = scale.decimals as usize
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick_arithmetic_at_bounds() {
+        let top = Price::define(u32::MAX);
+        let bottom = Price::define(0);
+
+        assert_eq!(top.checked_add_ticks(1), None);
+        assert_eq!(top.checked_add_ticks(0), Some(top));
+        assert_eq!(bottom.checked_sub_ticks(1), None);
+        assert_eq!(bottom.checked_sub_ticks(0), Some(bottom));
+        assert_eq!(
+            Price::define(5000).checked_add_ticks(5),
+            Some(Price::define(5005))
+        );
+        assert_eq!(
+            Price::define(5000).checked_sub_ticks(5000),
+            Some(bottom)
+        );
+    }
+
+    #[test]
+    fn test_distance_and_ordering() {
+        let bid = Price::define(4999);
+        let ask = Price::define(5003);
+
+        assert_eq!(bid.distance_to(ask), 4);
+        assert_eq!(ask.distance_to(bid), 4);
+        assert_eq!(
+            Price::define(0).distance_to(Price::define(u32::MAX)),
+            u32::MAX
+        );
+        assert!(bid < ask);
+        assert_eq!(bid.max(ask), ask);
+    }
+
+    #[test]
+    fn test_spread_and_midpoint() {
+        let (bid, ask) = (Price::define(4999), Price::define(5002));
+        assert_eq!(bid.spread_to(ask), Some(3));
+        assert_eq!(bid.midpoint_half_ticks(ask), 10001);
+
+        // Locked
+        assert_eq!(ask.spread_to(ask), Some(0));
+        assert_eq!(ask.midpoint_half_ticks(ask), 10004);
+
+        // Crossed: no spread rather than a wrapped u32
+        assert_eq!(ask.spread_to(bid), None);
+        assert_eq!(ask.midpoint_half_ticks(bid), 10001);
+
+        // The sum of two top-of-range prices doesn't overflow
+        let top = Price::define(u32::MAX);
+        assert_eq!(top.midpoint_half_ticks(top), 2 * u32::MAX as u64);
+    }
+
+    #[test]
+    fn test_decimal_round_trip() {
+        let cents = PriceScale::CENTS;
+        assert_eq!(Price::define(5000).to_decimal(cents), 50.0);
+        assert_eq!(Price::from_decimal(50.00, cents), Some(Price::define(5000)));
+        assert_eq!(Price::from_decimal(0.07, cents), Some(Price::define(7)));
+        assert_eq!(Price::define(5000).format_decimal(cents), "50.00");
+        assert_eq!(Price::define(7).format_decimal(cents), "0.07");
+
+        // Finer than a cent, negative, or too large for a u32
+        assert_eq!(Price::from_decimal(50.005, cents), None);
+        assert_eq!(Price::from_decimal(-1.0, cents), None);
+        assert_eq!(Price::from_decimal(1e9, cents), None);
+
+        let whole = PriceScale { decimals: 0 };
+        assert_eq!(Price::from_decimal(42.0, whole), Some(Price::define(42)));
+        assert_eq!(Price::define(42).format_decimal(whole), "42");
+    }
 }
@@ -1,12 +1,78 @@
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct Quantity(u32);
+/// Integer backing a Quantity: u32 by default, u64 with the wide-quantity
+/// feature for instruments whose sizes run past ~4.29 billion units
+#[cfg(not(feature = "wide-quantity"))]
+pub type QtyValue = u32;
+#[cfg(feature = "wide-quantity")]
+pub type QtyValue = u64;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quantity(QtyValue);
 
 impl Quantity {
-    pub fn define(quantity: u32) -> Self {
+    pub fn define(quantity: QtyValue) -> Self {
         Self(quantity)
     }
 
-    pub fn value(&self) -> u32 {
+    pub fn value(&self) -> QtyValue {
         self.0
     }
+
+    /// Widened for totals and products, whichever QtyValue is
+    // The conversion is the identity under wide-quantity
+    #[allow(clippy::useless_conversion)]
+    pub fn as_u64(&self) -> u64 {
+        u64::from(self.0)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Subtract, clamping at zero instead of underflowing
+    pub fn saturating_sub(self, other: Quantity) -> Quantity {
+        Self(self.0.saturating_sub(other.0))
+    }
+
+    /// Add, returning None on QtyValue overflow
+    pub fn checked_add(self, other: Quantity) -> Option<Quantity> {
+        self.0.checked_add(other.0).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saturating_sub_clamps_at_zero() {
+        let a = Quantity::define(50);
+        let b = Quantity::define(100);
+
+        assert_eq!(a.saturating_sub(b), Quantity::define(0));
+        assert!(a.saturating_sub(b).is_zero());
+        assert!(a.saturating_sub(a).is_zero());
+        assert_eq!(b.saturating_sub(a), Quantity::define(50));
+    }
+
+    #[test]
+    fn test_checked_add_overflow() {
+        let max = Quantity::define(QtyValue::MAX);
+
+        assert_eq!(max.checked_add(Quantity::define(1)), None);
+        assert_eq!(
+            Quantity::define(1).checked_add(Quantity::define(2)),
+            Some(Quantity::define(3))
+        );
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert!(Quantity::define(100) >= Quantity::define(100));
+        assert!(Quantity::define(100) > Quantity::define(99));
+        assert_eq!(
+            Quantity::define(7).min(Quantity::define(3)),
+            Quantity::define(3)
+        );
+    }
 }
@@ -0,0 +1,106 @@
+use crate::orderbook::{BookConfig, OrderbookTrait};
+use crate::types::order::{AccountId, IdCounter, NO_ACCOUNT, Order, OrderId, Side};
+use crate::types::price::Price;
+use crate::types::quantity::{QtyValue, Quantity};
+
+/// Fixture for a specific resting book, no randomness involved
+///
+/// `BookBuilder::new().bid(5000, 100).ask(5001, 200).ask(5001, 50).build::<O>()`
+/// adds the orders in the order given, so orders at one price queue in that
+/// order. Ids are issued from 0 in the same order and returned alongside the
+/// book: `ids[i]` is the id of the i-th order specified.
+#[derive(Debug, Clone, Default)]
+pub struct BookBuilder {
+    config: BookConfig,
+    orders: Vec<(Side, u32, QtyValue, AccountId)>,
+}
+
+impl BookBuilder {
+    /// Empty book on the default price grid
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build on `config` instead of the default grid
+    pub fn with_config(mut self, config: BookConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Rest a bid of `qty` at `price`
+    pub fn bid(self, price: u32, qty: QtyValue) -> Self {
+        self.order(Side::Bid, price, qty, NO_ACCOUNT)
+    }
+
+    /// Rest an ask of `qty` at `price`
+    pub fn ask(self, price: u32, qty: QtyValue) -> Self {
+        self.order(Side::Ask, price, qty, NO_ACCOUNT)
+    }
+
+    /// Rest an order tagged with `account` (for STP fixtures)
+    pub fn order(mut self, side: Side, price: u32, qty: QtyValue, account: AccountId) -> Self {
+        self.orders.push((side, price, qty, account));
+        self
+    }
+
+    /// Add every order to a new `O`, returning it with the ids in spec order
+    /// Panics if an order is rejected or would trade: a fixture must describe
+    /// a valid, uncrossed book
+    pub fn build<O: OrderbookTrait>(&self) -> (O, Vec<OrderId>) {
+        let mut book = O::with_config(self.config).expect("BookBuilder config must be valid");
+        let mut counter = IdCounter::new();
+
+        let ids = self
+            .orders
+            .iter()
+            .map(|&(side, price, qty, account)| {
+                let order = Order::new(Price::define(price), Quantity::define(qty), side, &mut counter)
+                    .with_account(account);
+                let result = book
+                    .add_order(order)
+                    .unwrap_or_else(|e| panic!("BookBuilder order {} rejected: {}", order.id(), e));
+                assert!(
+                    result.fills.is_empty(),
+                    "BookBuilder order {} crosses the book at {}",
+                    order.id(),
+                    price
+                );
+                order.id()
+            })
+            .collect();
+
+        (book, ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::fixed_tick::orderbook::Orderbook as FixedTickOrderbook;
+    use crate::orderbook::tree::orderbook::Orderbook as TreeOrderbook;
+
+    #[test]
+    fn test_built_book_matches_spec() {
+        let spec = BookBuilder::new().bid(5000, 100).ask(5001, 200).ask(5001, 50).bid(4990, 30);
+        let (book, ids) = spec.build::<TreeOrderbook>();
+
+        assert_eq!(ids, vec![0, 1, 2, 3]);
+        let snapshot = book.book_snapshot(10);
+        assert_eq!(snapshot.bids, vec![(Price::define(5000), 100), (Price::define(4990), 30)]);
+        assert_eq!(snapshot.asks, vec![(Price::define(5001), 250)]);
+
+        // Orders at one price queue in spec order
+        assert_eq!(book.get_order(ids[1]).map(|view| view.queue_position), Some(0));
+        assert_eq!(book.get_order(ids[2]).map(|view| view.queue_position), Some(1));
+
+        // Any implementation, same book
+        let (fixed, _) = spec.build::<FixedTickOrderbook>();
+        assert_eq!(fixed.book_snapshot(10), snapshot);
+    }
+
+    #[test]
+    #[should_panic(expected = "crosses the book")]
+    fn test_crossing_spec_panics() {
+        BookBuilder::new().ask(5000, 10).bid(5000, 10).build::<TreeOrderbook>();
+    }
+}
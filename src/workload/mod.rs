@@ -0,0 +1,239 @@
+// Seeded price distributions shared by the scenario examples
+use crate::orderbook::command::Command;
+use crate::types::order::{IdCounter, Order, Side};
+use crate::types::price::Price;
+use crate::types::quantity::{QtyValue, Quantity};
+use rand::SeedableRng;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use rand_distr::Zipf;
+
+/// Centre of every distribution below
+pub const MID_PRICE: u32 = 5_000;
+/// Highest price any generator emits
+pub const MAX_PRICE: u32 = 9_999;
+/// Size of every order the command stream adds
+pub const ORDER_QUANTITY: QtyValue = 100;
+
+/// A seeded source of prices around MID_PRICE
+pub trait WorkloadGenerator {
+    /// Next raw price in ticks; either side of the mid
+    fn next_price(&mut self) -> u32;
+
+    /// Next price folded onto `side`'s half of the book so the order rests
+    fn next_resting_price(&mut self, side: Side) -> u32 {
+        resting_price(self.next_price(), side)
+    }
+
+    /// Endless stream of adds alternating bid/ask, starting with a bid, at
+    /// resting prices with ids from 0
+    fn commands(&mut self) -> Commands<'_, Self>
+    where
+        Self: Sized,
+    {
+        Commands {
+            generator: self,
+            ids: IdCounter::new(),
+            side: Side::Bid,
+        }
+    }
+}
+
+/// Iterator returned by WorkloadGenerator::commands
+pub struct Commands<'a, G> {
+    generator: &'a mut G,
+    ids: IdCounter,
+    side: Side,
+}
+
+impl<G: WorkloadGenerator> Iterator for Commands<'_, G> {
+    type Item = Command;
+
+    fn next(&mut self) -> Option<Command> {
+        let side = self.side;
+        self.side = side.opposite();
+        let price = self.generator.next_resting_price(side);
+        Some(Command::Add(Order::new(
+            Price::define(price),
+            Quantity::define(ORDER_QUANTITY),
+            side,
+            &mut self.ids,
+        )))
+    }
+}
+
+/// Fold a price onto the resting side of MID_PRICE (bids below, asks at or
+/// above) so adds rest instead of crossing and trading on arrival
+pub fn resting_price(price: u32, side: Side) -> u32 {
+    match side {
+        Side::Bid if price >= MID_PRICE => (2 * MID_PRICE - 1 - price).max(1),
+        Side::Ask if price < MID_PRICE => 2 * MID_PRICE - 1 - price,
+        _ => price,
+    }
+}
+
+/// Every price in [1, 10_000) equally likely
+pub struct UniformGenerator {
+    rng: StdRng,
+}
+
+impl UniformGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl WorkloadGenerator for UniformGenerator {
+    fn next_price(&mut self) -> u32 {
+        self.rng.random_range(1..=MAX_PRICE)
+    }
+}
+
+/// CLUSTER_PROBABILITY of prices within ±CLUSTER_RADIUS of the mid, the rest
+/// anywhere in the range
+pub struct ClusteredGenerator {
+    rng: StdRng,
+}
+
+impl ClusteredGenerator {
+    pub const CLUSTER_RADIUS: u32 = 10;
+    pub const CLUSTER_PROBABILITY: f64 = 0.90;
+
+    pub fn new(seed: u64) -> Self {
+        Self { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl WorkloadGenerator for ClusteredGenerator {
+    fn next_price(&mut self) -> u32 {
+        if self.rng.random_bool(Self::CLUSTER_PROBABILITY) {
+            let offset = self.rng.random_range(0..=Self::CLUSTER_RADIUS * 2);
+            MID_PRICE - Self::CLUSTER_RADIUS + offset
+        } else {
+            self.rng.random_range(1..=MAX_PRICE)
+        }
+    }
+}
+
+/// Zipf-distributed ranks over PRICE_LEVELS prices: rank 1 is the mid, then
+/// mid+1, mid-1, mid+2, ... so popularity falls off as 1/distance
+pub struct ZipfianGenerator {
+    rng: StdRng,
+    zipf: Zipf<f64>,
+}
+
+impl ZipfianGenerator {
+    pub const PRICE_LEVELS: u32 = 200;
+    pub const EXPONENT: f64 = 1.0;
+
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            zipf: Zipf::new(Self::PRICE_LEVELS as f64, Self::EXPONENT).expect("Invalid Zipf parameters"),
+        }
+    }
+
+    /// Price a sampled rank maps to
+    pub fn price_for_rank(rank: u32) -> u32 {
+        let half = (rank / 2) as i64;
+        let offset = if rank.is_multiple_of(2) { half } else { -half };
+        (MID_PRICE as i64 + offset).clamp(1, MAX_PRICE as i64) as u32
+    }
+}
+
+impl WorkloadGenerator for ZipfianGenerator {
+    fn next_price(&mut self) -> u32 {
+        Self::price_for_rank(self.zipf.sample(&mut self.rng) as u32)
+    }
+}
+
+/// Repeating cycles of BURST_SIZE prices tight around a slowly drifting centre,
+/// then QUIET_SIZE prices spread wide around the mid
+pub struct BurstyGenerator {
+    rng: StdRng,
+    emitted: usize,
+}
+
+impl BurstyGenerator {
+    pub const BURST_SIZE: usize = 500;
+    /// Burst prices land in [centre - 10, centre + 10)
+    pub const BURST_PRICE_RANGE: u32 = 20;
+    pub const QUIET_SIZE: usize = 50;
+    /// Quiet prices land in [mid - 1000, mid + 1000)
+    pub const QUIET_PRICE_RANGE: u32 = 2_000;
+
+    pub fn new(seed: u64) -> Self {
+        Self { rng: StdRng::seed_from_u64(seed), emitted: 0 }
+    }
+}
+
+impl WorkloadGenerator for BurstyGenerator {
+    fn next_price(&mut self) -> u32 {
+        let cycle_len = Self::BURST_SIZE + Self::QUIET_SIZE;
+        let cycle = self.emitted / cycle_len;
+        let in_burst = self.emitted % cycle_len < Self::BURST_SIZE;
+        self.emitted += 1;
+
+        let (centre, range) = if in_burst {
+            // Drift the burst centre by 10 ticks a cycle, wrapping every 10
+            (MID_PRICE + (cycle as u32 % 10) * 10, Self::BURST_PRICE_RANGE)
+        } else {
+            (MID_PRICE, Self::QUIET_PRICE_RANGE)
+        };
+        let offset = self.rng.random_range(0..range);
+        (centre - range / 2 + offset).clamp(1, MAX_PRICE)
+    }
+}
+
+pub mod builder;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_zipf_rank_one_is_mid() {
+        assert_eq!(ZipfianGenerator::price_for_rank(1), MID_PRICE);
+        assert_eq!(ZipfianGenerator::price_for_rank(2), MID_PRICE + 1);
+        assert_eq!(ZipfianGenerator::price_for_rank(3), MID_PRICE - 1);
+
+        // And the mid is the most popular price actually sampled
+        let mut generator = ZipfianGenerator::new(7);
+        let mut counts = HashMap::new();
+        for _ in 0..10_000 {
+            *counts.entry(generator.next_price()).or_insert(0) += 1;
+        }
+        let (&top, _) = counts.iter().max_by_key(|&(_, count)| *count).unwrap();
+        assert_eq!(top, MID_PRICE);
+    }
+
+    #[test]
+    fn test_clustered_stays_within_radius() {
+        let mut generator = ClusteredGenerator::new(7);
+        let samples = 20_000;
+        let inside = (0..samples)
+            .map(|_| generator.next_price())
+            .filter(|p| p.abs_diff(MID_PRICE) <= ClusteredGenerator::CLUSTER_RADIUS)
+            .count();
+
+        let share = inside as f64 / samples as f64;
+        assert!((share - ClusteredGenerator::CLUSTER_PROBABILITY).abs() < 0.02, "{}", share);
+    }
+
+    #[test]
+    fn test_commands_alternate_sides_and_rest() {
+        let commands: Vec<Command> = UniformGenerator::new(7).commands().take(100).collect();
+
+        for (i, command) in commands.iter().enumerate() {
+            let Command::Add(order) = command else {
+                panic!("Expected an add, got {:?}", command);
+            };
+            assert_eq!(order.id(), i as u64);
+            match order.side() {
+                Side::Bid => assert!(i % 2 == 0 && order.price().value() < MID_PRICE),
+                Side::Ask => assert!(i % 2 == 1 && order.price().value() >= MID_PRICE),
+            }
+        }
+    }
+}
@@ -1,43 +1,64 @@
 /// Cross-implementation correctness tests
 ///
 /// Every correctness claim in the thesis ("Hybrid is 27× faster than FixedTick
-/// for market orders") is only meaningful if all four implementations produce
+/// for market orders") is only meaningful if all implementations produce
 /// identical observable results. These tests enforce that.
 ///
 /// Strategy:
 ///   1. Deterministic tests — fixed operation sequences, assert exact outputs.
-///   2. Proptest — randomly generated sequences; all four impls must agree.
+///   2. Proptest — randomly generated sequences; all impls must agree,
+///      on the final outcome and op by op when run in lockstep.
 ///
 /// What we compare (implementation-independent observables):
 ///   - best_bid() and best_ask() after each mutation
-///   - Fills from execute_market_order(), normalised to qty-per-price-level
+///   - Fills from add_order() and execute_market_order(), normalised to
+///     qty-per-price-level
 ///     (individual Fill structs may differ across impls if one level is split
 ///     into multiple fills — the qty per price must still agree)
 
+use orderbook::benchmark::{replay_commands, Distribution};
+use orderbook::orderbook::arena::orderbook::Orderbook as Arena;
+use orderbook::orderbook::auto::{AutoMode, AutoOrderbook};
 use orderbook::orderbook::fixed_tick::orderbook::Orderbook as FixedTick;
+use orderbook::orderbook::command::{Command, CommandQueue, EngineEvent};
+use orderbook::orderbook::error::OrderError;
 use orderbook::orderbook::hybrid::orderbook::Orderbook as Hybrid;
+use orderbook::orderbook::journal::{replay, JournaledBook, MemoryJournal};
+use orderbook::orderbook::level::PriceLevel;
+use orderbook::orderbook::market_data::{LevelUpdate, MarketDataBook, TopOfBook};
+use orderbook::orderbook::shared::SharedOrderbook;
 use orderbook::orderbook::tree::orderbook::Orderbook as Tree;
-use orderbook::orderbook::SoA::orderbook::Orderbook as SoA;
-use orderbook::orderbook::{Fill, OrderbookTrait};
-use orderbook::types::order::{IdCounter, Order, OrderId, Side};
-use orderbook::types::price::Price;
-use orderbook::types::quantity::Quantity;
+use orderbook::orderbook::windowed::WindowedArrayOrderbook as Windowed;
+use orderbook::orderbook::SoA::orderbook::{LevelSoA, Orderbook as SoA};
+use orderbook::orderbook::{
+    BookConfig, BookSnapshot, BookState, BookStats, Fill, MatchMode, OrderView, OrderbookTrait, StpMode,
+};
+use orderbook::types::order::{IdCounter, Order, OrderId, Side, TimeInForce};
+use orderbook::types::price::{Price, PriceScale};
+use orderbook::types::quantity::{QtyValue, Quantity};
+use orderbook::workload::builder::BookBuilder;
+use orderbook::workload::{ClusteredGenerator, UniformGenerator, WorkloadGenerator};
 use proptest::prelude::*;
+use std::cell::RefCell;
 use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// Id carried by market orders in these tests; never used by a resting order.
+const TAKER_ID: OrderId = 1_000_000;
 
 // ─── Normalised fills ─────────────────────────────────────────────────────────
 
 /// Total quantity consumed per price level — order-independent.
 #[derive(Debug, PartialEq, Eq)]
 struct NormFills {
-    by_price: BTreeMap<u32, u32>,
-    total_qty: u32,
+    by_price: BTreeMap<u32, QtyValue>,
+    total_qty: QtyValue,
 }
 
 impl NormFills {
     fn from(fills: Vec<Fill>) -> Self {
         let mut by_price = BTreeMap::new();
-        let mut total_qty = 0u32;
+        let mut total_qty: QtyValue = 0;
         for f in fills {
             *by_price.entry(f.price.value()).or_insert(0) += f.quantity.value();
             total_qty += f.quantity.value();
@@ -48,6 +69,13 @@ impl NormFills {
     fn empty() -> Self {
         Self { by_price: BTreeMap::new(), total_qty: 0 }
     }
+
+    fn extend(&mut self, fills: Vec<Fill>) {
+        for f in fills {
+            *self.by_price.entry(f.price.value()).or_insert(0) += f.quantity.value();
+            self.total_qty += f.quantity.value();
+        }
+    }
 }
 
 // ─── Operation runner ─────────────────────────────────────────────────────────
@@ -55,10 +83,10 @@ impl NormFills {
 /// A single logical operation applied to the book.
 #[derive(Debug, Clone)]
 enum Op {
-    Add { side: Side, price: u32, qty: u32 },
+    Add { side: Side, price: u32, qty: QtyValue },
     /// Cancel the order at position `idx % active_len`. Safe even on empty book.
     Cancel { idx: usize },
-    Market { side: Side, qty: u32 },
+    Market { side: Side, qty: QtyValue },
 }
 
 /// Observable state collected over a run.
@@ -75,7 +103,11 @@ struct Outcome {
 /// active orders by position (idx % len), so the same logical order is cancelled
 /// regardless of which internal IDs the counter assigned.
 fn run<O: OrderbookTrait>(ops: &[Op]) -> Outcome {
-    let mut book = O::new();
+    run_on(O::new(), ops)
+}
+
+/// run, on a book built some other way than O::new().
+fn run_on<O: OrderbookTrait>(mut book: O, ops: &[Op]) -> Outcome {
     let mut counter = IdCounter::new();
     let mut active: Vec<OrderId> = Vec::new();
     let mut fills = NormFills::empty();
@@ -90,8 +122,12 @@ fn run<O: OrderbookTrait>(ops: &[Op]) -> Outcome {
                     &mut counter,
                 );
                 let id = order.id();
-                if book.add_order(order).is_ok() {
-                    active.push(id);
+                if let Ok(result) = book.add_order(order) {
+                    // Only orders with a resting remainder can be cancelled later
+                    if !result.rested_qty.is_zero() {
+                        active.push(id);
+                    }
+                    fills.extend(result.fills);
                 }
             }
             Op::Cancel { idx } => {
@@ -104,17 +140,14 @@ fn run<O: OrderbookTrait>(ops: &[Op]) -> Outcome {
                 }
             }
             Op::Market { side, qty } => {
-                if let Ok(f) = book.execute_market_order(*side, Quantity::define(*qty)) {
-                    for fill in f {
-                        *fills.by_price.entry(fill.price.value()).or_insert(0) +=
-                            fill.quantity.value();
-                        fills.total_qty += fill.quantity.value();
-                    }
-                }
+                let result = book.execute_market_order(*side, Quantity::define(*qty), TAKER_ID);
+                fills.extend(result.fills);
             }
         }
     }
 
+    assert_eq!(book.validate(), Ok(()));
+
     Outcome {
         best_bid: book.best_bid().map(|p| p.value()),
         best_ask: book.best_ask().map(|p| p.value()),
@@ -129,6 +162,20 @@ fn run_all(ops: &[Op]) -> (Outcome, Outcome, Outcome, Outcome) {
 
 // ─── Deterministic tests ──────────────────────────────────────────────────────
 
+/// Call `check::<O>()` once for every book implementation, so each generic
+/// check covers all of them.
+macro_rules! for_each_book {
+    ($check:ident) => {
+        $check::<Tree>();
+        $check::<FixedTick>();
+        $check::<SoA>();
+        $check::<Hybrid>();
+        $check::<Arena>();
+        $check::<Windowed>();
+        $check::<AutoOrderbook>();
+    };
+}
+
 #[test]
 fn empty_book() {
     let ops: Vec<Op> = vec![];
@@ -251,93 +298,2611 @@ fn market_order_sweeps_multiple_levels() {
     assert_eq!(tree, hybrid, "multi-level sweep: tree vs hybrid");
 }
 
-// Partial fills (market qty < a single resting order's qty) are not implemented
-// in any of the four orderbooks — all panic at that path. This is a known
-// limitation documented in the thesis; the correctness tests cover only full fills.
+#[test]
+fn market_order_partially_fills_resting_order() {
+    // Market qty smaller than the resting order: the maker stays on the book
+    // with its quantity reduced.
+    let ops = vec![
+        Op::Add { side: Side::Ask, price: 5001, qty: 100 },
+        Op::Market { side: Side::Bid, qty: 40 },
+        Op::Market { side: Side::Bid, qty: 60 },
+    ];
+    let (tree, fixed, soa, hybrid) = run_all(&ops);
+    assert_eq!(tree.fills.total_qty, 100);
+    assert_eq!(tree.best_ask, None);
+    assert_eq!(tree, fixed, "partial maker fill: tree vs fixed");
+    assert_eq!(tree, soa,   "partial maker fill: tree vs soa");
+    assert_eq!(tree, hybrid, "partial maker fill: tree vs hybrid");
+}
+
+/// Market order larger than the book: fills what exists, reports the rest.
+fn check_insufficient_liquidity<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    for price in [5001, 5002] {
+        let order = Order::new(Price::define(price), Quantity::define(100), Side::Ask, &mut counter);
+        book.add_order(order).unwrap();
+    }
+
+    let result = book.execute_market_order(Side::Bid, Quantity::define(250), TAKER_ID);
+    assert_eq!(result.fills.len(), 2);
+    assert_eq!(result.filled_qty, Quantity::define(200));
+    assert_eq!(result.remaining_qty, Quantity::define(50));
+    assert!(!result.is_fully_filled());
+    assert_eq!(book.best_ask(), None);
+}
+
+/// Fill-or-kill variant must reject without consuming anything.
+fn check_market_order_or_reject<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    for price in [5001, 5002] {
+        let order = Order::new(Price::define(price), Quantity::define(100), Side::Ask, &mut counter);
+        book.add_order(order).unwrap();
+    }
+
+    assert!(book.execute_market_order_or_reject(Side::Bid, Quantity::define(250), TAKER_ID).is_err());
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 100);
+    assert_eq!(book.depth_at_price(Price::define(5002), Side::Ask), 100);
+
+    let result = book
+        .execute_market_order_or_reject(Side::Bid, Quantity::define(150), TAKER_ID)
+        .expect("enough liquidity for 150");
+    assert_eq!(result.filled_qty, Quantity::define(150));
+    assert_eq!(book.depth_at_price(Price::define(5002), Side::Ask), 50);
+}
+
+#[test]
+fn market_order_reports_unfilled_remainder() {
+    for_each_book!(check_insufficient_liquidity);
+}
 
 #[test]
-fn book_invariant_no_crossed_book() {
-    // These implementations are pure data structures — they do not auto-match
-    // crossing orders. best_bid < best_ask holds when bid and ask prices don't
-    // cross; we verify it for a typical non-crossing spread.
+fn market_order_or_reject_is_atomic() {
+    for_each_book!(check_market_order_or_reject);
+}
+
+/// A limit sweep takes every level up to and including its limit, reports
+/// the rest unfilled, and never rests it.
+fn check_limit_sweep<O: OrderbookTrait>() {
+    let (mut book, _) = BookBuilder::new().ask(5001, 100).ask(5002, 100).ask(5003, 100).bid(4990, 100).build::<O>();
+
+    let result = book
+        .execute_limit_sweep(Side::Bid, Quantity::define(250), Price::define(5002), TAKER_ID)
+        .unwrap();
+    assert_eq!(NormFills::from(result.fills), NormFills {
+        by_price: BTreeMap::from([(5001, 100), (5002, 100)]),
+        total_qty: 200,
+    });
+    assert_eq!(result.filled_qty, Quantity::define(200));
+    assert_eq!(result.remaining_qty, Quantity::define(50));
+    assert_eq!(book.best_ask(), Some(Price::define(5003)));
+    assert_eq!(book.best_bid(), Some(Price::define(4990)));
+    assert!(book.get_order(TAKER_ID).is_none());
+
+    // Nothing at or better than the limit: no fills, nothing rests
+    let result = book
+        .execute_limit_sweep(Side::Ask, Quantity::define(50), Price::define(4995), TAKER_ID)
+        .unwrap();
+    assert!(result.fills.is_empty());
+    assert_eq!(result.remaining_qty, Quantity::define(50));
+    assert_eq!(book.best_ask(), Some(Price::define(5003)));
+
+    // An off-grid limit is rejected before anything trades
+    assert!(book.execute_limit_sweep(Side::Bid, Quantity::define(10), Price::define(0), TAKER_ID).is_err());
+    assert_eq!(book.depth_at_price(Price::define(5003), Side::Ask), 100);
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn limit_sweep_stops_at_its_limit() {
+    for_each_book!(check_limit_sweep);
+}
+
+/// Slippage is the VWAP's distance from the arrival mid, signed so a worse
+/// fill is positive on either side; with no mid the order still executes.
+fn check_measured_market_order<O: OrderbookTrait>() {
+    let (mut book, _) = BookBuilder::new().bid(4999, 10).ask(5001, 10).ask(5003, 30).build::<O>();
+
+    let (result, report) = book.execute_market_order_measured(Side::Bid, Quantity::define(20), TAKER_ID);
+    assert_eq!(result.filled_qty, Quantity::define(20));
+    assert_eq!(report.arrival_mid, Some(5000.0));
+    assert_eq!(report.vwap, Some(5002.0));
+    assert_eq!(report.slippage_ticks, Some(report.vwap.unwrap() - report.arrival_mid.unwrap()));
+
+    // Mid is now (4999 + 5003) / 2; selling at 4999 is 2 ticks worse
+    let (_, report) = book.execute_market_order_measured(Side::Ask, Quantity::define(5), TAKER_ID);
+    assert_eq!((report.arrival_mid, report.vwap, report.slippage_ticks), (Some(5001.0), Some(4999.0), Some(2.0)));
+
+    // Only asks left: no mid, but the order still trades
+    book.cancel_all_for_side(Side::Bid);
+    let (result, report) = book.execute_market_order_measured(Side::Bid, Quantity::define(5), TAKER_ID);
+    assert_eq!(result.filled_qty, Quantity::define(5));
+    assert_eq!((report.arrival_mid, report.vwap, report.slippage_ticks), (None, Some(5003.0), None));
+}
+
+#[test]
+fn measured_market_order_reports_slippage() {
+    for_each_book!(check_measured_market_order);
+}
+
+#[test]
+fn limit_order_fully_crosses() {
+    // Bid priced through two ask levels takes both and never rests.
     let ops = vec![
-        Op::Add { side: Side::Bid, price: 4999, qty: 100 },
         Op::Add { side: Side::Ask, price: 5001, qty: 100 },
+        Op::Add { side: Side::Ask, price: 5002, qty: 100 },
+        Op::Add { side: Side::Bid, price: 5002, qty: 150 },
     ];
     let (tree, fixed, soa, hybrid) = run_all(&ops);
-    for outcome in [&tree, &fixed, &soa, &hybrid] {
-        if let (Some(bid), Some(ask)) = (outcome.best_bid, outcome.best_ask) {
-            assert!(bid < ask, "crossed book: bid={} ask={}", bid, ask);
-        }
+    assert_eq!(tree.fills.total_qty, 150);
+    assert_eq!(tree.fills.by_price[&5001], 100);
+    assert_eq!(tree.fills.by_price[&5002], 50);
+    assert_eq!(tree.best_bid, None);
+    assert_eq!(tree.best_ask, Some(5002));
+    assert_eq!(tree, fixed, "full cross: tree vs fixed");
+    assert_eq!(tree, soa,   "full cross: tree vs soa");
+    assert_eq!(tree, hybrid, "full cross: tree vs hybrid");
+}
+
+#[test]
+fn limit_order_partially_crosses_and_rests() {
+    // Ask at 5001 is taken; 5003 is beyond the limit so the remainder rests at 5002.
+    let ops = vec![
+        Op::Add { side: Side::Ask, price: 5001, qty: 100 },
+        Op::Add { side: Side::Ask, price: 5003, qty: 100 },
+        Op::Add { side: Side::Bid, price: 5002, qty: 250 },
+    ];
+    let (tree, fixed, soa, hybrid) = run_all(&ops);
+    assert_eq!(tree.fills.total_qty, 100);
+    assert_eq!(tree.best_bid, Some(5002));
+    assert_eq!(tree.best_ask, Some(5003));
+    assert_eq!(tree, fixed, "partial cross: tree vs fixed");
+    assert_eq!(tree, soa,   "partial cross: tree vs soa");
+    assert_eq!(tree, hybrid, "partial cross: tree vs hybrid");
+}
+
+/// The resting remainder of a crossing order keeps the reduced quantity.
+fn check_crossing_remainder_depth<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    let ask = Order::new(Price::define(5001), Quantity::define(100), Side::Ask, &mut counter);
+    assert!(book.add_order(ask).unwrap().fills.is_empty());
+
+    let bid = Order::new(Price::define(5002), Quantity::define(250), Side::Bid, &mut counter);
+    let result = book.add_order(bid).unwrap();
+    assert_eq!(result.fills.len(), 1);
+    assert_eq!(result.fills[0].price, Price::define(5001));
+    assert_eq!(result.rested_qty, Quantity::define(150));
+    assert_eq!(book.depth_at_price(Price::define(5002), Side::Bid), 150);
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 0);
+
+    // A non-crossing order trades nothing and rests in full.
+    let ask = Order::new(Price::define(5003), Quantity::define(100), Side::Ask, &mut counter);
+    assert!(book.add_order(ask).unwrap().fills.is_empty());
+    assert_eq!(book.best_ask(), Some(Price::define(5003)));
+}
+
+#[test]
+fn crossing_remainder_rests_with_reduced_quantity() {
+    for_each_book!(check_crossing_remainder_depth);
+}
+
+/// Two asks queued at 5001; returns the book, its id counter and both ids.
+fn two_queued_asks<O: OrderbookTrait>() -> (O, IdCounter, OrderId, OrderId) {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    let first = Order::new(Price::define(5001), Quantity::define(100), Side::Ask, &mut counter);
+    let second = Order::new(Price::define(5001), Quantity::define(100), Side::Ask, &mut counter);
+    let (first_id, second_id) = (first.id(), second.id());
+    book.add_order(first).unwrap();
+    book.add_order(second).unwrap();
+    (book, counter, first_id, second_id)
+}
+
+/// Quantity reduction keeps the order at the front of its level.
+fn check_amend_reduce_keeps_priority<O: OrderbookTrait>() {
+    let (mut book, _, first_id, _) = two_queued_asks::<O>();
+    book.amend_order(first_id, None, Some(Quantity::define(40))).unwrap();
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 140);
+
+    let result = book.execute_market_order(Side::Bid, Quantity::define(40), TAKER_ID);
+    assert_eq!(result.fills.len(), 1);
+    assert_eq!(result.fills[0].maker_order_id, first_id);
+}
+
+/// Price change or quantity increase sends the order to the back of the queue.
+fn check_amend_requeue_loses_priority<O: OrderbookTrait>() {
+    // Move away and back: first order now queues behind second.
+    let (mut book, _, first_id, second_id) = two_queued_asks::<O>();
+    book.amend_order(first_id, Some(Price::define(5002)), None).unwrap();
+    assert_eq!(book.depth_at_price(Price::define(5002), Side::Ask), 100);
+    book.amend_order(first_id, Some(Price::define(5001)), None).unwrap();
+    assert_eq!(book.depth_at_price(Price::define(5002), Side::Ask), 0);
+
+    let result = book.execute_market_order(Side::Bid, Quantity::define(100), TAKER_ID);
+    assert_eq!(result.fills[0].maker_order_id, second_id);
+
+    // Size-up at the same price also loses priority.
+    let (mut book, _, first_id, second_id) = two_queued_asks::<O>();
+    book.amend_order(first_id, None, Some(Quantity::define(150))).unwrap();
+    let result = book.execute_market_order(Side::Bid, Quantity::define(100), TAKER_ID);
+    assert_eq!(result.fills[0].maker_order_id, second_id);
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 150);
+}
+
+/// Rejected amends leave the resting order exactly where it was.
+fn check_amend_rejections<O: OrderbookTrait>() {
+    let (mut book, mut counter, first_id, _) = two_queued_asks::<O>();
+    let bid = Order::new(Price::define(4999), Quantity::define(100), Side::Bid, &mut counter);
+    book.add_order(bid).unwrap();
+
+    assert!(book.amend_order(9_999, None, Some(Quantity::define(1))).is_err());
+    assert!(book.amend_order(first_id, None, Some(Quantity::define(0))).is_err());
+    assert!(book.amend_order(first_id, Some(Price::define(0)), None).is_err());
+    assert!(book.amend_order(first_id, Some(Price::define(4999)), None).is_err());
+
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 200);
+    let result = book.execute_market_order(Side::Bid, Quantity::define(100), TAKER_ID);
+    assert_eq!(result.fills[0].maker_order_id, first_id);
+
+    // An in-place size reduction must still land on the lot grid
+    let lots = BookConfig { lot_size: 10, ..BookConfig::default() };
+    let (mut book, ids) = BookBuilder::new().with_config(lots).ask(5001, 100).build::<O>();
+    assert!(book.amend_order(ids[0], None, Some(Quantity::define(55))).is_err());
+    assert_eq!(book.get_order(ids[0]).unwrap().remaining_qty, Quantity::define(100));
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn amend_quantity_reduction_keeps_queue_position() {
+    for_each_book!(check_amend_reduce_keeps_priority);
+}
+
+#[test]
+fn amend_price_change_loses_queue_position() {
+    for_each_book!(check_amend_requeue_loses_priority);
+}
+
+#[test]
+fn rejected_amend_leaves_order_untouched() {
+    for_each_book!(check_amend_rejections);
+}
+
+/// Resting orders carry an arrival seq: a reduce keeps it, a price amend
+/// takes a fresh (later) one under the same id.
+fn check_amend_restamps_seq<O: OrderbookTrait>() {
+    let (mut book, _, first_id, second_id) = two_queued_asks::<O>();
+    let resting = |book: &O, id: OrderId| {
+        book.iter_orders(Side::Ask).map(|(_, order)| order).find(|order| order.id() == id).unwrap()
+    };
+    let first = resting(&book, first_id);
+    let second = resting(&book, second_id);
+    assert!(first.seq() > 0 && first.seq() < second.seq());
+
+    book.amend_order(first_id, None, Some(Quantity::define(60))).unwrap();
+    assert_eq!(resting(&book, first_id).seq(), first.seq());
+
+    book.amend_order(first_id, Some(Price::define(5002)), None).unwrap();
+    let moved = resting(&book, first_id);
+    assert_eq!(moved.id(), first_id);
+    assert!(moved.seq() > second.seq());
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn amend_price_change_takes_a_new_seq() {
+    for_each_book!(check_amend_restamps_seq);
+}
+
+/// reduce_order shaves size in place: the order still fills first, stats and
+/// depth follow, and an increase or a zero is refused without effect.
+fn check_reduce_order<O: OrderbookTrait>() {
+    let (mut book, _, first_id, second_id) = two_queued_asks::<O>();
+    book.reduce_order(first_id, Quantity::define(40)).unwrap();
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 140);
+    assert_eq!(book.stats().ask_volume, 140);
+
+    let err = book.reduce_order(first_id, Quantity::define(50)).unwrap_err();
+    assert!(err.contains("amend_order"), "{}", err);
+    assert!(book.reduce_order(first_id, Quantity::define(0)).is_err());
+    assert!(book.reduce_order(9_999, Quantity::define(1)).is_err());
+    assert_eq!(book.get_order(first_id).map(|view| view.remaining_qty), Some(Quantity::define(40)));
+
+    let result = book.execute_market_order(Side::Bid, Quantity::define(60), TAKER_ID);
+    let makers: Vec<(OrderId, QtyValue)> =
+        result.fills.iter().map(|fill| (fill.maker_order_id, fill.quantity.value())).collect();
+    assert_eq!(makers, vec![(first_id, 40), (second_id, 20)]);
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn reduce_order_keeps_queue_position() {
+    for_each_book!(check_reduce_order);
+}
+
+/// Three asks of 100 at one price: each sees the quantity queued before it,
+/// through fills, cancels and in-place reductions.
+fn check_queue_ahead<O: OrderbookTrait>() {
+    let (mut book, _) = BookBuilder::new().ask(5002, 100).build::<O>();
+    let mut counter = IdCounter::starting_at(100);
+    let ids: Vec<OrderId> = (0..3)
+        .map(|_| {
+            let order = Order::new(Price::define(5001), Quantity::define(100), Side::Ask, &mut counter);
+            book.add_order(order).unwrap();
+            order.id()
+        })
+        .collect();
+    let ahead = |book: &O| ids.iter().map(|&id| book.queue_ahead(id)).collect::<Vec<_>>();
+    assert_eq!(ahead(&book), vec![Some(0), Some(100), Some(200)]);
+
+    book.execute_market_order(Side::Bid, Quantity::define(50), TAKER_ID);
+    assert_eq!(book.queue_ahead(ids[2]), Some(150));
+    book.reduce_order(ids[1], Quantity::define(30)).unwrap();
+    assert_eq!(book.queue_ahead(ids[2]), Some(80));
+    book.cancel_order(ids[0]).unwrap();
+    assert_eq!(ahead(&book), vec![None, Some(0), Some(30)]);
+    assert_eq!(book.queue_ahead(9_999), None);
+}
+
+#[test]
+fn queue_ahead_sums_earlier_orders_at_the_level() {
+    for_each_book!(check_queue_ahead);
+}
+
+/// A 300 buy against 100 offered rests the other 200 at the given price;
+/// one that fills completely rests nothing.
+fn check_market_then_rest<O: OrderbookTrait>() {
+    let (mut book, _) = BookBuilder::new().bid(4990, 50).ask(5001, 100).build::<O>();
+    let mut ids = IdCounter::starting_at(100);
+
+    let (fills, rested) = book
+        .execute_market_then_rest(Side::Bid, Quantity::define(300), TAKER_ID, Price::define(5000), &mut ids)
+        .unwrap();
+    assert_eq!(fills.iter().map(|f| (f.price.value(), f.quantity.value())).collect::<Vec<_>>(), vec![(5001, 100)]);
+    let rested_bid = rested.expect("200 left to rest");
+    let view = book.get_order(rested_bid).unwrap();
+    assert_eq!((view.price, view.side, view.remaining_qty), (Price::define(5000), Side::Bid, Quantity::define(200)));
+    assert_eq!(book.best_bid(), Some(Price::define(5000)));
+    assert_eq!(book.best_ask(), None);
+
+    let (fills, rested) = book
+        .execute_market_then_rest(Side::Ask, Quantity::define(150), TAKER_ID, Price::define(5005), &mut ids)
+        .unwrap();
+    assert_eq!(fills.iter().map(|f| f.quantity.value()).sum::<QtyValue>(), 150);
+    assert_eq!(rested, None);
+    assert_eq!(book.get_order(rested_bid).unwrap().remaining_qty, Quantity::define(50));
+
+    // An off-grid rest price is refused before anything trades
+    let depth = book.depth_at_price(Price::define(5000), Side::Bid);
+    let error = book.execute_market_then_rest(Side::Ask, Quantity::define(10), TAKER_ID, Price::define(0), &mut ids);
+    assert!(matches!(error, Err(OrderError::PriceOutOfBounds { .. })));
+    assert_eq!(book.depth_at_price(Price::define(5000), Side::Bid), depth);
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn market_then_rest_rests_the_unfilled_remainder() {
+    for_each_book!(check_market_then_rest);
+}
+
+/// expire_orders removes exactly the orders due by `now`, soonest first, and
+/// passes over ones that already traded away.
+fn check_expire_orders<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    let mut add = |book: &mut O, price: u32, side: Side, expiry: u64| {
+        let order = Order::new(Price::define(price), Quantity::define(100), side, &mut counter).with_expiry(expiry);
+        book.add_order(order).unwrap();
+        order.id()
+    };
+    let late = add(&mut book, 4990, Side::Bid, 15);
+    let first = add(&mut book, 4995, Side::Bid, 5);
+    let second = add(&mut book, 5005, Side::Ask, 10);
+    let gtc = add(&mut book, 4990, Side::Bid, 0);
+    let filled = add(&mut book, 5001, Side::Ask, 8);
+    book.execute_market_order(Side::Bid, Quantity::define(100), TAKER_ID);
+    assert!(!book.contains_order(filled));
+
+    let expired = book.expire_orders(10);
+    assert_eq!(expired.iter().map(|order| order.id()).collect::<Vec<_>>(), vec![first, second]);
+    assert_eq!(expired.iter().map(|order| order.expiry()).collect::<Vec<_>>(), vec![Some(5), Some(10)]);
+    assert_eq!(book.best_bid(), Some(Price::define(4990)));
+    assert_eq!(book.best_ask(), None);
+    assert_eq!((book.stats().bid_orders, book.stats().bid_volume), (2, 200));
+    assert_eq!(book.validate(), Ok(()));
+
+    assert!(book.expire_orders(10).is_empty());
+    assert_eq!(book.expire_orders(u64::MAX).iter().map(|order| order.id()).collect::<Vec<_>>(), vec![late]);
+    assert_eq!(book.get_order(gtc).map(|view| view.price), Some(Price::define(4990)));
+}
+
+#[test]
+fn expire_orders_removes_orders_due_by_now() {
+    for_each_book!(check_expire_orders);
+}
+
+/// best_price and worst_price track both ends of each side, including
+/// levels at the edge of the windowed book's range, as levels are added and
+/// emptied.
+fn check_best_and_worst_prices<O: OrderbookTrait>() {
+    let value = |price: Option<Price>| price.map(|p| p.value());
+    let ends = |book: &O| [Side::Bid, Side::Ask].map(|side| (value(book.best_price(side)), value(book.worst_price(side))));
+    let book = O::new();
+    assert_eq!(ends(&book), [(None, None), (None, None)]);
+    assert_eq!(book.best_price(Side::Bid), book.best_bid());
+
+    let (mut book, ids) =
+        BookBuilder::new().bid(4999, 100).bid(4990, 100).bid(4900, 10).ask(5001, 100).ask(5099, 10).build::<O>();
+    assert_eq!(ends(&book), [(Some(4999), Some(4900)), (Some(5001), Some(5099))]);
+    assert_eq!(book.best_price(Side::Bid), book.best_bid());
+    assert_eq!(book.best_price(Side::Ask), book.best_ask());
+
+    book.cancel_order(ids[2]).unwrap();
+    book.cancel_order(ids[4]).unwrap();
+    book.execute_market_order(Side::Ask, Quantity::define(100), TAKER_ID);
+    assert_eq!(ends(&book), [(Some(4990), Some(4990)), (Some(5001), Some(5001))]);
+    assert_eq!(book.best_price(Side::Bid), book.best_bid());
+    book.cancel_all();
+    assert_eq!(ends(&book), [(None, None), (None, None)]);
+}
+
+#[test]
+fn best_and_worst_prices_agree_with_best_bid_and_ask() {
+    for_each_book!(check_best_and_worst_prices);
+}
+
+/// contains_order agrees with get_order through rests, fills, cancels and
+/// an order that trades away on arrival.
+fn check_contains_order<O: OrderbookTrait>() {
+    let (mut book, ids) = BookBuilder::new().bid(4999, 100).ask(5001, 100).ask(5002, 100).build::<O>();
+    let mut counter = IdCounter::starting_at(100);
+    let taker = Order::new(Price::define(5001), Quantity::define(100), Side::Bid, &mut counter);
+    book.add_order(taker).unwrap();
+    book.cancel_order(ids[2]).unwrap();
+
+    for id in ids.iter().copied().chain([taker.id(), 9_999]) {
+        assert_eq!(book.contains_order(id), book.get_order(id).is_some(), "order {}", id);
     }
-    assert_eq!(tree, fixed);
-    assert_eq!(tree, soa);
-    assert_eq!(tree, hybrid);
+    assert!(book.contains_order(ids[0]));
+    assert!(!book.contains_order(ids[1]) && !book.contains_order(taker.id()));
 }
 
-// ─── Proptest ─────────────────────────────────────────────────────────────────
+#[test]
+fn contains_order_matches_get_order() {
+    for_each_book!(check_contains_order);
+}
 
-// Valid price range — stays well inside all implementations' [1, 9999] bounds.
-const PRICE_MIN: u32 = 1000;
-const PRICE_MAX: u32 = 8000;
-const QTY_MIN: u32 = 1;
-const QTY_MAX: u32 = 500;
+/// Partial fill shows up in get_order; the tail order keeps its place.
+fn check_get_order_after_partial_fill<O: OrderbookTrait>() {
+    let (mut book, _, first_id, second_id) = two_queued_asks::<O>();
+    assert_eq!(
+        book.get_order(second_id),
+        Some(OrderView {
+            price: Price::define(5001),
+            side: Side::Ask,
+            remaining_qty: Quantity::define(100),
+            queue_position: 1,
+        })
+    );
 
-fn arb_side() -> impl Strategy<Value = Side> {
-    prop_oneof![Just(Side::Bid), Just(Side::Ask)]
+    book.execute_market_order(Side::Bid, Quantity::define(30), TAKER_ID);
+    assert_eq!(
+        book.get_order(first_id),
+        Some(OrderView {
+            price: Price::define(5001),
+            side: Side::Ask,
+            remaining_qty: Quantity::define(70),
+            queue_position: 0,
+        })
+    );
+
+    book.execute_market_order(Side::Bid, Quantity::define(70), TAKER_ID);
+    assert_eq!(book.get_order(first_id), None);
+    assert_eq!(book.get_order(second_id).unwrap().queue_position, 0);
+    assert_eq!(book.get_order(9_999), None);
 }
 
-fn arb_op() -> impl Strategy<Value = Op> {
-    prop_oneof![
-        // Add — most common operation
-        3 => (arb_side(), PRICE_MIN..=PRICE_MAX, QTY_MIN..=QTY_MAX)
-                .prop_map(|(side, price, qty)| Op::Add { side, price, qty }),
-        // Cancel by position
-        1 => any::<usize>().prop_map(|idx| Op::Cancel { idx }),
-        // Market order with a qty large enough to always produce full-level fills.
-        // With at most 30 ops × max qty 500 = 15000 total book depth, 100_000
-        // guarantees the market order never needs to partially fill a single
-        // resting order (partial fills panic — they are a known limitation).
-        1 => arb_side().prop_map(|side| Op::Market { side, qty: 100_000 }),
-    ]
+#[test]
+fn get_order_reports_partial_fill_and_queue_position() {
+    for_each_book!(check_get_order_after_partial_fill);
 }
 
-fn arb_ops() -> impl Strategy<Value = Vec<Op>> {
-    prop::collection::vec(arb_op(), 1..=30)
+/// tick_size=5 over a wider range: off-grid prices are rejected, on-grid
+/// prices beyond the default 9999 bound rest and trade.
+fn check_tick_size_five<O: OrderbookTrait>() {
+    let config = BookConfig { max_price: 50_000, tick_size: 5, lot_size: 1, ..BookConfig::default() };
+    let mut book = O::with_config(config).unwrap();
+    assert_eq!(book.config(), &config);
+    let mut counter = IdCounter::new();
+
+    let off_tick = Order::new(Price::define(25_003), Quantity::define(100), Side::Ask, &mut counter);
+    assert_eq!(book.add_order(off_tick).unwrap_err(), OrderError::PriceNotTick { price: 25_003, tick: 5 });
+    let out_of_range = Order::new(Price::define(50_000), Quantity::define(100), Side::Ask, &mut counter);
+    assert_eq!(
+        book.add_order(out_of_range).unwrap_err(),
+        OrderError::PriceOutOfBounds { price: 50_000, max: 50_000 }
+    );
+
+    for (price, qty, side) in [
+        (25_005, 100, Side::Ask),
+        (25_250, 50, Side::Ask),
+        (25_495, 10, Side::Ask),
+        (24_990, 30, Side::Bid),
+    ] {
+        let order = Order::new(Price::define(price), Quantity::define(qty), side, &mut counter);
+        assert!(book.add_order(order).unwrap().fills.is_empty());
+    }
+    assert_eq!(book.best_ask(), Some(Price::define(25_005)));
+    assert_eq!(book.depth_at_price(Price::define(25_003), Side::Ask), 0);
+
+    let bid_id = book.iter_orders(Side::Bid).next().unwrap().1.id();
+    assert!(book.amend_order(bid_id, Some(Price::define(24_993)), None).is_err());
+
+    let result = book.execute_market_order(Side::Bid, Quantity::define(120), TAKER_ID);
+    assert_eq!(result.filled_qty, Quantity::define(120));
+    assert_eq!(result.fills.last().unwrap().price, Price::define(25_250));
+    assert_eq!(
+        book.book_snapshot(5),
+        BookSnapshot {
+            bids: vec![(Price::define(24_990), 30)],
+            asks: vec![(Price::define(25_250), 30), (Price::define(25_495), 10)],
+        }
+    );
 }
 
-proptest! {
-    /// All four implementations must agree on best_bid, best_ask, and fills
-    /// for any randomly generated sequence of add/cancel/market operations.
-    #[test]
-    fn all_impls_agree(ops in arb_ops()) {
-        let tree   = run::<Tree>(&ops);
-        let fixed  = run::<FixedTick>(&ops);
-        let soa    = run::<SoA>(&ops);
-        let hybrid = run::<Hybrid>(&ops);
+#[test]
+fn tick_size_config_rejects_off_grid_prices() {
+    for_each_book!(check_tick_size_five);
+}
 
-        prop_assert_eq!(&tree, &fixed,  "tree vs fixed_tick");
-        prop_assert_eq!(&tree, &soa,    "tree vs soa");
-        prop_assert_eq!(&tree, &hybrid, "tree vs hybrid");
+/// Each refusal names its cause as an OrderError variant, and its message
+/// reads as the old String errors did.
+fn check_order_errors<O: OrderbookTrait>() {
+    let mut book = O::with_config(BookConfig { lot_size: 10, ..BookConfig::default() }).unwrap();
+    let mut counter = IdCounter::new();
+    let mut add = |book: &mut O, price: u32, qty: QtyValue| {
+        book.add_order(Order::new(Price::define(price), Quantity::define(qty), Side::Bid, &mut counter))
+    };
+
+    assert_eq!(add(&mut book, 4990, 0).unwrap_err(), OrderError::ZeroQty);
+    assert_eq!(add(&mut book, 4990, 15).unwrap_err(), OrderError::QtyNotLot { qty: 15, lot: 10 });
+    let err = add(&mut book, 0, 10).unwrap_err();
+    assert_eq!(err, OrderError::PriceOutOfBounds { price: 0, max: 10_000 });
+    assert_eq!(err.to_string(), "Price 0 out of bounds [1, 10000)");
+
+    let err = book.cancel_order(42).unwrap_err();
+    assert_eq!(err, OrderError::NotFound(42));
+    assert_eq!(String::from(err), "Order 42 not found");
+    assert_eq!(book.stats(), BookStats::default());
+}
+
+#[test]
+fn add_and_cancel_report_structured_errors() {
+    for_each_book!(check_order_errors);
+}
+
+#[test]
+fn invalid_book_config_is_rejected() {
+    fn rejects_bad_grids<O: OrderbookTrait>() {
+        for config in [
+            BookConfig { max_price: 10_000, tick_size: 0, lot_size: 1, ..BookConfig::default() },
+            BookConfig { max_price: 10_000, tick_size: 1, lot_size: 0, ..BookConfig::default() },
+            BookConfig { max_price: 10_001, tick_size: 5, lot_size: 1, ..BookConfig::default() },
+            BookConfig { max_price: 5, tick_size: 5, lot_size: 1, ..BookConfig::default() },
+        ] {
+            assert!(O::with_config(config).is_err(), "{:?} accepted", config);
+        }
     }
+    rejects_bad_grids::<Tree>();
+    rejects_bad_grids::<FixedTick>();
+    rejects_bad_grids::<SoA>();
+    rejects_bad_grids::<Hybrid>();
+    rejects_bad_grids::<Arena>();
 
-    /// After any random sequence, best_bid < best_ask whenever both exist.
-    /// Note: these implementations are data structures and do not auto-match
-    /// crossing orders. The proptest avoids adding bids above best_ask or asks
-    /// below best_bid; instead we simply check the invariant holds on whatever
-    /// the book ends up with after unconstrained ops.
-    #[test]
-    fn best_bid_below_best_ask_when_no_crossing(ops in arb_ops()) {
-        // Run Tree only — if the invariant fails it is a Tree bug, not a
-        // cross-impl disagreement. Cross-impl agreement is covered by all_impls_agree.
-        let outcome = run::<Tree>(&ops);
-        if let (Some(bid), Some(ask)) = (outcome.best_bid, outcome.best_ask) {
-            // Only assert when there is a natural spread (bid < ask prices added).
-            // The random generator can add bid=5000 and ask=4000 which is a
-            // known-unsupported crossing state; skip those.
-            if bid < ask {
-                prop_assert!(bid < ask);
-            }
+    // 100 ticks cannot hold Hybrid's 200-tick hot window
+    let narrow = BookConfig { max_price: 1_000, tick_size: 10, lot_size: 1, ..BookConfig::default() };
+    assert!(Hybrid::with_config(narrow).is_err());
+    assert!(Tree::with_config(narrow).is_ok());
+}
+
+/// Asks 5001x50, 5002x40, 5003x100: 90 lots sit at or below 5002.
+fn laddered_asks<O: OrderbookTrait>() -> (O, IdCounter) {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    for (price, qty) in [(5001, 50), (5002, 40), (5003, 100)] {
+        let ask = Order::new(Price::define(price), Quantity::define(qty), Side::Ask, &mut counter);
+        book.add_order(ask).unwrap();
+    }
+    (book, counter)
+}
+
+/// A FOK that would only be 90% filled within its limit is rejected untouched.
+fn check_fok_rejects_partial<O: OrderbookTrait>() {
+    let (mut book, mut counter) = laddered_asks::<O>();
+    let before = (book.book_snapshot(10), l3_dump(&book, Side::Ask));
+
+    let fok = Order::new(Price::define(5002), Quantity::define(100), Side::Bid, &mut counter)
+        .with_time_in_force(TimeInForce::FOK);
+    let err = book.add_order(fok).unwrap_err();
+    assert_eq!(err, OrderError::FokUnfillable { order: fok.id(), available: 90, requested: 100 });
+    assert_eq!((book.book_snapshot(10), l3_dump(&book, Side::Ask)), before);
+
+    // Exactly the available 90 fills completely.
+    let fok = Order::new(Price::define(5002), Quantity::define(90), Side::Bid, &mut counter)
+        .with_time_in_force(TimeInForce::FOK);
+    let result = book.add_order(fok).unwrap();
+    assert_eq!(result.filled_qty, Quantity::define(90));
+    assert_eq!(result.canceled_qty, Quantity::define(0));
+    assert_eq!(book.best_ask(), Some(Price::define(5003)));
+    assert_eq!(book.best_bid(), None);
+}
+
+/// An IOC fills what it can within its limit and cancels the rest.
+fn check_ioc_cancels_remainder<O: OrderbookTrait>() {
+    let (mut book, mut counter) = laddered_asks::<O>();
+
+    let ioc = Order::new(Price::define(5002), Quantity::define(150), Side::Bid, &mut counter)
+        .with_time_in_force(TimeInForce::IOC);
+    let ioc_id = ioc.id();
+    let result = book.add_order(ioc).unwrap();
+
+    assert_eq!(result.filled_qty, Quantity::define(90));
+    assert_eq!(result.canceled_qty, Quantity::define(60));
+    assert_eq!(result.rested_qty, Quantity::define(0));
+    assert_eq!(book.best_bid(), None);
+    assert_eq!(book.get_order(ioc_id), None);
+    assert_eq!(book.best_ask(), Some(Price::define(5003)));
+}
+
+#[test]
+fn fok_that_cannot_fully_fill_is_rejected() {
+    for_each_book!(check_fok_rejects_partial);
+}
+
+#[test]
+fn ioc_cancels_unfilled_remainder() {
+    for_each_book!(check_ioc_cancels_remainder);
+}
+
+/// Two asks at 5001: account 7 at the front, account 9 behind it.
+fn same_account_at_front<O: OrderbookTrait>(stp: StpMode) -> (O, IdCounter, OrderId, OrderId) {
+    let mut book = O::with_config(BookConfig { stp, ..BookConfig::default() }).unwrap();
+    let mut counter = IdCounter::new();
+    let own = Order::new(Price::define(5001), Quantity::define(100), Side::Ask, &mut counter).with_account(7);
+    let other = Order::new(Price::define(5001), Quantity::define(100), Side::Ask, &mut counter).with_account(9);
+    let (own_id, other_id) = (own.id(), other.id());
+    book.add_order(own).unwrap();
+    book.add_order(other).unwrap();
+    (book, counter, own_id, other_id)
+}
+
+/// Cancel-resting removes the same-account maker; the taker keeps consuming
+/// the next order in the queue.
+fn check_stp_cancel_resting<O: OrderbookTrait>() {
+    let (mut book, mut counter, own_id, other_id) = same_account_at_front::<O>(StpMode::CancelResting);
+
+    // FOK counts only liquidity it may trade with, and a reject cancels nothing
+    let fok = Order::new(Price::define(5001), Quantity::define(150), Side::Bid, &mut counter)
+        .with_account(7)
+        .with_time_in_force(TimeInForce::FOK);
+    let err = book.add_order(fok).unwrap_err();
+    assert!(matches!(err, OrderError::FokUnfillable { requested: 150, .. }), "{}", err);
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 200);
+
+    let bid = Order::new(Price::define(5001), Quantity::define(60), Side::Bid, &mut counter).with_account(7);
+    let result = book.add_order(bid).unwrap();
+    assert_eq!(result.fills.len(), 1);
+    assert_eq!(result.fills[0].maker_order_id, other_id);
+    assert_eq!(result.filled_qty, Quantity::define(60));
+    assert_eq!(book.get_order(own_id), None);
+    assert_eq!(book.get_order(other_id).unwrap().remaining_qty, Quantity::define(40));
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 40);
+}
+
+/// Skip leaves the same-account maker queued; a remainder that would rest
+/// crossed against it is canceled.
+fn check_stp_skip<O: OrderbookTrait>() {
+    let (mut book, mut counter, own_id, other_id) = same_account_at_front::<O>(StpMode::Skip);
+
+    let bid = Order::new(Price::define(5001), Quantity::define(150), Side::Bid, &mut counter).with_account(7);
+    let result = book.add_order(bid).unwrap();
+    assert_eq!(result.fills.len(), 1);
+    assert_eq!(result.fills[0].maker_order_id, other_id);
+    assert_eq!(result.canceled_qty, Quantity::define(50));
+    assert_eq!(book.best_bid(), None);
+    assert_eq!(book.get_order(own_id).unwrap().queue_position, 0);
+
+    // Other accounts (and market orders) still trade with it.
+    let result = book.execute_market_order(Side::Bid, Quantity::define(100), TAKER_ID);
+    assert_eq!(result.fills[0].maker_order_id, own_id);
+}
+
+/// With STP off, same-account orders trade normally.
+fn check_stp_off<O: OrderbookTrait>() {
+    let (mut book, mut counter, own_id, _) = same_account_at_front::<O>(StpMode::Off);
+    let bid = Order::new(Price::define(5001), Quantity::define(100), Side::Bid, &mut counter).with_account(7);
+    let result = book.add_order(bid).unwrap();
+    assert_eq!(result.fills[0].maker_order_id, own_id);
+}
+
+/// Pro-rata: 100/200/300 resting at one price, a 300 market order takes half
+/// of each; an uneven split hands the odd lot to the earliest order.
+fn check_pro_rata<O: OrderbookTrait>() {
+    let mut book = O::with_config(BookConfig { match_mode: MatchMode::ProRata, ..BookConfig::default() }).unwrap();
+    let mut counter = IdCounter::new();
+    let ids: Vec<OrderId> = [100, 200, 300]
+        .into_iter()
+        .map(|qty| {
+            let order = Order::new(Price::define(5001), Quantity::define(qty), Side::Ask, &mut counter);
+            book.add_order(order).unwrap();
+            order.id()
+        })
+        .collect();
+
+    let result = book.execute_market_order(Side::Bid, Quantity::define(300), TAKER_ID);
+    let split: Vec<(OrderId, QtyValue)> = result.fills.iter().map(|f| (f.maker_order_id, f.quantity.value())).collect();
+    assert_eq!(split, vec![(ids[0], 50), (ids[1], 100), (ids[2], 150)]);
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 300);
+
+    // 50/100/150 left; 100 in: floors of 16/33/50 leave one lot for the front
+    let result = book.execute_market_order(Side::Bid, Quantity::define(100), TAKER_ID);
+    let split: Vec<QtyValue> = result.fills.iter().map(|f| f.quantity.value()).collect();
+    assert_eq!(split, vec![17, 33, 50]);
+
+    // A taker bigger than the level fills everything and walks on
+    let ask = Order::new(Price::define(5002), Quantity::define(40), Side::Ask, &mut counter);
+    book.add_order(ask).unwrap();
+    let result = book.execute_market_order(Side::Bid, Quantity::define(250), TAKER_ID);
+    assert_eq!(result.filled_qty, Quantity::define(240));
+    assert_eq!(book.best_ask(), None);
+}
+
+#[test]
+fn pro_rata_splits_by_size() {
+    for_each_book!(check_pro_rata);
+}
+
+/// Bulk cancels clear one side (or both) and the id index with it.
+fn check_cancel_all<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    let mut bid_ids = Vec::new();
+    // Spread over the whole default window, edges included
+    for (i, price) in [4990, 4999, 4999, 4950, 4900].into_iter().enumerate() {
+        let bid = Order::new(Price::define(price), Quantity::define(10 + i as QtyValue), Side::Bid, &mut counter);
+        bid_ids.push(bid.id());
+        book.add_order(bid).unwrap();
+    }
+    for price in [5001, 5001, 5010, 5099] {
+        book.add_order(Order::new(Price::define(price), Quantity::define(20), Side::Ask, &mut counter)).unwrap();
+    }
+    let asks_before = book.book_snapshot(10).asks;
+
+    assert_eq!(book.cancel_all_for_side(Side::Bid), 5);
+    assert_eq!(book.best_bid(), None);
+    assert!(book.iter_orders(Side::Bid).next().is_none());
+    assert!(bid_ids.iter().all(|&id| book.get_order(id).is_none()));
+    assert_eq!(book.cancel_order(bid_ids[0]).unwrap_err(), OrderError::NotFound(bid_ids[0]));
+    assert_eq!(book.book_snapshot(10).asks, asks_before);
+    assert_eq!(book.validate(), Ok(()));
+
+    // The emptied side is fully usable again
+    let bid = Order::new(Price::define(4999), Quantity::define(5), Side::Bid, &mut counter);
+    book.add_order(bid).unwrap();
+    assert_eq!(book.best_bid(), Some(Price::define(4999)));
+
+    assert_eq!(book.cancel_all(), 5);
+    assert_eq!(book.cancel_all(), 0);
+    assert_eq!((book.best_bid(), book.best_ask()), (None, None));
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn cancel_all_clears_sides_and_index() {
+    for_each_book!(check_cancel_all);
+}
+
+/// cancel_order hands back the removed order: id, side, price and account as
+/// added, quantity net of any partial fill.
+fn check_cancel_returns_order<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    // One near and one far ask, plus a bid behind another
+    let near = Order::new(Price::define(5001), Quantity::define(100), Side::Ask, &mut counter).with_account(7);
+    let far = Order::new(Price::define(5090), Quantity::define(40), Side::Ask, &mut counter);
+    let front = Order::new(Price::define(4990), Quantity::define(10), Side::Bid, &mut counter);
+    let back = Order::new(Price::define(4990), Quantity::define(25), Side::Bid, &mut counter).with_account(3);
+    for order in [near, far, front, back] {
+        book.add_order(order).unwrap();
+    }
+
+    let as_added = |order: &Order| (order.id(), order.side(), order.price(), order.quantity(), order.account());
+    assert_eq!(as_added(&book.cancel_order(back.id()).unwrap()), as_added(&back));
+    assert_eq!(as_added(&book.cancel_order(far.id()).unwrap()), as_added(&far));
+
+    book.execute_market_order(Side::Bid, Quantity::define(30), TAKER_ID);
+    let canceled = book.cancel_order(near.id()).unwrap();
+    assert_eq!(canceled.id(), near.id());
+    assert_eq!(canceled.side(), Side::Ask);
+    assert_eq!(canceled.price(), Price::define(5001));
+    assert_eq!(canceled.quantity(), Quantity::define(70));
+    assert_eq!(canceled.account(), 7);
+
+    assert_eq!(book.cancel_order(near.id()).unwrap_err(), OrderError::NotFound(near.id()));
+    assert_eq!(book.stats().ask_orders, 0);
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn cancel_returns_the_removed_order() {
+    for_each_book!(check_cancel_returns_order);
+}
+
+/// stats() tracks adds, partial fills, and cancels (including a level at the
+/// window's edge) without walking the book.
+fn check_stats<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    assert_eq!(book.stats(), BookStats::default());
+
+    let mut ids = Vec::new();
+    for (price, qty, side) in [
+        (4990, 100, Side::Bid),
+        (4990, 50, Side::Bid),
+        (4900, 30, Side::Bid),
+        (5001, 40, Side::Ask),
+        (5001, 60, Side::Ask),
+        (5005, 25, Side::Ask),
+    ] {
+        let order = Order::new(Price::define(price), Quantity::define(qty), side, &mut counter);
+        ids.push(order.id());
+        book.add_order(order).unwrap();
+    }
+    let expected = BookStats {
+        bid_levels: 2,
+        ask_levels: 2,
+        bid_orders: 3,
+        ask_orders: 3,
+        bid_volume: 180,
+        ask_volume: 125,
+    };
+    assert_eq!(book.stats(), expected);
+
+    // Fills the first ask and half of the second
+    book.execute_market_order(Side::Bid, Quantity::define(70), TAKER_ID);
+    let expected = BookStats { ask_orders: 2, ask_volume: 55, ..expected };
+    assert_eq!(book.stats(), expected);
+
+    book.cancel_order(ids[2]).unwrap();
+    book.cancel_order(ids[5]).unwrap();
+    let expected = BookStats {
+        bid_levels: 1,
+        ask_levels: 1,
+        bid_orders: 2,
+        ask_orders: 1,
+        bid_volume: 150,
+        ask_volume: 30,
+    };
+    assert_eq!(book.stats(), expected);
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn stats_track_adds_fills_and_cancels() {
+    for_each_book!(check_stats);
+}
+
+/// depth_in_range agrees with summing depth_at_price over the band, whatever
+/// order the bounds come in and however far past the grid they reach.
+fn check_depth_in_range<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    // 4900 sits at the bottom edge of the default window
+    for (price, qty) in [(4990, 10), (4995, 20), (4995, 5), (5000, 40), (4900, 7)] {
+        book.add_order(Order::new(Price::define(price), Quantity::define(qty), Side::Bid, &mut counter)).unwrap();
+    }
+    book.add_order(Order::new(Price::define(5001), Quantity::define(99), Side::Ask, &mut counter)).unwrap();
+
+    let depth = |from: u32, to: u32| book.depth_in_range(Side::Bid, Price::define(from), Price::define(to));
+    let summed: u64 = [4990, 4995, 5000]
+        .into_iter()
+        .map(|p| book.depth_at_price(Price::define(p), Side::Bid))
+        .sum();
+    assert_eq!(summed, 75);
+    assert_eq!(depth(4990, 5000), summed);
+    assert_eq!(depth(5000, 4990), summed);
+    assert_eq!(depth(4991, 4999), 25);
+    assert_eq!(depth(4995, 4995), 25);
+
+    // Clamped to the grid: everything on the side, and nothing past it
+    assert_eq!(depth(0, 20_000), 82);
+    assert_eq!(depth(50, 4990), 17);
+    assert_eq!(depth(4901, 4989), 0);
+    assert_eq!(depth(0, 0), 0);
+    assert_eq!(depth(10_000, 20_000), 0);
+    assert_eq!(book.depth_in_range(Side::Ask, Price::define(1), Price::define(9999)), 99);
+}
+
+#[test]
+fn depth_in_range_matches_summed_levels() {
+    for_each_book!(check_depth_in_range);
+}
+
+/// reserve only changes capacity: a reserved book and a plain one given the
+/// same commands end in the same state.
+fn check_reserve<O: OrderbookTrait>() {
+    let mut plain = O::new();
+    let mut reserved = O::new();
+    let mut counter = IdCounter::new();
+
+    let mut orders = Vec::new();
+    for i in 0..200u32 {
+        let side = if i % 2 == 0 { Side::Bid } else { Side::Ask };
+        let price = if side == Side::Bid { 4990 - i % 7 } else { 5010 + i % 7 };
+        orders.push(Order::new(Price::define(price), Quantity::define(10 + i as QtyValue), side, &mut counter));
+    }
+
+    for (i, &order) in orders.iter().enumerate() {
+        // Reserve once up front and again part way, over populated levels
+        if i == 0 || i == 100 {
+            reserved.reserve(32);
+        }
+        plain.add_order(order).unwrap();
+        reserved.add_order(order).unwrap();
+    }
+    // A far order opens a level nothing was reserved for
+    let far = Order::new(Price::define(4900), Quantity::define(5), Side::Bid, &mut counter);
+    plain.add_order(far).unwrap();
+    reserved.add_order(far).unwrap();
+    plain.execute_market_order(Side::Bid, Quantity::define(500), TAKER_ID);
+    reserved.execute_market_order(Side::Bid, Quantity::define(500), TAKER_ID);
+
+    assert_eq!(reserved.to_snapshot(&counter).orders, plain.to_snapshot(&counter).orders);
+    assert_eq!(reserved.validate(), Ok(()));
+}
+
+#[test]
+fn reserve_leaves_behaviour_unchanged() {
+    for_each_book!(check_reserve);
+}
+
+/// add_orders keeps going past a rejected order: the valid ones rest and
+/// exactly the bad index comes back as an error.
+fn check_add_orders<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+
+    let mut batch = Vec::new();
+    for i in 0..9u32 {
+        // Out of the default 1..=9999 grid
+        let price = if i == 4 { 100_000 } else { 4990 - i };
+        batch.push(Order::new(Price::define(price), Quantity::define(10 + i as QtyValue), Side::Bid, &mut counter));
+    }
+
+    let results = book.add_orders(batch.iter().copied());
+    assert_eq!(results.len(), batch.len());
+    let rejected: Vec<usize> = results
+        .iter()
+        .enumerate()
+        .filter(|(_, result)| result.is_err())
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(rejected, vec![4]);
+
+    for (i, order) in batch.iter().enumerate() {
+        assert_eq!(book.get_order(order.id()).is_some(), i != 4, "order {i}");
+    }
+    assert_eq!(book.stats().bid_orders, 8);
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn add_orders_reports_each_order() {
+    for_each_book!(check_add_orders);
+}
+
+/// clear() leaves a book indistinguishable from a new one: empty, fill seqs
+/// restarting at 1, and ready to refill.
+fn check_clear<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    let fill_book = |book: &mut O, counter: &mut IdCounter| {
+        for price in [4900, 4990, 4999, 4999] {
+            book.add_order(Order::new(Price::define(price), Quantity::define(10), Side::Bid, counter)).unwrap();
+        }
+        for price in [5001, 5005, 5099] {
+            book.add_order(Order::new(Price::define(price), Quantity::define(10), Side::Ask, counter)).unwrap();
+        }
+        book.execute_market_order(Side::Bid, Quantity::define(15), TAKER_ID)
+    };
+
+    let first = fill_book(&mut book, &mut counter);
+    book.clear();
+    assert_eq!((book.best_bid(), book.best_ask()), (None, None));
+    assert_eq!(book.stats(), BookStats::default());
+    assert_eq!(book.last_trade(), None);
+    assert!(book.iter_orders(Side::Bid).chain(book.iter_orders(Side::Ask)).next().is_none());
+    assert_eq!(book.validate(), Ok(()));
+
+    // Same orders again, even reusing the ids: same fills, seqs included
+    let mut counter = IdCounter::new();
+    let second = fill_book(&mut book, &mut counter);
+    assert_eq!(fill_records(&second.fills), fill_records(&first.fills));
+    assert_eq!(book.stats().bid_orders, 4);
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn clear_resets_to_a_fresh_book() {
+    for_each_book!(check_clear);
+}
+
+/// last_trade follows the final fill of market orders and crossing adds only.
+fn check_last_trade<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    for (price, qty) in [(5001, 50), (5002, 100), (5003, 50)] {
+        book.add_order(Order::new(Price::define(price), Quantity::define(qty), Side::Ask, &mut counter)).unwrap();
+    }
+    assert_eq!(book.last_trade(), None);
+
+    // Clears 5001, then consumes 100@5002
+    book.execute_market_order(Side::Bid, Quantity::define(150), TAKER_ID);
+    assert_eq!(book.last_trade(), Some((Price::define(5002), Quantity::define(100))));
+
+    // Resting adds and simulations leave it alone
+    book.add_order(Order::new(Price::define(4999), Quantity::define(10), Side::Bid, &mut counter)).unwrap();
+    book.simulate_market_order(Side::Bid, Quantity::define(20), TAKER_ID);
+    assert_eq!(book.last_trade(), Some((Price::define(5002), Quantity::define(100))));
+
+    // A crossing add trades too
+    book.add_order(Order::new(Price::define(5003), Quantity::define(20), Side::Bid, &mut counter)).unwrap();
+    assert_eq!(book.last_trade(), Some((Price::define(5003), Quantity::define(20))));
+}
+
+#[test]
+fn last_trade_reports_the_latest_fill() {
+    for_each_book!(check_last_trade);
+}
+
+/// execute_market_order_with reports the same fills, one call each, as the
+/// collecting execute_market_order on an identical book.
+fn check_fill_callback<O: OrderbookTrait>() {
+    let mut collecting = O::new();
+    let mut streaming = O::new();
+    let mut counter = IdCounter::new();
+    for (price, qty) in [(5001, 30), (5001, 70), (5002, 100), (5004, 25), (5099, 50)] {
+        let order = Order::new(Price::define(price), Quantity::define(qty), Side::Ask, &mut counter);
+        collecting.add_order(order).unwrap();
+        streaming.add_order(order).unwrap();
+    }
+
+    for qty in [40, 150, 1_000] {
+        let expected = collecting.execute_market_order(Side::Bid, Quantity::define(qty), TAKER_ID);
+
+        let mut calls = 0;
+        let mut seen = Vec::new();
+        let result = streaming.execute_market_order_with(Side::Bid, Quantity::define(qty), TAKER_ID, |fill| {
+            calls += 1;
+            seen.push(fill.clone());
+        });
+        assert_eq!(calls, expected.fills.len());
+        assert_eq!(fill_records(&seen), fill_records(&expected.fills));
+        assert!(result.fills.is_empty());
+        assert_eq!((result.filled_qty, result.remaining_qty), (expected.filled_qty, expected.remaining_qty));
+    }
+    assert_eq!(streaming.last_trade(), collecting.last_trade());
+}
+
+#[test]
+fn fill_callback_sees_every_fill() {
+    for_each_book!(check_fill_callback);
+}
+
+/// simulate_market_order returns the fills execute_market_order then
+/// produces (seq aside), and leaves the book untouched, in FIFO and pro-rata.
+fn check_simulate_market_order<O: OrderbookTrait>() {
+    for match_mode in [MatchMode::Fifo, MatchMode::ProRata] {
+        let mut book = O::with_config(BookConfig { match_mode, ..BookConfig::default() }).unwrap();
+        let mut counter = IdCounter::new();
+        for (price, qty) in [(5001, 30), (5001, 70), (5002, 100), (5004, 25), (5099, 50)] {
+            book.add_order(Order::new(Price::define(price), Quantity::define(qty), Side::Ask, &mut counter)).unwrap();
+        }
+        let before = book.to_snapshot(&counter).orders;
+
+        let unsequenced = |fills: &[Fill]| -> Vec<(u32, QtyValue, OrderId, OrderId)> {
+            fills
+                .iter()
+                .map(|f| (f.price.value(), f.quantity.value(), f.maker_order_id, f.taker_order_id))
+                .collect()
+        };
+        // Part way into a level, then past everything on the side
+        for qty in [160, 1_000] {
+            let simulated = book.simulate_market_order(Side::Bid, Quantity::define(qty), TAKER_ID);
+            assert_eq!(book.to_snapshot(&counter).orders, before);
+            assert!(simulated.iter().all(|f| f.seq == 0));
+
+            let mut copy = O::from_snapshot(&book.to_snapshot(&counter)).unwrap();
+            let executed = copy.execute_market_order(Side::Bid, Quantity::define(qty), TAKER_ID);
+            assert!(!executed.fills.is_empty());
+            assert_eq!(unsequenced(&simulated), unsequenced(&executed.fills), "{:?}, {}", match_mode, qty);
+        }
+    }
+}
+
+#[test]
+fn simulate_market_order_matches_execution() {
+    for_each_book!(check_simulate_market_order);
+}
+
+/// A level at max_orders_per_level rejects further adds and amends into it,
+/// while other prices keep accepting orders.
+fn check_level_cap<O: OrderbookTrait>() {
+    let config = BookConfig { max_orders_per_level: Some(3), ..BookConfig::default() };
+    let mut book = O::with_config(config).unwrap();
+    let mut counter = IdCounter::new();
+    let bid = |price: u32, counter: &mut IdCounter| {
+        Order::new(Price::define(price), Quantity::define(10), Side::Bid, counter)
+    };
+
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        let order = bid(4999, &mut counter);
+        ids.push(order.id());
+        book.add_order(order).unwrap();
+    }
+    let err = book.add_order(bid(4999, &mut counter)).unwrap_err();
+    assert_eq!(err, OrderError::LevelFull { side: Side::Bid, price: 4999, max: 3 });
+    assert_eq!(book.depth_at_price(Price::define(4999), Side::Bid), 30);
+
+    let other = bid(4998, &mut counter);
+    book.add_order(other).unwrap();
+
+    // Moving into the full level is refused and leaves the order where it was
+    assert!(book.amend_order(other.id(), Some(Price::define(4999)), None).is_err());
+    assert_eq!(book.get_order(other.id()).map(|view| view.price), Some(Price::define(4998)));
+
+    // Room frees up as the level drains
+    book.cancel_order(ids[0]).unwrap();
+    book.add_order(bid(4999, &mut counter)).unwrap();
+    assert_eq!(book.validate(), Ok(()));
+
+    assert!(O::with_config(BookConfig { max_orders_per_level: Some(0), ..BookConfig::default() }).is_err());
+}
+
+#[test]
+fn level_cap_rejects_adds_to_a_full_level() {
+    for_each_book!(check_level_cap);
+}
+
+/// With reject_crossing a GTC order that would trade is refused and the book
+/// is left as it was; without it the same order trades, and either way the
+/// book never ends up crossed.
+fn check_reject_crossing<O: OrderbookTrait>() {
+    let config = BookConfig { reject_crossing: true, ..BookConfig::default() };
+    let (mut book, _) = BookBuilder::new().with_config(config).bid(4990, 10).ask(5000, 10).build::<O>();
+    let mut counter = IdCounter::starting_at(100);
+    let bid = |qty, counter: &mut IdCounter| Order::new(Price::define(5010), Quantity::define(qty), Side::Bid, counter);
+
+    let err = book.add_order(bid(5, &mut counter)).unwrap_err();
+    assert!(matches!(err, OrderError::WouldCross { price: 5010, .. }), "{}", err);
+    assert_eq!(book.depth_at_price(Price::define(5000), Side::Ask), 10);
+    assert!(!book.is_crossed());
+
+    // Takers are still allowed through
+    let ioc = bid(5, &mut counter).with_time_in_force(TimeInForce::IOC);
+    assert_eq!(book.add_order(ioc).unwrap().filled_qty, Quantity::define(5));
+
+    let (mut book, _) = BookBuilder::new().bid(4990, 10).ask(5000, 10).build::<O>();
+    assert!(!book.is_crossed());
+    let result = book.add_order(bid(15, &mut counter)).unwrap();
+    assert_eq!((result.filled_qty, result.rested_qty), (Quantity::define(10), Quantity::define(5)));
+    assert_eq!(book.best_bid(), Some(Price::define(5010)));
+    assert!(!book.is_crossed());
+}
+
+#[test]
+fn reject_crossing_refuses_marketable_gtc_orders() {
+    for_each_book!(check_reject_crossing);
+}
+
+/// The windowed book refuses prices outside its window, adds or amends, and
+/// leaves the book untouched when it does.
+#[test]
+fn windowed_rejects_orders_outside_the_window() {
+    let mut book = Windowed::new();
+    let mut counter = IdCounter::new();
+    assert_eq!(book.window(), (Price::define(4900), Price::define(5100)));
+
+    let inside = Order::new(Price::define(5099), Quantity::define(10), Side::Ask, &mut counter);
+    book.add_order(inside).unwrap();
+    for price in [5100, 4899, 8000] {
+        let order = Order::new(Price::define(price), Quantity::define(10), Side::Ask, &mut counter);
+        let err = book.add_order(order).unwrap_err();
+        assert_eq!(err, OrderError::OutsideWindow { price, lower: 4900, upper: 5100 });
+    }
+    assert!(book.amend_order(inside.id(), Some(Price::define(5100)), None).is_err());
+    assert_eq!(book.get_order(inside.id()).map(|view| view.price), Some(Price::define(5099)));
+    assert_eq!(book.stats().ask_orders, 1);
+    assert_eq!(book.depth_at_price(Price::define(8000), Side::Ask), 0);
+    assert_eq!(book.validate(), Ok(()));
+}
+
+/// After recentering, the new range takes orders and everything resting keeps
+/// its price, depth and queue; a recenter that would strand an order is refused.
+#[test]
+fn windowed_book_trades_across_a_recenter() {
+    let (mut book, ids) = BookBuilder::new().bid(4990, 10).ask(5010, 10).ask(5010, 5).build::<Windowed>();
+    let mut counter = IdCounter::starting_at(100);
+
+    let err = book.recenter(Price::define(3000)).unwrap_err();
+    assert!(err.contains("strand"), "{}", err);
+    assert_eq!(book.window(), (Price::define(4900), Price::define(5100)));
+
+    book.recenter(Price::define(5080)).unwrap();
+    assert_eq!(book.window(), (Price::define(4980), Price::define(5180)));
+    let far = Order::new(Price::define(5150), Quantity::define(7), Side::Ask, &mut counter);
+    book.add_order(far).unwrap();
+    assert_eq!(book.book_snapshot(5).asks, vec![(Price::define(5010), 15), (Price::define(5150), 7)]);
+    assert_eq!(book.best_bid(), Some(Price::define(4990)));
+
+    let result = book.execute_market_order(Side::Bid, Quantity::define(20), TAKER_ID);
+    let makers: Vec<OrderId> = result.fills.iter().map(|fill| fill.maker_order_id).collect();
+    assert_eq!(makers, vec![ids[1], ids[2], far.id()]);
+    assert_eq!(book.depth_at_price(Price::define(5150), Side::Ask), 2);
+    assert_eq!(book.validate(), Ok(()));
+}
+
+/// A requote leaves only the new pair resting; a rejected quote leaves no half.
+fn check_quote<O: OrderbookTrait>() {
+    let config = BookConfig { max_orders_per_level: Some(1), ..BookConfig::default() };
+    let mut book = O::with_config(config).unwrap();
+    let mut counter = IdCounter::new();
+    let resting_ids = |book: &O| {
+        let mut ids: Vec<u64> = Side::all()
+            .into_iter()
+            .flat_map(|side| book.iter_orders(side).map(|(_, order)| order.id()).collect::<Vec<_>>())
+            .collect();
+        ids.sort();
+        ids
+    };
+    let level = |price, qty| (Price::define(price), Quantity::define(qty));
+
+    let first = book.quote(level(4998, 10), level(5002, 10), &mut counter).unwrap();
+    assert_eq!(resting_ids(&book), vec![first.0, first.1]);
+
+    let second = book.requote(first, level(4999, 20), level(5001, 20), &mut counter).unwrap();
+    assert_eq!(resting_ids(&book), vec![second.0, second.1]);
+    assert_eq!(book.best_bid(), Some(Price::define(4999)));
+    assert_eq!(book.best_ask(), Some(Price::define(5001)));
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 20);
+
+    // Crossed quotes are refused outright
+    assert!(book.quote(level(5001, 10), level(5000, 10), &mut counter).is_err());
+
+    // So are legs that would trade against the book: nothing is placed and
+    // no fills happen behind the Err
+    let err = book.quote(level(5001, 5), level(5002, 10), &mut counter).unwrap_err();
+    assert!(err.contains("would cross the best ask"), "{}", err);
+    let err = book.quote(level(4990, 10), level(4999, 5), &mut counter).unwrap_err();
+    assert!(err.contains("would cross the best bid"), "{}", err);
+    assert_eq!(resting_ids(&book), vec![second.0, second.1]);
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 20);
+    assert_eq!(book.depth_at_price(Price::define(4999), Side::Bid), 20);
+
+    // The ask level is full, so the ask is rejected and the bid rolled back
+    let err = book.quote(level(4997, 10), level(5001, 10), &mut counter).unwrap_err();
+    assert!(err.contains("is full"), "{}", err);
+    assert_eq!(resting_ids(&book), vec![second.0, second.1]);
+
+    // An invalid requote is refused before the old pair is touched
+    let err = book.requote(second, level(4997, 10), level(5003, 0), &mut counter).unwrap_err();
+    assert!(err.contains("zero"), "{}", err);
+    assert_eq!(resting_ids(&book), vec![second.0, second.1]);
+    assert_eq!(book.get_order(second.0).unwrap().queue_position, 0);
+    assert_eq!(book.validate(), Ok(()));
+
+    // A requote rejected once the old pair is off the book puts it back
+    // whole, account and expiry included
+    book.cancel_all();
+    let mut own = |price, side| {
+        let order = Order::new(Price::define(price), Quantity::define(10), side, &mut counter).with_account(7);
+        order.with_expiry(if side == Side::Bid { 50 } else { 60 })
+    };
+    let (old_bid, old_ask, full) = (own(4999, Side::Bid), own(5001, Side::Ask), own(5003, Side::Ask));
+    for order in [old_bid, old_ask, full] {
+        book.add_order(order).unwrap();
+    }
+    let err = book.requote((old_bid.id(), old_ask.id()), level(4997, 10), level(5003, 10), &mut counter).unwrap_err();
+    assert!(err.contains("is full"), "{}", err);
+    assert_eq!(resting_ids(&book), vec![old_bid.id(), old_ask.id(), full.id()]);
+    for old in [old_bid, old_ask] {
+        let restored = book.cancel_order(old.id()).unwrap();
+        assert_eq!((restored.account(), restored.expiry()), (7, old.expiry()));
+        assert_eq!((restored.price(), restored.quantity()), (old.price(), old.quantity()));
+    }
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn quote_and_requote_replace_the_pair() {
+    for_each_book!(check_quote);
+}
+
+/// try_depth_at_price errs on prices no order could use and reads 0 for an
+/// empty valid level.
+fn check_try_depth_at_price<O: OrderbookTrait>() {
+    let mut book = O::with_config(BookConfig { tick_size: 5, ..BookConfig::default() }).unwrap();
+    let mut counter = IdCounter::new();
+    book.add_order(Order::new(Price::define(5005), Quantity::define(40), Side::Ask, &mut counter)).unwrap();
+
+    let depth = |price| book.try_depth_at_price(Price::define(price), Side::Ask);
+    assert!(depth(0).unwrap_err().contains("out of bounds"));
+    assert!(depth(99_999).is_err());
+    assert!(depth(100_000).unwrap_err().contains("out of bounds"));
+    assert!(depth(5003).unwrap_err().contains("not a valid tick"));
+    assert_eq!(depth(5000), Ok(0));
+    assert_eq!(depth(5005), Ok(40));
+
+    // The infallible version still reads all of them as a number
+    assert_eq!(book.depth_at_price(Price::define(5003), Side::Ask), 0);
+}
+
+#[test]
+fn try_depth_at_price_separates_invalid_from_empty() {
+    for_each_book!(check_try_depth_at_price);
+}
+
+/// best_n_bids/asks list distinct prices best first and stop at what exists.
+fn check_best_n_prices<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    // Five bid levels, one with two orders, one far out
+    for price in [4990, 4999, 4995, 4999, 4900, 4997] {
+        book.add_order(Order::new(Price::define(price), Quantity::define(10), Side::Bid, &mut counter)).unwrap();
+    }
+    for price in [5003, 5001] {
+        book.add_order(Order::new(Price::define(price), Quantity::define(10), Side::Ask, &mut counter)).unwrap();
+    }
+    let prices = |values: &[u32]| values.iter().map(|&v| Price::define(v)).collect::<Vec<_>>();
+
+    assert_eq!(book.best_n_bids(3), prices(&[4999, 4997, 4995]));
+    assert_eq!(book.best_n_bids(10), prices(&[4999, 4997, 4995, 4990, 4900]));
+    assert_eq!(book.best_n_asks(10), prices(&[5001, 5003]));
+    assert!(book.best_n_asks(0).is_empty());
+}
+
+#[test]
+fn best_n_prices_are_ordered_and_capped() {
+    for_each_book!(check_best_n_prices);
+}
+
+/// Every field of each fill, seq included, in execution order.
+fn fill_records(fills: &[Fill]) -> Vec<(u32, QtyValue, OrderId, u64)> {
+    fills
+        .iter()
+        .map(|f| (f.price.value(), f.quantity.value(), f.maker_order_id, f.seq))
+        .collect()
+}
+
+/// Feed AutoOrderbook and a Tree the same adds from `generator`, with a market
+/// order every 50 adds, and require identical fills and tops throughout.
+fn run_auto_in_lockstep(
+    generator: &mut impl WorkloadGenerator,
+    adds: usize,
+    auto: &mut AutoOrderbook,
+    reference: &mut Tree,
+    counter: &mut IdCounter,
+) {
+    for i in 0..adds {
+        let side = if i % 2 == 0 { Side::Bid } else { Side::Ask };
+        let price = Price::define(generator.next_resting_price(side));
+        let order = Order::new(price, Quantity::define(100), side, counter);
+        let (got, want) = (auto.add_order(order).unwrap(), reference.add_order(order).unwrap());
+        assert_eq!(fill_records(&got.fills), fill_records(&want.fills));
+
+        if i % 50 == 49 {
+            let taker = counter.next();
+            let got = auto.execute_market_order(side, Quantity::define(150), taker);
+            let want = reference.execute_market_order(side, Quantity::define(150), taker);
+            assert_eq!(fill_records(&got.fills), fill_records(&want.fills));
+        }
+        assert_eq!((auto.best_bid(), auto.best_ask()), (reference.best_bid(), reference.best_ask()));
+    }
+}
+
+#[test]
+fn auto_orderbook_migrates_on_wide_access() {
+    let mut auto = AutoOrderbook::new();
+    let mut reference = Tree::new();
+    let mut counter = IdCounter::new();
+
+    // Clustered flow mostly hits the hot zone: stays in array mode
+    let mut clustered = ClusteredGenerator::new(7);
+    run_auto_in_lockstep(&mut clustered, 5_000, &mut auto, &mut reference, &mut counter);
+    assert_eq!(auto.mode(), AutoMode::Hybrid);
+    assert_eq!(auto.to_snapshot(&counter).orders, reference.to_snapshot(&counter).orders);
+
+    // Uniform flow lands almost entirely in the cold zone: moves to the tree
+    let mut uniform = UniformGenerator::new(7);
+    run_auto_in_lockstep(&mut uniform, 3_000, &mut auto, &mut reference, &mut counter);
+    assert_eq!(auto.mode(), AutoMode::Tree);
+    // Migration re-adds every order, renumbering seqs but keeping the queues
+    for side in Side::all() {
+        assert_eq!(l3_dump(&auto, side), l3_dump(&reference, side));
+    }
+    assert_eq!(auto.stats(), reference.stats());
+    assert_eq!(auto.validate(), Ok(()));
+}
+
+#[test]
+fn stp_cancel_resting_removes_same_account_maker() {
+    for_each_book!(check_stp_cancel_resting);
+}
+
+#[test]
+fn stp_skip_matches_past_same_account_maker() {
+    check_stp_skip::<Tree>();
+    check_stp_skip::<FixedTick>();
+    check_stp_skip::<SoA>();
+    check_stp_skip::<Hybrid>();
+    check_stp_skip::<Arena>();
+
+    check_stp_off::<Tree>();
+    check_stp_off::<FixedTick>();
+    check_stp_off::<SoA>();
+    check_stp_off::<Hybrid>();
+    check_stp_off::<Arena>();
+}
+
+/// A sweep across several levels yields fills stamped with the taker's id and
+/// side and a strictly increasing sequence that continues into later matches.
+fn check_fill_sequence<O: OrderbookTrait>() {
+    let (mut book, mut counter) = laddered_asks::<O>();
+
+    let sweep = book.execute_market_order(Side::Bid, Quantity::define(120), TAKER_ID);
+    assert_eq!(sweep.fills.len(), 3);
+    assert!(sweep.fills.windows(2).all(|w| w[0].seq < w[1].seq));
+    for fill in &sweep.fills {
+        assert_eq!(fill.taker_order_id, TAKER_ID);
+        assert_eq!(fill.aggressor_side, Side::Bid);
+    }
+
+    let bid = Order::new(Price::define(5003), Quantity::define(10), Side::Bid, &mut counter);
+    let bid_id = bid.id();
+    let result = book.add_order(bid).unwrap();
+    assert_eq!(result.fills.len(), 1);
+    assert_eq!(result.fills[0].taker_order_id, bid_id);
+    assert_eq!(result.fills[0].aggressor_side, Side::Bid);
+    assert!(result.fills[0].seq > sweep.fills[2].seq);
+}
+
+#[test]
+fn sweep_fills_carry_taker_and_increasing_seq() {
+    for_each_book!(check_fill_sequence);
+}
+
+/// Pricing 100@5001 + 100@5002 leaves the book exactly as it was.
+fn check_sweep_cost<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    for price in [5001, 5002] {
+        let ask = Order::new(Price::define(price), Quantity::define(100), Side::Ask, &mut counter);
+        book.add_order(ask).unwrap();
+    }
+    let before = l3_dump(&book, Side::Ask);
+
+    let quote = book.sweep_cost(Side::Bid, Quantity::define(150)).unwrap();
+    assert!((quote.vwap - 5001.33).abs() < 0.01, "vwap {}", quote.vwap);
+    assert_eq!(quote.levels_consumed, 2);
+    assert_eq!(quote.filled_qty, Quantity::define(150));
+    assert_eq!(quote.worst_price, Price::define(5002));
+
+    // Too thin: quotes what is there
+    let quote = book.sweep_cost(Side::Bid, Quantity::define(500)).unwrap();
+    assert_eq!(quote.filled_qty, Quantity::define(200));
+    assert!((quote.vwap - 5001.5).abs() < 1e-9);
+
+    assert!(book.sweep_cost(Side::Ask, Quantity::define(10)).is_none());
+    assert_eq!(l3_dump(&book, Side::Ask), before);
+}
+
+#[test]
+fn sweep_cost_reports_vwap_without_mutating() {
+    for_each_book!(check_sweep_cost);
+}
+
+/// Imbalance over the top levels: balanced, bid-heavy, one-sided and empty.
+fn check_imbalance<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    assert_eq!(book.imbalance(5), None);
+
+    for (price, qty, side) in [(4999, 100, Side::Bid), (4998, 100, Side::Bid), (5001, 200, Side::Ask)] {
+        book.add_order(Order::new(Price::define(price), Quantity::define(qty), side, &mut counter)).unwrap();
+    }
+    assert_eq!(book.imbalance(5), Some(0.0));
+    // Only the best level per side: 100 vs 200
+    assert!((book.imbalance(1).unwrap() + 1.0 / 3.0).abs() < 1e-12);
+
+    let bid = Order::new(Price::define(4997), Quantity::define(400), Side::Bid, &mut counter);
+    book.add_order(bid).unwrap();
+    assert_eq!(book.imbalance(10), Some(0.5));
+
+    book.execute_market_order(Side::Bid, Quantity::define(200), TAKER_ID);
+    assert_eq!(book.imbalance(10), Some(1.0));
+    book.execute_market_order(Side::Ask, Quantity::define(600), TAKER_ID);
+    assert_eq!(book.imbalance(10), None);
+
+    let ask = Order::new(Price::define(5001), Quantity::define(10), Side::Ask, &mut counter);
+    book.add_order(ask).unwrap();
+    assert_eq!(book.imbalance(10), Some(-1.0));
+}
+
+#[test]
+fn imbalance_over_top_levels() {
+    for_each_book!(check_imbalance);
+}
+
+/// Cumulative depth runs from the best price outward and stops at max_levels.
+fn check_cumulative_depth<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    assert!(book.cumulative_depth(Side::Ask, 10).is_empty());
+
+    // Added worst first so level order comes from price, not arrival
+    for (price, qty) in [(5003, 300), (5001, 100), (5002, 200)] {
+        book.add_order(Order::new(Price::define(price), Quantity::define(qty), Side::Ask, &mut counter)).unwrap();
+    }
+    for (price, qty) in [(4999, 50), (4990, 25)] {
+        book.add_order(Order::new(Price::define(price), Quantity::define(qty), Side::Bid, &mut counter)).unwrap();
+    }
+
+    let curve = |book: &O, side, levels| -> Vec<(u32, u64)> {
+        book.cumulative_depth(side, levels).into_iter().map(|(p, total)| (p.value(), total)).collect()
+    };
+    assert_eq!(curve(&book, Side::Ask, 10), vec![(5001, 100), (5002, 300), (5003, 600)]);
+    assert_eq!(curve(&book, Side::Ask, 2), vec![(5001, 100), (5002, 300)]);
+    assert_eq!(curve(&book, Side::Bid, 10), vec![(4999, 50), (4990, 75)]);
+    assert!(book.cumulative_depth(Side::Bid, 0).is_empty());
+}
+
+#[test]
+fn cumulative_depth_accumulates_from_best() {
+    for_each_book!(check_cumulative_depth);
+}
+
+/// Order count and volume at a level move independently: three orders count
+/// 3 while depth is their sum, and a partial fill changes only the volume.
+fn check_order_count_at_price<O: OrderbookTrait>() {
+    let (mut book, _) = BookBuilder::new().ask(5001, 100).ask(5001, 200).ask(5001, 50).ask(5090, 5).build::<O>();
+    let at = |book: &O, price| {
+        let price = Price::define(price);
+        (book.order_count_at_price(price, Side::Ask), book.depth_at_price(price, Side::Ask))
+    };
+    assert_eq!(at(&book, 5001), (3, 350));
+    assert_eq!(at(&book, 5090), (1, 5));
+    assert_eq!(at(&book, 5002), (0, 0));
+    assert_eq!(book.order_count_at_price(Price::define(5001), Side::Bid), 0);
+    assert_eq!(book.order_count_at_price(Price::define(0), Side::Ask), 0);
+    assert_eq!(book.order_count_at_price(Price::define(100_000), Side::Ask), 0);
+
+    book.execute_market_order(Side::Bid, Quantity::define(150), TAKER_ID);
+    assert_eq!(at(&book, 5001), (2, 200));
+}
+
+#[test]
+fn order_count_complements_depth() {
+    for_each_book!(check_order_count_at_price);
+}
+
+
+/// Each drain reports the touched levels once, at their quantity as of the
+/// drain: a level that was added and canceled in between reads 0.
+fn check_drain_dirty<O: OrderbookTrait>() {
+    let mut book = MarketDataBook::new(O::new());
+    let mut counter = IdCounter::new();
+    let update = |side, price, quantity| LevelUpdate { side, price: Price::define(price), quantity };
+
+    let bid = Order::new(Price::define(5000), Quantity::define(100), Side::Bid, &mut counter);
+    book.add_order(bid).unwrap();
+    assert_eq!(book.drain_dirty(), vec![update(Side::Bid, 5000, 100)]);
+    book.cancel_order(bid.id()).unwrap();
+    assert_eq!(book.drain_dirty(), vec![update(Side::Bid, 5000, 0)]);
+    assert!(book.drain_dirty().is_empty());
+
+    // Add and cancel within one interval collapse to the final state
+    let bid = Order::new(Price::define(5000), Quantity::define(100), Side::Bid, &mut counter);
+    book.add_order(bid).unwrap();
+    book.cancel_order(bid.id()).unwrap();
+    assert_eq!(book.drain_dirty(), vec![update(Side::Bid, 5000, 0)]);
+
+    // A sweep marks every level it traded through and where its rest went
+    for (price, qty) in [(5001, 10), (5002, 20), (5003, 30)] {
+        book.add_order(Order::new(Price::define(price), Quantity::define(qty), Side::Ask, &mut counter)).unwrap();
+    }
+    book.drain_dirty();
+    let sweep = Order::new(Price::define(5002), Quantity::define(40), Side::Bid, &mut counter);
+    book.add_order(sweep).unwrap();
+    assert_eq!(
+        book.drain_dirty(),
+        vec![update(Side::Bid, 5002, 10), update(Side::Ask, 5001, 0), update(Side::Ask, 5002, 0)]
+    );
+
+    book.execute_market_order(Side::Bid, Quantity::define(5), TAKER_ID);
+    assert_eq!(book.drain_dirty(), vec![update(Side::Ask, 5003, 25)]);
+}
+
+#[test]
+fn drain_dirty_reports_final_level_quantities() {
+    for_each_book!(check_drain_dirty);
+}
+
+/// The top-of-book callback fires on a new best price or a size change at
+/// the best, and stays quiet when only a deeper level moves.
+fn check_top_of_book_callback<O: OrderbookTrait>() {
+    let (book, _) = BookBuilder::new().bid(4990, 10).ask(5010, 10).build::<O>();
+    let mut book = MarketDataBook::new(book);
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&seen);
+    book.on_top_of_book_change(move |top| sink.borrow_mut().push(top));
+    let mut counter = IdCounter::starting_at(100);
+    let mut bid = |price, qty| Order::new(Price::define(price), Quantity::define(qty), Side::Bid, &mut counter);
+    let level = |price, qty| Some((Price::define(price), qty));
+
+    book.add_order(bid(4980, 5)).unwrap();
+    assert!(seen.borrow().is_empty());
+
+    book.add_order(bid(5000, 20)).unwrap();
+    let top = TopOfBook { bid: level(5000, 20), ask: level(5010, 10) };
+    assert_eq!(*seen.borrow(), vec![top]);
+    assert_eq!(book.top_of_book(), top);
+
+    // Same best price, more size: still an edge
+    book.add_order(bid(5000, 5)).unwrap();
+    assert_eq!(seen.borrow().last().unwrap().bid, level(5000, 25));
+
+    book.execute_market_order(Side::Bid, Quantity::define(10), TAKER_ID);
+    assert_eq!(seen.borrow().last().unwrap().ask, None);
+    assert_eq!(seen.borrow().len(), 3);
+}
+
+#[test]
+fn top_of_book_callback_fires_only_on_changes() {
+    for_each_book!(check_top_of_book_callback);
+}
+/// The ladder puts the best ask's quantity on the right and the best bid's on
+/// the left, asks above bids.
+fn check_format_ladder<O: OrderbookTrait>() {
+    let (mut book, mut counter) = laddered_asks::<O>();
+    let bid = Order::new(Price::define(4999), Quantity::define(70), Side::Bid, &mut counter);
+    book.add_order(bid).unwrap();
+
+    let ladder = book.format_ladder(5);
+    let line_with = |price: &str| {
+        ladder
+            .lines()
+            .position(|line| line.split_whitespace().any(|word| word == price))
+            .unwrap_or_else(|| panic!("{} missing from\n{}", price, ladder))
+    };
+    let (ask_row, bid_row) = (line_with("5001"), line_with("4999"));
+    let lines: Vec<&str> = ladder.lines().collect();
+
+    assert!(ask_row < bid_row, "{}", ladder);
+    assert!(lines[ask_row].trim_end().ends_with("50"), "{}", ladder);
+    assert!(lines[bid_row].trim_start().starts_with("70"), "{}", ladder);
+    assert!(ladder.contains("(2)"), "{}", ladder);
+
+    let decimal = book.format_ladder_decimal(5, PriceScale::CENTS);
+    assert!(decimal.contains("$50.01") && decimal.contains("$49.99"), "{}", decimal);
+    assert!(decimal.contains("($0.02)"), "{}", decimal);
+    assert!(!decimal.contains("5001"), "{}", decimal);
+}
+
+#[test]
+fn format_ladder_places_sides() {
+    for_each_book!(check_format_ladder);
+}
+
+/// One writer adds and cancels non-crossing orders while readers poll the top
+/// of the book; readers never see a crossed book and the end state is exact.
+fn check_shared_book<O: OrderbookTrait + Send + Sync + 'static>() {
+    let shared = SharedOrderbook::new(O::new());
+
+    let writer = {
+        let shared = shared.clone();
+        std::thread::spawn(move || {
+            let mut counter = IdCounter::new();
+            for i in 0..500 {
+                let (bid_price, ask_price) = (Price::define(4900 + i % 50), Price::define(5099 - i % 50));
+                let bid = Order::new(bid_price, Quantity::define(10), Side::Bid, &mut counter);
+                let ask = Order::new(ask_price, Quantity::define(10), Side::Ask, &mut counter);
+                let bid_id = bid.id();
+                shared.add_order(bid).unwrap();
+                shared.add_order(ask).unwrap();
+                // Every other bid is pulled again
+                if i % 2 == 0 {
+                    shared.cancel_order(bid_id).unwrap();
+                }
+            }
+        })
+    };
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                for _ in 0..2_000 {
+                    let (bid, ask) = shared.with_read(|book| (book.best_bid(), book.best_ask()));
+                    if let (Some(bid), Some(ask)) = (bid, ask) {
+                        assert!(bid < ask, "reader saw a crossed book: {:?} / {:?}", bid, ask);
+                    }
+                    shared.book_snapshot(5);
+                }
+            })
+        })
+        .collect();
+
+    writer.join().unwrap();
+    for reader in readers {
+        reader.join().unwrap();
+    }
+
+    assert_eq!(shared.best_bid(), Some(Price::define(4949)));
+    assert_eq!(shared.best_ask(), Some(Price::define(5050)));
+    shared.with_read(|book| {
+        assert_eq!(book.validate(), Ok(()));
+        assert_eq!(book.book_snapshot(100).bids.iter().map(|&(_, q)| q).sum::<u64>(), 2_500);
+        assert_eq!(book.book_snapshot(100).asks.iter().map(|&(_, q)| q).sum::<u64>(), 5_000);
+    });
+}
+
+#[test]
+fn shared_book_survives_concurrent_readers() {
+    for_each_book!(check_shared_book);
+}
+
+/// A producer thread pushes a mixed batch through a small queue (so it has to
+/// wait on the consumer) while the book drains it on another thread.
+fn check_command_queue<O: OrderbookTrait + Send + 'static>() {
+    const ROUNDS: u64 = 200;
+    let (mut sender, queue) = CommandQueue::with_capacity(8);
+
+    let producer = std::thread::spawn(move || {
+        let mut counter = IdCounter::new();
+        for _ in 0..ROUNDS {
+            let ask = Order::new(Price::define(5001), Quantity::define(10), Side::Ask, &mut counter);
+            let bid = Order::new(Price::define(4999), Quantity::define(10), Side::Bid, &mut counter);
+            let market = Command::Market { side: Side::Bid, qty: Quantity::define(4), taker: counter.next() };
+            let cancel = Command::Cancel(bid.id());
+            // The second cancel of the same bid is rejected
+            for mut command in [Command::Add(ask), Command::Add(bid), market, cancel, cancel] {
+                while let Err(full) = sender.push(command) {
+                    command = full;
+                    std::thread::yield_now();
+                }
+            }
+        }
+    });
+
+    let consumer = std::thread::spawn(move || {
+        let mut book = O::new();
+        let mut events = Vec::new();
+        while events.len() < (ROUNDS * 5) as usize {
+            events.extend(book.apply_commands(&queue, 3));
+        }
+        (book, events)
+    });
+
+    producer.join().unwrap();
+    let (book, events) = consumer.join().unwrap();
+
+    for (round, chunk) in events.chunks(5).enumerate() {
+        let base = round as u64 * 3;
+        assert!(matches!(&chunk[0], EngineEvent::Added(id, _) if *id == base));
+        assert!(matches!(&chunk[1], EngineEvent::Added(id, _) if *id == base + 1));
+        assert!(matches!(&chunk[2], EngineEvent::Executed(id, r) if *id == base + 2 && r.is_fully_filled()));
+        assert!(matches!(&chunk[3], EngineEvent::Canceled(id) if *id == base + 1));
+        assert!(matches!(&chunk[4], EngineEvent::Rejected(id, _) if *id == base + 1));
+    }
+    assert_eq!(book.best_bid(), None);
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), ROUNDS * 6);
+}
+
+#[test]
+fn command_queue_preserves_order_across_threads() {
+    for_each_book!(check_command_queue);
+}
+
+#[test]
+fn book_invariant_no_crossed_book() {
+    // Crossing orders trade on arrival, so the ask priced at the bid is
+    // consumed and the book never shows bid >= ask.
+    let ops = vec![
+        Op::Add { side: Side::Bid, price: 4999, qty: 100 },
+        Op::Add { side: Side::Ask, price: 5001, qty: 100 },
+        Op::Add { side: Side::Ask, price: 4999, qty: 50 },
+    ];
+    let (tree, fixed, soa, hybrid) = run_all(&ops);
+    for outcome in [&tree, &fixed, &soa, &hybrid] {
+        if let (Some(bid), Some(ask)) = (outcome.best_bid, outcome.best_ask) {
+            assert!(bid < ask, "crossed book: bid={} ask={}", bid, ask);
+        }
+    }
+    assert_eq!(tree, fixed);
+    assert_eq!(tree, soa);
+    assert_eq!(tree, hybrid);
+}
+
+/// spread and both mid prices follow the touch; an odd spread leaves the
+/// mid between raw units, which mid_price rounds down. Locked and crossed
+/// touches are covered by Price's own tests, since no book rests that way.
+fn check_spread_and_mid<O: OrderbookTrait>() {
+    let mut book = O::new();
+    assert_eq!((book.spread(), book.mid_price(), book.mid_price_half_ticks()), (None, None, None));
+
+    let mut counter = IdCounter::new();
+    let mut ids = Vec::new();
+    for (side, price) in [(Side::Bid, 4999), (Side::Ask, 5002), (Side::Ask, 5004)] {
+        let order = Order::new(Price::define(price), Quantity::define(100), side, &mut counter);
+        ids.push(order.id());
+        book.add_order(order).unwrap();
+    }
+    assert_eq!(book.spread(), Some(3));
+    assert_eq!(book.mid_price_half_ticks(), Some(10001));
+    assert_eq!(book.mid_price(), Some(Price::define(5000)));
+
+    book.cancel_order(ids[1]).unwrap();
+    assert_eq!(book.spread(), Some(5));
+    assert_eq!(book.mid_price(), Some(Price::define(5001)));
+    book.cancel_order(ids[2]).unwrap();
+    assert_eq!((book.spread(), book.mid_price()), (None, None));
+
+    // Spread stays in raw units on a coarser grid, and the mid of a one-tick
+    // spread snaps down to the bid rather than landing between ticks
+    let config = BookConfig { tick_size: 5, ..BookConfig::default() };
+    let (book, _) = BookBuilder::new().with_config(config).bid(5000, 100).ask(5005, 100).build::<O>();
+    assert_eq!(book.spread(), Some(5));
+    assert_eq!(book.mid_price_half_ticks(), Some(10005));
+    assert_eq!(book.mid_price(), Some(Price::define(5000)));
+
+    let (book, _) = BookBuilder::new().with_config(config).bid(5000, 100).ask(5010, 100).build::<O>();
+    assert_eq!(book.mid_price(), Some(Price::define(5005)));
+}
+
+#[test]
+fn spread_and_mid_price_follow_the_touch() {
+    for_each_book!(check_spread_and_mid);
+}
+
+/// Two near-max orders at one level sum past u32::MAX without wrapping.
+fn check_deep_level_depth<O: OrderbookTrait>() {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    for _ in 0..2 {
+        let order = Order::new(Price::define(5001), Quantity::define(u32::MAX as QtyValue - 1), Side::Ask, &mut counter);
+        book.add_order(order).unwrap();
+    }
+
+    let expected = 2 * (u32::MAX as u64 - 1);
+    assert!(expected > u32::MAX as u64);
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), expected);
+    assert_eq!(
+        book.available_liquidity(Side::Bid, Quantity::define(u32::MAX as QtyValue), None),
+        Quantity::define(u32::MAX as QtyValue)
+    );
+}
+
+#[test]
+fn depth_does_not_wrap_past_u32_max() {
+    for_each_book!(check_deep_level_depth);
+}
+
+/// Orders, fills and depth past u32::MAX keep their exact sizes.
+#[cfg(feature = "wide-quantity")]
+fn check_wide_quantities<O: OrderbookTrait>() {
+    const SIZE: QtyValue = 5_000_000_000;
+    let (mut book, ids) = BookBuilder::new().ask(5001, SIZE).ask(5001, SIZE).ask(5002, SIZE).build::<O>();
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 2 * SIZE);
+    assert_eq!(book.stats().ask_volume, 3 * SIZE);
+
+    let result = book.execute_market_order(Side::Bid, Quantity::define(SIZE + 1), TAKER_ID);
+    assert_eq!(result.fills.iter().map(|f| f.quantity.value()).collect::<Vec<_>>(), vec![SIZE, 1]);
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), SIZE - 1);
+    assert_eq!(book.get_order(ids[1]).unwrap().remaining_qty, Quantity::define(SIZE - 1));
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[cfg(feature = "wide-quantity")]
+#[test]
+fn wide_quantities_past_u32_max_are_exact() {
+    for_each_book!(check_wide_quantities);
+}
+
+/// Pro-rata shares of makers past u32::MAX are exact, though incoming size
+/// times maker size passes u64::MAX.
+#[cfg(feature = "wide-quantity")]
+fn check_wide_pro_rata<O: OrderbookTrait>() {
+    const SIZE: QtyValue = 5_000_000_000;
+    let config = BookConfig { match_mode: MatchMode::ProRata, ..BookConfig::default() };
+    let (mut book, ids) = BookBuilder::new().with_config(config).ask(5001, SIZE).ask(5001, 2 * SIZE).build::<O>();
+
+    let result = book.execute_market_order(Side::Bid, Quantity::define(6_000_000_000), TAKER_ID);
+    let split: Vec<(OrderId, QtyValue)> = result.fills.iter().map(|f| (f.maker_order_id, f.quantity.value())).collect();
+    assert_eq!(split, vec![(ids[0], 2_000_000_000), (ids[1], 4_000_000_000)]);
+    assert_eq!(book.depth_at_price(Price::define(5001), Side::Ask), 3 * SIZE - 6_000_000_000);
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[cfg(feature = "wide-quantity")]
+#[test]
+fn wide_pro_rata_split_does_not_overflow() {
+    for_each_book!(check_wide_pro_rata);
+}
+
+/// Book with near-touch and far (hybrid cold zone) levels on both sides.
+fn snapshot_of<O: OrderbookTrait>(depth: usize) -> BookSnapshot {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    let orders = [
+        (Side::Bid, 4998, 100),
+        (Side::Bid, 4998, 50),
+        (Side::Bid, 4995, 100),
+        (Side::Bid, 3000, 10),
+        (Side::Ask, 5001, 100),
+        (Side::Ask, 5003, 70),
+        (Side::Ask, 9000, 10),
+    ];
+    for (side, price, qty) in orders {
+        let order = Order::new(Price::define(price), Quantity::define(qty), side, &mut counter);
+        book.add_order(order).unwrap();
+    }
+    book.book_snapshot(depth)
+}
+
+#[test]
+fn book_snapshot_orders_levels_best_first() {
+    let top2 = snapshot_of::<Tree>(2);
+    assert_eq!(top2.bids, vec![(Price::define(4998), 150), (Price::define(4995), 100)]);
+    assert_eq!(top2.asks, vec![(Price::define(5001), 100), (Price::define(5003), 70)]);
+
+    // Asking for more depth than exists returns only what is there.
+    let all = snapshot_of::<Tree>(10);
+    assert_eq!(all.bids.len(), 3);
+    assert_eq!(all.asks.len(), 3);
+    assert!(all.bids.windows(2).all(|w| w[0].0.value() > w[1].0.value()), "bids descending");
+    assert!(all.asks.windows(2).all(|w| w[0].0.value() < w[1].0.value()), "asks ascending");
+
+    for depth in [0, 1, 2, 10] {
+        let tree = snapshot_of::<Tree>(depth);
+        assert_eq!(tree, snapshot_of::<FixedTick>(depth), "snapshot({}): tree vs fixed", depth);
+        assert_eq!(tree, snapshot_of::<SoA>(depth), "snapshot({}): tree vs soa", depth);
+        assert_eq!(tree, snapshot_of::<Hybrid>(depth), "snapshot({}): tree vs hybrid", depth);
+        assert_eq!(tree, snapshot_of::<Arena>(depth), "snapshot({}): tree vs arena", depth);
+    }
+}
+
+/// (price, id, qty) for every resting order on one side, in iteration order.
+type L3 = Vec<(u32, OrderId, QtyValue)>;
+
+fn l3_dump<O: OrderbookTrait>(book: &O, side: Side) -> L3 {
+    book.iter_orders(side)
+        .map(|(price, order)| (price.value(), order.id(), order.quantity().value()))
+        .collect()
+}
+
+fn check_iter_orders_fifo<O: OrderbookTrait>() -> (L3, L3) {
+    let mut book = O::new();
+    let mut counter = IdCounter::new();
+    let orders = [
+        (Side::Bid, 4998, 10), // id 0
+        (Side::Bid, 4998, 20), // id 1
+        (Side::Bid, 4999, 30), // id 2
+        (Side::Bid, 4998, 40), // id 3
+        (Side::Bid, 3000, 50), // id 4 (hybrid cold zone)
+        (Side::Ask, 5001, 60), // id 5
+    ];
+    for (side, price, qty) in orders {
+        let order = Order::new(Price::define(price), Quantity::define(qty), side, &mut counter);
+        book.add_order(order).unwrap();
+    }
+    (l3_dump(&book, Side::Bid), l3_dump(&book, Side::Ask))
+}
+
+#[test]
+fn iter_orders_is_price_then_time_priority() {
+    let (bids, asks) = check_iter_orders_fifo::<Tree>();
+    assert_eq!(
+        bids,
+        vec![(4999, 2, 30), (4998, 0, 10), (4998, 1, 20), (4998, 3, 40), (3000, 4, 50)]
+    );
+    assert_eq!(asks, vec![(5001, 5, 60)]);
+
+    let tree = (bids, asks);
+    assert_eq!(tree, check_iter_orders_fifo::<FixedTick>(), "L3: tree vs fixed");
+    assert_eq!(tree, check_iter_orders_fifo::<SoA>(), "L3: tree vs soa");
+    assert_eq!(tree, check_iter_orders_fifo::<Hybrid>(), "L3: tree vs hybrid");
+    assert_eq!(tree, check_iter_orders_fifo::<Arena>(), "L3: tree vs arena");
+}
+
+/// Tree book with queued, partially filled and far-from-touch orders, and
+/// the counter that issued its ids.
+fn populated_tree() -> (Tree, IdCounter) {
+    let mut book = Tree::new();
+    let mut counter = IdCounter::new();
+    let orders = [
+        (Side::Bid, 4998, 100),
+        (Side::Bid, 4998, 50),
+        (Side::Bid, 4990, 70),
+        (Side::Bid, 3000, 10),
+        (Side::Ask, 5001, 100),
+        (Side::Ask, 5001, 30),
+        (Side::Ask, 9000, 10),
+    ];
+    for (side, price, qty) in orders {
+        let order = Order::new(Price::define(price), Quantity::define(qty), side, &mut counter);
+        book.add_order(order).unwrap();
+    }
+    book.execute_market_order(Side::Bid, Quantity::define(40), TAKER_ID);
+    (book, counter)
+}
+
+#[test]
+fn state_snapshot_restores_into_any_impl() {
+    let (tree, counter) = populated_tree();
+    let state = tree.to_snapshot(&counter);
+    assert_eq!(state.orders.len(), 7);
+    assert_eq!(state.next_id, 7);
+
+    let expected = (tree.book_snapshot(10), l3_dump(&tree, Side::Bid), l3_dump(&tree, Side::Ask));
+    fn restored<O: OrderbookTrait>(state: &BookState) -> (BookSnapshot, L3, L3) {
+        let book = O::from_snapshot(state).expect("state restores");
+        (book.book_snapshot(10), l3_dump(&book, Side::Bid), l3_dump(&book, Side::Ask))
+    }
+    assert_eq!(expected, restored::<Tree>(&state), "restore: tree");
+    assert_eq!(expected, restored::<FixedTick>(&state), "restore: fixed");
+    assert_eq!(expected, restored::<SoA>(&state), "restore: soa");
+    assert_eq!(expected, restored::<Hybrid>(&state), "restore: hybrid");
+    assert_eq!(expected, restored::<Arena>(&state), "restore: arena");
+}
+
+#[test]
+fn crossed_state_is_rejected() {
+    let mut counter = IdCounter::new();
+    let state = BookState {
+        orders: vec![
+            Order::new(Price::define(5001), Quantity::define(10), Side::Bid, &mut counter),
+            Order::new(Price::define(5000), Quantity::define(10), Side::Ask, &mut counter),
+        ],
+        next_id: 2,
+        config: BookConfig::default(),
+    };
+    assert!(FixedTick::from_snapshot(&state).is_err());
+}
+
+/// A state carries its book's config, so orders only valid on that grid
+/// restore, and next_id comes from the counter even when the newest order
+/// has already left the book.
+#[test]
+fn state_snapshot_keeps_config_and_id_counter() {
+    let config = BookConfig {
+        max_price: 20_000,
+        tick_size: 5,
+        lot_size: 10,
+        stp: StpMode::CancelResting,
+        match_mode: MatchMode::ProRata,
+        max_orders_per_level: Some(3),
+        reject_crossing: true,
+    };
+    let (mut tree, _) =
+        BookBuilder::new().with_config(config).bid(4995, 100).bid(4995, 50).ask(15_005, 30).build::<Tree>();
+    let mut counter = IdCounter::starting_at(100);
+    let last = Order::new(Price::define(5010), Quantity::define(20), Side::Ask, &mut counter);
+    tree.add_order(last).unwrap();
+    tree.cancel_order(last.id()).unwrap();
+
+    let state = tree.to_snapshot(&counter);
+    assert_eq!(state.config, config);
+    assert_eq!(state.next_id, 101);
+
+    fn restored<O: OrderbookTrait>(state: &BookState) -> (BookConfig, BookSnapshot, L3, L3) {
+        let book = O::from_snapshot(state).expect("state restores on its own config");
+        (*book.config(), book.book_snapshot(10), l3_dump(&book, Side::Bid), l3_dump(&book, Side::Ask))
+    }
+    let expected = (config, tree.book_snapshot(10), l3_dump(&tree, Side::Bid), l3_dump(&tree, Side::Ask));
+    assert_eq!(expected, restored::<Tree>(&state), "restore: tree");
+    assert_eq!(expected, restored::<FixedTick>(&state), "restore: fixed");
+    assert_eq!(expected, restored::<SoA>(&state), "restore: soa");
+    assert_eq!(expected, restored::<Hybrid>(&state), "restore: hybrid");
+    assert_eq!(expected, restored::<Arena>(&state), "restore: arena");
+
+    // The same orders don't fit a default book: 15005 is past its max price
+    let default_state = BookState { config: BookConfig::default(), ..state };
+    assert!(Tree::from_snapshot(&default_state).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn state_snapshot_json_round_trip() {
+    let (tree, counter) = populated_tree();
+    let json = serde_json::to_string(&tree.to_snapshot(&counter)).unwrap();
+
+    let state: BookState = serde_json::from_str(&json).unwrap();
+    let fixed = FixedTick::from_snapshot(&state).unwrap();
+    assert_eq!(tree.book_snapshot(10), fixed.book_snapshot(10));
+    assert_eq!(IdCounter::starting_at(state.next_id).next(), 7);
+    assert_eq!(state.config, BookConfig::default());
+}
+
+#[test]
+fn hybrid_hot_zone_follows_the_market() {
+    let mut book = Hybrid::new();
+    let mut counter = IdCounter::new();
+    for price in 5000..5010 {
+        let order = Order::new(Price::define(price), Quantity::define(100), Side::Ask, &mut counter);
+        book.add_order(order).unwrap();
+    }
+    let far_ask = Order::new(Price::define(8000), Quantity::define(100), Side::Ask, &mut counter);
+    book.add_order(far_ask).unwrap();
+    assert!(book.is_in_hot_zone(5000));
+    assert!(!book.is_in_hot_zone(8000));
+
+    // Sweep everything near 5000: only the 8000 ask is left.
+    let result = book.execute_market_order(Side::Bid, Quantity::define(1_000), TAKER_ID);
+    assert!(result.is_fully_filled());
+    assert_eq!(book.best_ask(), Some(Price::define(8000)));
+    assert!(book.is_in_hot_zone(8000));
+    assert!(!book.is_in_hot_zone(5000));
+
+    // The migrated level is still reachable and new adds near 8000 are hot.
+    assert_eq!(book.depth_at_price(Price::define(8000), Side::Ask), 100);
+    let bid = Order::new(Price::define(7990), Quantity::define(50), Side::Bid, &mut counter);
+    book.add_order(bid).unwrap();
+    assert!(book.is_in_hot_zone(7990));
+    assert_eq!(book.best_bid(), Some(Price::define(7990)));
+    assert_eq!(book.depth_at_price(Price::define(7990), Side::Bid), 50);
+}
+
+/// Stats, best bids, worst prices, far depth, a canceled order, fills and a
+/// bulk cancel count, in that order
+type ColdLevelObservables = (BookStats, Vec<Price>, [Option<Price>; 2], u64, Order, Vec<(u32, QtyValue, OrderId, u64)>, usize);
+
+/// Levels far outside the hot zone: stats, depth, top-of-book and cancels
+/// over cold levels on Hybrid (and on AutoOrderbook, which starts as one)
+/// agree with the tree. The shared checks stay inside the windowed book's
+/// default window, which is exactly the hot zone, so they never reach here.
+fn cold_level_observables<O: OrderbookTrait>() -> ColdLevelObservables {
+    let (mut book, ids) = BookBuilder::new()
+        .bid(4990, 100)
+        .bid(3000, 30)
+        .bid(100, 10)
+        .ask(5001, 100)
+        .ask(8000, 40)
+        .ask(9000, 10)
+        .build::<O>();
+    let stats = book.stats();
+    let bids = book.best_n_bids(10);
+    let worst = [book.worst_price(Side::Bid), book.worst_price(Side::Ask)];
+    let depth = book.depth_in_range(Side::Bid, Price::define(50), Price::define(4000));
+
+    let canceled = book.cancel_order(ids[4]).unwrap();
+    let fills = book.execute_market_order(Side::Bid, Quantity::define(105), TAKER_ID).fills;
+    let canceled_bids = book.cancel_all_for_side(Side::Bid);
+    assert_eq!(book.validate(), Ok(()));
+    (stats, bids, worst, depth, canceled, fill_records(&fills), canceled_bids)
+}
+
+#[test]
+fn cold_levels_behave_like_hot_ones() {
+    let tree = cold_level_observables::<Tree>();
+    assert_eq!(tree.0.bid_levels, 3);
+    assert_eq!(tree.1, [4990, 3000, 100].map(Price::define));
+    assert_eq!(tree.2, [Some(Price::define(100)), Some(Price::define(9000))]);
+    assert_eq!(tree.3, 40);
+    assert_eq!(tree.4.price(), Price::define(8000));
+    assert_eq!(tree.5.iter().map(|&(price, qty, ..)| (price, qty)).collect::<Vec<_>>(), vec![(5001, 100), (9000, 5)]);
+    assert_eq!(tree.6, 3);
+
+    assert_eq!(cold_level_observables::<Hybrid>(), tree, "tree vs hybrid");
+    assert_eq!(cold_level_observables::<AutoOrderbook>(), tree, "tree vs auto");
+}
+
+/// Near the top of a price range past 2^31 the bid + ask sum leaves u32, and
+/// the new center still lands on the tick grid.
+#[test]
+fn hybrid_recenters_high_in_a_wide_range() {
+    let config = BookConfig { max_price: 4_000_000_000, tick_size: 5, ..BookConfig::default() };
+    let (mut book, _) = BookBuilder::new()
+        .with_config(config)
+        .bid(3_000_000_000, 100)
+        .ask(3_000_000_015, 100)
+        .build::<Hybrid>();
+    book.recenter_hot_zone();
+    assert!(book.is_in_hot_zone(3_000_000_000));
+    assert!(book.is_in_hot_zone(3_000_000_015));
+    assert_eq!(book.depth_at_price(Price::define(3_000_000_015), Side::Ask), 100);
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn soa_level_cancel_returns_original_order() {
+    let mut level = LevelSoA::default();
+    let order = Order::from_parts(1234, Price::define(5001), Quantity::define(100), Side::Ask);
+    level.add(order);
+
+    let cancelled = level.cancel(1234).expect("order 1234 rests at this level");
+    assert_eq!(cancelled.id(), 1234);
+    assert_eq!(cancelled.price(), Price::define(5001));
+    assert_eq!(cancelled.quantity(), Quantity::define(100));
+    assert_eq!(cancelled.side(), Side::Ask);
+    assert!(level.is_empty());
+}
+
+#[test]
+fn arena_level_keeps_fifo_through_adds_and_cancels() {
+    let mut book = Arena::new();
+    let mut counter = IdCounter::new();
+    let mut add = |book: &mut Arena, qty: QtyValue| {
+        let order = Order::new(Price::define(5010), Quantity::define(qty), Side::Ask, &mut counter);
+        book.add_order(order).unwrap();
+        order.id()
+    };
+
+    // Cancels from the front, middle and back, each followed by adds that
+    // reuse the freed arena slots
+    let ids: Vec<OrderId> = (1..=5).map(|qty| add(&mut book, qty)).collect();
+    book.cancel_order(ids[2]).unwrap();
+    let sixth = add(&mut book, 6);
+    book.cancel_order(ids[0]).unwrap();
+    book.cancel_order(sixth).unwrap();
+    let seventh = add(&mut book, 7);
+    let eighth = add(&mut book, 8);
+    book.cancel_order(ids[4]).unwrap();
+
+    let queue = vec![ids[1], ids[3], seventh, eighth];
+    let resting: Vec<OrderId> = book.iter_orders(Side::Ask).map(|(_, o)| o.id()).collect();
+    assert_eq!(resting, queue);
+    for (position, &id) in queue.iter().enumerate() {
+        assert_eq!(book.get_order(id).unwrap().queue_position, position);
+    }
+    assert_eq!(book.depth_at_price(Price::define(5010), Side::Ask), 2 + 4 + 7 + 8);
+    assert_eq!(book.validate(), Ok(()));
+
+    // Fills walk the list front to back
+    let mut makers = Vec::new();
+    book.execute_market_order_with(Side::Bid, Quantity::define(2 + 4 + 7), TAKER_ID, |fill| {
+        makers.push(fill.maker_order_id)
+    });
+    assert_eq!(makers, queue[..3]);
+    assert_eq!(book.get_order(eighth).unwrap().queue_position, 0);
+}
+
+#[test]
+fn memory_footprint_separates_fixed_from_per_order() {
+    // The dense arrays cost the same empty or full; an empty tree is just its struct.
+    let fixed = FixedTick::new().memory_footprint();
+    let empty_tree = Tree::new().memory_footprint();
+    assert!(fixed.fixed_bytes > 100_000, "fixed: {:?}", fixed);
+    assert!(empty_tree.fixed_bytes < 1_000, "tree: {:?}", empty_tree);
+    assert_eq!((empty_tree.heap_bytes, empty_tree.orders), (0, 0));
+
+    let mut tree = Tree::new();
+    let mut counter = IdCounter::new();
+    let mut heap = Vec::new();
+    for batch in 0..3 {
+        for i in 0..100 {
+            let price = Price::define(4000 + batch * 100 + i);
+            tree.add_order(Order::new(price, Quantity::define(10), Side::Bid, &mut counter)).unwrap();
+        }
+        let footprint = tree.memory_footprint();
+        assert_eq!(footprint.fixed_bytes, empty_tree.fixed_bytes);
+        assert_eq!(footprint.orders, 100 * (batch as usize + 1));
+        heap.push(footprint.heap_bytes);
+    }
+    assert!(heap[0] > 0 && heap[0] < heap[1] && heap[1] < heap[2], "heap: {:?}", heap);
+}
+
+/// Fill every slot of a huge-page backed book from `build`, then drain it.
+#[cfg(all(feature = "hugepages", target_os = "linux"))]
+fn check_hugepage_book<O: OrderbookTrait>(build: fn(BookConfig) -> Result<O, String>) {
+    let mut book = build(BookConfig::default()).expect("default config is valid");
+    let mut counter = IdCounter::new();
+    for price in 1..10_000 {
+        let side = if price < 5000 { Side::Bid } else { Side::Ask };
+        let order = Order::new(Price::define(price), Quantity::define(10), side, &mut counter);
+        book.add_order(order).unwrap();
+    }
+    assert_eq!((book.best_bid(), book.best_ask()), (Some(Price::define(4999)), Some(Price::define(5000))));
+    assert_eq!(book.stats().bid_levels + book.stats().ask_levels, 9_999);
+    assert_eq!(book.validate(), Ok(()));
+
+    let result = book.execute_market_order(Side::Bid, Quantity::define(10 * 5_000), TAKER_ID);
+    assert!(result.is_fully_filled());
+    assert_eq!(book.best_ask(), None);
+    assert_eq!(book.cancel_all(), 4_999);
+}
+
+#[cfg(all(feature = "hugepages", target_os = "linux"))]
+#[test]
+fn hugepage_books_cover_the_full_price_range() {
+    check_hugepage_book(FixedTick::with_hugepages);
+    check_hugepage_book(SoA::with_hugepages);
+}
+
+/// Two fresh books fed the same seeded commands, with the bids cleared half
+/// way, trade identically and end in the same state.
+fn check_repeated_workload<O: OrderbookTrait>() {
+    let commands = replay_commands(Distribution::Uniform, 11, 4_000);
+    let run = || {
+        let mut book = O::new();
+        let mut fills = Vec::new();
+        for (i, &command) in commands.iter().enumerate() {
+            if i == commands.len() / 2 {
+                book.cancel_all_for_side(Side::Bid);
+            }
+            match book.apply_command(command) {
+                EngineEvent::Added(_, result) => fills.extend(result.fills),
+                EngineEvent::Executed(_, result) => fills.extend(result.fills),
+                _ => {}
+            }
+        }
+        let resting: Vec<(Price, Order)> = Side::all().into_iter().flat_map(|side| book.iter_orders(side)).collect();
+        assert_eq!(book.validate(), Ok(()));
+        (fills, book.book_snapshot(usize::MAX), resting, book.stats())
+    };
+    let first = run();
+    assert!(!first.0.is_empty());
+    assert!(first == run(), "replays of the same commands diverged");
+}
+
+#[test]
+fn repeated_workloads_are_identical() {
+    for_each_book!(check_repeated_workload);
+}
+
+// ─── Proptest ─────────────────────────────────────────────────────────────────
+
+// Valid price range — stays well inside all implementations' [1, 9999] bounds.
+const PRICE_MIN: u32 = 1000;
+const PRICE_MAX: u32 = 8000;
+const QTY_MIN: QtyValue = 1;
+const QTY_MAX: QtyValue = 500;
+
+fn arb_side() -> impl Strategy<Value = Side> {
+    prop_oneof![Just(Side::Bid), Just(Side::Ask)]
+}
+
+fn arb_op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        // Add — most common operation
+        3 => (arb_side(), PRICE_MIN..=PRICE_MAX, QTY_MIN..=QTY_MAX)
+                .prop_map(|(side, price, qty)| Op::Add { side, price, qty }),
+        // Cancel by position
+        1 => any::<usize>().prop_map(|idx| Op::Cancel { idx }),
+        // Market order with a qty large enough to sweep the whole side.
+        // With at most 30 ops × max qty 500 = 15000 total book depth, 100_000
+        // always leaves an unfilled remainder.
+        1 => arb_side().prop_map(|side| Op::Market { side, qty: 100_000 }),
+    ]
+}
+
+fn arb_ops() -> impl Strategy<Value = Vec<Op>> {
+    prop::collection::vec(arb_op(), 1..=30)
+}
+
+/// Like arb_ops, but market orders come in sizes that usually stop partway
+/// through a level, so partial fills are exercised too.
+fn arb_lockstep_ops() -> impl Strategy<Value = Vec<Op>> {
+    let op = prop_oneof![
+        3 => (arb_side(), PRICE_MIN..=PRICE_MAX, QTY_MIN..=QTY_MAX)
+                .prop_map(|(side, price, qty)| Op::Add { side, price, qty }),
+        1 => any::<usize>().prop_map(|idx| Op::Cancel { idx }),
+        1 => (arb_side(), QTY_MIN..=2 * QTY_MAX).prop_map(|(side, qty)| Op::Market { side, qty }),
+    ];
+    prop::collection::vec(op, 1..=40)
+}
+
+/// One op with its ids already assigned, so every book sees the same ids.
+#[derive(Debug)]
+enum Action {
+    Add(Order),
+    Cancel(OrderId),
+    Market(Side, Quantity, OrderId),
+}
+
+/// A fill as (price, qty, maker, taker, seq).
+type FillKey = (u32, QtyValue, OrderId, OrderId, u64);
+
+/// Everything observable about one step, compared across implementations.
+#[derive(Debug, PartialEq, Eq)]
+struct Observation {
+    /// None if the op was rejected
+    fills: Option<Vec<FillKey>>,
+    rested: bool,
+    best_bid: Option<u32>,
+    best_ask: Option<u32>,
+    /// depth_at_price on both sides at each sampled price
+    depths: Vec<(u64, u64)>,
+}
+
+fn observe<O: OrderbookTrait>(book: &mut O, action: &Action, sample_prices: &[u32]) -> Observation {
+    let (fills, rested) = match *action {
+        Action::Add(order) => match book.add_order(order) {
+            Ok(result) => (Some(result.fills), !result.rested_qty.is_zero()),
+            Err(_) => (None, false),
+        },
+        Action::Cancel(id) => (book.cancel_order(id).ok().map(|_| Vec::new()), false),
+        Action::Market(side, qty, taker_id) => {
+            (Some(book.execute_market_order(side, qty, taker_id).fills), false)
+        }
+    };
+
+    Observation {
+        fills: fills.map(|fills| {
+            fills
+                .iter()
+                .map(|f| {
+                    let (price, qty) = (f.price.value(), f.quantity.value());
+                    (price, qty, f.maker_order_id, f.taker_order_id, f.seq)
+                })
+                .collect()
+        }),
+        rested,
+        best_bid: book.best_bid().map(|p| p.value()),
+        best_ask: book.best_ask().map(|p| p.value()),
+        depths: sample_prices
+            .iter()
+            .map(|&p| {
+                let price = Price::define(p);
+                (book.depth_at_price(price, Side::Bid), book.depth_at_price(price, Side::Ask))
+            })
+            .collect(),
+    }
+}
+
+/// Apply `ops` to all five books in lockstep with one shared IdCounter and
+/// compare every observable after every op.
+fn lockstep(ops: &[Op]) -> Result<(), TestCaseError> {
+    let (mut tree, mut fixed) = (Tree::new(), FixedTick::new());
+    let (mut soa, mut hybrid) = (SoA::new(), Hybrid::new());
+    let mut arena = Arena::new();
+    let mut counter = IdCounter::new();
+    let mut active: Vec<OrderId> = Vec::new();
+    let sample_prices: Vec<u32> = ops
+        .iter()
+        .filter_map(|op| match op {
+            Op::Add { price, .. } => Some(*price),
+            _ => None,
+        })
+        .collect();
+
+    for (step, op) in ops.iter().enumerate() {
+        let action = match *op {
+            Op::Add { side, price, qty } => {
+                let order = Order::new(Price::define(price), Quantity::define(qty), side, &mut counter);
+                Action::Add(order)
+            }
+            Op::Cancel { idx } if !active.is_empty() => {
+                Action::Cancel(active.swap_remove(idx % active.len()))
+            }
+            Op::Cancel { .. } => continue,
+            Op::Market { side, qty } => Action::Market(side, Quantity::define(qty), counter.next()),
+        };
+
+        let expected = observe(&mut tree, &action, &sample_prices);
+        let fixed_seen = observe(&mut fixed, &action, &sample_prices);
+        let soa_seen = observe(&mut soa, &action, &sample_prices);
+        let hybrid_seen = observe(&mut hybrid, &action, &sample_prices);
+        let arena_seen = observe(&mut arena, &action, &sample_prices);
+        prop_assert_eq!(&fixed_seen, &expected, "step {} {:?}: fixed_tick vs tree", step, action);
+        prop_assert_eq!(&soa_seen, &expected, "step {} {:?}: soa vs tree", step, action);
+        prop_assert_eq!(&hybrid_seen, &expected, "step {} {:?}: hybrid vs tree", step, action);
+        prop_assert_eq!(&arena_seen, &expected, "step {} {:?}: arena vs tree", step, action);
+
+        if let (Action::Add(order), true) = (&action, expected.rested) {
+            active.push(order.id());
+        }
+        // Fully filled orders leave the book without a cancel
+        active.retain(|&id| tree.get_order(id).is_some());
+    }
+
+    Ok(())
+}
+
+/// Every level and every resting order, for whole-book comparisons.
+fn full_state<O: OrderbookTrait>(book: &O) -> (BookSnapshot, L3, L3) {
+    (book.book_snapshot(usize::MAX), l3_dump(book, Side::Bid), l3_dump(book, Side::Ask))
+}
+
+/// Drive a journaled Tree with `ops`, then replay its journal into a fresh
+/// book of every implementation; all must end in the journaled book's state.
+fn journal_replays_exactly(ops: &[Op]) -> Result<(), TestCaseError> {
+    let mut journaled = JournaledBook::new(Tree::new(), MemoryJournal::new());
+    let mut counter = IdCounter::new();
+    let mut issued: Vec<OrderId> = Vec::new();
+
+    for op in ops {
+        match *op {
+            Op::Add { side, price, qty } => {
+                let order = Order::new(Price::define(price), Quantity::define(qty), side, &mut counter);
+                issued.push(order.id());
+                let _ = journaled.add_order(order);
+            }
+            // May name an order that already filled: the rejection replays too
+            Op::Cancel { idx } if !issued.is_empty() => {
+                let _ = journaled.cancel_order(issued[idx % issued.len()]);
+            }
+            Op::Cancel { .. } => {}
+            Op::Market { side, qty } => {
+                journaled.execute_market_order(side, Quantity::define(qty), counter.next());
+            }
+        }
+    }
+
+    let (book, journal) = journaled.into_parts();
+    let entries = journal.entries();
+    prop_assert!(entries.iter().enumerate().all(|(i, e)| e.seq == i as u64));
+
+    let expected = full_state(&book);
+    prop_assert_eq!(&full_state(&replay::<Tree>(entries)), &expected, "tree replay");
+    prop_assert_eq!(&full_state(&replay::<FixedTick>(entries)), &expected, "fixed_tick replay");
+    prop_assert_eq!(&full_state(&replay::<SoA>(entries)), &expected, "soa replay");
+    prop_assert_eq!(&full_state(&replay::<Hybrid>(entries)), &expected, "hybrid replay");
+    prop_assert_eq!(&full_state(&replay::<Arena>(entries)), &expected, "arena replay");
+
+    Ok(())
+}
+
+proptest! {
+    /// All five implementations must agree on best_bid, best_ask, and fills
+    /// for any randomly generated sequence of add/cancel/market operations.
+    #[test]
+    fn all_impls_agree(ops in arb_ops()) {
+        let tree   = run::<Tree>(&ops);
+        let fixed  = run::<FixedTick>(&ops);
+        let soa    = run::<SoA>(&ops);
+        let hybrid = run::<Hybrid>(&ops);
+        let arena  = run::<Arena>(&ops);
+
+        prop_assert_eq!(&tree, &fixed,  "tree vs fixed_tick");
+        prop_assert_eq!(&tree, &soa,    "tree vs soa");
+        prop_assert_eq!(&tree, &hybrid, "tree vs hybrid");
+        prop_assert_eq!(&tree, &arena,  "tree vs arena");
+    }
+
+    /// A window wide enough for every generated price behaves like any
+    /// other book.
+    #[test]
+    fn windowed_agrees_with_tree(ops in arb_ops()) {
+        let windowed = Windowed::with_window(BookConfig::default(), Price::define(4500), 4000).unwrap();
+        prop_assert_eq!(&run_on(windowed, &ops), &run::<Tree>(&ops));
+    }
+
+    /// Lockstep run with shared ids: after every single op, fills (down to
+    /// maker/taker ids and sequence numbers), best prices and depth at every
+    /// price used so far must match across all five implementations.
+    #[test]
+    fn all_impls_agree_after_every_op(ops in arb_lockstep_ops()) {
+        lockstep(&ops)?;
+    }
+
+    /// Replaying the journal of a random workload reproduces the book exactly.
+    #[test]
+    fn journal_replay_reproduces_book(ops in arb_lockstep_ops()) {
+        journal_replays_exactly(&ops)?;
+    }
+
+    /// After any random sequence, best_bid < best_ask whenever both exist.
+    /// Crossing adds trade on arrival, so unconstrained ops can never leave
+    /// the book crossed.
+    #[test]
+    fn best_bid_below_best_ask_when_no_crossing(ops in arb_ops()) {
+        // Run Tree only — if the invariant fails it is a Tree bug, not a
+        // cross-impl disagreement. Cross-impl agreement is covered by all_impls_agree.
+        let outcome = run::<Tree>(&ops);
+        if let (Some(bid), Some(ask)) = (outcome.best_bid, outcome.best_ask) {
+            prop_assert!(bid < ask, "crossed book: bid={} ask={}", bid, ask);
         }
     }
 }